@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::net::SocketAddr;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
 use anyhow::{bail, Context};
 use clap::Args;
@@ -21,7 +21,7 @@ use rundler_builder::{
     TransactionSenderArgs, TransactionSenderKind,
 };
 use rundler_pool::RemotePoolClient;
-use rundler_sim::{MempoolConfigs, PriorityFeeMode};
+use rundler_sim::{MempoolConfigs, NoopStorageSlotLabeler, PriorityFeeMode, SimulationSettings};
 use rundler_task::{
     server::{connect_with_retries_shutdown, format_socket_addr},
     TaskSpawnerExt,
@@ -336,6 +336,8 @@ impl BuilderArgs {
         let da_gas_tracking_enabled =
             super::lint_da_gas_tracking(common.da_gas_tracking_enabled, &chain_spec);
 
+        let sim_settings: SimulationSettings = (common, &chain_spec).try_into()?;
+
         Ok(BuilderTaskArgs {
             entry_points,
             chain_spec,
@@ -351,7 +353,12 @@ impl BuilderArgs {
             bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
             priority_fee_mode,
             sender_args,
-            sim_settings: common.try_into()?,
+            sim_settings,
+            admission_policies_v0_6: vec![],
+            admission_policies_v0_7: vec![],
+            rejection_sink: super::rejection_sink(common),
+            storage_slot_labeler: Arc::new(NoopStorageSlotLabeler),
+            paymaster_time_range_decoders: HashMap::new(),
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
             max_cancellation_fee_increases: self.max_cancellation_fee_increases,
@@ -11,9 +11,9 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use alloy_primitives::U256;
+use alloy_primitives::{Address, Selector, B256, U256};
 use anyhow::{bail, Context};
 use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
 
@@ -33,11 +33,12 @@ use reth_tasks::TaskManager;
 use rpc::RpcCliArgs;
 use rundler_provider::{
     AlloyEntryPointV0_6, AlloyEntryPointV0_7, AlloyEvmProvider, DAGasOracleSync,
-    EntryPointProvider, EvmProvider, Providers,
+    EntryPointProvider, EvmProvider, Providers, DEFAULT_PROVIDER_SERVICE_NAME,
 };
 use rundler_rpc::{EthApiSettings, RundlerApiSettings};
 use rundler_sim::{
-    EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
+    EstimationSettings, PrecheckSettings, PriorityFeeMode, RejectionSink, SimulationSettings,
+    TracingRejectionSink, ViolationPriority, DEPOSIT_TO_SELECTOR, MIN_CALL_GAS_LIMIT,
 };
 use rundler_types::{
     chain::ChainSpec, da::DAGasOracleType, v0_6::UserOperation as UserOperationV0_6,
@@ -159,6 +160,19 @@ pub struct CommonArgs {
     )]
     node_http: Option<String>,
 
+    /// ETH Node HTTP URL that validation traces are sent to. Defaults to `node_http`
+    #[arg(
+        long = "node_http_trace",
+        name = "node_http_trace",
+        env = "NODE_HTTP_TRACE",
+        global = true
+    )]
+    node_http_trace: Option<String>,
+
+    /// ETH Node WebSocket URL, used to subscribe to new heads
+    #[arg(long = "node_ws", name = "node_ws", env = "NODE_WS", global = true)]
+    node_ws: Option<String>,
+
     /// Flag for turning unsafe bundling mode on
     #[arg(long = "unsafe", env = "UNSAFE", global = true)]
     unsafe_mode: bool,
@@ -181,23 +195,23 @@ pub struct CommonArgs {
     )]
     max_bundle_gas: u128,
 
+    /// Minimum stake value, overrides the chain spec's value if set
     #[arg(
         long = "min_stake_value",
         name = "min_stake_value",
         env = "MIN_STAKE_VALUE",
-        default_value = "1000000000000000000",
         global = true
     )]
-    min_stake_value: u128,
+    min_stake_value: Option<u128>,
 
+    /// Minimum unstake delay, overrides the chain spec's value if set
     #[arg(
         long = "min_unstake_delay",
         name = "min_unstake_delay",
         env = "MIN_UNSTAKE_DELAY",
-        default_value = "84600",
         global = true
     )]
-    min_unstake_delay: u32,
+    min_unstake_delay: Option<u32>,
 
     /// String representation of the timeout of a custom tracer in a format that is parsable by the
     /// `ParseDuration` function on the ethereum node. See Docs: https://pkg.go.dev/time#ParseDuration
@@ -210,6 +224,328 @@ pub struct CommonArgs {
     )]
     tracer_timeout: String,
 
+    /// Gas added to the validation trace call on top of the max verification gas, to cover
+    /// the entry point's own overhead
+    #[arg(
+        long = "tracer_gas_overhead",
+        name = "tracer_gas_overhead",
+        env = "TRACER_GAS_OVERHEAD",
+        default_value = "100000",
+        global = true
+    )]
+    tracer_gas_overhead: u64,
+
+    /// How operations whose sender is also their paymaster are handled: `reject` them, or
+    /// `require_stake` from the sender
+    #[arg(
+        long = "sender_is_paymaster_policy",
+        name = "sender_is_paymaster_policy",
+        env = "SENDER_IS_PAYMASTER_POLICY",
+        value_parser = PossibleValuesParser::new(["reject", "require_stake"]),
+        default_value = "require_stake",
+        global = true
+    )]
+    sender_is_paymaster_policy: String,
+
+    /// Allow staked entities to use the TIMESTAMP, NUMBER and COINBASE opcodes during
+    /// validation
+    #[arg(
+        long = "staked_environment_opcodes_allowed",
+        name = "staked_environment_opcodes_allowed",
+        env = "STAKED_ENVIRONMENT_OPCODES_ALLOWED",
+        default_value = "false",
+        global = true
+    )]
+    staked_environment_opcodes_allowed: bool,
+
+    /// Optional ordering of simulation violations, highest priority first, used to pick
+    /// which violation is returned when an operation is rejected.
+    ///
+    /// Format: NotStaked,InvalidStorageAccess,...
+    #[arg(
+        long = "violation_priority",
+        name = "violation_priority",
+        env = "VIOLATION_PRIORITY",
+        value_delimiter = ',',
+        global = true
+    )]
+    violation_priority: Option<Vec<String>>,
+
+    /// Fall back to a plain `simulateValidation` call, skipping opcode and storage rule
+    /// checks, when the node does not support `debug_traceCall`
+    #[arg(
+        long = "trace_fallback_enabled",
+        name = "trace_fallback_enabled",
+        env = "TRACE_FALLBACK_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    trace_fallback_enabled: bool,
+
+    /// Admit to the pool, and bundle, operations that were validated without tracing
+    /// because of `trace_fallback_enabled`
+    #[arg(
+        long = "untraced_ops_allowed",
+        name = "untraced_ops_allowed",
+        env = "UNTRACED_OPS_ALLOWED",
+        default_value = "false",
+        global = true
+    )]
+    untraced_ops_allowed: bool,
+
+    /// Maximum time in milliseconds for each stage of simulation (tracing, then code hash
+    /// and aggregator checks) before an operation is rejected as timed out
+    #[arg(
+        long = "simulation_timeout_millis",
+        name = "simulation_timeout_millis",
+        env = "SIMULATION_TIMEOUT_MILLIS",
+        default_value = "20000",
+        global = true
+    )]
+    simulation_timeout_millis: u64,
+
+    /// Check operation nonces against the entry point's nonce manager before simulation,
+    /// rejecting used nonces and nonce gaps
+    #[arg(
+        long = "nonce_check_enabled",
+        name = "nonce_check_enabled",
+        env = "NONCE_CHECK_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    nonce_check_enabled: bool,
+
+    /// Reject operations whose validity time range is empty (valid_after and valid_until
+    /// both 0) instead of treating the range as valid for all time
+    #[arg(
+        long = "reject_empty_time_range",
+        name = "reject_empty_time_range",
+        env = "REJECT_EMPTY_TIME_RANGE",
+        default_value = "false",
+        global = true
+    )]
+    reject_empty_time_range: bool,
+
+    /// Check that a paymaster's entry point deposit covers an operation's required prefund
+    #[arg(
+        long = "paymaster_deposit_check_enabled",
+        name = "paymaster_deposit_check_enabled",
+        env = "PAYMASTER_DEPOSIT_CHECK_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    paymaster_deposit_check_enabled: bool,
+
+    /// Maximum number of simulation stages making node calls at once, across all
+    /// concurrent simulations. Unbounded if not set.
+    #[arg(
+        long = "max_concurrent_simulation_calls",
+        name = "max_concurrent_simulation_calls",
+        env = "MAX_CONCURRENT_SIMULATION_CALLS",
+        global = true
+    )]
+    max_concurrent_simulation_calls: Option<usize>,
+
+    /// Allow validation to probe undeployed addresses with EXTCODESIZE, EXTCODEHASH or
+    /// EXTCODECOPY, only rejecting calls into undeployed contracts
+    #[arg(
+        long = "allow_undeployed_contract_probes",
+        name = "allow_undeployed_contract_probes",
+        env = "ALLOW_UNDEPLOYED_CONTRACT_PROBES",
+        default_value = "false",
+        global = true
+    )]
+    allow_undeployed_contract_probes: bool,
+
+    /// Cache validation traces by user operation hash and signature for the latest block, so that
+    /// re-validating an operation at the same block does not trace it again
+    #[arg(
+        long = "trace_cache_enabled",
+        name = "trace_cache_enabled",
+        env = "TRACE_CACHE_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    trace_cache_enabled: bool,
+
+    /// Log every user operation rejected by simulation, with its violations
+    #[arg(
+        long = "rejection_log_enabled",
+        name = "rejection_log_enabled",
+        env = "REJECTION_LOG_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    rejection_log_enabled: bool,
+
+    /// Contract code hashes (EXTCODEHASH) of validation code that is trusted when it changes
+    /// between simulations, e.g. during a known account implementation upgrade
+    ///
+    /// Format: 0x1234...,0xabcd...
+    #[arg(
+        long = "trusted_code_hashes",
+        name = "trusted_code_hashes",
+        env = "TRUSTED_CODE_HASHES",
+        value_delimiter = ',',
+        global = true
+    )]
+    trusted_code_hashes: Option<Vec<B256>>,
+
+    /// Addresses of validator modules installed on modular accounts, whose storage is
+    /// treated like the sender's during account validation
+    ///
+    /// Format: 0x1234...,0xabcd...
+    #[arg(
+        long = "validator_modules",
+        name = "validator_modules",
+        env = "VALIDATOR_MODULES",
+        value_delimiter = ',',
+        global = true
+    )]
+    validator_modules: Option<Vec<Address>>,
+
+    /// Addresses that operations may not use as their sender, factory or paymaster
+    ///
+    /// Format: 0x1234...,0xabcd...
+    #[arg(
+        long = "denylist",
+        name = "denylist",
+        env = "DENYLIST",
+        value_delimiter = ',',
+        global = true
+    )]
+    denylist: Option<Vec<Address>>,
+
+    /// Whether to retry a validation trace that ends with too few phases once with more
+    /// gas, to tell a gas-starved trace apart from a broken entry point
+    #[arg(
+        long = "phase_retry_enabled",
+        name = "phase_retry_enabled",
+        env = "PHASE_RETRY_ENABLED",
+        default_value = "false",
+        global = true
+    )]
+    phase_retry_enabled: bool,
+
+    /// Factor by which the tracer's gas limit is multiplied when retrying a trace with
+    /// too few phases
+    #[arg(
+        long = "phase_retry_gas_factor",
+        name = "phase_retry_gas_factor",
+        env = "PHASE_RETRY_GAS_FACTOR",
+        default_value = "2",
+        global = true
+    )]
+    phase_retry_gas_factor: u64,
+
+    /// Maximum gas a factory may use during deployment, unbounded if unset
+    #[arg(
+        long = "max_factory_gas",
+        name = "max_factory_gas",
+        env = "MAX_FACTORY_GAS",
+        global = true
+    )]
+    max_factory_gas: Option<u128>,
+
+    /// Simulations taking longer than this many milliseconds are logged at warn level.
+    /// Disabled if unset
+    #[arg(
+        long = "slow_simulation_threshold_millis",
+        name = "slow_simulation_threshold_millis",
+        env = "SLOW_SIMULATION_THRESHOLD_MILLIS",
+        global = true
+    )]
+    slow_simulation_threshold_millis: Option<u64>,
+
+    /// Code hashes of helper contracts that factories may deploy with CREATE
+    ///
+    /// Format: 0x1234...,0xabcd...
+    #[arg(
+        long = "factory_create_code_hashes",
+        name = "factory_create_code_hashes",
+        env = "FACTORY_CREATE_CODE_HASHES",
+        value_delimiter = ',',
+        global = true
+    )]
+    factory_create_code_hashes: Option<Vec<B256>>,
+
+    /// Report violations allowlisted by a mempool in simulation results, attributed
+    /// to their entity
+    #[arg(
+        long = "collect_allowlisted_violations",
+        name = "collect_allowlisted_violations",
+        env = "COLLECT_ALLOWLISTED_VIOLATIONS",
+        default_value = "false",
+        global = true
+    )]
+    collect_allowlisted_violations: bool,
+
+    /// Selectors of entry point methods, in addition to depositTo, that entities may
+    /// call during validation
+    ///
+    /// Format: 0x12345678,0x9abcdef0
+    #[arg(
+        long = "allowed_entry_point_methods",
+        name = "allowed_entry_point_methods",
+        env = "ALLOWED_ENTRY_POINT_METHODS",
+        value_delimiter = ',',
+        global = true
+    )]
+    allowed_entry_point_methods: Option<Vec<Selector>>,
+
+    /// How validation is traced. `struct_logs` reconstructs the tracer output from the
+    /// default tracer for providers without custom tracer support (entry point v0.6 only),
+    /// `auto` detects the supported mode at startup
+    #[arg(
+        long = "tracer_mode",
+        name = "tracer_mode",
+        env = "TRACER_MODE",
+        value_parser = PossibleValuesParser::new(["custom", "struct_logs", "auto"]),
+        default_value = "custom",
+        global = true
+    )]
+    tracer_mode: String,
+
+    /// Maximum number of opcode steps a validation trace may execute before it is
+    /// aborted and the operation rejected. Unlimited if unset
+    #[arg(
+        long = "max_trace_steps",
+        name = "max_trace_steps",
+        env = "MAX_TRACE_STEPS",
+        global = true
+    )]
+    max_trace_steps: Option<u64>,
+
+    /// Maximum number of storage accesses a validation trace may make before it is
+    /// aborted and the operation rejected. Unlimited if unset
+    #[arg(
+        long = "max_trace_storage_accesses",
+        name = "max_trace_storage_accesses",
+        env = "MAX_TRACE_STORAGE_ACCESSES",
+        global = true
+    )]
+    max_trace_storage_accesses: Option<u64>,
+
+    /// Maximum number of distinct contracts validation may access. Operations accessing
+    /// more are rejected. Unlimited if unset
+    #[arg(
+        long = "max_accessed_contracts",
+        name = "max_accessed_contracts",
+        env = "MAX_ACCESSED_CONTRACTS",
+        global = true
+    )]
+    max_accessed_contracts: Option<usize>,
+
+    /// Maximum number of expected storage slots retained per simulated operation.
+    /// Larger sets are truncated and the simulation result is flagged. Unbounded if unset
+    #[arg(
+        long = "max_expected_storage_slots",
+        name = "max_expected_storage_slots",
+        env = "MAX_EXPECTED_STORAGE_SLOTS",
+        global = true
+    )]
+    max_expected_storage_slots: Option<usize>,
+
     /// Amount of blocks to search when calling eth_getUserOperationByHash.
     /// Defaults from 0 to latest block
     #[arg(
@@ -404,19 +740,89 @@ impl TryFrom<&CommonArgs> for PrecheckSettings {
     }
 }
 
-impl TryFrom<&CommonArgs> for SimulationSettings {
+impl TryFrom<(&CommonArgs, &ChainSpec)> for SimulationSettings {
     type Error = anyhow::Error;
 
-    fn try_from(value: &CommonArgs) -> Result<Self, Self::Error> {
+    fn try_from((value, chain_spec): (&CommonArgs, &ChainSpec)) -> Result<Self, Self::Error> {
         if go_parse_duration::parse_duration(&value.tracer_timeout).is_err() {
             bail!("Invalid value for tracer_timeout, must be parsable by the ParseDuration function. See docs https://pkg.go.dev/time#ParseDuration")
         }
+        if value.phase_retry_gas_factor < 2 {
+            bail!(
+                "phase_retry_gas_factor ({}) must be at least 2",
+                value.phase_retry_gas_factor
+            )
+        }
 
-        Ok(Self::new(
-            value.min_unstake_delay,
-            U256::from(value.min_stake_value),
-            value.tracer_timeout.clone(),
-        ))
+        let violation_priority = value
+            .violation_priority
+            .clone()
+            .map(ViolationPriority::new)
+            .transpose()?;
+
+        Ok(Self {
+            min_unstake_delay: value
+                .min_unstake_delay
+                .unwrap_or(chain_spec.min_unstake_delay),
+            min_stake_value: value
+                .min_stake_value
+                .map_or(chain_spec.min_stake_value, U256::from),
+            tracer_timeout: value.tracer_timeout.clone(),
+            violation_priority,
+            trace_fallback_enabled: value.trace_fallback_enabled,
+            untraced_ops_allowed: value.untraced_ops_allowed,
+            simulation_timeout: Duration::from_millis(value.simulation_timeout_millis),
+            extra_allowed_precompiles: chain_spec
+                .extra_allowed_precompiles
+                .iter()
+                .copied()
+                .collect(),
+            nonce_check_enabled: value.nonce_check_enabled,
+            // set from the token paymaster config by the pool
+            token_paymasters: HashMap::new(),
+            trusted_code_hashes: value
+                .trusted_code_hashes
+                .iter()
+                .flatten()
+                .copied()
+                .collect(),
+            reject_empty_time_range: value.reject_empty_time_range,
+            paymaster_deposit_check_enabled: value.paymaster_deposit_check_enabled,
+            max_simulate_handle_ops_gas: value.max_simulate_handle_ops_gas,
+            max_concurrent_provider_calls: value.max_concurrent_simulation_calls,
+            allow_undeployed_contract_probes: value.allow_undeployed_contract_probes,
+            validator_modules: value.validator_modules.iter().flatten().copied().collect(),
+            max_expected_storage_slots: value.max_expected_storage_slots,
+            trace_cache_enabled: value.trace_cache_enabled,
+            denylist: value.denylist.iter().flatten().copied().collect(),
+            phase_retry_enabled: value.phase_retry_enabled,
+            phase_retry_gas_factor: value.phase_retry_gas_factor,
+            max_factory_gas: value.max_factory_gas,
+            slow_simulation_threshold: value
+                .slow_simulation_threshold_millis
+                .map(Duration::from_millis),
+            factory_create_code_hashes: value
+                .factory_create_code_hashes
+                .iter()
+                .flatten()
+                .copied()
+                .collect(),
+            collect_allowlisted_violations: value.collect_allowlisted_violations,
+            allowed_entry_point_methods: value
+                .allowed_entry_point_methods
+                .iter()
+                .flatten()
+                .copied()
+                .chain([DEPOSIT_TO_SELECTOR])
+                .collect(),
+            tracer_mode: value.tracer_mode.parse()?,
+            max_trace_steps: value.max_trace_steps,
+            max_trace_storage_accesses: value.max_trace_storage_accesses,
+            max_accessed_contracts: value.max_accessed_contracts,
+            tracer_gas_overhead: value.tracer_gas_overhead,
+            sender_is_paymaster_policy: value.sender_is_paymaster_policy.parse()?,
+            staked_environment_opcodes_allowed: value.staked_environment_opcodes_allowed,
+        })
     }
 }
 
@@ -554,6 +960,7 @@ pub struct Cli {
 #[derive(Clone)]
 pub struct RundlerProviders<P, EP06, EP07, D> {
     provider: P,
+    trace_provider: Option<P>,
     ep_v0_6: Option<EP06>,
     ep_v0_7: Option<EP07>,
     da_gas_oracle_sync: Option<D>,
@@ -575,6 +982,10 @@ where
         &self.provider
     }
 
+    fn trace_evm(&self) -> &Option<Self::Evm> {
+        &self.trace_provider
+    }
+
     fn ep_v0_6(&self) -> &Option<Self::EntryPointV0_6> {
         &self.ep_v0_6
     }
@@ -588,13 +999,23 @@ where
     }
 }
 
+/// Service name that requests to the trace node are recorded under in metrics
+const TRACE_PROVIDER_SERVICE_NAME: &str = "alloy_trace_provider_client";
+
 pub fn construct_providers(
     args: &CommonArgs,
     chain_spec: &ChainSpec,
 ) -> anyhow::Result<impl Providers> {
     let provider = Arc::new(rundler_provider::new_alloy_provider(
         args.node_http.as_ref().context("must provide node_http")?,
+        DEFAULT_PROVIDER_SERVICE_NAME,
     )?);
+    let trace_provider = args
+        .node_http_trace
+        .as_ref()
+        .map(|url| rundler_provider::new_alloy_provider(url, TRACE_PROVIDER_SERVICE_NAME))
+        .transpose()?
+        .map(|provider| AlloyEvmProvider::new(Arc::new(provider)));
     let (da_gas_oracle, da_gas_oracle_sync) =
         rundler_provider::new_alloy_da_gas_oracle(chain_spec, provider.clone());
 
@@ -626,12 +1047,19 @@ pub fn construct_providers(
 
     Ok(RundlerProviders {
         provider: AlloyEvmProvider::new(provider),
+        trace_provider,
         ep_v0_6,
         ep_v0_7,
         da_gas_oracle_sync,
     })
 }
 
+fn rejection_sink(common: &CommonArgs) -> Option<Arc<dyn RejectionSink>> {
+    common
+        .rejection_log_enabled
+        .then(|| Arc::new(TracingRejectionSink) as Arc<dyn RejectionSink>)
+}
+
 fn lint_da_gas_tracking(da_gas_tracking_enabled: bool, chain_spec: &ChainSpec) -> bool {
     if !da_gas_tracking_enabled {
         return false;
@@ -11,13 +11,13 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use alloy_primitives::Address;
 use anyhow::Context;
 use clap::Args;
-use rundler_pool::{LocalPoolBuilder, PoolConfig, PoolTask, PoolTaskArgs};
-use rundler_sim::MempoolConfigs;
+use rundler_pool::{LocalPoolBuilder, OpSizeLimits, PoolConfig, PoolTask, PoolTaskArgs};
+use rundler_sim::{MempoolConfigs, NoopStorageSlotLabeler, SimulationSettings};
 use rundler_task::TaskSpawnerExt;
 use rundler_types::{chain::ChainSpec, EntryPointVersion};
 use rundler_utils::emit::{self, EVENT_CHANNEL_CAPACITY};
@@ -89,6 +89,13 @@ pub struct PoolArgs {
     )]
     pub allowlist_path: Option<String>,
 
+    #[arg(
+        long = "pool.token_paymaster_config_path",
+        name = "pool.token_paymaster_config_path",
+        env = "POOL_TOKEN_PAYMASTER_CONFIG_PATH"
+    )]
+    pub token_paymaster_config_path: Option<String>,
+
     /// Interval at which the pool polls an Eth node for new blocks
     #[arg(
         long = "pool.chain_poll_interval_millis",
@@ -179,6 +186,92 @@ pub struct PoolArgs {
         default_value = "0.0"
     )]
     pub gas_limit_efficiency_reject_threshold: f32,
+
+    /// Maximum number of ops to re-validate on each new block, 0 to disable
+    #[arg(
+        long = "pool.revalidation_max_ops_per_block",
+        name = "pool.revalidation_max_ops_per_block",
+        env = "POOL_REVALIDATION_MAX_OPS_PER_BLOCK",
+        default_value = "0"
+    )]
+    pub revalidation_max_ops_per_block: usize,
+
+    /// Maximum number of ops orphaned by a reorg to re-validate on each new block, 0 to disable
+    #[arg(
+        long = "pool.reorg_revalidation_max_ops_per_block",
+        name = "pool.reorg_revalidation_max_ops_per_block",
+        env = "POOL_REORG_REVALIDATION_MAX_OPS_PER_BLOCK",
+        default_value = "100"
+    )]
+    pub reorg_revalidation_max_ops_per_block: usize,
+
+    /// Maximum time to spend re-validating ops on each new block, in milliseconds
+    #[arg(
+        long = "pool.revalidation_timeout_millis",
+        name = "pool.revalidation_timeout_millis",
+        env = "POOL_REVALIDATION_TIMEOUT_MILLIS",
+        default_value = "1000"
+    )]
+    pub revalidation_timeout_millis: u64,
+
+    /// Addresses of the aggregators whose user operations the pool accepts
+    ///
+    /// Operations using any other aggregator are rejected. Leave empty to disable aggregation.
+    #[arg(
+        long = "pool.supported_aggregators",
+        name = "pool.supported_aggregators",
+        env = "POOL_SUPPORTED_AGGREGATORS",
+        value_delimiter = ','
+    )]
+    pub supported_aggregators: Vec<Address>,
+
+    /// Maximum size in bytes of a gRPC request received by the pool server
+    #[arg(
+        long = "pool.max_request_size",
+        name = "pool.max_request_size",
+        env = "POOL_MAX_REQUEST_SIZE"
+    )]
+    pub max_request_size: Option<usize>,
+
+    /// Maximum size in bytes of an encoded user operation received over gRPC
+    #[arg(
+        long = "pool.max_op_size",
+        name = "pool.max_op_size",
+        env = "POOL_MAX_OP_SIZE"
+    )]
+    pub max_op_size: Option<usize>,
+
+    /// Maximum size in bytes of a user operation's init code received over gRPC
+    #[arg(
+        long = "pool.max_init_code_size",
+        name = "pool.max_init_code_size",
+        env = "POOL_MAX_INIT_CODE_SIZE"
+    )]
+    pub max_init_code_size: Option<usize>,
+
+    /// Maximum size in bytes of a user operation's call data received over gRPC
+    #[arg(
+        long = "pool.max_call_data_size",
+        name = "pool.max_call_data_size",
+        env = "POOL_MAX_CALL_DATA_SIZE"
+    )]
+    pub max_call_data_size: Option<usize>,
+
+    /// Maximum size in bytes of a user operation's paymaster and data received over gRPC
+    #[arg(
+        long = "pool.max_paymaster_and_data_size",
+        name = "pool.max_paymaster_and_data_size",
+        env = "POOL_MAX_PAYMASTER_AND_DATA_SIZE"
+    )]
+    pub max_paymaster_and_data_size: Option<usize>,
+
+    /// Maximum size in bytes of a user operation's signature received over gRPC
+    #[arg(
+        long = "pool.max_signature_size",
+        name = "pool.max_signature_size",
+        env = "POOL_MAX_SIGNATURE_SIZE"
+    )]
+    pub max_signature_size: Option<usize>,
 }
 
 impl PoolArgs {
@@ -212,6 +305,14 @@ impl PoolArgs {
         let da_gas_tracking_enabled =
             super::lint_da_gas_tracking(common.da_gas_tracking_enabled, &chain_spec);
 
+        let mut sim_settings: SimulationSettings = (common, &chain_spec).try_into()?;
+        if let Some(path) = &self.token_paymaster_config_path {
+            sim_settings.token_paymasters = get_json_config(path)
+                .await
+                .with_context(|| format!("should load token paymaster config from {path}"))?;
+        }
+        tracing::info!("Token paymasters: {:?}", sim_settings.token_paymasters);
+
         let pool_config_base = PoolConfig {
             // update per entry point
             entry_point: Address::ZERO,
@@ -226,7 +327,7 @@ impl PoolArgs {
             blocklist: blocklist.clone(),
             allowlist: allowlist.clone(),
             precheck_settings: common.try_into()?,
-            sim_settings: common.try_into()?,
+            sim_settings,
             throttled_entity_mempool_count: self.throttled_entity_mempool_count,
             throttled_entity_live_blocks: self.throttled_entity_live_blocks,
             paymaster_tracking_enabled: self.paymaster_tracking_enabled,
@@ -235,6 +336,10 @@ impl PoolArgs {
             drop_min_num_blocks: self.drop_min_num_blocks,
             da_gas_tracking_enabled,
             gas_limit_efficiency_reject_threshold: self.gas_limit_efficiency_reject_threshold,
+            revalidation_max_ops_per_block: self.revalidation_max_ops_per_block,
+            reorg_revalidation_max_ops_per_block: self.reorg_revalidation_max_ops_per_block,
+            revalidation_timeout: Duration::from_millis(self.revalidation_timeout_millis),
+            supported_aggregators: self.supported_aggregators.iter().copied().collect(),
         };
 
         let mut pool_configs = vec![];
@@ -264,11 +369,27 @@ impl PoolArgs {
             chain_spec,
             unsafe_mode: common.unsafe_mode,
             http_url: common.node_http.clone().context("must provide node_http")?,
+            ws_url: common.node_ws.clone(),
             chain_poll_interval: Duration::from_millis(self.chain_poll_interval_millis),
             chain_max_sync_retries: self.chain_sync_max_retries,
             pool_configs,
+            admission_policies_v0_6: vec![],
+            admission_policies_v0_7: vec![],
+            rejection_sink: super::rejection_sink(common),
+            storage_slot_labeler: Arc::new(NoopStorageSlotLabeler),
+            paymaster_time_range_decoders: HashMap::new(),
+            estimation_settings: common.try_into()?,
             remote_address,
+            op_size_limits: OpSizeLimits {
+                max_request_size: self.max_request_size,
+                max_op_size: self.max_op_size,
+                max_init_code_size: self.max_init_code_size,
+                max_call_data_size: self.max_call_data_size,
+                max_paymaster_and_data_size: self.max_paymaster_and_data_size,
+                max_signature_size: self.max_signature_size,
+            },
             chain_update_channel_capacity: self.chain_update_channel_capacity.unwrap_or(1024),
+            initial_operations: vec![],
         })
     }
 }
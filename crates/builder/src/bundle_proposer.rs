@@ -32,8 +32,8 @@ use rundler_provider::{
     ProvidersWithEntryPointT, SignatureAggregator,
 };
 use rundler_sim::{
-    ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError, SimulationResult, Simulator,
-    ViolationError,
+    can_add_to_bundle, ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError,
+    SimulationResult, Simulator, ViolationError,
 };
 use rundler_types::{
     chain::ChainSpec,
@@ -153,6 +153,7 @@ pub(crate) struct Settings {
     pub(crate) bundle_priority_fee_overhead_percent: u32,
     pub(crate) priority_fee_mode: PriorityFeeMode,
     pub(crate) da_gas_tracking_enabled: bool,
+    pub(crate) untraced_ops_allowed: bool,
 }
 
 #[async_trait]
@@ -537,6 +538,33 @@ where
                 }
             };
 
+            // an op that only validated with an overridden prefund may not be funded
+            if simulation.prefund_overridden {
+                warn!(
+                    "Excluding op from {:?} because it was simulated with a prefund override",
+                    op.sender()
+                );
+                continue;
+            }
+
+            // an op simulated without tracing has not had the ERC-7562 rules enforced
+            if simulation.tracing_skipped && !self.settings.untraced_ops_allowed {
+                warn!(
+                    "Excluding op from {:?} because it was simulated without tracing",
+                    op.sender()
+                );
+                continue;
+            }
+
+            // an op simulated with a signature override has not had its signature checked
+            if simulation.signature_bypassed {
+                warn!(
+                    "Excluding op from {:?} because it was simulated with a signature override",
+                    op.sender()
+                );
+                continue;
+            }
+
             // filter time range
             if !simulation
                 .valid_time_range
@@ -564,8 +592,13 @@ where
             }
 
             // Skip this op if the bundle does not have enough remaining gas to execute it.
-            let required_gas = gas_spent + op.execution_gas_limit(&self.settings.chain_spec, None);
-            if required_gas > self.settings.max_bundle_gas {
+            if !can_add_to_bundle(
+                gas_spent,
+                &op,
+                &simulation,
+                &self.settings.chain_spec,
+                self.settings.max_bundle_gas,
+            ) {
                 continue;
             }
 
@@ -600,7 +633,7 @@ where
             }
 
             // Update the running gas that would need to be be spent to execute the bundle so far.
-            gas_spent += op.execution_gas_limit(&self.settings.chain_spec, None);
+            gas_spent += simulation.bundle_gas(&op, &self.settings.chain_spec);
 
             constructed_bundle_size =
                 constructed_bundle_size.saturating_add(op_size_with_offset_word);
@@ -772,6 +805,7 @@ where
                 context.to_ops_per_aggregator(),
                 self.settings.beneficiary,
                 Some(gas),
+                None,
             )
             .await
             .context("should call handle ops with candidate bundle")?;
@@ -986,7 +1020,7 @@ where
         let ret = self
             .ep_providers
             .entry_point()
-            .call_handle_ops(bundle, self.settings.beneficiary, None)
+            .call_handle_ops(bundle, self.settings.beneficiary, None, None)
             .await;
         match ret {
             Ok(out) => {
@@ -1019,7 +1053,7 @@ where
         let ret = self
             .ep_providers
             .entry_point()
-            .call_handle_ops(bundle, self.settings.beneficiary, None)
+            .call_handle_ops(bundle, self.settings.beneficiary, None, None)
             .await;
         match ret {
             Ok(out) => {
@@ -1152,7 +1186,7 @@ struct OpWithSimulation<UO> {
 impl<UO: UserOperation> OpWithSimulation<UO> {
     fn op_with_replaced_sig(&self) -> UO {
         let mut op = self.op.clone();
-        if self.simulation.aggregator.is_some() {
+        if !self.simulation.aggregators.is_empty() {
             // if using an aggregator, clear out the user op signature
             op.clear_signature();
         }
@@ -1409,19 +1443,27 @@ impl<UO: UserOperation> ProposalContext<UO> {
                 SimulationViolation::AccessedUndeployedContract(entity, _) => {
                     self.add_entity_update(entity, entity_infos)
                 }
-                SimulationViolation::InvalidStorageAccess(entity, _) => {
+                SimulationViolation::InvalidStorageAccess(entity, _, _) => {
+                    self.add_entity_update(entity, entity_infos)
+                }
+                SimulationViolation::InvalidEntityStorageAccess(entity, _, _) => {
                     self.add_entity_update(entity, entity_infos)
                 }
-                SimulationViolation::CalledBannedEntryPointMethod(entity) => {
+                SimulationViolation::CalledBannedEntryPointMethod(entity, _) => {
                     self.add_entity_update(entity, entity_infos)
                 }
-                SimulationViolation::CallHadValue(entity) => {
+                SimulationViolation::CallHadValue(entity, _) => {
                     self.add_entity_update(entity, entity_infos)
                 }
                 SimulationViolation::NotStaked(stake_data) => {
                     self.add_entity_update(stake_data.needs_stake, entity_infos)
                 }
-                SimulationViolation::UnintendedRevertWithMessage(entity_type, message, address) => {
+                SimulationViolation::UnintendedRevertWithMessage(
+                    entity_type,
+                    message,
+                    address,
+                    _,
+                ) => {
                     match &message[..4] {
                         // do not penalize an entity for invalid account nonces or already deployed senders,
                         // which can occur without malicious intent from the sender or factory
@@ -1439,7 +1481,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                         }
                     }
                 }
-                SimulationViolation::UnintendedRevert(entity_type, address) => {
+                SimulationViolation::UnintendedRevert(entity_type, address, _) => {
                     if let Some(entity_address) = address {
                         self.add_entity_update(
                             Entity {
@@ -1450,7 +1492,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                         );
                     }
                 }
-                SimulationViolation::OutOfGas(entity) => {
+                SimulationViolation::OutOfGas(entity, _) => {
                     self.add_entity_update(entity, entity_infos)
                 }
                 _ => continue,
@@ -1841,10 +1883,10 @@ mod tests {
                     op: aggregated_op_a1.clone(),
                     simulation_result: Box::new(move || {
                         Ok(SimulationResult {
-                            aggregator: Some(AggregatorSimOut {
+                            aggregators: vec![AggregatorSimOut {
                                 address: aggregator_a_address,
                                 signature: bytes(op_a1_aggregated_sig),
-                            }),
+                            }],
                             ..Default::default()
                         })
                     }),
@@ -1853,10 +1895,10 @@ mod tests {
                     op: aggregated_op_a2.clone(),
                     simulation_result: Box::new(move || {
                         Ok(SimulationResult {
-                            aggregator: Some(AggregatorSimOut {
+                            aggregators: vec![AggregatorSimOut {
                                 address: aggregator_a_address,
                                 signature: bytes(op_a2_aggregated_sig),
-                            }),
+                            }],
                             ..Default::default()
                         })
                     }),
@@ -1865,10 +1907,10 @@ mod tests {
                     op: aggregated_op_b.clone(),
                     simulation_result: Box::new(move || {
                         Ok(SimulationResult {
-                            aggregator: Some(AggregatorSimOut {
+                            aggregators: vec![AggregatorSimOut {
                                 address: aggregator_b_address,
                                 signature: bytes(op_b_aggregated_sig),
-                            }),
+                            }],
                             ..Default::default()
                         })
                     }),
@@ -2237,10 +2279,10 @@ mod tests {
                     op: aggregated_op_a1.clone(),
                     simulation_result: Box::new(move || {
                         Ok(SimulationResult {
-                            aggregator: Some(AggregatorSimOut {
+                            aggregators: vec![AggregatorSimOut {
                                 address: aggregator_a_address,
                                 signature: bytes(op_a1_aggregated_sig),
-                            }),
+                            }],
                             ..Default::default()
                         })
                     }),
@@ -2249,10 +2291,10 @@ mod tests {
                     op: aggregated_op_a2.clone(),
                     simulation_result: Box::new(move || {
                         Ok(SimulationResult {
-                            aggregator: Some(AggregatorSimOut {
+                            aggregators: vec![AggregatorSimOut {
                                 address: aggregator_a_address,
                                 signature: bytes(op_a2_aggregated_sig),
-                            }),
+                            }],
                             ..Default::default()
                         })
                     }),
@@ -2444,8 +2486,8 @@ mod tests {
             entry_point
                 .expect_call_handle_ops()
                 .times(..=1)
-                .withf(move |_, &b, _| b == beneficiary)
-                .return_once(|_, _, _| Ok(call_res));
+                .withf(move |_, &b, _, _| b == beneficiary)
+                .return_once(|_, _, _, _| Ok(call_res));
         }
         for deposit in mock_paymaster_deposits {
             entry_point
@@ -2537,6 +2579,7 @@ mod tests {
                 bundle_base_fee_overhead_percent: 27,
                 bundle_priority_fee_overhead_percent: 0,
                 da_gas_tracking_enabled,
+                untraced_ops_allowed: false,
             },
             event_sender,
         );
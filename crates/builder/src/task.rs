@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use alloy_primitives::{Address, B256};
 use anyhow::Context;
@@ -19,11 +19,14 @@ use rundler_provider::{Providers as ProvidersT, ProvidersWithEntryPointT};
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl},
     simulation::{self, UnsafeSimulator},
-    MempoolConfig, PriorityFeeMode, SimulationSettings, Simulator,
+    AdmissionPolicy, MempoolConfig, PaymasterTimeRangeDecoder, PriorityFeeMode, RejectionSink,
+    SimulationSettings, Simulator, StorageSlotLabeler, TracerMode,
 };
 use rundler_task::TaskSpawnerExt;
 use rundler_types::{
-    chain::ChainSpec, pool::Pool as PoolT, EntryPointVersion, UserOperation, UserOperationVariant,
+    chain::ChainSpec, pool::Pool as PoolT, v0_6::UserOperation as UserOperationV0_6,
+    v0_7::UserOperation as UserOperationV0_7, EntryPointVersion, UserOperation,
+    UserOperationVariant,
 };
 use rundler_utils::emit::WithEntryPoint;
 use tokio::{
@@ -75,6 +78,16 @@ pub struct Args {
     pub sender_args: TransactionSenderArgs,
     /// Operation simulation settings
     pub sim_settings: SimulationSettings,
+    /// Admission policies evaluated against v0.6 operations that pass validation
+    pub admission_policies_v0_6: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_6>>>,
+    /// Admission policies evaluated against v0.7 operations that pass validation
+    pub admission_policies_v0_7: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_7>>>,
+    /// Sink sent a record of every operation rejected by simulation, if any
+    pub rejection_sink: Option<Arc<dyn RejectionSink>>,
+    /// Labeler describing storage slots in storage access violations
+    pub storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    /// Decoders of the sponsorship time range of paymasters, keyed by paymaster address
+    pub paymaster_time_range_decoders: HashMap<Address, Arc<dyn PaymasterTimeRangeDecoder>>,
     /// Maximum number of blocks to wait for a transaction to be mined
     pub max_blocks_to_wait_for_mine: u64,
     /// Percentage to increase the fees by when replacing a bundle transaction
@@ -141,7 +154,34 @@ where
     Providers: ProvidersT + 'static,
 {
     /// Spawn the builder task on the given task spawner
-    pub async fn spawn<T: TaskSpawnerExt>(self, task_spawner: T) -> anyhow::Result<()> {
+    pub async fn spawn<T: TaskSpawnerExt>(mut self, task_spawner: T) -> anyhow::Result<()> {
+        if self.args.sim_settings.tracer_mode == TracerMode::Auto {
+            let tracer_mode = simulation::detect_tracer_mode(
+                self.providers
+                    .trace_evm()
+                    .as_ref()
+                    .unwrap_or(self.providers.evm()),
+            )
+            .await;
+            info!("Detected tracer mode: {tracer_mode}");
+            self.args.sim_settings.tracer_mode = tracer_mode;
+        }
+
+        // struct log tracing can only validate v0.6 operations
+        if !self.args.unsafe_mode && self.args.sim_settings.tracer_mode == TracerMode::StructLogs {
+            if let Some(ep) = self
+                .args
+                .entry_points
+                .iter()
+                .find(|ep| ep.version == EntryPointVersion::V0_7)
+            {
+                anyhow::bail!(
+                    "struct log tracing is not supported for entry point v0.7 {:?}, use the custom tracer or disable entry point v0.7",
+                    ep.address
+                );
+            }
+        }
+
         let mut bundle_sender_actions = vec![];
         let mut pk_iter = self.args.private_keys.clone().into_iter();
 
@@ -219,7 +259,10 @@ where
                     task_spawner,
                     i + ep.bundle_builder_index_offset,
                     ep_providers.clone(),
-                    UnsafeSimulator::new(ep_providers.entry_point().clone()),
+                    UnsafeSimulator::new(
+                        ep_providers.entry_point().clone(),
+                        self.args.sim_settings.violation_priority.clone(),
+                    ),
                     pk_iter,
                 )
                 .await?
@@ -230,9 +273,15 @@ where
                     ep_providers.clone(),
                     simulation::new_v0_6_simulator(
                         ep_providers.evm().clone(),
+                        self.providers.trace_evm().clone(),
                         ep_providers.entry_point().clone(),
+                        self.args.chain_spec.id,
                         self.args.sim_settings.clone(),
                         ep.mempool_configs.clone(),
+                        self.args.admission_policies_v0_6.clone(),
+                        self.args.rejection_sink.clone(),
+                        self.args.storage_slot_labeler.clone(),
+                        self.args.paymaster_time_range_decoders.clone(),
                     ),
                     pk_iter,
                 )
@@ -266,7 +315,10 @@ where
                     task_spawner,
                     i + ep.bundle_builder_index_offset,
                     ep_providers.clone(),
-                    UnsafeSimulator::new(ep_providers.entry_point().clone()),
+                    UnsafeSimulator::new(
+                        ep_providers.entry_point().clone(),
+                        self.args.sim_settings.violation_priority.clone(),
+                    ),
                     pk_iter,
                 )
                 .await?
@@ -277,9 +329,15 @@ where
                     ep_providers.clone(),
                     simulation::new_v0_7_simulator(
                         ep_providers.evm().clone(),
+                        self.providers.trace_evm().clone(),
                         ep_providers.entry_point().clone(),
+                        self.args.chain_spec.id,
                         self.args.sim_settings.clone(),
                         ep.mempool_configs.clone(),
+                        self.args.admission_policies_v0_7.clone(),
+                        self.args.rejection_sink.clone(),
+                        self.args.storage_slot_labeler.clone(),
+                        self.args.paymaster_time_range_decoders.clone(),
                     ),
                     pk_iter,
                 )
@@ -353,6 +411,7 @@ where
             bundle_base_fee_overhead_percent: self.args.bundle_base_fee_overhead_percent,
             bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
             da_gas_tracking_enabled: self.args.da_gas_tracking_enabled,
+            untraced_ops_allowed: self.args.sim_settings.untraced_ops_allowed,
         };
 
         let transaction_sender = self
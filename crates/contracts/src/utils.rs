@@ -33,3 +33,21 @@ sol!(
     StorageLoader,
     "contracts/out/utils/StorageLoader.sol/StorageLoader.json"
 );
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IERC20 {
+        function balanceOf(address account) external view returns (uint256);
+
+        function allowance(address owner, address spender) external view returns (uint256);
+    }
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IERC165 {
+        function supportsInterface(bytes4 interfaceId) external view returns (bool);
+    }
+);
@@ -131,6 +131,9 @@ sol! {
         function simulateValidation(UserOperation calldata userOp) external;
 
         function simulateHandleOp(UserOperation calldata op, address target, bytes calldata targetCallData) external;
+
+        // From INonceManager
+        function getNonce(address sender, uint192 key) external view returns (uint256 nonce);
     }
 
     #[allow(missing_docs)]
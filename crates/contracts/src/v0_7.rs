@@ -158,6 +158,9 @@ sol!(
         ) external view returns (DepositInfo memory info);
 
         function balanceOf(address account) external view returns (uint256);
+
+        // Below from INonceManager
+        function getNonce(address sender, uint192 key) external view returns (uint256 nonce);
     }
 
     #[allow(missing_docs)]
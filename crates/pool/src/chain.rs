@@ -21,7 +21,7 @@ use alloy_primitives::{Address, B256, U256};
 use alloy_sol_types::SolEvent;
 use anyhow::{ensure, Context};
 use futures::future;
-use metrics::{Counter, Gauge};
+use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 use rundler_contracts::{
     v0_6::IEntryPoint::{
@@ -33,7 +33,7 @@ use rundler_contracts::{
         Withdrawn as WithdrawnV07,
     },
 };
-use rundler_provider::{Block, EvmProvider, Filter, Log};
+use rundler_provider::{Block, EvmProvider, Filter, HeadSubscription, Log};
 use rundler_task::{block_watcher, GracefulShutdown};
 use rundler_types::{EntryPointVersion, Timestamp, UserOperationId};
 use tokio::{
@@ -44,6 +44,8 @@ use tokio::{
 use tracing::{debug, info, warn};
 
 const MAX_LOAD_OPS_CONCURRENCY: usize = 64;
+const MIN_HEAD_SUBSCRIPTION_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_HEAD_SUBSCRIPTION_BACKOFF: Duration = Duration::from_secs(60);
 
 /// A data structure that holds the currently known recent state of the chain,
 /// with logic for updating itself and returning what has changed.
@@ -63,6 +65,12 @@ pub(crate) struct Chain<P: EvmProvider> {
     filter_template: Filter,
     /// Metrics of chain events.
     metrics: ChainMetrics,
+    /// Subscription to new heads, if configured and connected.
+    head_subscription: Option<HeadSubscription>,
+    /// Earliest time to attempt to (re)connect the head subscription.
+    head_subscription_retry_at: time::Instant,
+    /// Delay before the next reconnection attempt, doubled after each failure.
+    head_subscription_backoff: Duration,
 }
 
 #[derive(Default, Debug, Eq, PartialEq)]
@@ -118,6 +126,9 @@ pub(crate) struct Settings {
     pub(crate) poll_interval: Duration,
     pub(crate) entry_point_addresses: HashMap<Address, EntryPointVersion>,
     pub(crate) max_sync_retries: u64,
+    /// WebSocket URL to subscribe to new heads on. The chain is polled if unset, or
+    /// while the subscription is disconnected.
+    pub(crate) ws_url: Option<String>,
 }
 
 #[derive(Debug)]
@@ -173,6 +184,9 @@ impl<P: EvmProvider> Chain<P> {
             load_ops_semaphore: Semaphore::new(MAX_LOAD_OPS_CONCURRENCY),
             filter_template,
             metrics: ChainMetrics::default(),
+            head_subscription: None,
+            head_subscription_retry_at: time::Instant::now(),
+            head_subscription_backoff: MIN_HEAD_SUBSCRIPTION_BACKOFF,
         }
     }
 
@@ -201,12 +215,7 @@ impl<P: EvmProvider> Chain<P> {
             .map(|block| block.hash)
             .unwrap_or_default();
         loop {
-            let (hash, block) = block_watcher::wait_for_new_block(
-                &self.provider,
-                block_hash,
-                self.settings.poll_interval,
-            )
-            .await;
+            let (hash, block) = self.wait_for_new_block(block_hash).await;
             block_hash = hash;
 
             for i in 0..=self.settings.max_sync_retries {
@@ -233,6 +242,78 @@ impl<P: EvmProvider> Chain<P> {
         }
     }
 
+    /// Wait for a new head, pushed by the head subscription if connected, otherwise
+    /// polled from the provider.
+    async fn wait_for_new_block(&mut self, last_block_hash: B256) -> (B256, Block) {
+        loop {
+            self.connect_head_subscription().await;
+            let Some(subscription) = &mut self.head_subscription else {
+                break;
+            };
+
+            // A stalled subscription must not stall block processing, poll if no head
+            // arrives in time
+            let Ok(next) = time::timeout(self.settings.poll_interval, subscription.next()).await
+            else {
+                debug!("No new head pushed within the poll interval, polling for new blocks");
+                break;
+            };
+            match next {
+                Some(hash) => {
+                    self.head_subscription_backoff = MIN_HEAD_SUBSCRIPTION_BACKOFF;
+                    if hash != last_block_hash {
+                        break;
+                    }
+                }
+                None => {
+                    warn!("Head subscription dropped, polling for new blocks until reconnected");
+                    self.head_subscription = None;
+                    self.backoff_head_subscription();
+                }
+            }
+        }
+
+        // Either a new head was pushed, in which case the latest block is loaded right away,
+        // or there is no subscription and the provider is polled
+        block_watcher::wait_for_new_block(
+            &self.provider,
+            last_block_hash,
+            self.settings.poll_interval,
+        )
+        .await
+    }
+
+    async fn connect_head_subscription(&mut self) {
+        let Some(ws_url) = &self.settings.ws_url else {
+            return;
+        };
+        if self.head_subscription.is_some()
+            || time::Instant::now() < self.head_subscription_retry_at
+        {
+            return;
+        }
+
+        match HeadSubscription::connect(ws_url).await {
+            Ok(subscription) => {
+                info!("Subscribed to new heads");
+                self.head_subscription = Some(subscription);
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to subscribe to new heads, polling for new blocks. Retrying in {:?}: {error:?}",
+                    self.head_subscription_backoff
+                );
+                self.backoff_head_subscription();
+            }
+        }
+    }
+
+    fn backoff_head_subscription(&mut self) {
+        self.head_subscription_retry_at = time::Instant::now() + self.head_subscription_backoff;
+        self.head_subscription_backoff =
+            (self.head_subscription_backoff * 2).min(MAX_HEAD_SUBSCRIPTION_BACKOFF);
+    }
+
     pub(crate) async fn sync_to_block(&mut self, new_head: Block) -> anyhow::Result<ChainUpdate> {
         let new_head = BlockSummary::try_from_block_without_ops(new_head, None)?;
         let Some(current_block) = self.blocks.back() else {
@@ -335,6 +416,7 @@ impl<P: EvmProvider> Chain<P> {
         if reorg_depth > 0 {
             self.metrics.reorgs_detected.increment(1);
             self.metrics.total_reorg_depth.increment(reorg_depth);
+            self.metrics.reorg_depth.record(reorg_depth as f64);
         }
 
         self.new_update(
@@ -711,6 +793,8 @@ struct ChainMetrics {
     reorgs_detected: Counter,
     #[metric(describe = "the count of reorg depth.")]
     total_reorg_depth: Counter,
+    #[metric(describe = "the depth of detected reorgs.")]
+    reorg_depth: Histogram,
     #[metric(describe = "the count of sync retries.")]
     sync_retries: Counter,
     #[metric(describe = "the count of sync abanded.")]
@@ -1439,6 +1523,7 @@ mod tests {
                     (ENTRY_POINT_ADDRESS_V0_7, EntryPointVersion::V0_7),
                 ]),
                 max_sync_retries: 1,
+                ws_url: None,
             },
         );
         (chain, controller)
@@ -14,7 +14,9 @@
 use std::fmt::Display;
 
 use alloy_primitives::{Address, B256};
-use rundler_types::{Entity, EntityType, Timestamp, UserOperation, UserOperationVariant};
+use rundler_types::{
+    pool::SimulationViolation, Entity, EntityType, Timestamp, UserOperation, UserOperationVariant,
+};
 use rundler_utils::strs;
 
 use crate::mempool::OperationOrigin;
@@ -140,6 +142,13 @@ pub enum OpRemovalReason {
         valid_until: Timestamp,
     },
     PoolSizeExceeded,
+    /// Op was removed because it failed re-validation at a new block
+    RevalidationFailed {
+        /// Block number the op was re-validated at
+        block_number: u64,
+        /// The violations found during re-validation
+        violations: Vec<SimulationViolation>,
+    },
 }
 
 impl EntitySummary {
@@ -28,7 +28,7 @@ mod mempool;
 pub use mempool::PoolConfig;
 
 mod server;
-pub use server::{LocalPoolBuilder, LocalPoolHandle, RemotePoolClient};
+pub use server::{LocalPoolBuilder, LocalPoolHandle, OpSizeLimits, RemotePoolClient};
 
 mod task;
 pub use task::{Args as PoolTaskArgs, PoolTask};
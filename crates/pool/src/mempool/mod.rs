@@ -26,19 +26,23 @@ mod uo_pool;
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 #[cfg(test)]
 use mockall::automock;
 use rundler_sim::{MempoolConfig, PrecheckSettings, SimulationSettings};
 use rundler_types::{
     chain::ChainSpec,
     pool::{
-        MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+        MempoolError, PaymasterMetadata, PoolOperation, PooledOp, Reputation, ReputationChange,
+        ReputationStatus, StakeStatus,
     },
-    EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
+    EntityUpdate, EntryPointVersion, UserOperation, UserOperationId, UserOperationVariant,
+    ValidTimeRange,
 };
+use tokio::sync::broadcast;
 use tonic::async_trait;
 pub(crate) use uo_pool::{UoPool, UoPoolProviders};
 
@@ -72,20 +76,24 @@ pub trait Mempool: Send + Sync {
     /// Removes an operation from the pool by its ID.
     fn remove_op_by_id(&self, id: &UserOperationId) -> MempoolResult<Option<B256>>;
 
+    /// Removes a single operation from the pool by its hash.
+    /// Returns true if the operation was found and removed.
+    fn remove_op_by_hash(&self, hash: B256) -> bool;
+
     /// Updates the reputation of an entity.
     fn update_entity(&self, entity_update: EntityUpdate);
 
     /// Returns the best operations from the pool.
     ///
-    /// Returns the best operations from the pool based on their gas bids up to
-    /// the specified maximum number of operations, limiting to one per sender.
+    /// Returns the best operations from the pool based on their gas bids, greedily
+    /// selecting operations that fit within the given bundle constraints.
     ///
     /// The `shard_index` is used to divide the mempool into disjoint shards to ensure
     /// that two bundle builders don't attempt to but bundle the same operations. If
     /// the supplied `shard_index` does not exist, the call will error.
     fn best_operations(
         &self,
-        max: usize,
+        constraints: BundleConstraints,
         shard_index: u64,
     ) -> MempoolResult<Vec<Arc<PoolOperation>>>;
 
@@ -95,6 +103,12 @@ pub trait Mempool: Send + Sync {
     /// Looks up a user operation by hash, returns None if not found
     fn get_user_operation_by_hash(&self, hash: B256) -> Option<Arc<PoolOperation>>;
 
+    /// Returns the operations of a sender in the pool, sorted by nonce
+    fn get_operations_by_sender(&self, sender: Address) -> Vec<PooledOp>;
+
+    /// Returns the aggregators whose operations are accepted, sorted by address
+    fn supported_aggregators(&self) -> Vec<Address>;
+
     /// Debug methods
 
     /// Clears the mempool of UOs or reputation of all addresses
@@ -103,6 +117,10 @@ pub trait Mempool: Send + Sync {
     /// Dumps the mempool's reputation tracking
     fn dump_reputation(&self) -> Vec<Reputation>;
 
+    /// Takes a snapshot of all operations in the mempool, including those not
+    /// currently eligible for bundling
+    fn snapshot(&self) -> PoolSnapshot;
+
     /// Dumps the mempool's paymaster balance cache
     fn dump_paymaster_balances(&self) -> Vec<PaymasterMetadata>;
 
@@ -112,6 +130,9 @@ pub trait Mempool: Send + Sync {
     /// Overwrites the mempool's reputation for an address
     fn set_reputation(&self, address: Address, ops_seen: u64, ops_included: u64);
 
+    /// Subscribe to reputation status changes of the entities tracked by the mempool
+    fn subscribe_reputation_changes(&self) -> broadcast::Receiver<ReputationChange>;
+
     /// Get stake status for address
     async fn get_stake_status(&self, address: Address) -> MempoolResult<StakeStatus>;
 
@@ -120,6 +141,91 @@ pub trait Mempool: Send + Sync {
 
     /// Turns on and off tracking errors
     fn set_tracking(&self, paymaster: bool, reputation: bool);
+
+    /// Record periodic pool metrics, such as the age of the oldest operation
+    fn record_metrics(&self);
+}
+
+/// Constraints on the set of operations selected for a bundle
+#[derive(Debug, Clone, Copy)]
+pub struct BundleConstraints {
+    /// Maximum number of operations to select
+    pub max_ops: usize,
+    /// Maximum aggregate gas limit of the selected operations, unlimited if `None`
+    pub max_gas: Option<u128>,
+    /// Select at most one operation per sender.
+    ///
+    /// Operations of unstaked senders are always limited to one per bundle.
+    pub one_per_sender: bool,
+}
+
+impl BundleConstraints {
+    /// Constraints limiting only the number of operations
+    pub fn with_max_ops(max_ops: usize) -> Self {
+        Self {
+            max_ops,
+            max_gas: None,
+            one_per_sender: false,
+        }
+    }
+}
+
+/// A point in time copy of the operations in a mempool.
+///
+/// Used to inspect a pool offline, or to seed another pool instance via
+/// `Args::initial_operations` of the pool task.
+#[derive(Debug, Clone)]
+pub struct PoolSnapshot {
+    /// Address of the entry point the pool targets
+    pub entry_point: Address,
+    /// The operations in the pool, in no particular order
+    pub operations: Vec<SnapshotOperation>,
+}
+
+impl PoolSnapshot {
+    /// Consumes the snapshot, returning its pool operations
+    pub fn into_operations(self) -> Vec<PoolOperation> {
+        self.operations
+            .into_iter()
+            .map(|op| Arc::unwrap_or_clone(op.op))
+            .collect()
+    }
+}
+
+/// An operation in a pool snapshot along with its metadata.
+///
+/// The mempools an operation matched during simulation are not tracked by the pool,
+/// they are derived again when the operation is re-validated.
+#[derive(Debug, Clone)]
+pub struct SnapshotOperation {
+    /// Hash of the user operation
+    pub hash: B256,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Nonce of the user operation
+    pub nonce: U256,
+    /// Max fee per gas of the user operation
+    pub max_fee_per_gas: u128,
+    /// Max priority fee per gas of the user operation
+    pub max_priority_fee_per_gas: u128,
+    /// The time range for which the operation is valid
+    pub valid_time_range: ValidTimeRange,
+    /// The pooled operation
+    pub op: Arc<PoolOperation>,
+}
+
+impl SnapshotOperation {
+    fn new(hash: B256, op: Arc<PoolOperation>) -> Self {
+        Self {
+            hash,
+            sender: op.uo.sender(),
+            nonce: op.uo.nonce(),
+            max_fee_per_gas: op.uo.max_fee_per_gas(),
+            max_priority_fee_per_gas: op.uo.max_priority_fee_per_gas(),
+            valid_time_range: op.valid_time_range,
+            op,
+        }
+    }
 }
 
 /// Config for the mempool
@@ -130,6 +236,8 @@ pub struct PoolConfig {
     /// Address of the entry point this pool targets
     pub entry_point: Address,
     /// Version of the entry point this pool targets
+    ///
+    /// If `Unspecified`, the version is detected from the entry point contract on startup.
     pub entry_point_version: EntryPointVersion,
     /// The maximum number of operations an unstaked sender can have in the mempool
     pub same_sender_mempool_count: usize,
@@ -171,6 +279,21 @@ pub struct PoolConfig {
     /// Gas limit efficiency is defined as the ratio of the gas limit to the gas used.
     /// This applies to all the verification, call, and paymaster gas limits.
     pub gas_limit_efficiency_reject_threshold: f32,
+    /// The maximum number of operations to re-validate on each new block. Operations
+    /// most likely to be bundled next are re-validated first. Zero disables re-validation
+    /// on new blocks, operations orphaned by a reorg are bounded separately by
+    /// `reorg_revalidation_max_ops_per_block`.
+    pub revalidation_max_ops_per_block: usize,
+    /// The maximum number of operations simulated against blocks orphaned by a reorg to
+    /// re-validate on each new block, in addition to `revalidation_max_ops_per_block`.
+    /// Any others are re-validated on later blocks. Zero disables reorg re-validation.
+    pub reorg_revalidation_max_ops_per_block: usize,
+    /// The maximum time spent re-validating operations on each new block. Operations
+    /// whose re-validation doesn't finish in time are kept and checked on a later block.
+    pub revalidation_timeout: Duration,
+    /// Aggregators whose operations are accepted. Operations using any other aggregator
+    /// are rejected with `UnsupportedAggregator`. Empty if aggregation isn't supported.
+    pub supported_aggregators: HashSet<Address>,
 }
 
 /// Origin of an operation.
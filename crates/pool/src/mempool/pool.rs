@@ -13,12 +13,12 @@
 
 use std::{
     cmp::{self, Ordering},
-    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use anyhow::Context;
 use metrics::{Gauge, Histogram};
 use metrics_derive::Metrics;
@@ -27,7 +27,7 @@ use rundler_provider::DAGasOracleSync;
 use rundler_types::{
     chain::ChainSpec,
     da::DAGasBlockData,
-    pool::{MempoolError, PoolOperation},
+    pool::{MempoolError, PoolOperation, PooledOp},
     Entity, EntityType, GasFees, Timestamp, UserOperation, UserOperationId, UserOperationVariant,
 };
 use rundler_utils::{emit::WithEntryPoint, math};
@@ -41,6 +41,7 @@ use crate::{chain::MinedOp, emit::OpRemovalReason, PoolEvent};
 pub(crate) struct PoolInnerConfig {
     chain_spec: ChainSpec,
     entry_point: Address,
+    num_shards: u64,
     max_size_of_pool_bytes: usize,
     min_replacement_fee_increase_percentage: u32,
     throttled_entity_mempool_count: u64,
@@ -53,6 +54,7 @@ impl From<PoolConfig> for PoolInnerConfig {
         Self {
             chain_spec: config.chain_spec,
             entry_point: config.entry_point,
+            num_shards: config.num_shards,
             max_size_of_pool_bytes: config.max_size_of_pool_bytes,
             min_replacement_fee_increase_percentage: config.min_replacement_fee_increase_percentage,
             throttled_entity_mempool_count: config.throttled_entity_mempool_count,
@@ -73,6 +75,10 @@ pub(crate) struct PoolInner<D> {
     by_hash: HashMap<B256, Arc<OrderedPoolOperation>>,
     /// Operations by operation ID
     by_id: HashMap<UserOperationId, Arc<OrderedPoolOperation>>,
+    /// Operation hashes by sender, sorted by nonce
+    by_sender: HashMap<Address, BTreeMap<U256, B256>>,
+    /// Next nonce on chain by sender and nonce key, if known, for senders in the pool
+    next_nonces: HashMap<Address, HashMap<U256, U256>>,
     /// Best operations, sorted by gas price
     best: BTreeSet<Arc<OrderedPoolOperation>>,
     /// Time to mine info
@@ -86,6 +92,8 @@ pub(crate) struct PoolInner<D> {
     mined_hashes_with_block_numbers: BTreeSet<(u64, B256)>,
     /// Count of operations by entity address
     count_by_address: HashMap<Address, EntityCounter>,
+    /// Count of operations by sender shard
+    count_by_shard: Vec<usize>,
     /// Submission ID counter
     submission_id: u64,
     /// keeps track of the size of the pool in bytes
@@ -98,6 +106,8 @@ pub(crate) struct PoolInner<D> {
     prev_block_number: u64,
     /// The metrics of pool.
     metrics: PoolMetrics,
+    /// The metrics of each sender shard of the pool.
+    shard_metrics: Vec<PoolShardMetrics>,
     /// Event sender
     event_sender: broadcast::Sender<WithEntryPoint<PoolEvent>>,
 }
@@ -112,22 +122,35 @@ where
         event_sender: broadcast::Sender<WithEntryPoint<PoolEvent>>,
     ) -> Self {
         let entry_point = config.entry_point.to_string();
+        let num_shards = cmp::max(config.num_shards, 1) as usize;
+        let shard_metrics = (0..num_shards)
+            .map(|shard| {
+                PoolShardMetrics::new_with_labels(&[
+                    ("entry_point", entry_point.clone()),
+                    ("shard", shard.to_string()),
+                ])
+            })
+            .collect();
         Self {
             config,
             da_gas_oracle,
             by_hash: HashMap::new(),
             by_id: HashMap::new(),
+            by_sender: HashMap::new(),
+            next_nonces: HashMap::new(),
             best: BTreeSet::new(),
             time_to_mine: HashMap::new(),
             mined_at_block_number_by_hash: HashMap::new(),
             mined_hashes_with_block_numbers: BTreeSet::new(),
             count_by_address: HashMap::new(),
+            count_by_shard: vec![0; num_shards],
             submission_id: 0,
             pool_size: SizeTracker::default(),
             cache_size: SizeTracker::default(),
             prev_sys_block_time: Duration::default(),
             prev_block_number: 0,
             metrics: PoolMetrics::new_with_labels(&[("entry_point", entry_point)]),
+            shard_metrics,
             event_sender,
         }
     }
@@ -211,14 +234,29 @@ where
         Ok(hash)
     }
 
+    /// Returns the eligible operations in the pool, best first.
+    ///
+    /// Operations are queued by nonce per sender and nonce key. Only the lowest nonce in the
+    /// pool is ready, and each following nonce is held back until the operation before it has
+    /// been returned, so that a bundle built in this order doesn't fail the nonce check. If
+    /// the next nonce on chain is known, the lowest nonce is also held back until it matches,
+    /// so that operations behind a nonce gap are never returned.
     pub(crate) fn best_operations(&self) -> impl Iterator<Item = Arc<PoolOperation>> + '_ {
-        self.best.iter().filter_map(|p| {
-            if p.eligible() {
-                Some(p.po.clone())
-            } else {
-                None
-            }
-        })
+        BestOperations::new(self)
+    }
+
+    /// Returns all operations in the pool, best first, including operations queued behind a
+    /// lower nonce and ineligible operations.
+    ///
+    /// Use `best_operations` to select operations for a bundle.
+    pub(crate) fn all_operations(&self) -> impl Iterator<Item = Arc<PoolOperation>> + '_ {
+        self.best.iter().map(|op| op.po.clone())
+    }
+
+    /// Returns all operations in the pool, eligible or not, along with their hashes
+    /// in no particular order.
+    pub(crate) fn operations(&self) -> impl Iterator<Item = (B256, Arc<PoolOperation>)> + '_ {
+        self.by_hash.iter().map(|(hash, op)| (*hash, op.po.clone()))
     }
 
     /// Does maintenance on the pool.
@@ -298,11 +336,7 @@ where
                 }
             }
 
-            let uo_gas_price = cmp::min(
-                op.uo().max_fee_per_gas(),
-                op.uo().max_priority_fee_per_gas() + base_fee,
-            );
-            if candidate_gas_price > uo_gas_price {
+            if candidate_gas_price > op.uo().gas_price(base_fee) {
                 // don't mark as ineligible, but also not a candidate
                 continue;
             }
@@ -338,10 +372,79 @@ where
         self.by_hash.get(&hash).map(|o| o.po.clone())
     }
 
+    /// The block number an operation was first simulated at when it was added to the pool,
+    /// which re-validation does not change
+    pub(crate) fn get_inserted_block_number(&self, hash: B256) -> Option<u64> {
+        self.by_hash.get(&hash).map(|o| o.inserted_block_number)
+    }
+
+    /// Record that an operation was successfully simulated again at a newer block
+    pub(crate) fn set_sim_block(&mut self, hash: B256, block_hash: B256, block_number: u64) {
+        let Some(op) = self.by_hash.get(&hash) else {
+            return;
+        };
+        let mut po = (*op.po).clone();
+        po.sim_block_hash = block_hash;
+        po.sim_block_number = block_number;
+        let updated = Arc::new(OrderedPoolOperation {
+            po: Arc::new(po),
+            submission_id: op.submission_id,
+            eligible: RwLock::new(op.eligible()),
+            inserted_at: op.inserted_at,
+            inserted_block_number: op.inserted_block_number,
+        });
+
+        // the ordering is unchanged, so the operation is replaced in place
+        self.best.replace(updated.clone());
+        self.by_id.insert(updated.uo().id(), updated.clone());
+        self.by_hash.insert(hash, updated);
+    }
+
     pub(crate) fn get_operation_by_id(&self, id: &UserOperationId) -> Option<Arc<PoolOperation>> {
         self.by_id.get(id).map(|o| o.po.clone())
     }
 
+    // Returns the operation from the same sender with the next nonce, if it is in the pool
+    /// Record the next nonce on chain of a sender's nonce key. Only recorded while the sender
+    /// has operations in the pool.
+    pub(crate) fn set_next_nonce(&mut self, sender: Address, nonce: U256) {
+        if self.by_sender.contains_key(&sender) {
+            self.next_nonces
+                .entry(sender)
+                .or_default()
+                .insert(nonce >> 64, nonce);
+        }
+    }
+
+    fn next_nonce(&self, sender: Address, nonce: U256) -> Option<U256> {
+        self.next_nonces.get(&sender)?.get(&(nonce >> 64)).copied()
+    }
+
+    fn next_nonce_operation(&self, uo: &UserOperationVariant) -> Option<Arc<OrderedPoolOperation>> {
+        let hash = self
+            .by_sender
+            .get(&uo.sender())?
+            .get(&(uo.nonce() + U256::from(1)))?;
+        self.by_hash.get(hash).cloned()
+    }
+
+    pub(crate) fn get_operations_by_sender(&self, sender: Address) -> Vec<PooledOp> {
+        let Some(hashes) = self.by_sender.get(&sender) else {
+            return vec![];
+        };
+        hashes
+            .iter()
+            .filter_map(|(nonce, hash)| {
+                self.by_hash.get(hash).map(|op| PooledOp {
+                    hash: *hash,
+                    nonce: *nonce,
+                    max_fee_per_gas: op.uo().max_fee_per_gas(),
+                    max_priority_fee_per_gas: op.uo().max_priority_fee_per_gas(),
+                })
+            })
+            .collect()
+    }
+
     pub(crate) fn remove_operation_by_hash(&mut self, hash: B256) -> Option<Arc<PoolOperation>> {
         let ret = self.remove_operation_internal(hash, None);
         self.update_metrics();
@@ -404,6 +507,15 @@ where
         mined_op: &MinedOp,
         block_number: u64,
     ) -> Option<Arc<PoolOperation>> {
+        // the mined nonce is used on chain whether or not the operation is in the pool
+        let mined_next = mined_op.nonce + U256::from(1);
+        if self
+            .next_nonce(mined_op.sender, mined_op.nonce)
+            .map_or(true, |next| next < mined_next)
+        {
+            self.set_next_nonce(mined_op.sender, mined_next);
+        }
+
         let tx_in_pool = self.by_id.get(&mined_op.id())?;
 
         // TODO(danc): there is a bug here with replacements.
@@ -443,6 +555,14 @@ where
         if let Err(error) = self.put_back_unmined_operation(op.clone()) {
             info!("Could not put back unmined operation: {error}");
         };
+        // the unmined nonce is unused on chain again
+        let (sender, nonce) = (op.uo().sender(), op.uo().nonce());
+        if self
+            .next_nonce(sender, nonce)
+            .is_some_and(|next| next > nonce)
+        {
+            self.set_next_nonce(sender, nonce);
+        }
         self.update_metrics();
         Some(op.po.clone())
     }
@@ -461,7 +581,7 @@ where
             .filter(|o| {
                 // We want to remove ops that use the throttled entity and are older than THROTTLED_ENTITY_LIVE_BLOCKS behind head, or if we already have kept THROTTLED_ENTITY_MEMPOOL_COUNT ops
                 if o.po.contains_entity(&entity) {
-                    if o.po.sim_block_number + self.config.throttled_entity_live_blocks
+                    if o.inserted_block_number + self.config.throttled_entity_live_blocks
                         < current_block_number
                         || uos_kept == 0
                     {
@@ -516,11 +636,14 @@ where
     pub(crate) fn clear(&mut self) {
         self.by_hash.clear();
         self.by_id.clear();
+        self.by_sender.clear();
+        self.next_nonces.clear();
         self.best.clear();
         self.time_to_mine.clear();
         self.mined_at_block_number_by_hash.clear();
         self.mined_hashes_with_block_numbers.clear();
         self.count_by_address.clear();
+        self.count_by_shard.iter_mut().for_each(|c| *c = 0);
         self.pool_size = SizeTracker::default();
         self.cache_size = SizeTracker::default();
         self.update_metrics();
@@ -567,6 +690,8 @@ where
                 .or_default()
                 .increment_entity_count(&e.kind);
         }
+        let shard = self.shard_index(pool_op.uo().sender());
+        self.count_by_shard[shard] += 1;
 
         // create and insert ordered operation
         let hash = pool_op
@@ -575,6 +700,10 @@ where
         self.pool_size += pool_op.mem_size();
         self.by_hash.insert(hash, pool_op.clone());
         self.by_id.insert(pool_op.uo().id(), pool_op.clone());
+        self.by_sender
+            .entry(pool_op.uo().sender())
+            .or_default()
+            .insert(pool_op.uo().nonce(), hash);
         self.best.insert(pool_op);
         self.time_to_mine.insert(hash, TimeToMineInfo::new());
 
@@ -603,6 +732,13 @@ where
         let op = self.by_hash.remove(&hash)?;
         let id = &op.po.uo.id();
         self.by_id.remove(id);
+        if let Entry::Occupied(mut sender_entry) = self.by_sender.entry(id.sender) {
+            sender_entry.get_mut().remove(&id.nonce);
+            if sender_entry.get().is_empty() {
+                sender_entry.remove_entry();
+                self.next_nonces.remove(&id.sender);
+            }
+        }
         self.best.remove(&op);
         self.time_to_mine.remove(&hash);
 
@@ -617,6 +753,8 @@ where
         for e in op.po.entities() {
             self.decrement_address_count(e.address, &e.kind);
         }
+        let shard = self.shard_index(op.uo().sender());
+        self.count_by_shard[shard] -= 1;
 
         self.pool_size -= op.mem_size();
         Some(op.po.clone())
@@ -631,6 +769,16 @@ where
         }
     }
 
+    // Matches the sender sharding used by `best_operations`
+    fn shard_index(&self, sender: Address) -> usize {
+        let num_shards = self.count_by_shard.len();
+        if num_shards == 1 {
+            return 0;
+        }
+        let sender_num = U256::from_be_bytes(sender.into_word().into());
+        (sender_num % U256::from(num_shards)).to::<usize>()
+    }
+
     fn next_submission_id(&mut self) -> u64 {
         let id = self.submission_id;
         self.submission_id += 1;
@@ -657,6 +805,88 @@ where
             .num_ops_in_cache
             .set(self.mined_hashes_with_block_numbers.len() as f64);
         self.metrics.cache_size_bytes.set(self.cache_size.0 as f64);
+
+        for (metrics, count) in self.shard_metrics.iter().zip(&self.count_by_shard) {
+            metrics.num_ops_in_shard.set(*count as f64);
+        }
+    }
+
+    /// Age of the operation that has been in the pool the longest
+    pub(crate) fn oldest_operation_age(&self) -> Option<Duration> {
+        self.by_hash
+            .values()
+            .map(|op| op.inserted_at)
+            .min()
+            .map(|inserted_at| inserted_at.elapsed())
+    }
+
+    /// Record all pool metrics, including those too expensive to compute
+    /// on every pool update
+    pub(crate) fn record_metrics(&self) {
+        self.update_metrics();
+        self.metrics.oldest_op_age_secs.set(
+            self.oldest_operation_age()
+                .unwrap_or_default()
+                .as_secs_f64(),
+        );
+    }
+}
+
+/// Iterator over the best operations in a pool, see `PoolInner::best_operations`
+struct BestOperations<'a, D> {
+    pool: &'a PoolInner<D>,
+    /// Operations whose nonce is next on chain, or whose previous nonce has already been
+    /// returned
+    ready: BTreeSet<Arc<OrderedPoolOperation>>,
+}
+
+impl<'a, D> BestOperations<'a, D> {
+    fn new(pool: &'a PoolInner<D>) -> Self {
+        // the lowest nonce of each nonce key, nonces are sorted so a key's lowest comes first.
+        // The lowest nonce is held back behind a gap if the next nonce on chain is known.
+        let ready = pool
+            .by_sender
+            .iter()
+            .flat_map(|(sender, nonces)| {
+                let mut prev_key = None;
+                nonces.iter().filter_map(move |(nonce, hash)| {
+                    let key = nonce >> 64;
+                    if prev_key == Some(key) {
+                        return None;
+                    }
+                    prev_key = Some(key);
+                    if pool
+                        .next_nonce(*sender, *nonce)
+                        .is_some_and(|next| next != *nonce)
+                    {
+                        return None;
+                    }
+                    pool.by_hash.get(hash).cloned()
+                })
+            })
+            .collect();
+        Self { pool, ready }
+    }
+}
+
+impl<D> Iterator for BestOperations<'_, D>
+where
+    D: DAGasOracleSync,
+{
+    type Item = Arc<PoolOperation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let op = self.ready.pop_first()?;
+            // an ineligible operation holds back the operations after it
+            if !op.eligible() {
+                continue;
+            }
+            if let Some(next) = self.pool.next_nonce_operation(op.uo()) {
+                self.ready.insert(next);
+            }
+            return Some(op.po.clone());
+        }
     }
 }
 
@@ -667,14 +897,18 @@ struct OrderedPoolOperation {
     po: Arc<PoolOperation>,
     submission_id: u64,
     eligible: RwLock<bool>,
+    inserted_at: Instant,
+    inserted_block_number: u64,
 }
 
 impl OrderedPoolOperation {
     fn new(po: Arc<PoolOperation>, submission_id: u64, eligible: bool) -> Self {
         Self {
+            inserted_block_number: po.sim_block_number,
             po,
             submission_id,
             eligible: RwLock::new(eligible),
+            inserted_at: Instant::now(),
         }
     }
 
@@ -761,6 +995,15 @@ struct PoolMetrics {
     time_to_mine: Histogram,
     #[metric(describe = "the duration distribution of a blocked mined.")]
     blocks_to_mine: Histogram,
+    #[metric(describe = "the age in seconds of the oldest op in mempool.")]
+    oldest_op_age_secs: Gauge,
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "op_pool")]
+struct PoolShardMetrics {
+    #[metric(describe = "the number of ops in a sender shard of mempool.")]
+    num_ops_in_shard: Gauge,
 }
 
 #[cfg(test)]
@@ -815,6 +1058,119 @@ mod tests {
         assert_eq!(pool.get_operation_by_id(&bad_id), None);
     }
 
+    #[test]
+    fn test_get_by_sender() {
+        let mut pool = pool();
+        let sender = Address::random();
+        let hash_2 = pool.add_operation(create_op(sender, 2, 3), 0).unwrap();
+        let hash_0 = pool.add_operation(create_op(sender, 0, 1), 0).unwrap();
+        let hash_1 = pool.add_operation(create_op(sender, 1, 2), 0).unwrap();
+        pool.add_operation(create_op(Address::random(), 0, 1), 0)
+            .unwrap();
+
+        let ops = pool.get_operations_by_sender(sender);
+        assert_eq!(
+            ops.iter().map(|op| op.hash).collect::<Vec<_>>(),
+            vec![hash_0, hash_1, hash_2]
+        );
+        assert_eq!(
+            ops.iter().map(|op| op.nonce).collect::<Vec<_>>(),
+            vec![U256::from(0), U256::from(1), U256::from(2)]
+        );
+        assert_eq!(ops[2].max_fee_per_gas, 3);
+
+        pool.remove_operation_by_hash(hash_1);
+        let ops = pool.get_operations_by_sender(sender);
+        assert_eq!(
+            ops.iter().map(|op| op.hash).collect::<Vec<_>>(),
+            vec![hash_0, hash_2]
+        );
+
+        pool.remove_operation_by_hash(hash_0);
+        pool.remove_operation_by_hash(hash_2);
+        assert!(pool.get_operations_by_sender(sender).is_empty());
+        assert!(!pool.by_sender.contains_key(&sender));
+    }
+
+    #[test]
+    fn test_best_operations_nonce_order() {
+        let mut pool = pool();
+        let sender = Address::random();
+        let hash_1 = pool.add_operation(create_op(sender, 1, 3), 0).unwrap();
+        let hash_0 = pool.add_operation(create_op(sender, 0, 1), 0).unwrap();
+        let hash_3 = pool.add_operation(create_op(sender, 3, 4), 0).unwrap();
+        let hash_other = pool
+            .add_operation(create_op(Address::random(), 0, 2), 0)
+            .unwrap();
+        let mut keyed_op = create_op(sender, 0, 5);
+        let keyed_uo: &mut UserOperation = keyed_op.uo.as_mut();
+        keyed_uo.nonce = U256::from(1) << 64;
+        let hash_keyed = pool.add_operation(keyed_op, 0).unwrap();
+
+        let best_hashes = |pool: &PoolInner<Box<dyn DAGasOracleSync>>| {
+            pool.best_operations()
+                .map(|op| {
+                    op.uo
+                        .hash(pool.config.entry_point, pool.config.chain_spec.id)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // nonce 1 is held back until nonce 0 is returned, nonce 3 until nonce 2 is added
+        pool.set_next_nonce(sender, U256::ZERO);
+        assert_eq!(
+            best_hashes(&pool),
+            vec![hash_keyed, hash_other, hash_0, hash_1]
+        );
+
+        // once nonces 0 and 1 are mined, nonce 3 is the lowest but is still behind the gap
+        for (nonce, hash) in [(0, hash_0), (1, hash_1)] {
+            pool.mine_operation(
+                &MinedOp {
+                    entry_point: pool.config.entry_point,
+                    hash,
+                    sender,
+                    nonce: U256::from(nonce),
+                    actual_gas_cost: U256::ZERO,
+                    paymaster: None,
+                },
+                1,
+            );
+        }
+        assert_eq!(best_hashes(&pool), vec![hash_keyed, hash_other]);
+
+        // nonce 2 fills the gap, nonce 3 follows it
+        let hash_2 = pool.add_operation(create_op(sender, 2, 1), 0).unwrap();
+        assert_eq!(
+            best_hashes(&pool),
+            vec![hash_keyed, hash_other, hash_2, hash_3]
+        );
+
+        // nonce 3 is next on chain once nonce 2 is mined elsewhere
+        pool.remove_operation_by_hash(hash_2);
+        pool.set_next_nonce(sender, U256::from(3));
+        assert_eq!(best_hashes(&pool), vec![hash_keyed, hash_3, hash_other]);
+    }
+
+    #[test]
+    fn test_all_operations_includes_queued() {
+        let mut pool = pool();
+        let sender = Address::random();
+        let hash_0 = pool.add_operation(create_op(sender, 0, 1), 0).unwrap();
+        let hash_2 = pool.add_operation(create_op(sender, 2, 2), 0).unwrap();
+
+        // nonce 2 is queued behind a gap, it isn't eligible for a bundle but is in the pool
+        assert_eq!(pool.best_operations().count(), 1);
+        let all_hashes = pool
+            .all_operations()
+            .map(|op| {
+                op.uo
+                    .hash(pool.config.entry_point, pool.config.chain_spec.id)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(all_hashes, vec![hash_2, hash_0]);
+    }
+
     #[test]
     fn add_multiple_ops() {
         let mut pool = pool();
@@ -841,6 +1197,29 @@ mod tests {
         check_map_entry(pool.best.iter().nth(2), Some(&ops[0]));
     }
 
+    #[test]
+    fn count_by_shard() {
+        let mut conf = conf();
+        conf.num_shards = 2;
+        let mut pool = pool_with_conf(conf);
+        assert_eq!(pool.oldest_operation_age(), None);
+
+        let even = Address::repeat_byte(2);
+        let odd = Address::repeat_byte(3);
+        pool.add_operation(create_op(even, 0, 1), 0).unwrap();
+        pool.add_operation(create_op(even, 1, 1), 0).unwrap();
+        let hash = pool.add_operation(create_op(odd, 0, 1), 0).unwrap();
+        assert_eq!(pool.count_by_shard, vec![2, 1]);
+        assert!(pool.oldest_operation_age().is_some());
+
+        pool.remove_operation_by_hash(hash);
+        assert_eq!(pool.count_by_shard, vec![2, 0]);
+
+        pool.clear();
+        assert_eq!(pool.count_by_shard, vec![0, 0]);
+        assert_eq!(pool.oldest_operation_age(), None);
+    }
+
     #[test]
     fn best_ties() {
         let mut pool = pool();
@@ -1393,6 +1772,7 @@ mod tests {
         PoolInnerConfig {
             chain_spec: ChainSpec::default(),
             entry_point: Address::random(),
+            num_shards: 1,
             min_replacement_fee_increase_percentage: 10,
             max_size_of_pool_bytes: 20 * mem_size_of_ordered_pool_op(),
             throttled_entity_mempool_count: 4,
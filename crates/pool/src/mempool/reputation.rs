@@ -18,8 +18,8 @@ use std::{
 
 use alloy_primitives::Address;
 use parking_lot::RwLock;
-use rundler_types::pool::{Reputation, ReputationStatus};
-use tokio::time::interval;
+use rundler_types::pool::{Reputation, ReputationChange, ReputationStatus};
+use tokio::{sync::broadcast, time::interval};
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct ReputationParams {
@@ -83,8 +83,12 @@ impl ReputationParams {
     }
 }
 
+/// Capacity of the reputation change broadcast channel
+const REPUTATION_CHANGE_CHANNEL_CAPACITY: usize = 1024;
+
 pub(crate) struct AddressReputation {
     state: RwLock<AddressReputationInner>,
+    change_sender: broadcast::Sender<ReputationChange>,
 }
 
 impl AddressReputation {
@@ -99,16 +103,24 @@ impl AddressReputation {
                     .with_blocklist(blocklist)
                     .with_allowlist(allowlist),
             ),
+            change_sender: broadcast::channel(REPUTATION_CHANGE_CHANNEL_CAPACITY).0,
         }
     }
 
+    /// Subscribe to reputation status changes.
+    ///
+    /// Only changes caused by updates made after subscribing are received.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<ReputationChange> {
+        self.change_sender.subscribe()
+    }
+
     pub(crate) async fn run(&self) {
         let mut tick = interval(Duration::from_secs(
             self.state.read().params.decay_interval_secs,
         ));
         loop {
             tick.tick().await;
-            self.state.write().update();
+            self.update_all(|state| state.update());
         }
     }
 
@@ -117,15 +129,15 @@ impl AddressReputation {
     }
 
     pub(crate) fn add_seen(&self, address: Address) {
-        self.state.write().add_seen(address);
+        self.update_address(address, |state| state.add_seen(address));
     }
 
     pub(crate) fn handle_urep_030_penalty(&self, address: Address) {
-        self.state.write().handle_urep_030_penalty(address);
+        self.update_address(address, |state| state.handle_urep_030_penalty(address));
     }
 
     pub(crate) fn handle_srep_050_penalty(&self, address: Address) {
-        self.state.write().handle_srep_050_penalty(address);
+        self.update_address(address, |state| state.handle_srep_050_penalty(address));
     }
 
     pub(crate) fn dump_reputation(&self) -> Vec<Reputation> {
@@ -133,17 +145,17 @@ impl AddressReputation {
     }
 
     pub(crate) fn add_included(&self, address: Address) {
-        self.state.write().add_included(address);
+        self.update_address(address, |state| state.add_included(address));
     }
 
     pub(crate) fn remove_included(&self, address: Address) {
-        self.state.write().remove_included(address);
+        self.update_address(address, |state| state.remove_included(address));
     }
 
     pub(crate) fn set_reputation(&self, address: Address, ops_seen: u64, ops_included: u64) {
-        self.state
-            .write()
-            .set_reputation(address, ops_seen, ops_included);
+        self.update_address(address, |state| {
+            state.set_reputation(address, ops_seen, ops_included)
+        });
     }
 
     pub(crate) fn get_ops_allowed(&self, address: Address) -> u64 {
@@ -151,11 +163,63 @@ impl AddressReputation {
     }
 
     pub(crate) fn clear(&self) {
-        self.state.write().clear();
+        self.update_all(|state| state.clear());
     }
 
     pub(crate) fn set_tracking(&self, tracking_enabled: bool) {
-        self.state.write().set_tracking(tracking_enabled);
+        self.update_all(|state| state.set_tracking(tracking_enabled));
+    }
+
+    // Applies an update affecting a single address and emits a change if its status moved
+    fn update_address(&self, address: Address, f: impl FnOnce(&mut AddressReputationInner)) {
+        let change = {
+            let mut state = self.state.write();
+            let old_status = state.status(address);
+            f(&mut state);
+            let new_status = state.status(address);
+            (old_status != new_status).then_some(ReputationChange {
+                address,
+                old_status,
+                new_status,
+            })
+        };
+
+        if let Some(change) = change {
+            self.send_change(change);
+        }
+    }
+
+    // Applies an update that may affect every tracked address and emits all resulting changes
+    fn update_all(&self, f: impl FnOnce(&mut AddressReputationInner)) {
+        let changes = {
+            let mut state = self.state.write();
+            let old_statuses = state
+                .counts
+                .keys()
+                .map(|address| (*address, state.status(*address)))
+                .collect::<Vec<_>>();
+            f(&mut state);
+            old_statuses
+                .into_iter()
+                .filter_map(|(address, old_status)| {
+                    let new_status = state.status(address);
+                    (old_status != new_status).then_some(ReputationChange {
+                        address,
+                        old_status,
+                        new_status,
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for change in changes {
+            self.send_change(change);
+        }
+    }
+
+    fn send_change(&self, change: ReputationChange) {
+        // An error only means there are currently no subscribers
+        let _ = self.change_sender.send(change);
     }
 }
 
@@ -467,4 +531,40 @@ mod tests {
             assert!(addrs.contains(&rep.address));
         }
     }
+
+    #[test]
+    fn reputation_change_events() {
+        let addr = Address::random();
+        let reputation = AddressReputation::new(
+            ReputationParams::bundler_default(),
+            HashSet::new(),
+            HashSet::new(),
+        );
+        let mut rx = reputation.subscribe();
+
+        // no change in status, no event
+        reputation.add_seen(addr);
+        assert!(rx.try_recv().is_err());
+
+        reputation.set_reputation(addr, 1000, 0);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ReputationChange {
+                address: addr,
+                old_status: ReputationStatus::Ok,
+                new_status: ReputationStatus::Banned,
+            }
+        );
+
+        reputation.clear();
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ReputationChange {
+                address: addr,
+                old_status: ReputationStatus::Banned,
+                new_status: ReputationStatus::Ok,
+            }
+        );
+        assert!(rx.try_recv().is_err());
+    }
 }
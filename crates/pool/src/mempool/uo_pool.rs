@@ -13,20 +13,24 @@
 
 use std::{collections::HashSet, sync::Arc, time::Instant};
 
-use alloy_primitives::{utils::format_units, Address, Bytes, B256, U256};
+use alloy_primitives::{utils::format_units, Address, Bytes, B256, U192, U256};
 use anyhow::Context;
-use futures::TryFutureExt;
+use futures::{future, TryFutureExt};
 use itertools::Itertools;
 use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 use parking_lot::RwLock;
 use rundler_provider::{
-    DAGasOracleSync, EvmProvider, ProvidersWithEntryPointT, SimulationProvider, StateOverride,
+    DAGasOracleSync, EntryPoint, EvmProvider, ProvidersWithEntryPointT, SimulationProvider,
+    StateOverride,
+};
+use rundler_sim::{
+    CallGasEstimator, GasEstimationError, Prechecker, SimulationError, Simulator, ViolationError,
 };
-use rundler_sim::{Prechecker, Simulator};
 use rundler_types::{
     pool::{
-        MempoolError, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+        MempoolError, PaymasterMetadata, PoolOperation, PooledOp, Reputation, ReputationChange,
+        ReputationStatus, SimulationViolation, StakeStatus, ViolationCategory,
     },
     Entity, EntityUpdate, EntityUpdateType, EntryPointVersion, GasFees, UserOperation,
     UserOperationId, UserOperationVariant,
@@ -34,11 +38,11 @@ use rundler_types::{
 use rundler_utils::emit::WithEntryPoint;
 use tokio::sync::broadcast;
 use tonic::async_trait;
-use tracing::info;
+use tracing::{error, info, warn};
 
 use super::{
-    paymaster::PaymasterTracker, pool::PoolInner, reputation::AddressReputation, Mempool,
-    MempoolResult, OperationOrigin, PoolConfig,
+    paymaster::PaymasterTracker, pool::PoolInner, reputation::AddressReputation, BundleConstraints,
+    Mempool, MempoolResult, OperationOrigin, PoolConfig, PoolSnapshot, SnapshotOperation,
 };
 use crate::{
     chain::ChainUpdate,
@@ -65,6 +69,9 @@ pub(crate) struct UoPool<UP: UoPoolProvidersT, EP: ProvidersWithEntryPointT> {
 struct UoPoolState<D> {
     pool: PoolInner<D>,
     throttled_ops: HashSet<B256>,
+    // Operations simulated against blocks that were orphaned by a reorg, awaiting
+    // re-validation
+    reorged_ops: HashSet<B256>,
     block_number: u64,
     block_hash: B256,
     gas_fees: GasFees,
@@ -93,6 +100,7 @@ where
                     event_sender.clone(),
                 ),
                 throttled_ops: HashSet::new(),
+                reorged_ops: HashSet::new(),
                 block_number: 0,
                 block_hash: B256::ZERO,
                 gas_fees: GasFees::default(),
@@ -109,6 +117,46 @@ where
         }
     }
 
+    /// Seed the pool with operations, e.g. from a snapshot of another instance during a
+    /// warm start.
+    ///
+    /// If `revalidate` is set, each operation goes through the same prechecks and simulation
+    /// as a newly submitted operation. Otherwise operations are inserted with their recorded
+    /// simulation results. Operations for another entry point, or that fail to be added, are
+    /// logged and dropped.
+    pub(crate) async fn with_operations(self, ops: Vec<PoolOperation>, revalidate: bool) -> Self {
+        for op in ops {
+            let op_hash = op
+                .uo
+                .hash(self.config.entry_point, self.config.chain_spec.id);
+            if op.entry_point != self.config.entry_point {
+                warn!(
+                    "Dropping op {op_hash:?} for entry point {:?} while loading pool",
+                    op.entry_point
+                );
+                continue;
+            }
+
+            let result = if revalidate {
+                self.add_operation(OperationOrigin::Local, op.uo)
+                    .await
+                    .map(|_| ())
+            } else {
+                self.load_operation(op).await
+            };
+            if let Err(error) = result {
+                warn!("Dropping op {op_hash:?} while loading pool: {error}");
+            }
+        }
+        self
+    }
+
+    async fn load_operation(&self, op: PoolOperation) -> MempoolResult<()> {
+        // required pre-verification gas is unknown without a precheck, trust the recorded op
+        self.state.write().pool.add_operation(op.clone(), 0)?;
+        self.paymaster.add_or_update_balance(&op).await
+    }
+
     fn emit(&self, event: OpPoolEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: self.config.entry_point,
@@ -152,6 +200,63 @@ where
         self.ep_specific_metrics.removed_entities.increment(1);
     }
 
+    /// Remove the operations of entities as soon as they become banned.
+    ///
+    /// Runs until the reputation change channel closes.
+    pub(crate) async fn run_reputation_eviction(&self) {
+        let mut changes = self.subscribe_reputation_changes();
+        loop {
+            match changes.recv().await {
+                Ok(change) => {
+                    if change.new_status == ReputationStatus::Banned {
+                        self.remove_banned_address(change.address);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(c)) => {
+                    error!("reputation change receiver lagged {c} changes, rescanning for bans");
+                    self.remove_banned_entities();
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("reputation change receiver closed, ending eviction");
+                    break;
+                }
+            }
+        }
+    }
+
+    // Remove the operations of all banned entities, used to catch up on missed changes
+    fn remove_banned_entities(&self) {
+        let entities = self
+            .state
+            .read()
+            .pool
+            .operations()
+            .flat_map(|(_, op)| op.entities().collect::<Vec<_>>())
+            .collect::<HashSet<_>>();
+
+        for entity in entities {
+            if self.reputation.status(entity.address) == ReputationStatus::Banned {
+                self.remove_entity(entity);
+            }
+        }
+    }
+
+    fn remove_banned_address(&self, address: Address) {
+        // the same address may be used as different entity types across operations
+        let entities = self
+            .state
+            .read()
+            .pool
+            .operations()
+            .flat_map(|(_, op)| op.entities().collect::<Vec<_>>())
+            .filter(|entity| entity.address == address)
+            .collect::<HashSet<_>>();
+
+        for entity in entities {
+            self.remove_entity(entity);
+        }
+    }
+
     async fn check_call_gas_limit_efficiency(
         &self,
         op: UserOperationVariant,
@@ -213,6 +318,222 @@ where
 
         Ok(())
     }
+
+    // The sender's next nonce on chain for the op's nonce key, used to hold back ops behind a
+    // nonce gap. Fails open, an unknown nonce doesn't hold back any ops.
+    async fn get_next_nonce(
+        &self,
+        op: &UserOperationVariant,
+        block_hash: B256,
+    ) -> MempoolResult<Option<U256>> {
+        match self
+            .ep_providers
+            .entry_point()
+            .get_nonce(
+                op.sender(),
+                U192::from(op.nonce() >> 64),
+                Some(block_hash.into()),
+            )
+            .await
+        {
+            Ok(nonce) => Ok(Some(nonce)),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to get next nonce of sender {:?}: {e:?}",
+                    op.sender()
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    // Checked if any of the mempools the op matched rejects reverting calls. The op is run
+    // through the entry point's `simulateHandleOp`, so its call executes as it would in a
+    // bundle, after validation and with its state changes, e.g. a deployed sender.
+    async fn check_call_phase(
+        &self,
+        op: UserOperationVariant,
+        mempools: &[B256],
+        block_hash: B256,
+    ) -> MempoolResult<()> {
+        let check_enabled = mempools.iter().any(|id| {
+            self.config
+                .mempool_channel_configs
+                .get(id)
+                .is_some_and(|config| config.call_phase_revert_check())
+        });
+        if !check_enabled || op.call_data().is_empty() {
+            return Ok(());
+        }
+
+        let result = self
+            .pool_providers
+            .call_gas_estimator()
+            .simulate_handle_op_with_result(op.into(), block_hash, StateOverride::default())
+            .await;
+        match result {
+            Ok(()) => Ok(()),
+            Err(GasEstimationError::RevertInCallWithMessage(reason)) => {
+                Err(MempoolError::CallPhaseReverted { reason })
+            }
+            Err(GasEstimationError::RevertInCallWithBytes(revert_data)) => {
+                Err(MempoolError::CallPhaseReverted {
+                    reason: revert_data.to_string(),
+                })
+            }
+            Err(e) => Err(MempoolError::Other(
+                anyhow::Error::new(e).context("should check the call phase of the operation"),
+            )),
+        }
+    }
+
+    // Mark the operations that were simulated against blocks orphaned by a reorg for
+    // re-validation, as their expected code hash and storage may be stale.
+    fn mark_reorged_operations(&self, update: &ChainUpdate) {
+        let mut state = self.state.write();
+        // The first block replaced by the reorg
+        let fork_block_number = (state.block_number + 1).saturating_sub(update.reorg_depth);
+        let reorged = state
+            .pool
+            .operations()
+            .filter(|(_, op)| {
+                update.reorg_larger_than_history || op.sim_block_number >= fork_block_number
+            })
+            .map(|(hash, _)| hash)
+            .collect::<Vec<_>>();
+        if !reorged.is_empty() {
+            tracing::info!(
+                "Reorg of depth {} orphaned block {fork_block_number}, marking {} op(s) for re-validation",
+                update.reorg_depth,
+                reorged.len()
+            );
+        }
+        self.ep_specific_metrics
+            .reorged_operations
+            .increment(reorged.len() as u64);
+        state.reorged_ops.extend(reorged);
+    }
+
+    async fn revalidate_operations(&self, block_hash: B256, block_number: u64) {
+        // Operations simulated against an orphaned block are re-validated first, up to
+        // their own limit and in addition to the configured number of operations per block.
+        // Any others are re-validated on later blocks.
+        let ops = {
+            let mut state = self.state.write();
+            let state = &mut *state;
+            state
+                .reorged_ops
+                .retain(|hash| state.pool.get_operation_by_hash(*hash).is_some());
+            let reorged = state
+                .reorged_ops
+                .iter()
+                .filter_map(|hash| state.pool.get_operation_by_hash(*hash))
+                .take(self.config.reorg_revalidation_max_ops_per_block)
+                .collect::<Vec<_>>();
+
+            // Prioritize the operations that are most likely to be bundled next, skipping
+            // any that were simulated at this block already.
+            let best = state
+                .pool
+                .all_operations()
+                .filter(|op| {
+                    op.sim_block_number < block_number
+                        && (state.reorged_ops.is_empty()
+                            || !state.reorged_ops.contains(
+                                &op.uo
+                                    .hash(self.config.entry_point, self.config.chain_spec.id),
+                            ))
+                })
+                .take(self.config.revalidation_max_ops_per_block);
+            reorged.into_iter().chain(best).collect::<Vec<_>>()
+        };
+        if ops.is_empty() {
+            return;
+        }
+
+        // Bound the time chain update processing is held up, simulations still running
+        // at the deadline are abandoned and their ops are checked again on a later block
+        let start = Instant::now();
+        let deadline = tokio::time::Instant::now() + self.config.revalidation_timeout;
+        let results = future::join_all(ops.iter().map(|op| {
+            tokio::time::timeout_at(
+                deadline,
+                self.pool_providers.simulator().simulate_validation(
+                    op.uo.clone().into(),
+                    block_hash,
+                    Some(op.expected_code_hash),
+                ),
+            )
+        }))
+        .await;
+
+        let mut removed = vec![];
+        let mut timed_out = 0;
+        {
+            let mut state = self.state.write();
+            for (op, result) in ops.iter().zip(results) {
+                let op_hash = op
+                    .uo
+                    .hash(self.config.entry_point, self.config.chain_spec.id);
+                match result {
+                    Ok(Ok(_)) => {
+                        state.reorged_ops.remove(&op_hash);
+                        state.pool.set_sim_block(op_hash, block_hash, block_number);
+                    }
+                    Ok(Err(SimulationError {
+                        violation_error: ViolationError::Violations(violations),
+                        ..
+                    })) if !violations.iter().all(is_transient_revalidation_failure) => {
+                        if let Some(op) = state.pool.remove_operation_by_hash(op_hash) {
+                            self.paymaster.remove_operation(&op.uo.id());
+                            state.throttled_ops.remove(&op_hash);
+                            state.reorged_ops.remove(&op_hash);
+                            removed.push((op_hash, violations));
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        // Fail open, the op will be checked again on a later block
+                        tracing::warn!("Failed to re-validate op {op_hash:?}: {e:?}");
+                    }
+                    Err(_) => timed_out += 1,
+                }
+            }
+        }
+        if timed_out > 0 {
+            tracing::warn!(
+                "Re-validation of {timed_out} op(s) at block {block_number} timed out after {:?}",
+                self.config.revalidation_timeout
+            );
+        }
+
+        let removed_count = removed.len();
+        for (op_hash, violations) in removed {
+            self.emit(OpPoolEvent::RemovedOp {
+                op_hash,
+                reason: OpRemovalReason::RevalidationFailed {
+                    block_number,
+                    violations,
+                },
+            });
+        }
+
+        tracing::debug!(
+            "Re-validated {} op(s) at block {block_number}, removed {removed_count}",
+            ops.len()
+        );
+        self.ep_specific_metrics
+            .revalidated_operations
+            .increment(ops.len() as u64);
+        self.ep_specific_metrics
+            .revalidation_removed_operations
+            .increment(removed_count as u64);
+        self.ep_specific_metrics
+            .removed_operations
+            .increment(removed_count as u64);
+        self.ep_specific_metrics
+            .revalidation_time
+            .record(start.elapsed().as_millis() as f64);
+    }
 }
 
 #[async_trait]
@@ -336,6 +657,10 @@ where
                 update.latest_block_hash,
             );
         }
+        if update.reorg_depth > 0 && self.config.reorg_revalidation_max_ops_per_block > 0 {
+            self.mark_reorged_operations(update);
+        }
+
         let ops_seen: f64 = (mined_op_count as isize - unmined_op_count as isize) as f64;
         self.ep_specific_metrics.ops_seen.increment(ops_seen);
         self.ep_specific_metrics
@@ -412,10 +737,7 @@ where
             // Remove throttled ops that are too old
             let mut to_remove = HashSet::new();
             for hash in state.throttled_ops.iter() {
-                let block_seen = state
-                    .pool
-                    .get_operation_by_hash(*hash)
-                    .map(|po| po.sim_block_number);
+                let block_seen = state.pool.get_inserted_block_number(*hash);
                 if let Some(block) = block_seen {
                     if update.latest_block_number - block > self.config.throttled_entity_live_blocks
                     {
@@ -455,6 +777,9 @@ where
         self.ep_specific_metrics
             .maintenance_time
             .record(maintenance_time.as_micros() as f64);
+
+        self.revalidate_operations(update.latest_block_hash, update.latest_block_number)
+            .await;
     }
 
     fn entry_point(&self) -> Address {
@@ -549,10 +874,23 @@ where
             .simulate_validation(versioned_op, block_hash, None)
             .map_err(Into::into);
         let call_gas_check_future = self.check_call_gas_limit_efficiency(op.clone(), block_hash);
-        let (sim_result, _) = tokio::try_join!(sim_fut, call_gas_check_future)?;
+        let next_nonce_future = self.get_next_nonce(&op, block_hash);
+        let (sim_result, _, next_nonce) =
+            tokio::try_join!(sim_fut, call_gas_check_future, next_nonce_future)?;
+
+        // Ops validated without tracing haven't had the ERC-7562 rules enforced
+        if sim_result.tracing_skipped && !self.config.sim_settings.untraced_ops_allowed {
+            return Err(MempoolError::Other(anyhow::anyhow!(
+                "operation was validated without tracing, which this pool does not accept"
+            )));
+        }
 
-        // No aggregators supported for now
-        if let Some(agg) = &sim_result.aggregator {
+        // Only aggregators in the registry are supported
+        if let Some(agg) = sim_result
+            .aggregators
+            .iter()
+            .find(|agg| !self.config.supported_aggregators.contains(&agg.address))
+        {
             return Err(MempoolError::UnsupportedAggregator(agg.address));
         }
 
@@ -562,6 +900,10 @@ where
             .pool
             .check_associated_storage(&sim_result.associated_addresses, &op)?;
 
+        // Check the call phase against the mempools the op matched
+        self.check_call_phase(op.clone(), &sim_result.mempools, block_hash)
+            .await?;
+
         // Check pre op gas limit efficiency
         let pre_op_gas_efficiency = sim_result.pre_op_gas as f32 / op.pre_op_gas_limit() as f32;
         if pre_op_gas_efficiency < self.config.gas_limit_efficiency_reject_threshold {
@@ -575,7 +917,7 @@ where
         let pool_op = PoolOperation {
             uo: op,
             entry_point: self.config.entry_point,
-            aggregator: None,
+            aggregator: sim_result.aggregator_address(),
             valid_time_range,
             expected_code_hash: sim_result.code_hash,
             sim_block_hash: block_hash,
@@ -619,6 +961,9 @@ where
             let hash = state
                 .pool
                 .add_operation(pool_op.clone(), precheck_ret.required_pre_verification_gas)?;
+            if let Some(next_nonce) = next_nonce {
+                state.pool.set_next_nonce(pool_op.uo.sender(), next_nonce);
+            }
 
             if throttled {
                 state.throttled_ops.insert(hash);
@@ -723,6 +1068,20 @@ where
         Ok(Some(hash))
     }
 
+    fn remove_op_by_hash(&self, hash: B256) -> bool {
+        let Some(op) = self.state.write().pool.remove_operation_by_hash(hash) else {
+            return false;
+        };
+        self.paymaster.remove_operation(&op.uo.id());
+
+        self.emit(OpPoolEvent::RemovedOp {
+            op_hash: hash,
+            reason: OpRemovalReason::Requested,
+        });
+        self.ep_specific_metrics.removed_operations.increment(1);
+        true
+    }
+
     fn update_entity(&self, update: EntityUpdate) {
         let entity = update.entity;
         match update.update_type {
@@ -741,7 +1100,7 @@ where
 
     fn best_operations(
         &self,
-        max: usize,
+        constraints: BundleConstraints,
         shard_index: u64,
     ) -> MempoolResult<Vec<Arc<PoolOperation>>> {
         if shard_index >= self.config.num_shards {
@@ -753,37 +1112,68 @@ where
         let ordered_ops = state.pool.best_operations();
         // keep track of senders to avoid sending multiple ops from the same sender
         let mut senders = HashSet::<Address>::new();
+        // senders with an op skipped for gas, their later ops would have a nonce gap
+        let mut skipped_senders = HashSet::<Address>::new();
+        let mut gas_left = constraints.max_gas;
 
         Ok(ordered_ops
             .into_iter()
             .filter(|op| {
-                let sender_num = U256::from_be_bytes(op.uo.sender().into_word().into());
+                let sender = op.uo.sender();
+                let sender_num = U256::from_be_bytes(sender.into_word().into());
 
                 // short-circuit the mod if there is only 1 shard
-                ((self.config.num_shards == 1) ||
-                (sender_num
-                        % U256::from(self.config.num_shards)
-                        == U256::from(shard_index))) &&
-                // filter out ops from unstaked senders we've already seen
-                if !op.account_is_staked {
-                    senders.insert(op.uo.sender())
-                } else {
-                    true
+                if self.config.num_shards != 1
+                    && sender_num % U256::from(self.config.num_shards) != U256::from(shard_index)
+                {
+                    return false;
+                }
+
+                // filter out ops from senders we've already seen, unstaked senders
+                // are always limited to a single op
+                let limit_sender = constraints.one_per_sender || !op.account_is_staked;
+                if skipped_senders.contains(&sender) || (limit_sender && senders.contains(&sender))
+                {
+                    return false;
                 }
+
+                if let Some(gas_left) = gas_left.as_mut() {
+                    let gas = op.uo.gas_limit(&self.config.chain_spec, None);
+                    if gas > *gas_left {
+                        skipped_senders.insert(sender);
+                        return false;
+                    }
+                    *gas_left -= gas;
+                }
+
+                if limit_sender {
+                    senders.insert(sender);
+                }
+                true
             })
-            .take(max)
+            .take(constraints.max_ops)
             .map(Into::into)
             .collect())
     }
 
     fn all_operations(&self, max: usize) -> Vec<Arc<PoolOperation>> {
-        self.state.read().pool.best_operations().take(max).collect()
+        self.state.read().pool.all_operations().take(max).collect()
     }
 
     fn get_user_operation_by_hash(&self, hash: B256) -> Option<Arc<PoolOperation>> {
         self.state.read().pool.get_operation_by_hash(hash)
     }
 
+    fn get_operations_by_sender(&self, sender: Address) -> Vec<PooledOp> {
+        self.state.read().pool.get_operations_by_sender(sender)
+    }
+
+    fn supported_aggregators(&self) -> Vec<Address> {
+        let mut aggregators: Vec<_> = self.config.supported_aggregators.iter().copied().collect();
+        aggregators.sort();
+        aggregators
+    }
+
     // DEBUG METHODS
 
     fn clear_state(&self, clear_mempool: bool, clear_paymaster: bool, clear_reputation: bool) {
@@ -804,6 +1194,18 @@ where
         self.reputation.dump_reputation()
     }
 
+    fn snapshot(&self) -> PoolSnapshot {
+        // only clone the pointers while holding the lock
+        let operations = self.state.read().pool.operations().collect::<Vec<_>>();
+        PoolSnapshot {
+            entry_point: self.config.entry_point,
+            operations: operations
+                .into_iter()
+                .map(|(hash, op)| SnapshotOperation::new(hash, op))
+                .collect(),
+        }
+    }
+
     fn dump_paymaster_balances(&self) -> Vec<PaymasterMetadata> {
         self.paymaster.dump_paymaster_metadata()
     }
@@ -817,6 +1219,10 @@ where
             .set_reputation(address, ops_seen, ops_included)
     }
 
+    fn subscribe_reputation_changes(&self) -> broadcast::Receiver<ReputationChange> {
+        self.reputation.subscribe()
+    }
+
     async fn get_stake_status(&self, address: Address) -> MempoolResult<StakeStatus> {
         self.paymaster.get_stake_status(address).await
     }
@@ -829,6 +1235,10 @@ where
         self.paymaster.set_tracking(paymaster);
         self.reputation.set_tracking(reputation);
     }
+
+    fn record_metrics(&self) {
+        self.state.read().pool.record_metrics();
+    }
 }
 
 // Type erasure for UoPool providers
@@ -836,35 +1246,42 @@ pub(crate) trait UoPoolProvidersT: Send + Sync {
     type UO: UserOperation + From<UserOperationVariant>;
     type Prechecker: Prechecker<UO = Self::UO>;
     type Simulator: Simulator<UO = Self::UO>;
+    type CallGasEstimator: CallGasEstimator<UO = Self::UO>;
 
     fn prechecker(&self) -> &Self::Prechecker;
 
     fn simulator(&self) -> &Self::Simulator;
+
+    fn call_gas_estimator(&self) -> &Self::CallGasEstimator;
 }
 
-pub(crate) struct UoPoolProviders<S, P> {
+pub(crate) struct UoPoolProviders<S, P, C> {
     simulator: S,
     prechecker: P,
+    call_gas_estimator: C,
 }
 
-impl<S, P> UoPoolProviders<S, P> {
-    pub(crate) fn new(simulator: S, prechecker: P) -> Self {
+impl<S, P, C> UoPoolProviders<S, P, C> {
+    pub(crate) fn new(simulator: S, prechecker: P, call_gas_estimator: C) -> Self {
         Self {
             simulator,
             prechecker,
+            call_gas_estimator,
         }
     }
 }
 
-impl<S, P> UoPoolProvidersT for UoPoolProviders<S, P>
+impl<S, P, C> UoPoolProvidersT for UoPoolProviders<S, P, C>
 where
     S: Simulator,
     S::UO: UserOperation + From<UserOperationVariant>,
     P: Prechecker<UO = S::UO>,
+    C: CallGasEstimator<UO = S::UO>,
 {
     type UO = S::UO;
     type Prechecker = P;
     type Simulator = S;
+    type CallGasEstimator = C;
 
     fn prechecker(&self) -> &Self::Prechecker {
         &self.prechecker
@@ -873,6 +1290,18 @@ where
     fn simulator(&self) -> &Self::Simulator {
         &self.simulator
     }
+
+    fn call_gas_estimator(&self) -> &Self::CallGasEstimator {
+        &self.call_gas_estimator
+    }
+}
+
+// Transient violations, e.g. a timed out simulation, may be caused by the bundler's node
+// rather than the op, so they don't remove it on re-validation. A code hash change is
+// checked against the hash the op was admitted with, so it is final.
+fn is_transient_revalidation_failure(violation: &SimulationViolation) -> bool {
+    violation.category() == ViolationCategory::Transient
+        && !matches!(violation, SimulationViolation::CodeHashChanged(..))
 }
 
 #[derive(Metrics)]
@@ -888,6 +1317,14 @@ struct UoPoolMetricsEPSpecific {
     removed_entities: Counter,
     #[metric(describe = "time to run pool maintenance in µs.")]
     maintenance_time: Histogram,
+    #[metric(describe = "the count of ops re-validated on new blocks.")]
+    revalidated_operations: Counter,
+    #[metric(describe = "the count of ops removed after failing re-validation.")]
+    revalidation_removed_operations: Counter,
+    #[metric(describe = "time to re-validate ops on a new block in ms.")]
+    revalidation_time: Histogram,
+    #[metric(describe = "the count of ops marked for re-validation after a reorg.")]
+    reorged_operations: Counter,
 }
 
 #[derive(Metrics)]
@@ -903,17 +1340,17 @@ struct UoPoolMetrics {
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, vec};
+    use std::{collections::HashMap, time::Duration, vec};
 
     use alloy_primitives::{uint, Bytes};
     use mockall::Sequence;
     use rundler_provider::{
-        DepositInfo, ExecutionResult, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider,
-        ProvidersWithEntryPoint,
+        AggregatorSimOut, DepositInfo, ExecutionResult, MockDAGasOracleSync, MockEntryPointV0_6,
+        MockEvmProvider, ProvidersWithEntryPoint,
     };
     use rundler_sim::{
-        MockPrechecker, MockSimulator, PrecheckError, PrecheckReturn, PrecheckSettings,
-        SimulationError, SimulationResult, SimulationSettings, ViolationError,
+        MockCallGasEstimator, MockPrechecker, MockSimulator, PrecheckError, PrecheckReturn,
+        PrecheckSettings, SimulationError, SimulationResult, SimulationSettings, ViolationError,
     };
     use rundler_types::{
         chain::ChainSpec,
@@ -944,9 +1381,105 @@ mod tests {
             .add_operation(OperationOrigin::Local, op.op)
             .await
             .unwrap();
-        check_ops(pool.best_operations(1, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            uos,
+        );
         pool.remove_operations(&[hash]);
-        assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
+        assert_eq!(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn with_operations() {
+        // an op that would now fail simulation
+        let op = create_op_with_errors(
+            Address::random(),
+            0,
+            0,
+            None,
+            Some(SimulationViolation::DidNotRevert),
+            false,
+        );
+
+        for revalidate in [false, true] {
+            let pool = create_pool(vec![op.clone()]);
+            let pool_op = PoolOperation {
+                uo: op.op.clone(),
+                entry_point: pool.config.entry_point,
+                aggregator: None,
+                valid_time_range: ValidTimeRange::default(),
+                expected_code_hash: B256::ZERO,
+                sim_block_hash: B256::ZERO,
+                sim_block_number: 0,
+                account_is_staked: false,
+                entity_infos: EntityInfos::default(),
+                da_gas_data: DAGasUOData::Empty,
+            };
+            let other_ep_op = PoolOperation {
+                entry_point: Address::random(),
+                ..pool_op.clone()
+            };
+
+            let pool = pool
+                .with_operations(vec![pool_op, other_ep_op], revalidate)
+                .await;
+            if revalidate {
+                assert_eq!(
+                    pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                        .unwrap(),
+                    vec![]
+                );
+            } else {
+                check_ops(
+                    pool.best_operations(BundleConstraints::with_max_ops(2), 0)
+                        .unwrap(),
+                    vec![op.op.clone()],
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_seeds_pool() {
+        let ops = vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ];
+        let (pool, uos) = create_pool_insert_ops(ops.clone()).await;
+
+        let snapshot = pool.snapshot();
+        assert_eq!(snapshot.entry_point, pool.config.entry_point);
+        assert_eq!(snapshot.operations.len(), 2);
+        for op in &snapshot.operations {
+            assert_eq!(
+                op.hash,
+                op.op
+                    .uo
+                    .hash(pool.config.entry_point, pool.config.chain_spec.id)
+            );
+            assert_eq!(op.sender, op.op.uo.sender());
+            assert_eq!(op.max_fee_per_gas, op.op.uo.max_fee_per_gas());
+        }
+
+        // the snapshot seeds another instance of the pool
+        let config = PoolConfig {
+            entry_point: snapshot.entry_point,
+            ..default_config()
+        };
+        let seeded = create_pool_with_config(config, ops)
+            .with_operations(snapshot.into_operations(), false)
+            .await;
+        check_ops(
+            seeded
+                .best_operations(BundleConstraints::with_max_ops(2), 0)
+                .unwrap(),
+            uos,
+        );
     }
 
     #[tokio::test]
@@ -967,9 +1500,72 @@ mod tests {
                 .unwrap();
             hashes.push(hash);
         }
-        check_ops(pool.best_operations(3, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos,
+        );
         pool.remove_operations(&hashes);
-        assert_eq!(pool.best_operations(3, 0).unwrap(), vec![]);
+        assert_eq!(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn best_operations_max_gas() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+            create_op(Address::random(), 0, 1, None),
+        ])
+        .await;
+        let op_gas = uos[0].gas_limit(&pool.config.chain_spec, None);
+
+        let constraints = BundleConstraints {
+            max_ops: 3,
+            max_gas: Some(op_gas * 2),
+            one_per_sender: false,
+        };
+        check_ops(
+            pool.best_operations(constraints, 0).unwrap(),
+            uos[0..2].to_vec(),
+        );
+
+        let constraints = BundleConstraints {
+            max_ops: 3,
+            max_gas: Some(op_gas - 1),
+            one_per_sender: false,
+        };
+        check_ops(pool.best_operations(constraints, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn best_operations_one_per_sender() {
+        let address = Address::random();
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op_with_errors(address, 0, 2, None, None, true),
+            create_op_with_errors(address, 1, 2, None, None, true),
+        ])
+        .await;
+
+        // staked, so all ops are included unless limited
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos.clone(),
+        );
+
+        let constraints = BundleConstraints {
+            max_ops: 3,
+            max_gas: None,
+            one_per_sender: true,
+        };
+        check_ops(
+            pool.best_operations(constraints, 0).unwrap(),
+            uos[0..1].to_vec(),
+        );
     }
 
     #[tokio::test]
@@ -988,9 +1584,17 @@ mod tests {
                 .await
                 .unwrap();
         }
-        check_ops(pool.best_operations(3, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos,
+        );
         pool.clear_state(true, true, true);
-        assert_eq!(pool.best_operations(3, 0).unwrap(), vec![]);
+        assert_eq!(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            vec![]
+        );
     }
 
     #[tokio::test]
@@ -1016,7 +1620,11 @@ mod tests {
             entrypoint,
         )
         .await;
-        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos.clone(),
+        );
 
         pool.on_chain_update(&ChainUpdate {
             latest_block_number: 1,
@@ -1049,7 +1657,11 @@ mod tests {
         })
         .await;
 
-        check_ops(pool.best_operations(3, 0).unwrap(), uos[1..].to_vec());
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos[1..].to_vec(),
+        );
 
         let paymaster_balance = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
         assert_eq!(paymaster_balance.confirmed_balance, U256::from(1110));
@@ -1100,7 +1712,11 @@ mod tests {
         let metadata = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
 
         assert_eq!(metadata.pending_balance, U256::from(850));
-        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos.clone(),
+        );
 
         // mine the first op with actual gas cost of 10
         pool.on_chain_update(&ChainUpdate {
@@ -1135,7 +1751,8 @@ mod tests {
         .await;
 
         check_ops(
-            pool.best_operations(3, 0).unwrap(),
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
             uos.clone()[1..].to_vec(),
         );
 
@@ -1168,7 +1785,11 @@ mod tests {
         })
         .await;
 
-        check_ops(pool.best_operations(3, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos,
+        );
 
         let metadata = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
         assert_eq!(metadata.pending_balance, U256::from(840));
@@ -1182,7 +1803,11 @@ mod tests {
             create_op(Address::random(), 0, 1, None),
         ])
         .await;
-        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos.clone(),
+        );
 
         pool.on_chain_update(&ChainUpdate {
             latest_block_number: 1,
@@ -1205,7 +1830,220 @@ mod tests {
         })
         .await;
 
-        check_ops(pool.best_operations(3, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos,
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_update_revalidation() {
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.revalidation_error = Some(SimulationViolation::CodeHashChanged(
+            B256::ZERO,
+            B256::random(),
+        ));
+        let uo = op.op.clone();
+
+        let config = PoolConfig {
+            revalidation_max_ops_per_block: 1,
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op]);
+        pool.add_operation(OperationOrigin::Local, uo.clone())
+            .await
+            .unwrap();
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![uo],
+        );
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 0,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![],
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_update_revalidation_updates_sim_block() {
+        let op = create_op(Address::random(), 0, 1, None);
+        let config = PoolConfig {
+            revalidation_max_ops_per_block: 1,
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op.clone()]);
+        let hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+
+        let block_hash = B256::random();
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: block_hash,
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 0,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+
+        let pool_op = pool.get_user_operation_by_hash(hash).unwrap();
+        assert_eq!(pool_op.sim_block_hash, block_hash);
+        assert_eq!(pool_op.sim_block_number, 1);
+    }
+
+    #[tokio::test]
+    async fn chain_update_revalidation_timed_out() {
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.revalidation_error = Some(SimulationViolation::SimulationTimedOut);
+        let uo = op.op.clone();
+
+        let config = PoolConfig {
+            revalidation_max_ops_per_block: 1,
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op]);
+        pool.add_operation(OperationOrigin::Local, uo.clone())
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 0,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+
+        // a slow node doesn't evict the op
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![uo],
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_update_reorg_revalidation() {
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.revalidation_error = Some(SimulationViolation::CodeHashChanged(
+            B256::ZERO,
+            B256::random(),
+        ));
+        let uo = op.op.clone();
+
+        // re-validation on new blocks is disabled, but ops simulated against an
+        // orphaned block are still re-validated
+        let pool = create_pool(vec![op]);
+        pool.add_operation(OperationOrigin::Local, uo.clone())
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 0,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![uo],
+        );
+
+        // orphans the block the op was simulated at
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 2,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![],
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_update_reorg_revalidation_disabled() {
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.revalidation_error = Some(SimulationViolation::CodeHashChanged(
+            B256::ZERO,
+            B256::random(),
+        ));
+        let uo = op.op.clone();
+
+        let config = PoolConfig {
+            reorg_revalidation_max_ops_per_block: 0,
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op]);
+        pool.add_operation(OperationOrigin::Local, uo.clone())
+            .await
+            .unwrap();
+
+        // orphans the block the op was simulated at, but it isn't re-validated
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: B256::random(),
+            latest_block_timestamp: 0.into(),
+            earliest_remembered_block_number: 0,
+            reorg_depth: 1,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_balance_updates: vec![],
+            unmined_entity_balance_updates: vec![],
+            reorg_larger_than_history: false,
+        })
+        .await;
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![uo],
+        );
     }
 
     #[tokio::test]
@@ -1218,7 +2056,11 @@ mod tests {
         ])
         .await;
         // staked, so include all ops
-        check_ops(pool.best_operations(3, 0).unwrap(), uos[0..2].to_vec());
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos[0..2].to_vec(),
+        );
 
         let rep = pool.dump_reputation();
         assert_eq!(rep.len(), 1);
@@ -1338,6 +2180,45 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_revalidated_throttled_op_expires() {
+        let address = Address::random();
+        let op = create_op(address, 0, 2, None);
+        let uo = op.op.clone();
+        let config = PoolConfig {
+            revalidation_max_ops_per_block: 1,
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op]);
+
+        // Past throttle slack
+        let ops_seen = 100;
+        pool.set_reputation(address, ops_seen, ops_seen / 10 - THROTTLE_SLACK - 1);
+        let hash = pool
+            .add_operation(OperationOrigin::Local, uo)
+            .await
+            .unwrap();
+
+        // the op is re-validated on every block, but its age is counted from when it was added
+        for block_number in 1..=11 {
+            assert!(pool.get_user_operation_by_hash(hash).is_some());
+            pool.on_chain_update(&ChainUpdate {
+                latest_block_number: block_number,
+                latest_block_hash: B256::random(),
+                latest_block_timestamp: 0.into(),
+                earliest_remembered_block_number: 0,
+                reorg_depth: 0,
+                mined_ops: vec![],
+                unmined_ops: vec![],
+                entity_balance_updates: vec![],
+                unmined_entity_balance_updates: vec![],
+                reorg_larger_than_history: false,
+            })
+            .await;
+        }
+        assert!(pool.get_user_operation_by_hash(hash).is_none());
+    }
+
     #[tokio::test]
     async fn test_banned_account() {
         let address = Address::random();
@@ -1363,6 +2244,28 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_remove_banned_entities() {
+        let address = Address::random();
+        let op = create_op(address, 0, 0, None);
+        let uo = op.op.clone();
+        let pool = create_pool(vec![op]);
+
+        let hash = pool
+            .add_operation(OperationOrigin::Local, uo)
+            .await
+            .unwrap();
+
+        // ban the sender without going through the change handler, as if the change was missed
+        let ops_seen = 1000;
+        let ops_included = ops_seen / 10 - BAN_SLACK - 1;
+        pool.set_reputation(address, ops_seen, ops_included);
+        assert!(pool.get_user_operation_by_hash(hash).is_some());
+
+        pool.remove_banned_entities();
+        assert!(pool.get_user_operation_by_hash(hash).is_none());
+    }
+
     #[tokio::test]
     async fn test_paymaster_balance_insufficient() {
         let paymaster = Address::random();
@@ -1409,7 +2312,11 @@ mod tests {
             )) => {}
             _ => panic!("Expected InitCodeTooShort error"),
         }
-        assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
+        assert_eq!(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![]
+        );
     }
 
     #[tokio::test]
@@ -1429,7 +2336,11 @@ mod tests {
             Err(MempoolError::SimulationViolation(SimulationViolation::DidNotRevert)) => {}
             _ => panic!("Expected DidNotRevert error"),
         }
-        assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
+        assert_eq!(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![]
+        );
     }
 
     #[tokio::test]
@@ -1448,7 +2359,11 @@ mod tests {
             .unwrap_err();
         assert!(matches!(err, MempoolError::OperationAlreadyKnown));
 
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op],
+        );
     }
 
     #[tokio::test]
@@ -1472,7 +2387,11 @@ mod tests {
 
         assert!(matches!(err, MempoolError::ReplacementUnderpriced(_, _)));
 
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op],
+        );
     }
 
     #[tokio::test]
@@ -1528,7 +2447,11 @@ mod tests {
             .await
             .unwrap();
 
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![replacement]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![replacement],
+        );
 
         let paymaster_balance = pool.paymaster.paymaster_balance(paymaster).await.unwrap();
         assert_eq!(paymaster_balance.pending_balance, U256::from(900));
@@ -1553,7 +2476,11 @@ mod tests {
             .await
             .unwrap();
 
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op.clone()],
+        );
 
         pool.on_chain_update(&ChainUpdate {
             latest_block_timestamp: 11.into(),
@@ -1561,7 +2488,11 @@ mod tests {
         })
         .await;
 
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![],
+        );
     }
 
     #[tokio::test]
@@ -1592,7 +2523,11 @@ mod tests {
             pool.remove_op_by_id(&op.op.id()),
             Err(MempoolError::OperationDropTooSoon(_, _, _))
         ));
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op],
+        );
     }
 
     #[tokio::test]
@@ -1612,7 +2547,11 @@ mod tests {
             }),
             Ok(None)
         ));
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op],
+        );
     }
 
     #[tokio::test]
@@ -1633,7 +2572,37 @@ mod tests {
         .await;
 
         assert_eq!(pool.remove_op_by_id(&op.op.id()).unwrap().unwrap(), hash);
-        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_by_hash() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone())
+            .await
+            .unwrap();
+
+        assert!(!pool.remove_op_by_hash(B256::random()));
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![op.op],
+        );
+
+        assert!(pool.remove_op_by_hash(hash));
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![],
+        );
+        assert!(!pool.remove_op_by_hash(hash));
     }
 
     #[tokio::test]
@@ -1680,7 +2649,11 @@ mod tests {
         ])
         .await;
         // staked, so include all ops
-        check_ops(pool.best_operations(3, 0).unwrap(), uos);
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(3), 0)
+                .unwrap(),
+            uos,
+        );
     }
 
     #[tokio::test]
@@ -1791,17 +2764,77 @@ mod tests {
             .await
             .unwrap();
 
-        let best = pool.best_operations(10000, 0).unwrap();
+        let best = pool
+            .best_operations(BundleConstraints::with_max_ops(10000), 0)
+            .unwrap();
         assert_eq!(best.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_supported_aggregators() {
+        let aggregator = Address::random();
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.aggregator = Some(aggregator);
+        let uo = op.op.clone();
+
+        // aggregation not configured
+        let pool = create_pool(vec![op.clone()]);
+        assert!(pool.supported_aggregators().is_empty());
+        let ret = pool.add_operation(OperationOrigin::Local, uo.clone()).await;
+        assert!(matches!(
+            ret,
+            Err(MempoolError::UnsupportedAggregator(a)) if a == aggregator
+        ));
+
+        let config = PoolConfig {
+            supported_aggregators: HashSet::from([aggregator]),
+            ..default_config()
+        };
+        let pool = create_pool_with_config(config, vec![op]);
+        assert_eq!(pool.supported_aggregators(), vec![aggregator]);
+        let hash = pool
+            .add_operation(OperationOrigin::Local, uo)
+            .await
+            .unwrap();
+        assert_eq!(
+            pool.get_user_operation_by_hash(hash).unwrap().aggregator,
+            Some(aggregator)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_untraced_ops_allowed() {
+        let mut op = create_op(Address::random(), 0, 1, None);
+        op.tracing_skipped = true;
+        let uo = op.op.clone();
+
+        let pool = create_pool(vec![op.clone()]);
+        let ret = pool.add_operation(OperationOrigin::Local, uo.clone()).await;
+        assert!(matches!(ret, Err(MempoolError::Other(_))));
+
+        let mut config = default_config();
+        config.sim_settings.untraced_ops_allowed = true;
+        let pool = create_pool_with_config(config, vec![op]);
+        pool.add_operation(OperationOrigin::Local, uo.clone())
+            .await
+            .unwrap();
+        check_ops(
+            pool.best_operations(BundleConstraints::with_max_ops(1), 0)
+                .unwrap(),
+            vec![uo],
+        );
+    }
+
     #[derive(Clone, Debug)]
     struct OpWithErrors {
         op: UserOperationVariant,
         valid_time_range: ValidTimeRange,
         precheck_error: Option<PrecheckViolation>,
         simulation_error: Option<SimulationViolation>,
+        revalidation_error: Option<SimulationViolation>,
         staked: bool,
+        aggregator: Option<Address>,
+        tracing_skipped: bool,
     }
 
     fn default_config() -> PoolConfig {
@@ -1826,6 +2859,10 @@ mod tests {
             reputation_tracking_enabled: true,
             drop_min_num_blocks: 10,
             gas_limit_efficiency_reject_threshold: 0.0,
+            revalidation_max_ops_per_block: 0,
+            reorg_revalidation_max_ops_per_block: 100,
+            revalidation_timeout: Duration::from_secs(1),
+            supported_aggregators: HashSet::new(),
         }
     }
 
@@ -1855,8 +2892,12 @@ mod tests {
     fn create_pool_with_entry_point_config(
         args: PoolConfig,
         ops: Vec<OpWithErrors>,
-        entrypoint: MockEntryPointV0_6,
+        mut entrypoint: MockEntryPointV0_6,
     ) -> UoPool<impl UoPoolProvidersT, impl ProvidersWithEntryPointT> {
+        // an unknown next nonce doesn't hold back any ops
+        entrypoint
+            .expect_get_nonce()
+            .returning(|_, _, _| Err(anyhow::anyhow!("nonce unavailable").into()));
         let entrypoint = Arc::new(entrypoint);
 
         let mut evm = MockEvmProvider::new();
@@ -1906,8 +2947,13 @@ mod tests {
             });
             simulator
                 .expect_simulate_validation()
-                .returning(move |_, _, _| {
-                    if let Some(error) = &op.simulation_error {
+                .returning(move |_, _, expected_code_hash| {
+                    let error = if expected_code_hash.is_some() {
+                        op.revalidation_error.as_ref()
+                    } else {
+                        op.simulation_error.as_ref()
+                    };
+                    if let Some(error) = error {
                         Err(SimulationError {
                             violation_error: ViolationError::Violations(vec![error.clone()]),
                             entity_infos: None,
@@ -1924,6 +2970,15 @@ mod tests {
                                 ..EntityInfos::default()
                             },
                             pre_op_gas: 100_000,
+                            aggregators: op
+                                .aggregator
+                                .map(|address| AggregatorSimOut {
+                                    address,
+                                    ..Default::default()
+                                })
+                                .into_iter()
+                                .collect(),
+                            tracing_skipped: op.tracing_skipped,
                             ..SimulationResult::default()
                         })
                     }
@@ -1936,7 +2991,7 @@ mod tests {
         UoPool::new(
             args,
             ProvidersWithEntryPoint::new(Arc::new(evm), entry_point, Some(da_oracle)),
-            UoPoolProviders::new(simulator, prechecker),
+            UoPoolProviders::new(simulator, prechecker, MockCallGasEstimator::new()),
             event_sender,
             paymaster,
             reputation,
@@ -1996,7 +3051,10 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error: None,
             simulation_error: None,
+            revalidation_error: None,
             staked: false,
+            aggregator: None,
+            tracing_skipped: false,
         }
     }
 
@@ -2019,7 +3077,10 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error,
             simulation_error,
+            revalidation_error: None,
             staked,
+            aggregator: None,
+            tracing_skipped: false,
         }
     }
 
@@ -2029,7 +3090,10 @@ mod tests {
             valid_time_range: ValidTimeRange::default(),
             precheck_error: None,
             simulation_error: None,
+            revalidation_error: None,
             staked: false,
+            aggregator: None,
+            tracing_skipped: false,
         }
     }
 
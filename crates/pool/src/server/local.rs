@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
 
 use alloy_primitives::{Address, B256};
 use async_stream::stream;
@@ -25,18 +25,24 @@ use rundler_task::{
 use rundler_types::{
     pool::{
         MempoolError, NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult,
-        Reputation, ReputationStatus, StakeStatus,
+        PooledOp, Reputation, ReputationChange, ReputationStatus, StakeStatus,
     },
     EntityUpdate, EntryPointVersion, UserOperationId, UserOperationVariant,
 };
-use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot},
+    time::{self, Instant},
+};
 use tracing::{error, info};
 
 use crate::{
     chain::ChainUpdate,
-    mempool::{Mempool, OperationOrigin},
+    mempool::{BundleConstraints, Mempool, OperationOrigin},
 };
 
+// How often to record pool metrics that are not updated on every pool change
+const METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
 /// Local pool server builder
 #[derive(Debug)]
 pub struct LocalPoolBuilder {
@@ -130,6 +136,15 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn get_supported_aggregators(&self, entry_point: Address) -> PoolResult<Vec<Address>> {
+        let req = ServerRequestKind::GetSupportedAggregators { entry_point };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetSupportedAggregators { aggregators } => Ok(aggregators),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256> {
         let req = ServerRequestKind::AddOp {
             entry_point,
@@ -170,6 +185,22 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PooledOp>> {
+        let req = ServerRequestKind::GetOpsBySender {
+            entry_point,
+            sender,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpsBySender { ops } => Ok(ops),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let req = ServerRequestKind::RemoveOps { entry_point, ops };
         let resp = self.send(req).await?;
@@ -192,6 +223,15 @@ impl Pool for LocalPoolHandle {
         }
     }
 
+    async fn remove_op_by_hash(&self, entry_point: Address, hash: B256) -> PoolResult<bool> {
+        let req = ServerRequestKind::RemoveOpByHash { entry_point, hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::RemoveOpByHash { removed } => Ok(removed),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -343,6 +383,31 @@ impl Pool for LocalPoolHandle {
             _ => Err(PoolError::UnexpectedResponse),
         }
     }
+
+    async fn subscribe_reputation_changes(
+        &self,
+        entry_point: Address,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = ReputationChange> + Send>>> {
+        let req = ServerRequestKind::SubscribeReputationChanges { entry_point };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::SubscribeReputationChanges { mut changes } => Ok(Box::pin(stream! {
+                loop {
+                    match changes.recv().await {
+                        Ok(change) => yield change,
+                        Err(broadcast::error::RecvError::Lagged(c)) => {
+                            error!("reputation_changes_receiver lagged {c} changes");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            info!("reputation_changes_receiver closed, ending subscription");
+                            break;
+                        }
+                    }
+                }
+            })),
+            _ => Err(PoolError::UnexpectedResponse),
+        }
+    }
 }
 
 #[async_trait]
@@ -383,6 +448,11 @@ impl LocalPoolServerRunner {
             .ok_or_else(|| PoolError::MempoolError(MempoolError::UnknownEntryPoint(entry_point)))
     }
 
+    fn get_supported_aggregators(&self, entry_point: Address) -> PoolResult<Vec<Address>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.supported_aggregators())
+    }
+
     fn get_ops(
         &self,
         entry_point: Address,
@@ -391,7 +461,10 @@ impl LocalPoolServerRunner {
     ) -> PoolResult<Vec<PoolOperation>> {
         let mempool = self.get_pool(entry_point)?;
         Ok(mempool
-            .best_operations(max_ops as usize, shard_index)?
+            .best_operations(
+                BundleConstraints::with_max_ops(max_ops as usize),
+                shard_index,
+            )?
             .iter()
             .map(|op| (**op).clone())
             .collect())
@@ -406,6 +479,15 @@ impl LocalPoolServerRunner {
         Ok(None)
     }
 
+    fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PooledOp>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.get_operations_by_sender(sender))
+    }
+
     fn remove_ops(&self, entry_point: Address, ops: &[B256]) -> PoolResult<()> {
         let mempool = self.get_pool(entry_point)?;
         mempool.remove_operations(ops);
@@ -421,6 +503,11 @@ impl LocalPoolServerRunner {
         mempool.remove_op_by_id(id).map_err(|e| e.into())
     }
 
+    fn remove_op_by_hash(&self, entry_point: Address, hash: B256) -> PoolResult<bool> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.remove_op_by_hash(hash))
+    }
+
     fn update_entities<'a>(
         &self,
         entry_point: Address,
@@ -499,6 +586,14 @@ impl LocalPoolServerRunner {
         Ok(mempool.get_reputation_status(address))
     }
 
+    fn subscribe_reputation_changes(
+        &self,
+        entry_point: Address,
+    ) -> PoolResult<broadcast::Receiver<ReputationChange>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.subscribe_reputation_changes())
+    }
+
     fn get_pool_and_spawn<F, Fut>(
         &self,
         entry_point: Address,
@@ -522,11 +617,18 @@ impl LocalPoolServerRunner {
     }
 
     async fn run(mut self, shutdown: GracefulShutdown) {
+        let mut metrics_tick =
+            time::interval_at(Instant::now() + METRICS_INTERVAL, METRICS_INTERVAL);
         loop {
             tokio::select! {
                 _ = shutdown.clone() => {
                     break;
                 }
+                _ = metrics_tick.tick() => {
+                    for mempool in self.mempools.values() {
+                        mempool.record_metrics();
+                    }
+                }
                 chain_update = self.chain_updates.recv() => {
                     if let Ok(chain_update) = chain_update {
                         // Update each mempool before notifying listeners of the chain update
@@ -608,6 +710,12 @@ impl LocalPoolServerRunner {
                                 entry_points: self.mempools.keys().copied().collect()
                             })
                         },
+                        ServerRequestKind::GetSupportedAggregators { entry_point } => {
+                            match self.get_supported_aggregators(entry_point) {
+                                Ok(aggregators) => Ok(ServerResponse::GetSupportedAggregators { aggregators }),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::GetOps { entry_point, max_ops, shard_index } => {
                             match self.get_ops(entry_point, max_ops, shard_index) {
                                 Ok(ops) => Ok(ServerResponse::GetOps { ops }),
@@ -620,6 +728,12 @@ impl LocalPoolServerRunner {
                                 Err(e) => Err(e),
                             }
                         }
+                        ServerRequestKind::GetOpsBySender { entry_point, sender } => {
+                            match self.get_ops_by_sender(entry_point, sender) {
+                                Ok(ops) => Ok(ServerResponse::GetOpsBySender { ops }),
+                                Err(e) => Err(e),
+                            }
+                        }
                         ServerRequestKind::RemoveOps { entry_point, ops } => {
                             match self.remove_ops(entry_point, &ops) {
                                 Ok(_) => Ok(ServerResponse::RemoveOps),
@@ -632,6 +746,12 @@ impl LocalPoolServerRunner {
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::RemoveOpByHash { entry_point, hash } => {
+                            match self.remove_op_by_hash(entry_point, hash) {
+                                Ok(removed) => Ok(ServerResponse::RemoveOpByHash{ removed }),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::AdminSetTracking{ entry_point, paymaster, reputation } => {
                             match self.admin_set_tracking(entry_point, paymaster, reputation) {
                                 Ok(_) => Ok(ServerResponse::AdminSetTracking),
@@ -683,6 +803,12 @@ impl LocalPoolServerRunner {
                         ServerRequestKind::SubscribeNewHeads => {
                             Ok(ServerResponse::SubscribeNewHeads { new_heads: self.block_sender.subscribe() } )
                         }
+                        ServerRequestKind::SubscribeReputationChanges { entry_point } => {
+                            match self.subscribe_reputation_changes(entry_point) {
+                                Ok(changes) => Ok(ServerResponse::SubscribeReputationChanges { changes }),
+                                Err(e) => Err(e),
+                            }
+                        }
                     };
                     if let Err(e) = req.response.send(resp) {
                         tracing::error!("Failed to send response: {:?}", e);
@@ -702,6 +828,9 @@ struct ServerRequest {
 #[derive(Debug)]
 enum ServerRequestKind {
     GetSupportedEntryPoints,
+    GetSupportedAggregators {
+        entry_point: Address,
+    },
     AddOp {
         entry_point: Address,
         op: UserOperationVariant,
@@ -715,6 +844,10 @@ enum ServerRequestKind {
     GetOpByHash {
         hash: B256,
     },
+    GetOpsBySender {
+        entry_point: Address,
+        sender: Address,
+    },
     RemoveOps {
         entry_point: Address,
         ops: Vec<B256>,
@@ -723,6 +856,10 @@ enum ServerRequestKind {
         entry_point: Address,
         id: UserOperationId,
     },
+    RemoveOpByHash {
+        entry_point: Address,
+        hash: B256,
+    },
     UpdateEntities {
         entry_point: Address,
         entity_updates: Vec<EntityUpdate>,
@@ -759,6 +896,9 @@ enum ServerRequestKind {
         address: Address,
     },
     SubscribeNewHeads,
+    SubscribeReputationChanges {
+        entry_point: Address,
+    },
 }
 
 #[derive(Debug)]
@@ -766,6 +906,9 @@ enum ServerResponse {
     GetSupportedEntryPoints {
         entry_points: Vec<Address>,
     },
+    GetSupportedAggregators {
+        aggregators: Vec<Address>,
+    },
     AddOp {
         hash: B256,
     },
@@ -775,10 +918,16 @@ enum ServerResponse {
     GetOpByHash {
         op: Option<PoolOperation>,
     },
+    GetOpsBySender {
+        ops: Vec<PooledOp>,
+    },
     RemoveOps,
     RemoveOpById {
         hash: Option<B256>,
     },
+    RemoveOpByHash {
+        removed: bool,
+    },
     UpdateEntities,
     DebugClearState,
     AdminSetTracking,
@@ -801,6 +950,9 @@ enum ServerResponse {
     SubscribeNewHeads {
         new_heads: broadcast::Receiver<NewHead>,
     },
+    SubscribeReputationChanges {
+        changes: broadcast::Receiver<ReputationChange>,
+    },
 }
 
 #[cfg(test)]
@@ -880,6 +1032,29 @@ mod tests {
         assert_eq!(eps0, eps1);
     }
 
+    #[tokio::test]
+    async fn test_get_supported_aggregators() {
+        let ep = Address::random();
+        let aggregators = vec![Address::random()];
+        let mut mock_pool = MockMempool::new();
+        let ret = aggregators.clone();
+        mock_pool
+            .expect_supported_aggregators()
+            .returning(move || ret.clone());
+
+        let pool: Arc<dyn Mempool> = Arc::new(mock_pool);
+        let state = setup(HashMap::from([(ep, pool)]));
+        assert_eq!(
+            state.handle.get_supported_aggregators(ep).await.unwrap(),
+            aggregators
+        );
+        assert!(state
+            .handle
+            .get_supported_aggregators(Address::random())
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_multiple_entry_points() {
         let eps = [Address::random(), Address::random(), Address::random()];
@@ -927,6 +1102,41 @@ mod tests {
         _task_manager: TaskManager,
     }
 
+    #[tokio::test]
+    async fn test_subscribe_reputation_changes() {
+        let (tx, _) = broadcast::channel(10);
+        let mut mock_pool = MockMempool::new();
+        let pool_tx = tx.clone();
+        mock_pool
+            .expect_subscribe_reputation_changes()
+            .returning(move || pool_tx.subscribe());
+
+        let ep = Address::random();
+        let pool: Arc<dyn Mempool> = Arc::new(mock_pool);
+        let state = setup(HashMap::from([(ep, pool)]));
+
+        let mut sub = state.handle.subscribe_reputation_changes(ep).await.unwrap();
+
+        let change = ReputationChange {
+            address: Address::random(),
+            old_status: ReputationStatus::Ok,
+            new_status: ReputationStatus::Banned,
+        };
+        tx.send(change).unwrap();
+
+        assert_eq!(sub.next().await.unwrap(), change);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reputation_changes_unknown_entry_point() {
+        let state = setup(HashMap::new());
+        assert!(state
+            .handle
+            .subscribe_reputation_changes(Address::random())
+            .await
+            .is_err());
+    }
+
     fn setup(pools: HashMap<Address, Arc<dyn Mempool>>) -> State {
         let builder = LocalPoolBuilder::new(10, 10);
         let handle = builder.get_handle();
@@ -24,8 +24,8 @@ use rundler_task::{
 use rundler_types::{
     chain::ChainSpec,
     pool::{
-        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult, Reputation,
-        ReputationStatus, StakeStatus,
+        NewHead, PaymasterMetadata, Pool, PoolError, PoolOperation, PoolResult, PooledOp,
+        Reputation, ReputationChange, ReputationStatus, StakeStatus,
     },
     EntityUpdate, UserOperationId, UserOperationVariant,
 };
@@ -45,13 +45,15 @@ use super::protos::{
     self, add_op_response, admin_set_tracking_response, debug_clear_state_response,
     debug_dump_mempool_response, debug_dump_paymaster_balances_response,
     debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
-    op_pool_client::OpPoolClient, remove_op_by_id_response, remove_ops_response,
+    get_ops_by_sender_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response, get_supported_aggregators_response, op_pool_client::OpPoolClient,
+    remove_op_by_hash_response, remove_op_by_id_response, remove_ops_response,
     update_entities_response, AddOpRequest, AdminSetTrackingRequest, DebugClearStateRequest,
     DebugDumpMempoolRequest, DebugDumpPaymasterBalancesRequest, DebugDumpReputationRequest,
     DebugSetReputationRequest, GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest,
-    RemoveOpsRequest, ReputationStatus as ProtoReputationStatus, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
+    GetSupportedAggregatorsRequest, RemoveOpsRequest, ReputationStatus as ProtoReputationStatus,
+    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, SubscribeReputationChangesRequest,
+    SubscribeReputationChangesResponse, TryUoFromProto, UpdateEntitiesRequest,
 };
 
 /// Remote pool client
@@ -133,6 +135,43 @@ impl RemotePoolClient {
             }
         }
     }
+
+    // Handler for the reputation change subscription. Ends the subscription when the gRPC
+    // stream closes or errors, as changes missed while reconnecting can't be replayed.
+    async fn reputation_changes_subscription_handler(
+        mut stream: tonic::Streaming<SubscribeReputationChangesResponse>,
+        tx: mpsc::UnboundedSender<ReputationChange>,
+    ) {
+        loop {
+            match stream.message().await {
+                Ok(Some(SubscribeReputationChangesResponse {
+                    reputation_change: Some(c),
+                })) => match c.try_into() {
+                    Ok(change) => {
+                        if tx.send(change).is_err() {
+                            // recv handle dropped
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("error parsing reputation change: {:?}", e);
+                        break;
+                    }
+                },
+                Ok(Some(SubscribeReputationChangesResponse {
+                    reputation_change: None,
+                }))
+                | Ok(None) => {
+                    tracing::debug!("reputation change subscription closed");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error in reputation change subscription: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -152,6 +191,32 @@ impl Pool for RemotePoolClient {
             .map_err(anyhow::Error::from)?)
     }
 
+    async fn get_supported_aggregators(&self, entry_point: Address) -> PoolResult<Vec<Address>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_supported_aggregators(GetSupportedAggregatorsRequest {
+                entry_point: entry_point.to_vec(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_supported_aggregators_response::Result::Success(s)) => Ok(s
+                .aggregators
+                .into_iter()
+                .map(|a| from_bytes(a.as_slice()))
+                .collect::<Result<_, ConversionError>>()
+                .map_err(anyhow::Error::from)?),
+            Some(get_supported_aggregators_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256> {
         let res = self
             .op_pool_client
@@ -242,6 +307,37 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PooledOp>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_ops_by_sender(protos::GetOpsBySenderRequest {
+                entry_point: entry_point.to_proto_bytes(),
+                sender: sender.to_proto_bytes(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_ops_by_sender_response::Result::Success(s)) => s
+                .ops
+                .into_iter()
+                .map(PooledOp::try_from)
+                .map(|res| res.map_err(anyhow::Error::from).map_err(PoolError::from))
+                .collect(),
+            Some(get_ops_by_sender_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -297,6 +393,28 @@ impl Pool for RemotePoolClient {
         }
     }
 
+    async fn remove_op_by_hash(&self, entry_point: Address, hash: B256) -> PoolResult<bool> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .remove_op_by_hash(protos::RemoveOpByHashRequest {
+                entry_point: entry_point.to_proto_bytes(),
+                hash: hash.to_proto_bytes(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(remove_op_by_hash_response::Result::Success(s)) => Ok(s.removed),
+            Some(remove_op_by_hash_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -562,6 +680,28 @@ impl Pool for RemotePoolClient {
             .spawn(Box::pin(Self::new_heads_subscription_handler(client, tx)));
         Ok(Box::pin(UnboundedReceiverStream::new(rx)))
     }
+
+    async fn subscribe_reputation_changes(
+        &self,
+        entry_point: Address,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = ReputationChange> + Send>>> {
+        let stream = self
+            .op_pool_client
+            .clone()
+            .subscribe_reputation_changes(SubscribeReputationChangesRequest {
+                entry_point: entry_point.to_vec(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.task_spawner
+            .spawn(Box::pin(Self::reputation_changes_subscription_handler(
+                stream, tx,
+            )));
+        Ok(Box::pin(UnboundedReceiverStream::new(rx)))
+    }
 }
 
 #[async_trait]
@@ -18,29 +18,37 @@ use rundler_types::{
     pool::{
         MempoolError, NeedsStakeInformation, PoolError, PrecheckViolation, SimulationViolation,
     },
-    Opcode, StorageSlot, Timestamp, ValidationRevert, ViolationOpCode,
+    Opcode, StorageSlot, StorageSlotLabel, Timestamp, ValidationRevert, ViolationOpCode,
 };
 
 use super::protos::{
     mempool_error, precheck_violation_error, simulation_violation_error, validation_revert,
-    AccessedUndeployedContract, AccessedUnsupportedContractType, AggregatorValidationFailed,
-    AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender,
-    CallGasLimitEfficiencyTooLow, CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod,
-    CodeHashChanged, DidNotRevert, DiscardedOnInsertError, Entity, EntityThrottledError,
-    EntityType, EntryPointRevert, ExistingSenderWithInitCode, FactoryCalledCreate2Twice,
-    FactoryIsNotContract, InvalidAccountSignature, InvalidPaymasterSignature, InvalidSignature,
-    InvalidStorageAccess, InvalidTimeRange, MaxFeePerGasTooLow, MaxOperationsReachedError,
-    MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError, MultipleRolesViolation,
-    NotStaked, OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas,
-    PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract,
-    PreOpGasLimitEfficiencyTooLow, PreVerificationGasTooLow,
+    AccessedUndeployedContract, AccessedUnsupportedContractType, AddressDenied,
+    AdmissionPolicyRejected, AggregatorValidationFailed, AssociatedStorageDuringDeploy,
+    AssociatedStorageIsAlternateSender, CallGasLimitEfficiencyTooLow, CallGasLimitTooLow,
+    CallHadValue, CallPhaseReverted, CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert,
+    DiscardedOnInsertError, Entity, EntityThrottledError, EntityType, EntryPointRevert,
+    ExistingSenderWithInitCode, FactoryCalledCreate2Twice, FactoryDeployedUnapprovedContract,
+    FactoryGasLimitExceeded, FactoryIsNotContract, GasOpcodeMisuse, InsufficientPaymasterDeposit,
+    InsufficientTokenAllowance, InsufficientTokenBalance, InvalidAccountSignature,
+    InvalidEntityStorageAccess, InvalidPaymasterSignature, InvalidSignature, InvalidStorageAccess,
+    InvalidTimeRange, MalformedInitCode, MalformedPaymasterAndData, MaxFeePerGasTooLow,
+    MaxOperationsReachedError, MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError,
+    MempoolPriorityFeeTooLow, MultipleRolesViolation, NonceAlreadyUsed, NonceTooHigh, NotStaked,
+    OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas, PanicRevert,
+    PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract,
+    PaymasterSponsorshipExpired, PreOpGasLimitEfficiencyTooLow, PreVerificationGasTooLow,
     PrecheckViolationError as ProtoPrecheckViolationError, ReplacementUnderpricedError,
-    SenderAddressUsedAsAlternateEntity, SenderFundsTooLow, SenderIsNotContractAndNoInitCode,
-    SimulationViolationError as ProtoSimulationViolationError, TotalGasLimitTooHigh,
+    SenderAddressMismatch, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
+    SenderIsNotContractAndNoInitCode, SenderIsPaymaster, SimulationBudgetExceeded,
+    SimulationTimedOut, SimulationViolationError as ProtoSimulationViolationError,
+    SuspiciousTimeRange, TooManyContractsAccessed, TotalGasLimitExceeded, TotalGasLimitTooHigh,
     UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError, UnknownRevert,
-    UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregatorError, UsedForbiddenOpcode,
-    UsedForbiddenPrecompile, ValidationRevert as ProtoValidationRevert,
-    VerificationGasLimitBufferTooLow, VerificationGasLimitTooHigh, WrongNumberOfPhases,
+    UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregatorError,
+    UsedBannedEnvironmentOpcode, UsedForbiddenOpcode, UsedForbiddenPrecompile,
+    ValidationRevert as ProtoValidationRevert, VerificationGasLimitBufferTooLow,
+    VerificationGasLimitTooHigh, VerificationGasLimitTooLowForOp, WrongNumberOfPhases,
+    ZeroGasLimit, ZeroMaxFeePerGas, ZeroSender,
 };
 
 impl TryFrom<ProtoMempoolError> for PoolError {
@@ -133,6 +141,9 @@ impl TryFrom<ProtoMempoolError> for MempoolError {
             Some(mempool_error::Error::CallGasLimitEfficiencyTooLow(e)) => {
                 MempoolError::CallGasLimitEfficiencyTooLow(e.required, e.actual)
             }
+            Some(mempool_error::Error::CallPhaseReverted(e)) => {
+                MempoolError::CallPhaseReverted { reason: e.reason }
+            }
             None => bail!("unknown proto mempool error"),
         })
     }
@@ -247,6 +258,11 @@ impl From<MempoolError> for ProtoMempoolError {
                     CallGasLimitEfficiencyTooLow { required, actual },
                 )),
             },
+            MempoolError::CallPhaseReverted { reason } => ProtoMempoolError {
+                error: Some(mempool_error::Error::CallPhaseReverted(CallPhaseReverted {
+                    reason,
+                })),
+            },
         }
     }
 }
@@ -469,22 +485,25 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 ),
             },
-            SimulationViolation::UnintendedRevertWithMessage(et, reason, maybe_address) => {
-                ProtoSimulationViolationError {
-                    violation: Some(
-                        simulation_violation_error::Violation::UnintendedRevertWithMessage(
-                            UnintendedRevertWithMessage {
-                                entity: Some(Entity {
-                                    kind: EntityType::from(et) as i32,
-                                    address: maybe_address
-                                        .map_or(vec![], |addr| addr.to_proto_bytes()),
-                                }),
-                                reason,
-                            },
-                        ),
+            SimulationViolation::UnintendedRevertWithMessage(
+                et,
+                reason,
+                maybe_address,
+                revert_data,
+            ) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::UnintendedRevertWithMessage(
+                        UnintendedRevertWithMessage {
+                            entity: Some(Entity {
+                                kind: EntityType::from(et) as i32,
+                                address: maybe_address.map_or(vec![], |addr| addr.to_proto_bytes()),
+                            }),
+                            reason,
+                            revert_data: revert_data.to_vec(),
+                        },
                     ),
-                }
-            }
+                ),
+            },
             SimulationViolation::UsedForbiddenOpcode(entity, addr, opcode) => {
                 ProtoSimulationViolationError {
                     violation: Some(simulation_violation_error::Violation::UsedForbiddenOpcode(
@@ -496,6 +515,18 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     )),
                 }
             }
+            SimulationViolation::UsedBannedEnvironmentOpcode(entity, opcode) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::UsedBannedEnvironmentOpcode(
+                            UsedBannedEnvironmentOpcode {
+                                entity: Some((&entity).into()),
+                                opcode: opcode.0 as u32,
+                            },
+                        ),
+                    ),
+                }
+            }
             SimulationViolation::UsedForbiddenPrecompile(
                 entity,
                 contract_addr,
@@ -520,6 +551,107 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 ),
             },
+            SimulationViolation::AddressDenied(address) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::AddressDenied(
+                    AddressDenied {
+                        address: address.to_proto_bytes(),
+                    },
+                )),
+            },
+            SimulationViolation::ZeroSender => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::ZeroSender(
+                    ZeroSender {},
+                )),
+            },
+            SimulationViolation::ZeroGasLimit(field) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::ZeroGasLimit(
+                    ZeroGasLimit { field },
+                )),
+            },
+            SimulationViolation::ZeroMaxFeePerGas => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::ZeroMaxFeePerGas(
+                    ZeroMaxFeePerGas {},
+                )),
+            },
+            SimulationViolation::MalformedInitCode => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::MalformedInitCode(
+                    MalformedInitCode {},
+                )),
+            },
+            SimulationViolation::MalformedPaymasterAndData => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::MalformedPaymasterAndData(
+                        MalformedPaymasterAndData {},
+                    ),
+                ),
+            },
+            SimulationViolation::PaymasterSponsorshipExpired(
+                paymaster,
+                valid_until,
+                valid_after,
+            ) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::PaymasterSponsorshipExpired(
+                        PaymasterSponsorshipExpired {
+                            paymaster_address: paymaster.to_proto_bytes(),
+                            valid_until: valid_until.seconds_since_epoch(),
+                            valid_after: valid_after.seconds_since_epoch(),
+                        },
+                    ),
+                ),
+            },
+            SimulationViolation::VerificationGasLimitTooLowForOp(needed, provided) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::VerificationGasLimitTooLowForOp(
+                            VerificationGasLimitTooLowForOp {
+                                needed: needed.to_proto_bytes(),
+                                provided: provided.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::FactoryDeployedUnapprovedContract(
+                entity,
+                contract_address,
+                code_hash,
+            ) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::FactoryDeployedUnapprovedContract(
+                        FactoryDeployedUnapprovedContract {
+                            entity: Some((&entity).into()),
+                            contract_address: contract_address.to_proto_bytes(),
+                            code_hash: code_hash.to_proto_bytes(),
+                        },
+                    ),
+                ),
+            },
+            SimulationViolation::FactoryGasLimitExceeded(entity, gas_used, limit) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::FactoryGasLimitExceeded(
+                            FactoryGasLimitExceeded {
+                                entity: Some((&entity).into()),
+                                gas_used: gas_used.to_proto_bytes(),
+                                limit: limit.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::SenderAddressMismatch(declared, deployed) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::SenderAddressMismatch(
+                            SenderAddressMismatch {
+                                declared_sender: declared.to_proto_bytes(),
+                                deployed_address: deployed.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
             SimulationViolation::AssociatedStorageDuringDeploy(entity, slot) => {
                 ProtoSimulationViolationError {
                     violation: Some(
@@ -533,17 +665,32 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
-            SimulationViolation::InvalidStorageAccess(entity, slot) => {
+            SimulationViolation::InvalidStorageAccess(entity, slot, label) => {
                 ProtoSimulationViolationError {
                     violation: Some(simulation_violation_error::Violation::InvalidStorageAccess(
                         InvalidStorageAccess {
                             entity: Some((&entity).into()),
                             contract_address: slot.address.to_proto_bytes(),
                             slot: slot.slot.to_proto_bytes(),
+                            slot_label: label.0.unwrap_or_default(),
                         },
                     )),
                 }
             }
+            SimulationViolation::InvalidEntityStorageAccess(entity, owner, slot) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::InvalidEntityStorageAccess(
+                            InvalidEntityStorageAccess {
+                                entity: Some((&entity).into()),
+                                owner: Some((&owner).into()),
+                                contract_address: slot.address.to_proto_bytes(),
+                                slot: slot.slot.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
             SimulationViolation::NotStaked(stake_data) => ProtoSimulationViolationError {
                 violation: Some(simulation_violation_error::Violation::NotStaked(
                     NotStaked {
@@ -557,7 +704,7 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     },
                 )),
             },
-            SimulationViolation::UnintendedRevert(et, maybe_address) => {
+            SimulationViolation::UnintendedRevert(et, maybe_address, revert_data) => {
                 ProtoSimulationViolationError {
                     violation: Some(simulation_violation_error::Violation::UnintendedRevert(
                         UnintendedRevert {
@@ -565,6 +712,7 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                                 kind: EntityType::from(et) as i32,
                                 address: maybe_address.map_or(vec![], |addr| addr.to_proto_bytes()),
                             }),
+                            revert_data: revert_data.to_vec(),
                         },
                     )),
                 }
@@ -589,18 +737,30 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     WrongNumberOfPhases { num_phases },
                 )),
             },
-            SimulationViolation::CallHadValue(entity) => ProtoSimulationViolationError {
-                violation: Some(simulation_violation_error::Violation::CallHadValue(
-                    CallHadValue {
-                        entity: Some((&entity).into()),
-                    },
-                )),
-            },
-            SimulationViolation::OutOfGas(entity) => ProtoSimulationViolationError {
+            SimulationViolation::CallHadValue(entity, maybe_target) => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::CallHadValue(
+                        CallHadValue {
+                            entity: Some((&entity).into()),
+                            target_address: maybe_target
+                                .map_or(vec![], |addr| addr.to_proto_bytes()),
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::OutOfGas(entity, gas_used) => ProtoSimulationViolationError {
                 violation: Some(simulation_violation_error::Violation::OutOfGas(OutOfGas {
                     entity: Some((&entity).into()),
+                    gas_used: gas_used.to_proto_bytes(),
                 })),
             },
+            SimulationViolation::GasOpcodeMisuse(entity) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::GasOpcodeMisuse(
+                    GasOpcodeMisuse {
+                        entity: Some((&entity).into()),
+                    },
+                )),
+            },
             SimulationViolation::AccessedUndeployedContract(entity, contract_addr) => {
                 ProtoSimulationViolationError {
                     violation: Some(
@@ -613,22 +773,110 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
-            SimulationViolation::CalledBannedEntryPointMethod(entity) => {
+            SimulationViolation::CalledBannedEntryPointMethod(entity, maybe_method) => {
                 ProtoSimulationViolationError {
                     violation: Some(
                         simulation_violation_error::Violation::CalledBannedEntryPointMethod(
                             CalledBannedEntryPointMethod {
                                 entity: Some((&entity).into()),
+                                method: maybe_method
+                                    .map_or(vec![], |method| method.to_proto_bytes()),
                             },
                         ),
                     ),
                 }
             }
-            SimulationViolation::CodeHashChanged => ProtoSimulationViolationError {
-                violation: Some(simulation_violation_error::Violation::CodeHashChanged(
-                    CodeHashChanged {},
+            SimulationViolation::CodeHashChanged(expected, actual) => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::CodeHashChanged(
+                        CodeHashChanged {
+                            expected: expected.to_proto_bytes(),
+                            actual: actual.to_proto_bytes(),
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::SimulationTimedOut => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::SimulationTimedOut(
+                    SimulationTimedOut {},
                 )),
             },
+            SimulationViolation::SimulationBudgetExceeded => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::SimulationBudgetExceeded(
+                        SimulationBudgetExceeded {},
+                    ),
+                ),
+            },
+            SimulationViolation::TooManyContractsAccessed(num_contracts) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::TooManyContractsAccessed(
+                            TooManyContractsAccessed {
+                                num_contracts: num_contracts as u64,
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::SenderIsPaymaster(sender) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::SenderIsPaymaster(
+                    SenderIsPaymaster {
+                        sender_address: sender.to_proto_bytes(),
+                    },
+                )),
+            },
+            SimulationViolation::NonceTooHigh(expected, actual) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::NonceTooHigh(
+                    NonceTooHigh {
+                        expected: expected.to_proto_bytes(),
+                        actual: actual.to_proto_bytes(),
+                    },
+                )),
+            },
+            SimulationViolation::NonceAlreadyUsed(expected, actual) => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::NonceAlreadyUsed(
+                        NonceAlreadyUsed {
+                            expected: expected.to_proto_bytes(),
+                            actual: actual.to_proto_bytes(),
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::InsufficientTokenBalance(token, required, shortfall) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::InsufficientTokenBalance(
+                            InsufficientTokenBalance {
+                                token: token.to_proto_bytes(),
+                                required: required.to_proto_bytes(),
+                                shortfall: shortfall.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::InsufficientTokenAllowance(token, required, shortfall) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::InsufficientTokenAllowance(
+                            InsufficientTokenAllowance {
+                                token: token.to_proto_bytes(),
+                                required: required.to_proto_bytes(),
+                                shortfall: shortfall.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::AdmissionPolicyRejected(reason) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::AdmissionPolicyRejected(
+                        AdmissionPolicyRejected { reason },
+                    ),
+                ),
+            },
             SimulationViolation::InvalidTimeRange(valid_until, valid_after) => {
                 ProtoSimulationViolationError {
                     violation: Some(simulation_violation_error::Violation::InvalidTimeRange(
@@ -639,13 +887,35 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     )),
                 }
             }
-            SimulationViolation::AggregatorValidationFailed => ProtoSimulationViolationError {
-                violation: Some(
-                    simulation_violation_error::Violation::AggregatorValidationFailed(
-                        AggregatorValidationFailed {},
-                    ),
-                ),
+            SimulationViolation::SuspiciousTimeRange => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::SuspiciousTimeRange(
+                    SuspiciousTimeRange {},
+                )),
             },
+            SimulationViolation::PaymasterDepositTooLow(entity, required, available) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::InsufficientPaymasterDeposit(
+                            InsufficientPaymasterDeposit {
+                                entity: Some((&entity).into()),
+                                required: required.to_proto_bytes(),
+                                available: available.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::AggregatorValidationFailed(reason) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::AggregatorValidationFailed(
+                            AggregatorValidationFailed {
+                                reason: reason.unwrap_or_default(),
+                            },
+                        ),
+                    ),
+                }
+            }
             SimulationViolation::VerificationGasLimitBufferTooLow(limit, needed) => {
                 ProtoSimulationViolationError {
                     violation: Some(
@@ -658,6 +928,18 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
+            SimulationViolation::TotalGasLimitExceeded(total, limit) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::TotalGasLimitExceeded(
+                            TotalGasLimitExceeded {
+                                total: total.to_proto_bytes(),
+                                limit: limit.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
             SimulationViolation::AccessedUnsupportedContractType(contract_type, address) => {
                 ProtoSimulationViolationError {
                     violation: Some(
@@ -670,6 +952,19 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
+            SimulationViolation::MempoolPriorityFeeTooLow(
+                max_priority_fee_per_gas,
+                min_priority_fee,
+            ) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::MempoolPriorityFeeTooLow(
+                        MempoolPriorityFeeTooLow {
+                            max_priority_fee_per_gas: max_priority_fee_per_gas.to_proto_bytes(),
+                            min_priority_fee: min_priority_fee.to_proto_bytes(),
+                        },
+                    ),
+                ),
+            },
         }
     }
 }
@@ -711,6 +1006,7 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     )?,
                     e.reason,
                     addr,
+                    e.revert_data.into(),
                 )
             }
             Some(simulation_violation_error::Violation::UsedForbiddenOpcode(e)) => {
@@ -720,6 +1016,12 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     ViolationOpCode(Opcode::try_from(e.opcode as u8)?),
                 )
             }
+            Some(simulation_violation_error::Violation::UsedBannedEnvironmentOpcode(e)) => {
+                SimulationViolation::UsedBannedEnvironmentOpcode(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    ViolationOpCode(Opcode::try_from(e.opcode as u8)?),
+                )
+            }
             Some(simulation_violation_error::Violation::UsedForbiddenPrecompile(e)) => {
                 SimulationViolation::UsedForbiddenPrecompile(
                     (&e.entity.context("should have entity in error")?).try_into()?,
@@ -730,6 +1032,12 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
             Some(simulation_violation_error::Violation::FactoryCalledCreate2Twice(e)) => {
                 SimulationViolation::FactoryCalledCreate2Twice(from_bytes(&e.factory_address)?)
             }
+            Some(simulation_violation_error::Violation::SenderAddressMismatch(e)) => {
+                SimulationViolation::SenderAddressMismatch(
+                    from_bytes(&e.declared_sender)?,
+                    from_bytes(&e.deployed_address)?,
+                )
+            }
             Some(simulation_violation_error::Violation::AssociatedStorageDuringDeploy(e)) => {
                 SimulationViolation::AssociatedStorageDuringDeploy(
                     e.entity.as_ref().map(|e| e.try_into()).transpose()?,
@@ -746,6 +1054,17 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                         address: from_bytes(&e.contract_address)?,
                         slot: from_bytes(&e.slot)?,
                     },
+                    StorageSlotLabel((!e.slot_label.is_empty()).then_some(e.slot_label)),
+                )
+            }
+            Some(simulation_violation_error::Violation::InvalidEntityStorageAccess(e)) => {
+                SimulationViolation::InvalidEntityStorageAccess(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    (&e.owner.context("should have owner in error")?).try_into()?,
+                    StorageSlot {
+                        address: from_bytes(&e.contract_address)?,
+                        slot: from_bytes(&e.slot)?,
+                    },
                 )
             }
             Some(simulation_violation_error::Violation::NotStaked(e)) => {
@@ -783,6 +1102,7 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     } else {
                         Some(from_bytes(&address)?)
                     },
+                    e.revert_data.into(),
                 )
             }
             Some(simulation_violation_error::Violation::ValidationRevert(e)) => {
@@ -800,11 +1120,22 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
             Some(simulation_violation_error::Violation::CallHadValue(e)) => {
                 SimulationViolation::CallHadValue(
                     (&e.entity.context("should have entity in error")?).try_into()?,
+                    if e.target_address.is_empty() {
+                        None
+                    } else {
+                        Some(from_bytes(&e.target_address)?)
+                    },
                 )
             }
             Some(simulation_violation_error::Violation::OutOfGas(e)) => {
                 SimulationViolation::OutOfGas(
                     (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.gas_used)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::GasOpcodeMisuse(e)) => {
+                SimulationViolation::GasOpcodeMisuse(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
                 )
             }
             Some(simulation_violation_error::Violation::AccessedUndeployedContract(e)) => {
@@ -816,13 +1147,71 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
             Some(simulation_violation_error::Violation::CalledBannedEntryPointMethod(e)) => {
                 SimulationViolation::CalledBannedEntryPointMethod(
                     (&e.entity.context("should have entity in error")?).try_into()?,
+                    if e.method.is_empty() {
+                        None
+                    } else {
+                        Some(from_bytes(&e.method)?)
+                    },
                 )
             }
-            Some(simulation_violation_error::Violation::CodeHashChanged(_)) => {
-                SimulationViolation::CodeHashChanged
+            Some(simulation_violation_error::Violation::CodeHashChanged(e)) => {
+                SimulationViolation::CodeHashChanged(
+                    from_bytes(&e.expected)?,
+                    from_bytes(&e.actual)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::SimulationTimedOut(_)) => {
+                SimulationViolation::SimulationTimedOut
+            }
+            Some(simulation_violation_error::Violation::SimulationBudgetExceeded(_)) => {
+                SimulationViolation::SimulationBudgetExceeded
+            }
+            Some(simulation_violation_error::Violation::TooManyContractsAccessed(e)) => {
+                SimulationViolation::TooManyContractsAccessed(e.num_contracts as usize)
+            }
+            Some(simulation_violation_error::Violation::SenderIsPaymaster(e)) => {
+                SimulationViolation::SenderIsPaymaster(from_bytes(&e.sender_address)?)
+            }
+            Some(simulation_violation_error::Violation::NonceTooHigh(e)) => {
+                SimulationViolation::NonceTooHigh(from_bytes(&e.expected)?, from_bytes(&e.actual)?)
+            }
+            Some(simulation_violation_error::Violation::NonceAlreadyUsed(e)) => {
+                SimulationViolation::NonceAlreadyUsed(
+                    from_bytes(&e.expected)?,
+                    from_bytes(&e.actual)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::InsufficientTokenBalance(e)) => {
+                SimulationViolation::InsufficientTokenBalance(
+                    from_bytes(&e.token)?,
+                    from_bytes(&e.required)?,
+                    from_bytes(&e.shortfall)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::InsufficientTokenAllowance(e)) => {
+                SimulationViolation::InsufficientTokenAllowance(
+                    from_bytes(&e.token)?,
+                    from_bytes(&e.required)?,
+                    from_bytes(&e.shortfall)?,
+                )
             }
-            Some(simulation_violation_error::Violation::AggregatorValidationFailed(_)) => {
-                SimulationViolation::AggregatorValidationFailed
+            Some(simulation_violation_error::Violation::AdmissionPolicyRejected(e)) => {
+                SimulationViolation::AdmissionPolicyRejected(e.reason)
+            }
+            Some(simulation_violation_error::Violation::SuspiciousTimeRange(_)) => {
+                SimulationViolation::SuspiciousTimeRange
+            }
+            Some(simulation_violation_error::Violation::InsufficientPaymasterDeposit(e)) => {
+                SimulationViolation::PaymasterDepositTooLow(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.required)?,
+                    from_bytes(&e.available)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::AggregatorValidationFailed(e)) => {
+                SimulationViolation::AggregatorValidationFailed(
+                    Some(e.reason).filter(|s| !s.is_empty()),
+                )
             }
             Some(simulation_violation_error::Violation::VerificationGasLimitBufferTooLow(e)) => {
                 SimulationViolation::VerificationGasLimitBufferTooLow(
@@ -830,12 +1219,69 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     from_bytes(&e.needed)?,
                 )
             }
+            Some(simulation_violation_error::Violation::TotalGasLimitExceeded(e)) => {
+                SimulationViolation::TotalGasLimitExceeded(
+                    from_bytes(&e.total)?,
+                    from_bytes(&e.limit)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::AddressDenied(e)) => {
+                SimulationViolation::AddressDenied(from_bytes(&e.address)?)
+            }
+            Some(simulation_violation_error::Violation::ZeroSender(_)) => {
+                SimulationViolation::ZeroSender
+            }
+            Some(simulation_violation_error::Violation::ZeroGasLimit(e)) => {
+                SimulationViolation::ZeroGasLimit(e.field)
+            }
+            Some(simulation_violation_error::Violation::ZeroMaxFeePerGas(_)) => {
+                SimulationViolation::ZeroMaxFeePerGas
+            }
+            Some(simulation_violation_error::Violation::MalformedInitCode(_)) => {
+                SimulationViolation::MalformedInitCode
+            }
+            Some(simulation_violation_error::Violation::MalformedPaymasterAndData(_)) => {
+                SimulationViolation::MalformedPaymasterAndData
+            }
+            Some(simulation_violation_error::Violation::PaymasterSponsorshipExpired(e)) => {
+                SimulationViolation::PaymasterSponsorshipExpired(
+                    from_bytes(&e.paymaster_address)?,
+                    Timestamp::new(e.valid_until),
+                    Timestamp::new(e.valid_after),
+                )
+            }
+            Some(simulation_violation_error::Violation::VerificationGasLimitTooLowForOp(e)) => {
+                SimulationViolation::VerificationGasLimitTooLowForOp(
+                    from_bytes(&e.needed)?,
+                    from_bytes(&e.provided)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::FactoryDeployedUnapprovedContract(e)) => {
+                SimulationViolation::FactoryDeployedUnapprovedContract(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.contract_address)?,
+                    from_bytes(&e.code_hash)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::FactoryGasLimitExceeded(e)) => {
+                SimulationViolation::FactoryGasLimitExceeded(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.gas_used)?,
+                    from_bytes(&e.limit)?,
+                )
+            }
             Some(simulation_violation_error::Violation::AccessedUnsupportedContractType(e)) => {
                 SimulationViolation::AccessedUnsupportedContractType(
                     e.contract_type,
                     from_bytes(&e.contract_address)?,
                 )
             }
+            Some(simulation_violation_error::Violation::MempoolPriorityFeeTooLow(e)) => {
+                SimulationViolation::MempoolPriorityFeeTooLow(
+                    from_bytes(&e.max_priority_fee_per_gas)?,
+                    from_bytes(&e.min_priority_fee)?,
+                )
+            }
             None => {
                 bail!("unknown proto mempool simulation violation")
             }
@@ -901,7 +1347,7 @@ impl TryFrom<ProtoValidationRevert> for ValidationRevert {
 
 #[cfg(test)]
 mod tests {
-    use alloy_primitives::U256;
+    use alloy_primitives::{Address, Bytes, U256};
 
     use super::*;
 
@@ -938,6 +1384,7 @@ mod tests {
         let error = MempoolError::SimulationViolation(SimulationViolation::UnintendedRevert(
             rundler_types::EntityType::Aggregator,
             None,
+            Bytes::new(),
         ));
         let proto_error: ProtoMempoolError = error.into();
         let error2 = proto_error.try_into().unwrap();
@@ -945,11 +1392,60 @@ mod tests {
             MempoolError::SimulationViolation(SimulationViolation::UnintendedRevert(
                 rundler_types::EntityType::Aggregator,
                 None,
+                _,
             )) => {}
             _ => panic!("wrong error type"),
         }
     }
 
+    #[test]
+    fn test_unintended_revert_data() {
+        let revert_data = Bytes::from_static(&[0x08, 0xc3, 0x79, 0xa0, 0x01, 0x02]);
+        let paymaster = Address::random();
+
+        let error = MempoolError::SimulationViolation(SimulationViolation::UnintendedRevert(
+            rundler_types::EntityType::Paymaster,
+            Some(paymaster),
+            revert_data.clone(),
+        ));
+        let proto_error: ProtoMempoolError = error.into();
+        match proto_error.try_into().unwrap() {
+            MempoolError::SimulationViolation(SimulationViolation::UnintendedRevert(
+                rundler_types::EntityType::Paymaster,
+                Some(address),
+                data,
+            )) => {
+                assert_eq!(address, paymaster);
+                assert_eq!(data, revert_data);
+            }
+            _ => panic!("wrong error type"),
+        }
+
+        let error =
+            MempoolError::SimulationViolation(SimulationViolation::UnintendedRevertWithMessage(
+                rundler_types::EntityType::Paymaster,
+                "AA33 reverted".to_string(),
+                Some(paymaster),
+                revert_data.clone(),
+            ));
+        let proto_error: ProtoMempoolError = error.into();
+        match proto_error.try_into().unwrap() {
+            MempoolError::SimulationViolation(
+                SimulationViolation::UnintendedRevertWithMessage(
+                    rundler_types::EntityType::Paymaster,
+                    reason,
+                    Some(address),
+                    data,
+                ),
+            ) => {
+                assert_eq!(reason, "AA33 reverted");
+                assert_eq!(address, paymaster);
+                assert_eq!(data, revert_data);
+            }
+            _ => panic!("wrong error type"),
+        }
+    }
+
     #[test]
     fn test_invalid_convert() {
         let error = ProtoMempoolError { error: None };
@@ -19,3 +19,4 @@ mod server;
 
 pub use client::*;
 pub(crate) use server::remote_mempool_server_task;
+pub use server::OpSizeLimits;
@@ -22,7 +22,8 @@ use rundler_types::{
     },
     pool::{
         NewHead as PoolNewHead, PaymasterMetadata as PoolPaymasterMetadata, PoolOperation,
-        Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
+        PooledOp as PoolPooledOp, Reputation as PoolReputation,
+        ReputationChange as PoolReputationChange, ReputationStatus as PoolReputationStatus,
         StakeStatus as RundlerStakeStatus,
     },
     v0_6, v0_7, Entity as RundlerEntity, EntityInfos, EntityType as RundlerEntityType,
@@ -448,6 +449,30 @@ impl TryUoFromProto<MempoolOp> for PoolOperation {
     }
 }
 
+impl From<&PoolPooledOp> for PooledOp {
+    fn from(op: &PoolPooledOp) -> Self {
+        Self {
+            hash: op.hash.to_proto_bytes(),
+            nonce: op.nonce.to_proto_bytes(),
+            max_fee_per_gas: op.max_fee_per_gas.to_proto_bytes(),
+            max_priority_fee_per_gas: op.max_priority_fee_per_gas.to_proto_bytes(),
+        }
+    }
+}
+
+impl TryFrom<PooledOp> for PoolPooledOp {
+    type Error = ConversionError;
+
+    fn try_from(op: PooledOp) -> Result<Self, Self::Error> {
+        Ok(Self {
+            hash: from_bytes(&op.hash)?,
+            nonce: from_bytes(&op.nonce)?,
+            max_fee_per_gas: from_bytes(&op.max_fee_per_gas)?,
+            max_priority_fee_per_gas: from_bytes(&op.max_priority_fee_per_gas)?,
+        })
+    }
+}
+
 impl TryFrom<NewHead> for PoolNewHead {
     type Error = ConversionError;
 
@@ -468,6 +493,32 @@ impl From<PoolNewHead> for NewHead {
     }
 }
 
+impl TryFrom<ReputationChange> for PoolReputationChange {
+    type Error = ConversionError;
+
+    fn try_from(change: ReputationChange) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: from_bytes(&change.address)?,
+            old_status: ReputationStatus::try_from(change.old_status)
+                .map_err(|_| ConversionError::InvalidEnumValue(change.old_status))?
+                .try_into()?,
+            new_status: ReputationStatus::try_from(change.new_status)
+                .map_err(|_| ConversionError::InvalidEnumValue(change.new_status))?
+                .try_into()?,
+        })
+    }
+}
+
+impl From<PoolReputationChange> for ReputationChange {
+    fn from(change: PoolReputationChange) -> Self {
+        Self {
+            address: change.address.to_proto_bytes(),
+            old_status: ReputationStatus::from(change.old_status).into(),
+            new_status: ReputationStatus::from(change.new_status).into(),
+        }
+    }
+}
+
 impl TryFrom<PaymasterBalance> for PoolPaymasterMetadata {
     type Error = ConversionError;
 
@@ -22,6 +22,7 @@ use std::{
 use alloy_primitives::{Address, B256};
 use async_trait::async_trait;
 use futures_util::StreamExt;
+use prost::Message;
 use rundler_task::{
     grpc::{grpc_metrics::GrpcMetricsLayer, protos::from_bytes},
     GracefulShutdown, TaskSpawner,
@@ -39,38 +40,112 @@ use super::protos::{
     add_op_response, admin_set_tracking_response, debug_clear_state_response,
     debug_dump_mempool_response, debug_dump_paymaster_balances_response,
     debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
+    get_ops_by_sender_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response, get_supported_aggregators_response,
     op_pool_server::{OpPool, OpPoolServer},
-    remove_op_by_id_response, remove_ops_response, update_entities_response, AddOpRequest,
-    AddOpResponse, AddOpSuccess, AdminSetTrackingRequest, AdminSetTrackingResponse,
-    AdminSetTrackingSuccess, DebugClearStateRequest, DebugClearStateResponse,
-    DebugClearStateSuccess, DebugDumpMempoolRequest, DebugDumpMempoolResponse,
-    DebugDumpMempoolSuccess, DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
+    remove_op_by_hash_response, remove_op_by_id_response, remove_ops_response,
+    update_entities_response, user_operation, AddOpRequest, AddOpResponse, AddOpSuccess,
+    AdminSetTrackingRequest, AdminSetTrackingResponse, AdminSetTrackingSuccess,
+    DebugClearStateRequest, DebugClearStateResponse, DebugClearStateSuccess,
+    DebugDumpMempoolRequest, DebugDumpMempoolResponse, DebugDumpMempoolSuccess,
+    DebugDumpPaymasterBalancesRequest, DebugDumpPaymasterBalancesResponse,
     DebugDumpPaymasterBalancesSuccess, DebugDumpReputationRequest, DebugDumpReputationResponse,
     DebugDumpReputationSuccess, DebugSetReputationRequest, DebugSetReputationResponse,
     DebugSetReputationSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
-    GetOpsRequest, GetOpsResponse, GetOpsSuccess, GetReputationStatusRequest,
-    GetReputationStatusResponse, GetReputationStatusSuccess, GetStakeStatusRequest,
-    GetStakeStatusResponse, GetStakeStatusSuccess, GetSupportedEntryPointsRequest,
-    GetSupportedEntryPointsResponse, MempoolOp, RemoveOpByIdRequest, RemoveOpByIdResponse,
+    GetOpsBySenderRequest, GetOpsBySenderResponse, GetOpsBySenderSuccess, GetOpsRequest,
+    GetOpsResponse, GetOpsSuccess, GetReputationStatusRequest, GetReputationStatusResponse,
+    GetReputationStatusSuccess, GetStakeStatusRequest, GetStakeStatusResponse,
+    GetStakeStatusSuccess, GetSupportedAggregatorsRequest, GetSupportedAggregatorsResponse,
+    GetSupportedAggregatorsSuccess, GetSupportedEntryPointsRequest,
+    GetSupportedEntryPointsResponse, MempoolOp, PooledOp, RemoveOpByHashRequest,
+    RemoveOpByHashResponse, RemoveOpByHashSuccess, RemoveOpByIdRequest, RemoveOpByIdResponse,
     RemoveOpByIdSuccess, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess, ReputationStatus,
-    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, TryUoFromProto, UpdateEntitiesRequest,
-    UpdateEntitiesResponse, UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
+    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, SubscribeReputationChangesRequest,
+    SubscribeReputationChangesResponse, TryUoFromProto, UpdateEntitiesRequest,
+    UpdateEntitiesResponse, UpdateEntitiesSuccess, UserOperation as ProtoUserOperation,
+    OP_POOL_FILE_DESCRIPTOR_SET,
 };
 use crate::server::local::LocalPoolHandle;
 
 const MAX_REMOTE_BLOCK_SUBSCRIPTIONS: usize = 32;
+const MAX_REMOTE_REPUTATION_SUBSCRIPTIONS: usize = 32;
+
+/// Size limits applied to requests received by the remote pool server.
+///
+/// All limits are optional and unset by default. Requests exceeding `max_request_size` are
+/// rejected by the transport before they are decoded. Operations exceeding any other limit
+/// are rejected with `InvalidArgument` before they are converted and handed to the pool
+/// for simulation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpSizeLimits {
+    /// Maximum size of an encoded gRPC request, in bytes. Uses tonic's default if unset.
+    pub max_request_size: Option<usize>,
+    /// Maximum size of the protobuf encoded operation, in bytes
+    pub max_op_size: Option<usize>,
+    /// Maximum size of the init code (factory + factory data), in bytes
+    pub max_init_code_size: Option<usize>,
+    /// Maximum size of the call data, in bytes
+    pub max_call_data_size: Option<usize>,
+    /// Maximum size of the paymaster and data (paymaster + paymaster data), in bytes
+    pub max_paymaster_and_data_size: Option<usize>,
+    /// Maximum size of the signature, in bytes
+    pub max_signature_size: Option<usize>,
+}
+
+impl OpSizeLimits {
+    fn validate(&self, op: &ProtoUserOperation) -> Result<()> {
+        check_size("operation", op.encoded_len(), self.max_op_size)?;
+
+        let (init_code, call_data, paymaster_and_data, signature) = match &op.uo {
+            Some(user_operation::Uo::V06(op)) => (
+                op.init_code.len(),
+                op.call_data.len(),
+                op.paymaster_and_data.len(),
+                op.signature.len(),
+            ),
+            Some(user_operation::Uo::V07(op)) => (
+                op.factory.len() + op.factory_data.len(),
+                op.call_data.len(),
+                op.paymaster.len() + op.paymaster_data.len(),
+                op.signature.len(),
+            ),
+            None => return Ok(()),
+        };
+
+        check_size("init code", init_code, self.max_init_code_size)?;
+        check_size("call data", call_data, self.max_call_data_size)?;
+        check_size(
+            "paymaster and data",
+            paymaster_and_data,
+            self.max_paymaster_and_data_size,
+        )?;
+        check_size("signature", signature, self.max_signature_size)
+    }
+}
+
+fn check_size(field: &str, size: usize, max: Option<usize>) -> Result<()> {
+    if let Some(max) = max.filter(|max| size > *max) {
+        return Err(Status::invalid_argument(format!(
+            "Operation {field} size {size} exceeds maximum of {max} bytes"
+        )));
+    }
+    Ok(())
+}
 
 pub(crate) async fn remote_mempool_server_task(
     task_spawner: Box<dyn TaskSpawner>,
     chain_spec: ChainSpec,
     local_pool: LocalPoolHandle,
+    op_size_limits: OpSizeLimits,
     addr: SocketAddr,
     shutdown: GracefulShutdown,
 ) {
     // gRPC server
-    let pool_impl = OpPoolImpl::new(chain_spec, local_pool, task_spawner);
-    let op_pool_server = OpPoolServer::new(pool_impl);
+    let pool_impl = OpPoolImpl::new(chain_spec, local_pool, op_size_limits, task_spawner);
+    let mut op_pool_server = OpPoolServer::new(pool_impl);
+    if let Some(max_request_size) = op_size_limits.max_request_size {
+        op_pool_server = op_pool_server.max_decoding_message_size(max_request_size);
+    }
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(OP_POOL_FILE_DESCRIPTOR_SET)
         .build_v1()
@@ -101,7 +176,9 @@ pub(crate) async fn remote_mempool_server_task(
 struct OpPoolImpl {
     chain_spec: ChainSpec,
     local_pool: LocalPoolHandle,
+    op_size_limits: OpSizeLimits,
     num_block_subscriptions: Arc<AtomicUsize>,
+    num_reputation_subscriptions: Arc<AtomicUsize>,
     task_spawner: Box<dyn TaskSpawner>,
 }
 
@@ -109,12 +186,15 @@ impl OpPoolImpl {
     pub(crate) fn new(
         chain_spec: ChainSpec,
         local_pool: LocalPoolHandle,
+        op_size_limits: OpSizeLimits,
         task_spawner: Box<dyn TaskSpawner>,
     ) -> Self {
         Self {
             chain_spec,
             local_pool,
+            op_size_limits,
             num_block_subscriptions: Arc::new(AtomicUsize::new(0)),
+            num_reputation_subscriptions: Arc::new(AtomicUsize::new(0)),
             task_spawner,
         }
     }
@@ -148,6 +228,31 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_supported_aggregators(
+        &self,
+        request: Request<GetSupportedAggregatorsRequest>,
+    ) -> Result<Response<GetSupportedAggregatorsResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let resp = match self.local_pool.get_supported_aggregators(ep).await {
+            Ok(aggregators) => GetSupportedAggregatorsResponse {
+                result: Some(get_supported_aggregators_response::Result::Success(
+                    GetSupportedAggregatorsSuccess {
+                        aggregators: aggregators.into_iter().map(|a| a.to_vec()).collect(),
+                    },
+                )),
+            },
+            Err(error) => GetSupportedAggregatorsResponse {
+                result: Some(get_supported_aggregators_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn add_op(&self, request: Request<AddOpRequest>) -> Result<Response<AddOpResponse>> {
         let req = request.into_inner();
         let ep = self.get_entry_point(&req.entry_point)?;
@@ -155,6 +260,7 @@ impl OpPool for OpPoolImpl {
         let proto_op = req
             .op
             .ok_or_else(|| Status::invalid_argument("Operation is required in AddOpRequest"))?;
+        self.op_size_limits.validate(&proto_op)?;
         let uo =
             UserOperationVariant::try_uo_from_proto(proto_op, &self.chain_spec).map_err(|e| {
                 Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
@@ -222,6 +328,32 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_ops_by_sender(
+        &self,
+        request: Request<GetOpsBySenderRequest>,
+    ) -> Result<Response<GetOpsBySenderResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let sender = from_bytes(&req.sender).map_err(|e| {
+            Status::invalid_argument(format!("Invalid sender in GetOpsBySenderRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.get_ops_by_sender(ep, sender).await {
+            Ok(ops) => GetOpsBySenderResponse {
+                result: Some(get_ops_by_sender_response::Result::Success(
+                    GetOpsBySenderSuccess {
+                        ops: ops.iter().map(PooledOp::from).collect(),
+                    },
+                )),
+            },
+            Err(error) => GetOpsBySenderResponse {
+                result: Some(get_ops_by_sender_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn remove_ops(
         &self,
         request: Request<RemoveOpsRequest>,
@@ -287,6 +419,34 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn remove_op_by_hash(
+        &self,
+        request: Request<RemoveOpByHashRequest>,
+    ) -> Result<Response<RemoveOpByHashResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        if req.hash.len() != 32 {
+            return Err(Status::invalid_argument("Hash must be 32 bytes long"));
+        }
+
+        let resp = match self
+            .local_pool
+            .remove_op_by_hash(ep, B256::from_slice(&req.hash))
+            .await
+        {
+            Ok(removed) => RemoveOpByHashResponse {
+                result: Some(remove_op_by_hash_response::Result::Success(
+                    RemoveOpByHashSuccess { removed },
+                )),
+            },
+            Err(error) => RemoveOpByHashResponse {
+                result: Some(remove_op_by_hash_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn update_entities(
         &self,
         request: Request<UpdateEntitiesRequest>,
@@ -579,4 +739,141 @@ impl OpPool for OpPoolImpl {
 
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
+
+    type SubscribeReputationChangesStream =
+        UnboundedReceiverStream<Result<SubscribeReputationChangesResponse>>;
+
+    async fn subscribe_reputation_changes(
+        &self,
+        request: Request<SubscribeReputationChangesRequest>,
+    ) -> Result<Response<Self::SubscribeReputationChangesStream>> {
+        let req = request.into_inner();
+        let entry_point = self.get_entry_point(&req.entry_point)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        if self
+            .num_reputation_subscriptions
+            .fetch_add(1, Ordering::Relaxed)
+            >= MAX_REMOTE_REPUTATION_SUBSCRIPTIONS
+        {
+            self.num_reputation_subscriptions
+                .fetch_sub(1, Ordering::Relaxed);
+            return Err(Status::resource_exhausted(
+                "Too many reputation subscriptions",
+            ));
+        }
+
+        let num_reputation_subscriptions = Arc::clone(&self.num_reputation_subscriptions);
+        let mut changes = match self
+            .local_pool
+            .subscribe_reputation_changes(entry_point)
+            .await
+        {
+            Ok(changes) => changes,
+            Err(error) => {
+                num_reputation_subscriptions.fetch_sub(1, Ordering::Relaxed);
+                tracing::error!("Failed to subscribe to reputation changes: {error}");
+                return Err(Status::internal(format!(
+                    "Failed to subscribe to reputation changes: {error}"
+                )));
+            }
+        };
+
+        self.task_spawner.spawn(Box::pin(async move {
+            loop {
+                match changes.next().await {
+                    Some(change) => {
+                        if tx
+                            .send(Ok(SubscribeReputationChangesResponse {
+                                reputation_change: Some(change.into()),
+                            }))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    None => {
+                        tracing::warn!("reputation change subscription closed");
+                        break;
+                    }
+                }
+            }
+            num_reputation_subscriptions.fetch_sub(1, Ordering::Relaxed);
+        }));
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::remote::protos::{UserOperationV06, UserOperationV07};
+
+    fn v06_op(init_code: usize, call_data: usize, signature: usize) -> ProtoUserOperation {
+        ProtoUserOperation {
+            uo: Some(user_operation::Uo::V06(UserOperationV06 {
+                init_code: vec![1; init_code],
+                call_data: vec![1; call_data],
+                signature: vec![1; signature],
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn test_op_size_limits_ok() {
+        let limits = OpSizeLimits {
+            max_op_size: Some(65_536),
+            max_init_code_size: Some(32_768),
+            max_call_data_size: Some(32_768),
+            max_paymaster_and_data_size: Some(32_768),
+            max_signature_size: Some(32_768),
+            ..Default::default()
+        };
+        assert!(limits.validate(&v06_op(100, 100, 65)).is_ok());
+    }
+
+    #[test]
+    fn test_op_size_limits_unset() {
+        let limits = OpSizeLimits::default();
+        assert!(limits.validate(&v06_op(100_000, 100_000, 100_000)).is_ok());
+    }
+
+    #[test]
+    fn test_op_size_limits_field_too_large() {
+        let limits = OpSizeLimits {
+            max_call_data_size: Some(10),
+            ..Default::default()
+        };
+        let err = limits.validate(&v06_op(0, 11, 0)).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_op_size_limits_v07_paymaster_and_data() {
+        let limits = OpSizeLimits {
+            max_paymaster_and_data_size: Some(30),
+            ..Default::default()
+        };
+        let op = ProtoUserOperation {
+            uo: Some(user_operation::Uo::V07(UserOperationV07 {
+                paymaster: vec![1; 20],
+                paymaster_data: vec![1; 11],
+                ..Default::default()
+            })),
+        };
+        let err = limits.validate(&op).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_op_size_limits_encoded_too_large() {
+        let limits = OpSizeLimits {
+            max_op_size: Some(100),
+            ..Default::default()
+        };
+        let err = limits.validate(&v06_op(40, 40, 40)).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
 }
@@ -13,16 +13,25 @@
 
 use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
+use alloy_primitives::Address;
 use anyhow::{bail, Context};
 use futures::FutureExt;
-use rundler_provider::{Providers, ProvidersWithEntryPointT};
+use rundler_provider::{
+    EntryPointVersionDetector, EvmProvider, ProviderError, Providers, ProvidersWithEntryPointT,
+};
 use rundler_sim::{
     gas::{self, FeeEstimatorImpl},
     simulation::{self, UnsafeSimulator},
-    PrecheckerImpl, Simulator,
+    AdmissionPolicy, CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecializationV06,
+    CallGasEstimatorSpecializationV07, EstimationSettings, PaymasterTimeRangeDecoder,
+    PrecheckerImpl, RejectionSink, Simulator, StorageSlotLabeler, TracerMode,
 };
 use rundler_task::TaskSpawnerExt;
-use rundler_types::{chain::ChainSpec, EntryPointVersion, UserOperation, UserOperationVariant};
+use rundler_types::{
+    chain::ChainSpec, pool::PoolOperation, v0_6::UserOperation as UserOperationV0_6,
+    v0_7::UserOperation as UserOperationV0_7, EntryPointVersion, UserOperation,
+    UserOperationVariant,
+};
 use rundler_utils::emit::WithEntryPoint;
 use tokio::sync::broadcast;
 
@@ -34,7 +43,7 @@ use crate::{
         AddressReputation, Mempool, PaymasterConfig, PaymasterTracker, ReputationParams, UoPool,
         UoPoolProviders,
     },
-    server::{self, LocalPoolBuilder},
+    server::{self, LocalPoolBuilder, OpSizeLimits},
 };
 
 /// Arguments for the pool task.
@@ -46,17 +55,38 @@ pub struct Args {
     pub unsafe_mode: bool,
     /// HTTP URL for the full node.
     pub http_url: String,
+    /// WebSocket URL for the full node, if any. If set, the pool is driven by the
+    /// node's new heads subscription instead of polling.
+    pub ws_url: Option<String>,
     /// Interval to poll the chain for updates.
     pub chain_poll_interval: Duration,
     /// Number of times to retry a block sync at the `chain_poll_interval` before abandoning
     pub chain_max_sync_retries: u64,
     /// Pool configurations.
     pub pool_configs: Vec<PoolConfig>,
+    /// Admission policies evaluated against v0.6 operations that pass validation.
+    pub admission_policies_v0_6: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_6>>>,
+    /// Admission policies evaluated against v0.7 operations that pass validation.
+    pub admission_policies_v0_7: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_7>>>,
+    /// Sink sent a record of every operation rejected by simulation, if any.
+    pub rejection_sink: Option<Arc<dyn RejectionSink>>,
+    /// Labeler describing storage slots in storage access violations.
+    pub storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    /// Decoders of the sponsorship time range of paymasters, keyed by paymaster address.
+    pub paymaster_time_range_decoders: HashMap<Address, Arc<dyn PaymasterTimeRangeDecoder>>,
+    /// Gas estimation settings, used to run operations through the entry point for the
+    /// call phase revert check.
+    pub estimation_settings: EstimationSettings,
     /// Address to bind the remote mempool server to, if any.
     /// If not provided, a server will not be started.
     pub remote_address: Option<SocketAddr>,
+    /// Size limits for operations received by the remote mempool server.
+    pub op_size_limits: OpSizeLimits,
     /// Channel capacity for the chain update channel.
     pub chain_update_channel_capacity: usize,
+    /// Operations to seed the mempools with on startup, e.g. from a snapshot.
+    /// Each operation is re-validated before being added to the pool of its entry point.
+    pub initial_operations: Vec<PoolOperation>,
 }
 
 /// Mempool task.
@@ -89,16 +119,83 @@ where
     P: Providers + 'static,
 {
     /// Spawns the mempool task on the given task spawner.
-    pub async fn spawn<T: TaskSpawnerExt>(self, task_spawner: T) -> anyhow::Result<()> {
+    pub async fn spawn<T: TaskSpawnerExt>(mut self, task_spawner: T) -> anyhow::Result<()> {
         let chain_id = self.args.chain_spec.id;
         tracing::info!("Chain id: {chain_id}");
         tracing::info!("Http url: {:?}", self.args.http_url);
 
+        // Fail fast if the node serves a different chain than configured
+        let provider_chain_id = self
+            .providers
+            .evm()
+            .get_chain_id()
+            .await
+            .context("should get chain id from provider")?;
+        if provider_chain_id != chain_id {
+            return Err(ProviderError::ChainIdMismatch {
+                expected: chain_id,
+                actual: provider_chain_id,
+            }
+            .into());
+        }
+
+        // detect the version of entry points that are not configured with one
+        let detector = EntryPointVersionDetector::new(self.providers.evm().clone());
+        for pool_config in &mut self.args.pool_configs {
+            if pool_config.entry_point_version == EntryPointVersion::Unspecified {
+                pool_config.entry_point_version = detector
+                    .detect_version(pool_config.entry_point)
+                    .await
+                    .context("should detect entry point version")?;
+                tracing::info!(
+                    "Detected entry point {:?} version: {:?}",
+                    pool_config.entry_point,
+                    pool_config.entry_point_version
+                );
+            }
+        }
+
+        // resolve the tracer mode of pools configured to detect it
+        if self
+            .args
+            .pool_configs
+            .iter()
+            .any(|config| config.sim_settings.tracer_mode == TracerMode::Auto)
+        {
+            let tracer_mode = simulation::detect_tracer_mode(
+                self.providers
+                    .trace_evm()
+                    .as_ref()
+                    .unwrap_or(self.providers.evm()),
+            )
+            .await;
+            tracing::info!("Detected tracer mode: {tracer_mode}");
+            for pool_config in &mut self.args.pool_configs {
+                if pool_config.sim_settings.tracer_mode == TracerMode::Auto {
+                    pool_config.sim_settings.tracer_mode = tracer_mode;
+                }
+            }
+        }
+
+        // struct log tracing can only validate v0.6 operations
+        if !self.args.unsafe_mode {
+            if let Some(config) = self.args.pool_configs.iter().find(|config| {
+                config.entry_point_version == EntryPointVersion::V0_7
+                    && config.sim_settings.tracer_mode == TracerMode::StructLogs
+            }) {
+                bail!(
+                    "struct log tracing is not supported for entry point v0.7 {:?}, use the custom tracer or disable entry point v0.7",
+                    config.entry_point
+                );
+            }
+        }
+
         // create chain
         let chain_settings = chain::Settings {
             history_size: self.args.chain_spec.chain_history_size,
             poll_interval: self.args.chain_poll_interval,
             max_sync_retries: self.args.chain_max_sync_retries,
+            ws_url: self.args.ws_url.clone(),
             entry_point_addresses: self
                 .args
                 .pool_configs
@@ -127,6 +224,7 @@ where
                             self.args.unsafe_mode,
                             self.event_sender.clone(),
                         )
+                        .await
                         .context("should have created mempool")?;
 
                     mempools.insert(pool_config.entry_point, pool);
@@ -140,6 +238,7 @@ where
                             self.args.unsafe_mode,
                             self.event_sender.clone(),
                         )
+                        .await
                         .context("should have created mempool")?;
 
                     mempools.insert(pool_config.entry_point, pool);
@@ -170,6 +269,7 @@ where
                         ts_box,
                         self.args.chain_spec.clone(),
                         pool_handle,
+                        self.args.op_size_limits,
                         addr,
                         shutdown,
                     )
@@ -182,7 +282,7 @@ where
         Ok(())
     }
 
-    fn create_mempool_v0_6<T: TaskSpawnerExt>(
+    async fn create_mempool_v0_6<T: TaskSpawnerExt>(
         &self,
         task_spawner: &T,
         chain_spec: ChainSpec,
@@ -196,8 +296,17 @@ where
             .clone()
             .context("entry point v0.6 not supplied")?;
 
+        let call_gas_estimator = CallGasEstimatorImpl::new(
+            ep_providers.entry_point().clone(),
+            self.args.estimation_settings,
+            CallGasEstimatorSpecializationV06::new(chain_spec.clone()),
+        );
+
         if unsafe_mode {
-            let simulator = UnsafeSimulator::new(ep_providers.entry_point().clone());
+            let simulator = UnsafeSimulator::new(
+                ep_providers.entry_point().clone(),
+                pool_config.sim_settings.violation_priority.clone(),
+            );
             self.create_mempool(
                 task_spawner,
                 chain_spec,
@@ -205,13 +314,21 @@ where
                 event_sender,
                 ep_providers,
                 simulator,
+                call_gas_estimator,
             )
+            .await
         } else {
             let simulator = simulation::new_v0_6_simulator(
                 ep_providers.evm().clone(),
+                self.providers.trace_evm().clone(),
                 ep_providers.entry_point().clone(),
+                chain_spec.id,
                 pool_config.sim_settings.clone(),
                 pool_config.mempool_channel_configs.clone(),
+                self.args.admission_policies_v0_6.clone(),
+                self.args.rejection_sink.clone(),
+                self.args.storage_slot_labeler.clone(),
+                self.args.paymaster_time_range_decoders.clone(),
             );
             self.create_mempool(
                 task_spawner,
@@ -220,11 +337,13 @@ where
                 event_sender,
                 ep_providers,
                 simulator,
+                call_gas_estimator,
             )
+            .await
         }
     }
 
-    fn create_mempool_v0_7<T: TaskSpawnerExt>(
+    async fn create_mempool_v0_7<T: TaskSpawnerExt>(
         &self,
         task_spawner: &T,
         chain_spec: ChainSpec,
@@ -238,8 +357,17 @@ where
             .clone()
             .context("entry point v0.7 not supplied")?;
 
+        let call_gas_estimator = CallGasEstimatorImpl::new(
+            ep_providers.entry_point().clone(),
+            self.args.estimation_settings,
+            CallGasEstimatorSpecializationV07::new(chain_spec.clone()),
+        );
+
         if unsafe_mode {
-            let simulator = UnsafeSimulator::new(ep_providers.entry_point().clone());
+            let simulator = UnsafeSimulator::new(
+                ep_providers.entry_point().clone(),
+                pool_config.sim_settings.violation_priority.clone(),
+            );
             self.create_mempool(
                 task_spawner,
                 chain_spec,
@@ -247,13 +375,21 @@ where
                 event_sender,
                 ep_providers,
                 simulator,
+                call_gas_estimator,
             )
+            .await
         } else {
             let simulator = simulation::new_v0_7_simulator(
                 self.providers.evm().clone(),
+                self.providers.trace_evm().clone(),
                 ep_providers.entry_point().clone(),
+                chain_spec.id,
                 pool_config.sim_settings.clone(),
                 pool_config.mempool_channel_configs.clone(),
+                self.args.admission_policies_v0_7.clone(),
+                self.args.rejection_sink.clone(),
+                self.args.storage_slot_labeler.clone(),
+                self.args.paymaster_time_range_decoders.clone(),
             );
             self.create_mempool(
                 task_spawner,
@@ -262,11 +398,14 @@ where
                 event_sender,
                 ep_providers,
                 simulator,
+                call_gas_estimator,
             )
+            .await
         }
     }
 
-    fn create_mempool<T, UO, EP, S>(
+    #[allow(clippy::too_many_arguments)]
+    async fn create_mempool<T, UO, EP, S, C>(
         &self,
         task_spawner: &T,
         chain_spec: ChainSpec,
@@ -274,6 +413,7 @@ where
         event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
         ep_providers: EP,
         simulator: S,
+        call_gas_estimator: C,
     ) -> anyhow::Result<Arc<dyn Mempool + 'static>>
     where
         T: TaskSpawnerExt,
@@ -281,6 +421,7 @@ where
         UserOperationVariant: From<UO>,
         EP: ProvidersWithEntryPointT<UO = UO> + 'static,
         S: Simulator<UO = UO> + 'static,
+        C: CallGasEstimator<UO = UO> + 'static,
     {
         let fee_oracle = gas::get_fee_oracle(&chain_spec, ep_providers.evm().clone());
         let fee_estimator = FeeEstimatorImpl::new(
@@ -329,12 +470,30 @@ where
         let uo_pool = UoPool::new(
             pool_config.clone(),
             ep_providers,
-            UoPoolProviders::new(simulator, prechecker),
+            UoPoolProviders::new(simulator, prechecker, call_gas_estimator),
             event_sender,
             paymaster,
             reputation,
+        )
+        .with_operations(
+            self.args
+                .initial_operations
+                .iter()
+                .filter(|op| op.entry_point == pool_config.entry_point)
+                .cloned()
+                .collect(),
+            true,
+        )
+        .await;
+        let uo_pool = Arc::new(uo_pool);
+
+        // Evict operations of entities as they become banned
+        let eviction_pool = Arc::clone(&uo_pool);
+        task_spawner.spawn_critical(
+            "reputation eviction",
+            async move { eviction_pool.run_reputation_eviction().await }.boxed(),
         );
 
-        Ok(Arc::new(uo_pool))
+        Ok(uo_pool)
     }
 }
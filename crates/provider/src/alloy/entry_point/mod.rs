@@ -18,6 +18,7 @@ use alloy_rpc_types_eth::TransactionRequest;
 
 pub(crate) mod v0_6;
 pub(crate) mod v0_7;
+pub(crate) mod version;
 
 fn max_bundle_transaction_data(to_address: Address, data: Bytes, gas_price: u128) -> Bytes {
     // Fill in max values for unknown or varying fields
@@ -12,7 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_contract::Error as ContractError;
-use alloy_primitives::{Address, Bytes, U256};
+use alloy_primitives::{aliases::U192, Address, Bytes, U256};
 use alloy_provider::Provider as AlloyProvider;
 use alloy_rpc_types_eth::{state::StateOverride, BlockId, TransactionRequest};
 use alloy_sol_types::{ContractError as SolContractError, SolCall, SolError, SolInterface};
@@ -136,6 +136,22 @@ where
 
         Ok(out.balances)
     }
+
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let ret = block_id
+            .map_or(self.i_entry_point.getNonce(sender, key), |bid| {
+                self.i_entry_point.getNonce(sender, key).block(bid)
+            })
+            .call()
+            .await?;
+
+        Ok(ret.nonce)
+    }
 }
 
 #[async_trait::async_trait]
@@ -187,6 +203,7 @@ where
         &self,
         aggregator_address: Address,
         user_op: Self::UO,
+        block_id: Option<BlockId>,
     ) -> ProviderResult<AggregatorOut> {
         let aggregator = IAggregator::new(aggregator_address, self.i_entry_point.provider());
         let da_gas: u64 = user_op
@@ -194,11 +211,13 @@ where
             .try_into()
             .unwrap_or(u64::MAX);
 
-        let result = aggregator
+        let mut call = aggregator
             .validateUserOpSignature(user_op.into())
-            .gas(self.max_verification_gas.saturating_add(da_gas))
-            .call()
-            .await;
+            .gas(self.max_verification_gas.saturating_add(da_gas));
+        if let Some(block_id) = block_id {
+            call = call.block(block_id);
+        }
+        let result = call.call().await;
 
         match result {
             Ok(ret) => Ok(AggregatorOut::SuccessWithInfo(AggregatorSimOut {
@@ -206,8 +225,8 @@ where
                 signature: ret.sigForUserOp,
             })),
             Err(ContractError::TransportError(TransportError::ErrorResp(resp))) => {
-                if resp.as_revert_data().is_some() {
-                    Ok(AggregatorOut::ValidationReverted)
+                if let Some(revert_data) = resp.as_revert_data() {
+                    Ok(AggregatorOut::ValidationReverted(revert_data))
                 } else {
                     Err(TransportError::ErrorResp(resp).into())
                 }
@@ -215,6 +234,46 @@ where
             Err(error) => Err(error.into()),
         }
     }
+
+    async fn validate_signatures(
+        &self,
+        aggregator_address: Address,
+        ops: Vec<Self::UO>,
+        signature: Bytes,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<bool> {
+        let aggregator = IAggregator::new(aggregator_address, self.i_entry_point.provider());
+        let ops_len = ops.len();
+        let da_gas: u64 = ops
+            .iter()
+            .map(|op: &UserOperation| {
+                op.pre_verification_da_gas_limit(&self.chain_spec, Some(ops_len))
+            })
+            .sum::<u128>()
+            .try_into()
+            .unwrap_or(u64::MAX);
+        let ops: Vec<ContractUserOperation> = ops.into_iter().map(Into::into).collect();
+
+        let mut call = aggregator
+            .validateSignatures(ops, signature)
+            .gas(self.max_aggregation_gas.saturating_add(da_gas));
+        if let Some(block_id) = block_id {
+            call = call.block(block_id);
+        }
+        let result = call.call().await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(ContractError::TransportError(TransportError::ErrorResp(resp))) => {
+                if resp.as_revert_data().is_some() {
+                    Ok(false)
+                } else {
+                    Err(TransportError::ErrorResp(resp).into())
+                }
+            }
+            Err(error) => Err(error).context("aggregator contract should validate signatures")?,
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -231,6 +290,7 @@ where
         ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
         beneficiary: Address,
         gas_limit: Option<u64>,
+        block_id: Option<BlockId>,
     ) -> ProviderResult<HandleOpsOut> {
         let gas_limit = gas_limit.unwrap_or(self.max_simulate_handle_op_gas);
         let tx = get_handle_ops_call(
@@ -239,7 +299,11 @@ where
             beneficiary,
             gas_limit,
         );
-        let res = self.i_entry_point.provider().call(&tx).await;
+        let mut call = self.i_entry_point.provider().call(&tx);
+        if let Some(block_id) = block_id {
+            call = call.block(block_id);
+        }
+        let res = call.await;
 
         match res {
             Ok(_) => return Ok(HandleOpsOut::Success),
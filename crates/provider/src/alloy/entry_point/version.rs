@@ -0,0 +1,185 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::HashMap;
+
+use alloy_primitives::{fixed_bytes, Address, FixedBytes};
+use alloy_sol_types::SolCall;
+use alloy_transport::TransportError;
+use rundler_contracts::utils::IERC165::supportsInterfaceCall;
+use rundler_types::EntryPointVersion;
+use tokio::sync::RwLock;
+
+use crate::{EvmProvider, ProviderError, ProviderResult, StateOverride, TransactionRequest};
+
+// ERC-165 interface id of `supportsInterface` itself
+const ERC165_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("01ffc9a7");
+
+/// Detects the version of an entry point contract from its onchain code.
+///
+/// The v0.7 entry point implements ERC-165 while the v0.6 entry point does not, so
+/// a `supportsInterface` probe is used to tell them apart. Detected versions are cached,
+/// and overrides can be used to force the version of an address without probing it.
+#[derive(Debug)]
+pub struct EntryPointVersionDetector<P> {
+    provider: P,
+    overrides: HashMap<Address, EntryPointVersion>,
+    cache: RwLock<HashMap<Address, EntryPointVersion>>,
+}
+
+impl<P: EvmProvider> EntryPointVersionDetector<P> {
+    /// Create a new detector
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            overrides: HashMap::new(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Force the version of an entry point address, skipping detection
+    pub fn with_override(mut self, address: Address, version: EntryPointVersion) -> Self {
+        self.overrides.insert(address, version);
+        self
+    }
+
+    /// Detect the version of the entry point at `address`
+    ///
+    /// Returns `EntryPointVersion::Unspecified` if there is no code at the address. This
+    /// result is not cached, as the entry point may be deployed later.
+    pub async fn detect_version(&self, address: Address) -> ProviderResult<EntryPointVersion> {
+        if let Some(version) = self.overrides.get(&address) {
+            return Ok(*version);
+        }
+        if let Some(version) = self.cache.read().await.get(&address) {
+            return Ok(*version);
+        }
+
+        let code = self.provider.get_code(address, None).await?;
+        if code.is_empty() {
+            return Ok(EntryPointVersion::Unspecified);
+        }
+
+        let call = supportsInterfaceCall {
+            interfaceId: ERC165_INTERFACE_ID,
+        };
+        let tx = TransactionRequest::default()
+            .to(address)
+            .input(call.abi_encode().into());
+        let version = match self
+            .provider
+            .call(&tx, None, &StateOverride::default())
+            .await
+        {
+            Ok(ret) => match supportsInterfaceCall::abi_decode_returns(&ret, false) {
+                Ok(ret) if ret._0 => EntryPointVersion::V0_7,
+                _ => EntryPointVersion::V0_6,
+            },
+            // The v0.6 entry point has no fallback function and reverts on unknown selectors
+            Err(ProviderError::RPC(TransportError::ErrorResp(resp)))
+                if resp.message.contains("execution reverted") =>
+            {
+                EntryPointVersion::V0_6
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.cache.write().await.insert(address, version);
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_primitives::{address, Bytes};
+    use alloy_sol_types::SolValue;
+
+    use super::*;
+    use crate::MockEvmProvider;
+
+    const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+    fn provider_with_code() -> MockEvmProvider {
+        let mut provider = MockEvmProvider::default();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::from_static(&[0x60, 0x80])));
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_detect_v0_7() {
+        let mut provider = provider_with_code();
+        provider
+            .expect_call()
+            .times(1)
+            .returning(|_, _, _| Ok(true.abi_encode().into()));
+        let detector = EntryPointVersionDetector::new(provider);
+
+        assert_eq!(
+            detector.detect_version(ENTRY_POINT).await.unwrap(),
+            EntryPointVersion::V0_7
+        );
+        // cached, the provider is only called once
+        assert_eq!(
+            detector.detect_version(ENTRY_POINT).await.unwrap(),
+            EntryPointVersion::V0_7
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_v0_6() {
+        let mut provider = provider_with_code();
+        provider.expect_call().returning(|_, _, _| {
+            Err(ProviderError::RPC(TransportError::ErrorResp(
+                ErrorPayload {
+                    code: 3,
+                    message: "execution reverted".into(),
+                    data: None,
+                },
+            )))
+        });
+        let detector = EntryPointVersionDetector::new(provider);
+
+        assert_eq!(
+            detector.detect_version(ENTRY_POINT).await.unwrap(),
+            EntryPointVersion::V0_6
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_undeployed() {
+        let mut provider = MockEvmProvider::default();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::new()));
+        let detector = EntryPointVersionDetector::new(provider);
+
+        assert_eq!(
+            detector.detect_version(ENTRY_POINT).await.unwrap(),
+            EntryPointVersion::Unspecified
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_override() {
+        let detector = EntryPointVersionDetector::new(MockEvmProvider::default())
+            .with_override(ENTRY_POINT, EntryPointVersion::V0_6);
+
+        assert_eq!(
+            detector.detect_version(ENTRY_POINT).await.unwrap(),
+            EntryPointVersion::V0_6
+        );
+    }
+}
@@ -30,6 +30,7 @@ use rundler_contracts::utils::{
     GetCodeHashes::{self, GetCodeHashesInstance},
     GetGasUsed::{self, GasUsedResult},
     StorageLoader,
+    IERC20::IERC20Instance,
 };
 
 use crate::{EvmCall, EvmProvider, ProviderResult};
@@ -117,6 +118,10 @@ where
         Ok(self.inner.get_block_number().await?)
     }
 
+    async fn get_chain_id(&self) -> ProviderResult<u64> {
+        Ok(self.inner.get_chain_id().await?)
+    }
+
     async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>> {
         Ok(self
             .inner
@@ -133,6 +138,37 @@ where
         Ok(call.await?)
     }
 
+    async fn get_token_balance(
+        &self,
+        token: Address,
+        owner: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let token = IERC20Instance::new(token, &self.inner);
+        let mut call = token.balanceOf(owner);
+        if let Some(block) = block {
+            call = call.block(block);
+        }
+
+        Ok(call.call().await?._0)
+    }
+
+    async fn get_token_allowance(
+        &self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let token = IERC20Instance::new(token, &self.inner);
+        let mut call = token.allowance(owner, spender);
+        if let Some(block) = block {
+            call = call.block(block);
+        }
+
+        Ok(call.call().await?._0)
+    }
+
     async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>> {
         Ok(self.inner.get_transaction_by_hash(tx).await?)
     }
@@ -158,6 +194,13 @@ where
             .context("should have a next block base fee")?)
     }
 
+    async fn get_latest_base_fee(&self) -> ProviderResult<u128> {
+        let fee_history = self.fee_history(1, BlockNumberOrTag::Latest, &[]).await?;
+        Ok(fee_history
+            .latest_block_base_fee()
+            .context("should have a latest block base fee")?)
+    }
+
     async fn get_max_priority_fee(&self) -> ProviderResult<u128> {
         Ok(self.inner.get_max_priority_fee_per_gas().await?)
     }
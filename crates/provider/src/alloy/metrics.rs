@@ -23,10 +23,16 @@ use rundler_types::task::{
 use tower::{Layer, Service};
 
 /// Alloy provider metric layer.
-#[derive(Default)]
-pub(crate) struct AlloyMetricLayer {}
+pub(crate) struct AlloyMetricLayer {
+    service_name: String,
+}
 
-impl AlloyMetricLayer {}
+impl AlloyMetricLayer {
+    /// Create a metric layer recording requests under the given service name
+    pub(crate) fn new(service_name: String) -> Self {
+        Self { service_name }
+    }
+}
 
 impl<S> Layer<S> for AlloyMetricLayer
 where
@@ -35,12 +41,13 @@ where
     type Service = AlloyMetricMiddleware<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        AlloyMetricMiddleware::new(service)
+        AlloyMetricMiddleware::new(service, self.service_name.clone())
     }
 }
 
 pub struct AlloyMetricMiddleware<S> {
     service: S,
+    service_name: String,
 }
 
 impl<S> AlloyMetricMiddleware<S>
@@ -48,8 +55,11 @@ where
     S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError> + Sync,
 {
     /// carete an alloy provider metric layer.
-    pub fn new(service: S) -> Self {
-        Self { service }
+    pub fn new(service: S, service_name: String) -> Self {
+        Self {
+            service,
+            service_name,
+        }
     }
 }
 
@@ -60,6 +70,7 @@ where
     fn clone(&self) -> Self {
         Self {
             service: self.service.clone(),
+            service_name: self.service_name.clone(),
         }
     }
 }
@@ -84,7 +95,7 @@ where
     fn call(&mut self, request: RequestPacket) -> Self::Future {
         let method_name = get_method_name(&request);
         let method_logger = MethodSessionLogger::start(
-            "alloy_provider_client".to_string(),
+            self.service_name.clone(),
             method_name.clone(),
             "rpc".to_string(),
         );
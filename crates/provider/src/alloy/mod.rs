@@ -28,21 +28,28 @@ pub use da::new_alloy_da_gas_oracle;
 pub(crate) mod entry_point;
 pub(crate) mod evm;
 pub(crate) mod metrics;
+mod subscription;
+pub use subscription::HeadSubscription;
+
+/// Service name that provider requests are recorded under in metrics by default
+pub const DEFAULT_PROVIDER_SERVICE_NAME: &str = "alloy_provider_client";
 
 /// Create a new alloy evm provider from a given RPC URL
 pub fn new_alloy_evm_provider(rpc_url: &str) -> anyhow::Result<impl EvmProvider + Clone> {
-    let provider = new_alloy_provider(rpc_url)?;
+    let provider = new_alloy_provider(rpc_url, DEFAULT_PROVIDER_SERVICE_NAME)?;
     Ok(AlloyEvmProvider::new(provider))
 }
 
-/// Create a new alloy provider from a given RPC URL
+/// Create a new alloy provider from a given RPC URL, recording its requests in metrics
+/// under `service_name`
 pub fn new_alloy_provider(
     rpc_url: &str,
+    service_name: &str,
 ) -> anyhow::Result<
     impl AlloyProvider<RetryBackoffService<AlloyMetricMiddleware<Http<Client>>>> + Clone,
 > {
     let url = Url::parse(rpc_url).context("invalid rpc url")?;
-    let metric_layer = AlloyMetricLayer::default();
+    let metric_layer = AlloyMetricLayer::new(service_name.to_string());
     // TODO: make this configurable: use a large number for CUPS for now
     let retry_layer = alloy_transport::layers::RetryBackoffLayer::new(10, 500, 1_000_000);
     let client = ClientBuilder::default()
@@ -0,0 +1,64 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{fmt::Debug, pin::Pin};
+
+use alloy_primitives::B256;
+use alloy_provider::{Provider as AlloyProvider, ProviderBuilder, WsConnect};
+use anyhow::Context;
+use futures_util::{stream, Stream, StreamExt};
+
+/// A subscription to new chain heads
+pub struct HeadSubscription {
+    stream: Pin<Box<dyn Stream<Item = B256> + Send>>,
+}
+
+impl HeadSubscription {
+    /// Connect to a node over WebSocket and subscribe to its `newHeads`
+    pub async fn connect(ws_url: &str) -> anyhow::Result<Self> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(ws_url))
+            .await
+            .context("should connect to websocket")?;
+        let subscription = provider
+            .subscribe_blocks()
+            .await
+            .context("should subscribe to new heads")?;
+
+        // The provider is held by the stream, as dropping it closes the connection
+        let stream = stream::unfold(
+            (provider, subscription),
+            |(provider, mut subscription)| async move {
+                let block = subscription.recv().await.ok()?;
+                Some((block.header.hash, (provider, subscription)))
+            },
+        );
+
+        Ok(Self {
+            stream: Box::pin(stream),
+        })
+    }
+
+    /// Wait for the hash of the next head.
+    ///
+    /// Returns `None` once the subscription has been dropped, e.g. when the connection is lost.
+    pub async fn next(&mut self) -> Option<B256> {
+        self.stream.next().await
+    }
+}
+
+impl Debug for HeadSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeadSubscription").finish_non_exhaustive()
+    }
+}
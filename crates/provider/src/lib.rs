@@ -29,9 +29,11 @@ pub use alloy::{
             decode_validation_revert as decode_v0_7_validation_revert,
             EntryPointProvider as AlloyEntryPointV0_7,
         },
+        version::EntryPointVersionDetector,
     },
     evm::AlloyEvmProvider,
-    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider,
+    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider, HeadSubscription,
+    DEFAULT_PROVIDER_SERVICE_NAME,
 };
 
 mod traits;
@@ -47,8 +49,9 @@ pub use alloy_rpc_types_eth::{
     TransactionRequest,
 };
 pub use alloy_rpc_types_trace::geth::{
-    GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethTrace,
+    DefaultFrame, GethDebugBuiltInTracerType, GethDebugTracerConfig, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace,
+    StructLog,
 };
 // re-export contract types
 pub use rundler_contracts::utils::GetGasUsed::GasUsedResult;
@@ -77,6 +80,10 @@ pub trait Providers: Send + Sync + Clone {
     /// Returns the EVM provider.
     fn evm(&self) -> &Self::Evm;
 
+    /// Returns the EVM provider that validation traces are sent to, if traces are routed
+    /// to a separate node. Otherwise traces use the EVM provider.
+    fn trace_evm(&self) -> &Option<Self::Evm>;
+
     /// Returns the entry point provider for v0.6.
     fn ep_v0_6(&self) -> &Option<Self::EntryPointV0_6>;
 
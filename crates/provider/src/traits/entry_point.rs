@@ -11,18 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, Bytes, U256};
+use alloy_primitives::{aliases::U192, Address, Bytes, U256};
 use rundler_types::{
     da::{DAGasBlockData, DAGasUOData},
     GasFees, Timestamp, UserOperation, UserOpsPerAggregator, ValidationOutput, ValidationRevert,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     BlockHashOrNumber, BlockId, EvmCall, ProviderResult, StateOverride, TransactionRequest,
 };
 
 /// Output of a successful signature aggregator simulation call
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct AggregatorSimOut {
     /// Address of the aggregator contract
     pub address: Address,
@@ -37,8 +38,8 @@ pub enum AggregatorOut {
     NotNeeded,
     /// Successful call
     SuccessWithInfo(AggregatorSimOut),
-    /// Aggregator validation function reverted
-    ValidationReverted,
+    /// Aggregator validation function reverted, with its revert data
+    ValidationReverted(Bytes),
 }
 
 /// Result of an entry point handle ops call
@@ -103,6 +104,14 @@ pub trait EntryPoint: Send + Sync {
 
     /// Get the balances of a list of addresses in order
     async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<U256>>;
+
+    /// Get the next valid nonce of a sender for a nonce key
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<U256>;
 }
 
 /// Trait for handling signature aggregators
@@ -120,11 +129,26 @@ pub trait SignatureAggregator: Send + Sync {
     ) -> ProviderResult<Option<Bytes>>;
 
     /// Validate a user operation signature using an aggregator
+    ///
+    /// If `block_id` is `None`, the latest block is used.
     async fn validate_user_op_signature(
         &self,
         aggregator_address: Address,
         user_op: Self::UO,
+        block_id: Option<BlockId>,
     ) -> ProviderResult<AggregatorOut>;
+
+    /// Validate an aggregated signature over a set of operations using an aggregator
+    ///
+    /// Returns `false` if the aggregator reverts, i.e. the signature is invalid.
+    /// If `block_id` is `None`, the latest block is used.
+    async fn validate_signatures(
+        &self,
+        aggregator_address: Address,
+        ops: Vec<Self::UO>,
+        signature: Bytes,
+        block_id: Option<BlockId>,
+    ) -> ProviderResult<bool>;
 }
 
 /// Trait for submitting bundles of operations to an entry point contract
@@ -136,12 +160,14 @@ pub trait BundleHandler: Send + Sync {
 
     /// Call the entry point contract's `handleOps` function
     ///
-    /// If `gas_limit` is `None`, the maximum gas limit is used.
+    /// If `gas_limit` is `None`, the maximum gas limit is used. If `block_id` is `None`,
+    /// the call is made at the latest block.
     async fn call_handle_ops(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
         beneficiary: Address,
         gas_limit: Option<u64>,
+        block_id: Option<BlockId>,
     ) -> ProviderResult<HandleOpsOut>;
 
     /// Construct the transaction to send a bundle of operations to the entry point contract
@@ -12,6 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_contract::Error as ContractError;
+use alloy_primitives::Bytes;
 use alloy_transport::TransportError;
 
 /// Error enumeration for the Provider trait
@@ -23,6 +24,14 @@ pub enum ProviderError {
     /// Contract Error
     #[error("Contract Error: {0}")]
     ContractError(ContractError),
+    /// The node serves a different chain than the one configured
+    #[error("chain id mismatch: configured chain id is {expected} but the provider serves chain id {actual}")]
+    ChainIdMismatch {
+        /// Configured chain id
+        expected: u64,
+        /// Chain id reported by the provider
+        actual: u64,
+    },
     /// Internal errors
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -40,5 +49,70 @@ impl From<ContractError> for ProviderError {
     }
 }
 
+impl ProviderError {
+    /// Returns true if the node rejected the call because it does not support the RPC method
+    pub fn is_method_not_supported(&self) -> bool {
+        match self {
+            ProviderError::RPC(TransportError::ErrorResp(resp)) => {
+                let message = resp.message.to_lowercase();
+                resp.code == METHOD_NOT_FOUND_CODE
+                    || message.contains("not supported")
+                    || message.contains("unsupported method")
+                    || message.contains("does not exist")
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the revert data if the node rejected the call because it reverted
+    pub fn revert_data(&self) -> Option<Bytes> {
+        match self {
+            ProviderError::RPC(TransportError::ErrorResp(resp))
+            | ProviderError::ContractError(ContractError::TransportError(
+                TransportError::ErrorResp(resp),
+            )) => resp.as_revert_data(),
+            _ => None,
+        }
+    }
+}
+
+// JSON-RPC error code for a method that does not exist or is not available
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
 /// Result of a provider method call
 pub type ProviderResult<T> = Result<T, ProviderError>;
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::ErrorPayload;
+
+    use super::*;
+
+    fn error_resp(code: i64, message: &'static str) -> ProviderError {
+        ProviderError::RPC(TransportError::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: None,
+        }))
+    }
+
+    #[test]
+    fn test_is_method_not_supported() {
+        assert!(error_resp(
+            -32601,
+            "the method debug_traceCall does not exist/is not available"
+        )
+        .is_method_not_supported());
+        assert!(error_resp(-32000, "debug_traceCall is not supported").is_method_not_supported());
+        assert!(!error_resp(-32000, "execution reverted").is_method_not_supported());
+    }
+
+    #[test]
+    fn test_revert_data_without_data() {
+        assert_eq!(error_resp(3, "execution reverted").revert_data(), None);
+        assert_eq!(
+            ProviderError::Other(anyhow::anyhow!("execution reverted")).revert_data(),
+            None
+        );
+    }
+}
@@ -64,12 +64,32 @@ pub trait EvmProvider: Send + Sync {
     /// Get the current block number
     async fn get_block_number(&self) -> ProviderResult<u64>;
 
+    /// Get the chain id served by the node
+    async fn get_chain_id(&self) -> ProviderResult<u64>;
+
     /// Get a block by its hash or number
     async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>>;
 
     /// Get the balance of an address
     async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256>;
 
+    /// Get the ERC-20 token balance of an address
+    async fn get_token_balance(
+        &self,
+        token: Address,
+        owner: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256>;
+
+    /// Get the ERC-20 token allowance given by `owner` to `spender`
+    async fn get_token_allowance(
+        &self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256>;
+
     /// Get transaction by hash
     async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>>;
 
@@ -100,6 +120,9 @@ pub trait EvmProvider: Send + Sync {
     /// Get the base fee per gas of the pending block
     async fn get_pending_base_fee(&self) -> ProviderResult<u128>;
 
+    /// Get the base fee per gas of the latest block
+    async fn get_latest_base_fee(&self) -> ProviderResult<u128>;
+
     /// Get the max fee per gas as reported by the node's RPC
     async fn get_max_priority_fee(&self) -> ProviderResult<u128>;
 
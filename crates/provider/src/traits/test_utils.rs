@@ -12,7 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use alloy_json_rpc::{RpcParam, RpcReturn};
-use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
+use alloy_primitives::{aliases::U192, Address, Bytes, TxHash, B256, U256};
 use alloy_rpc_types_eth::{
     state::StateOverride, Block, BlockId, BlockNumberOrTag, FeeHistory, Filter, Log, Transaction,
     TransactionReceipt, TransactionRequest,
@@ -59,10 +59,27 @@ mockall::mock! {
 
         async fn get_block_number(&self) -> ProviderResult<u64>;
 
+        async fn get_chain_id(&self) -> ProviderResult<u64>;
+
         async fn get_block(&self, block_id: BlockId) -> ProviderResult<Option<Block>>;
 
         async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256>;
 
+        async fn get_token_balance(
+            &self,
+            token: Address,
+            owner: Address,
+            block: Option<BlockId>,
+        ) -> ProviderResult<U256>;
+
+        async fn get_token_allowance(
+            &self,
+            token: Address,
+            owner: Address,
+            spender: Address,
+            block: Option<BlockId>,
+        ) -> ProviderResult<U256>;
+
         async fn get_transaction_by_hash(&self, tx: TxHash) -> ProviderResult<Option<Transaction>>;
 
         async fn get_transaction_receipt(
@@ -87,6 +104,8 @@ mockall::mock! {
 
         async fn get_pending_base_fee(&self) -> ProviderResult<u128>;
 
+        async fn get_latest_base_fee(&self) -> ProviderResult<u128>;
+
         async fn get_max_priority_fee(&self) -> ProviderResult<u128>;
 
         async fn get_code(&self, address: Address, block: Option<BlockId>) -> ProviderResult<Bytes>;
@@ -121,6 +140,12 @@ mockall::mock! {
             -> ProviderResult<U256>;
         async fn get_deposit_info(&self, address: Address) -> ProviderResult<DepositInfo>;
         async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<U256>>;
+        async fn get_nonce(
+            &self,
+            sender: Address,
+            key: U192,
+            block_id: Option<BlockId>,
+        ) -> ProviderResult<U256>;
     }
 
     #[async_trait::async_trait]
@@ -135,7 +160,15 @@ mockall::mock! {
             &self,
             aggregator_address: Address,
             user_op: v0_6::UserOperation,
+            block_id: Option<BlockId>,
         ) -> ProviderResult<AggregatorOut>;
+        async fn validate_signatures(
+            &self,
+            aggregator_address: Address,
+            ops: Vec<v0_6::UserOperation>,
+            signature: Bytes,
+            block_id: Option<BlockId>,
+        ) -> ProviderResult<bool>;
     }
 
     #[async_trait::async_trait]
@@ -188,6 +221,7 @@ mockall::mock! {
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
             beneficiary: Address,
             gas_limit: Option<u64>,
+            block_id: Option<BlockId>,
         ) -> ProviderResult<HandleOpsOut>;
         fn get_send_bundle_transaction(
             &self,
@@ -211,6 +245,12 @@ mockall::mock! {
             -> ProviderResult<U256>;
         async fn get_deposit_info(&self, address: Address) -> ProviderResult<DepositInfo>;
         async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<U256>>;
+        async fn get_nonce(
+            &self,
+            sender: Address,
+            key: U192,
+            block_id: Option<BlockId>,
+        ) -> ProviderResult<U256>;
     }
 
     #[async_trait::async_trait]
@@ -225,7 +265,15 @@ mockall::mock! {
             &self,
             aggregator_address: Address,
             user_op: v0_7::UserOperation,
+            block_id: Option<BlockId>,
         ) -> ProviderResult<AggregatorOut>;
+        async fn validate_signatures(
+            &self,
+            aggregator_address: Address,
+            ops: Vec<v0_7::UserOperation>,
+            signature: Bytes,
+            block_id: Option<BlockId>,
+        ) -> ProviderResult<bool>;
     }
 
     #[async_trait::async_trait]
@@ -278,6 +326,7 @@ mockall::mock! {
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
             beneficiary: Address,
             gas_limit: Option<u64>,
+            block_id: Option<BlockId>,
         ) -> ProviderResult<HandleOpsOut>;
         fn get_send_bundle_transaction(
             &self,
@@ -23,7 +23,7 @@ use rundler_provider::ProviderError;
 use rundler_sim::GasEstimationError;
 use rundler_types::{
     pool::{MempoolError, PoolError, PrecheckViolation, SimulationViolation},
-    Entity, EntityType, Opcode, Timestamp, ValidationRevert,
+    Entity, EntityType, Opcode, StorageSlotLabel, Timestamp, ValidationRevert,
 };
 use serde::Serialize;
 
@@ -46,6 +46,9 @@ const SIGNATURE_CHECK_FAILED_CODE: i32 = -32507;
 const PAYMASTER_DEPOSIT_TOO_LOW: i32 = -32508;
 const EXECUTION_REVERTED: i32 = -32521;
 
+// Multiple of the gas an entity used before running out of gas suggested as its new gas limit
+const OUT_OF_GAS_SUGGESTED_LIMIT_MULTIPLIER: u128 = 2;
+
 pub(crate) type EthResult<T> = Result<T, EthRpcError>;
 
 /// Error returned by the RPC server eth namespace
@@ -60,6 +63,10 @@ pub enum EthRpcError {
     /// wallet creation or validation
     #[error("{0}")]
     EntryPointValidationRejected(String),
+    /// Validation reverted in entrypoint or during wallet creation or validation,
+    /// the raw revert data is included as the error data
+    #[error("{}", .0.reason)]
+    EntryPointValidationRejectedWithData(EntryPointValidationRejectedData),
     /// Paymaster rejected the operation
     #[error("{}", .0.reason)]
     PaymasterValidationRejected(PaymasterValidationRejectedData),
@@ -76,8 +83,8 @@ pub enum EthRpcError {
     #[error("The sender address {0} is used as a different entity in another UserOperation currently in mempool")]
     SenderAddressUsedAsAlternateEntity(Address),
     /// Simulation ran out of gas
-    #[error("Simulation ran out of gas for entity: {0}")]
-    OutOfGas(Entity),
+    #[error("Simulation ran out of gas for entity: {}", .0.entity)]
+    OutOfGas(OutOfGasData),
     /// Opcode violation
     #[error("{0} uses banned opcode: {1:?}")]
     OpcodeViolation(EntityType, Opcode),
@@ -88,8 +95,8 @@ pub enum EthRpcError {
     #[error("Sender storage at (address: {1:?} slot: {2:#032x}) accessed during deployment. Factory (or {0:?}) must be staked")]
     AssociatedStorageDuringDeploy(Option<EntityType>, Address, U256),
     /// Invalid storage access, maps to Opcode Violation
-    #[error("{0} accesses inaccessible storage at address: {1:?} slot: {2:#032x}")]
-    InvalidStorageAccess(EntityType, Address, U256),
+    #[error("{0} accesses inaccessible storage at address: {1:?} slot: {2:#032x}{3}")]
+    InvalidStorageAccess(EntityType, Address, U256, StorageSlotLabel),
     /// Operation is out of time range
     #[error("operation is out of time range")]
     OutOfTimeRange(OutOfTimeRangeData),
@@ -139,10 +146,20 @@ pub enum EthRpcError {
 }
 
 #[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPointValidationRejectedData {
+    #[serde(skip_serializing)] // this is included in the message
+    pub reason: String,
+    pub revert_data: Bytes,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PaymasterValidationRejectedData {
     pub paymaster: Address,
     #[serde(skip_serializing)] // this is included in the message
     pub reason: String,
+    pub revert_data: Bytes,
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -153,6 +170,28 @@ pub struct OutOfTimeRangeData {
     pub paymaster: Option<Address>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutOfGasData {
+    entity: Entity,
+    gas_used: U128,
+    suggested_gas_limit: Option<U128>,
+}
+
+impl OutOfGasData {
+    /// `gas_used` is the gas the entity used before running out, zero if not measured. If
+    /// measured, the entity's gas limit is suggested to be raised to a multiple of it.
+    pub fn new(entity: Entity, gas_used: u128) -> Self {
+        Self {
+            entity,
+            gas_used: U128::from(gas_used),
+            suggested_gas_limit: (gas_used > 0).then(|| {
+                U128::from(gas_used.saturating_mul(OUT_OF_GAS_SUGGESTED_LIMIT_MULTIPLIER))
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StakeTooLowData {
@@ -317,6 +356,7 @@ impl From<MempoolError> for EthRpcError {
             MempoolError::CallGasLimitEfficiencyTooLow(_, _) => {
                 Self::InvalidParams(value.to_string())
             }
+            MempoolError::CallPhaseReverted { reason } => Self::ExecutionReverted(reason),
         }
     }
 }
@@ -337,21 +377,36 @@ impl From<SimulationViolation> for EthRpcError {
                 EntityType::Paymaster,
                 reason,
                 Some(paymaster),
+                revert_data,
             ) => Self::PaymasterValidationRejected(PaymasterValidationRejectedData {
                 paymaster,
                 reason,
+                revert_data,
             }),
-            SimulationViolation::UnintendedRevertWithMessage(_, reason, _) => {
-                Self::EntryPointValidationRejected(reason)
-            }
-            SimulationViolation::UsedForbiddenOpcode(entity, _, op) => {
+            SimulationViolation::UnintendedRevertWithMessage(_, reason, _, revert_data) => {
+                Self::EntryPointValidationRejectedWithData(EntryPointValidationRejectedData {
+                    reason,
+                    revert_data,
+                })
+            }
+            SimulationViolation::UnintendedRevert(_, _, ref revert_data) => {
+                Self::EntryPointValidationRejectedWithData(EntryPointValidationRejectedData {
+                    revert_data: revert_data.clone(),
+                    reason: value.to_string(),
+                })
+            }
+            SimulationViolation::UsedForbiddenOpcode(entity, _, op)
+            | SimulationViolation::UsedBannedEnvironmentOpcode(entity, op) => {
                 Self::OpcodeViolation(entity.kind, op.0)
             }
             SimulationViolation::UsedForbiddenPrecompile(_, _, _)
             | SimulationViolation::AccessedUndeployedContract(_, _)
             | SimulationViolation::AccessedUnsupportedContractType(_, _)
-            | SimulationViolation::CalledBannedEntryPointMethod(_)
-            | SimulationViolation::CallHadValue(_) => Self::OpcodeViolationMap(value),
+            | SimulationViolation::CalledBannedEntryPointMethod(_, _)
+            | SimulationViolation::CallHadValue(_, _) => Self::OpcodeViolationMap(value),
+            SimulationViolation::GasOpcodeMisuse(entity) => {
+                Self::OpcodeViolation(entity.kind, Opcode::GAS)
+            }
             SimulationViolation::FactoryCalledCreate2Twice(_) => {
                 Self::OpcodeViolation(EntityType::Factory, Opcode::CREATE2)
             }
@@ -359,8 +414,16 @@ impl From<SimulationViolation> for EthRpcError {
             SimulationViolation::AssociatedStorageDuringDeploy(e, s) => {
                 Self::AssociatedStorageDuringDeploy(e.map(|e| e.kind), s.address, s.slot)
             }
-            SimulationViolation::InvalidStorageAccess(entity, slot) => {
-                Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot)
+            SimulationViolation::InvalidStorageAccess(entity, slot, label) => {
+                Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot, label)
+            }
+            SimulationViolation::InvalidEntityStorageAccess(entity, _, slot) => {
+                Self::InvalidStorageAccess(
+                    entity.kind,
+                    slot.address,
+                    slot.slot,
+                    StorageSlotLabel::default(),
+                )
             }
             SimulationViolation::NotStaked(stake_data) => {
                 Self::StakeTooLow(Box::new(StakeTooLowData::new(
@@ -373,9 +436,30 @@ impl From<SimulationViolation> for EthRpcError {
                     U32::from(stake_data.min_unstake_delay),
                 )))
             }
-            SimulationViolation::AggregatorValidationFailed => Self::SignatureCheckFailed,
-            SimulationViolation::OutOfGas(entity) => Self::OutOfGas(entity),
+            SimulationViolation::AggregatorValidationFailed(_) => Self::SignatureCheckFailed,
+            SimulationViolation::OutOfGas(entity, gas_used) => {
+                Self::OutOfGas(OutOfGasData::new(entity, gas_used))
+            }
             SimulationViolation::ValidationRevert(revert) => Self::ValidationRevert(revert.into()),
+            SimulationViolation::PaymasterDepositTooLow(_, required, available) => {
+                Self::PaymasterBalanceTooLow(required, available)
+            }
+            SimulationViolation::PaymasterSponsorshipExpired(
+                paymaster,
+                valid_until,
+                valid_after,
+            ) => Self::OutOfTimeRange(OutOfTimeRangeData {
+                valid_until,
+                valid_after,
+                paymaster: Some(paymaster),
+            }),
+            SimulationViolation::ZeroSender
+            | SimulationViolation::ZeroGasLimit(_)
+            | SimulationViolation::ZeroMaxFeePerGas
+            | SimulationViolation::MalformedInitCode
+            | SimulationViolation::MalformedPaymasterAndData => {
+                Self::InvalidParams(value.to_string())
+            }
             _ => Self::SimulationFailed(value),
         }
     }
@@ -391,20 +475,22 @@ impl From<EthRpcError> for ErrorObjectOwned {
             EthRpcError::EntryPointValidationRejected(_) | EthRpcError::SimulationFailed(_) => {
                 rpc_err(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg)
             }
+            EthRpcError::EntryPointValidationRejectedWithData(data) => {
+                rpc_err_with_data(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg, data)
+            }
             EthRpcError::PaymasterValidationRejected(data) => {
                 rpc_err_with_data(PAYMASTER_VALIDATION_REJECTED_CODE, msg, data)
             }
             EthRpcError::PaymasterBalanceTooLow(_, _) => rpc_err(PAYMASTER_DEPOSIT_TOO_LOW, msg),
             EthRpcError::OpcodeViolation(_, _)
             | EthRpcError::OpcodeViolationMap(_)
-            | EthRpcError::OutOfGas(_)
             | EthRpcError::UnstakedAggregator
             | EthRpcError::MultipleRolesViolation(_)
             | EthRpcError::UnstakedPaymasterContext
             | EthRpcError::SenderAddressUsedAsAlternateEntity(_)
             | EthRpcError::AssociatedStorageIsAlternateSender
             | EthRpcError::AssociatedStorageDuringDeploy(_, _, _)
-            | EthRpcError::InvalidStorageAccess(_, _, _) => rpc_err(OPCODE_VIOLATION_CODE, msg),
+            | EthRpcError::InvalidStorageAccess(_, _, _, _) => rpc_err(OPCODE_VIOLATION_CODE, msg),
             EthRpcError::OutOfTimeRange(data) => {
                 rpc_err_with_data(OUT_OF_TIME_RANGE_CODE, msg, data)
             }
@@ -412,6 +498,7 @@ impl From<EthRpcError> for ErrorObjectOwned {
                 rpc_err_with_data(THROTTLED_OR_BANNED_CODE, msg, data)
             }
             EthRpcError::StakeTooLow(data) => rpc_err_with_data(OPCODE_VIOLATION_CODE, msg, data),
+            EthRpcError::OutOfGas(data) => rpc_err_with_data(OPCODE_VIOLATION_CODE, msg, data),
             EthRpcError::UnsupportedAggregator(data) => {
                 rpc_err_with_data(UNSUPORTED_AGGREGATOR_CODE, msg, data)
             }
@@ -477,6 +564,7 @@ impl From<ProviderErrorWithContext> for EthRpcError {
                 },
                 _ => error.to_string(),
             },
+            ProviderError::ChainIdMismatch { .. } => e.error.to_string(),
             ProviderError::Other(error) => {
                 format!("other error: {}", error)
             }
@@ -13,8 +13,8 @@
 
 use std::{fmt::Debug, marker::PhantomData, sync::Arc};
 
-use alloy_primitives::{Address, B256};
-use rundler_provider::{EntryPoint, SimulationProvider, StateOverride};
+use alloy_primitives::{aliases::U192, Address, B256, U256};
+use rundler_provider::{BlockId, EntryPoint, SimulationProvider, StateOverride};
 use rundler_sim::{GasEstimationError, GasEstimator};
 use rundler_types::{
     EntryPointVersion, GasEstimate, UserOperation, UserOperationOptionalGas, UserOperationVariant,
@@ -198,6 +198,19 @@ impl EntryPointRouter {
             .map_err(Into::into)
     }
 
+    pub(crate) async fn get_nonce(
+        &self,
+        entry_point: &Address,
+        sender: Address,
+        key: U192,
+        block_id: Option<BlockId>,
+    ) -> EthResult<U256> {
+        self.get_route(entry_point)?
+            .get_nonce(sender, key, block_id)
+            .await
+            .map_err(Into::into)
+    }
+
     fn get_ep_version(&self, entry_point: &Address) -> EthResult<EntryPointVersion> {
         if let Some((addr, _)) = self.v0_6 {
             if addr == *entry_point {
@@ -245,6 +258,13 @@ pub(crate) trait EntryPointRoute: Send + Sync {
     ) -> Result<GasEstimate, GasEstimationError>;
 
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool>;
+
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        block_id: Option<BlockId>,
+    ) -> anyhow::Result<U256>;
 }
 
 #[derive(Debug)]
@@ -301,6 +321,15 @@ where
 
         Ok(!output.return_info.account_sig_failed)
     }
+
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        block_id: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
+        Ok(self.entry_point.get_nonce(sender, key, block_id).await?)
+    }
 }
 
 impl<UO, E, G, EP> EntryPointRouteImpl<UO, E, G, EP> {
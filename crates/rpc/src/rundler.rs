@@ -11,10 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, B256, U128};
+use alloy_primitives::{aliases::U192, Address, B256, U128, U256};
 use anyhow::Context;
 use async_trait::async_trait;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use rundler_provider::BlockId;
 use rundler_sim::{gas, FeeEstimator};
 use rundler_types::{chain::ChainSpec, pool::Pool, UserOperation, UserOperationVariant};
 
@@ -54,6 +55,20 @@ pub trait RundlerApi {
         uo: RpcUserOperation,
         entry_point: Address,
     ) -> RpcResult<Option<B256>>;
+
+    /// Returns the next valid nonce of `sender` for the 2D nonce `key`, as reported by the
+    /// entry point's nonce manager. The returned value already encodes the key in its upper
+    /// 192 bits, so it can be used directly as a user operation's `nonce`.
+    ///
+    /// Queries the latest block if `block_id` is not provided.
+    #[method(name = "getNonce")]
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        entry_point: Address,
+        block_id: Option<BlockId>,
+    ) -> RpcResult<U256>;
 }
 
 pub(crate) struct RundlerApi<P, F> {
@@ -88,6 +103,20 @@ where
         )
         .await
     }
+
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        entry_point: Address,
+        block_id: Option<BlockId>,
+    ) -> RpcResult<U256> {
+        utils::safe_call_rpc_handler(
+            "rundler_getNonce",
+            RundlerApi::get_nonce(self, sender, key, entry_point, block_id),
+        )
+        .await
+    }
 }
 
 impl<P, F> RundlerApi<P, F>
@@ -160,4 +189,72 @@ where
 
         Ok(ret)
     }
+
+    async fn get_nonce(
+        &self,
+        sender: Address,
+        key: U192,
+        entry_point: Address,
+        block_id: Option<BlockId>,
+    ) -> EthResult<U256> {
+        self.entry_point_router
+            .get_nonce(&entry_point, sender, key, block_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mockall::predicate::eq;
+    use rundler_provider::{MockEntryPointV0_6, MockEvmProvider};
+    use rundler_sim::{MockFeeEstimator, MockGasEstimator};
+    use rundler_types::pool::MockPool;
+
+    use super::*;
+    use crate::eth::{
+        EntryPointRouteImpl, EntryPointRouterBuilder, UserOperationEventProviderV0_6,
+    };
+
+    #[tokio::test]
+    async fn test_get_nonce() {
+        let ep = Address::random();
+        let sender = Address::random();
+        let key = U192::from(7);
+        let nonce = (U256::from(7) << 64) | U256::from(3);
+
+        let mut entry_point = MockEntryPointV0_6::default();
+        entry_point.expect_address().return_const(ep);
+        entry_point
+            .expect_get_nonce()
+            .with(eq(sender), eq(key), eq(None))
+            .times(1)
+            .returning(move |_, _, _| Ok(nonce));
+
+        let chain_spec = ChainSpec::default();
+        let router = EntryPointRouterBuilder::default()
+            .v0_6(EntryPointRouteImpl::new(
+                Arc::new(entry_point),
+                MockGasEstimator::default(),
+                UserOperationEventProviderV0_6::new(
+                    chain_spec.clone(),
+                    Arc::new(MockEvmProvider::default()),
+                    None,
+                ),
+            ))
+            .build();
+        let api = RundlerApi::new(
+            &chain_spec,
+            router,
+            MockPool::default(),
+            MockFeeEstimator::default(),
+        );
+
+        assert_eq!(api.get_nonce(sender, key, ep, None).await.unwrap(), nonce);
+        assert!(api
+            .get_nonce(sender, key, Address::random(), None)
+            .await
+            .is_err());
+    }
 }
@@ -2,6 +2,8 @@ use alloy_primitives::{Address, Bytes, B256};
 use alloy_sol_types::{Revert, SolError, SolInterface};
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
+#[cfg(feature = "test-utils")]
+use mockall::automock;
 use rundler_contracts::{
     v0_6::CallGasEstimationProxy::TestCallGasResult,
     v0_7::CallGasEstimationProxy::CallGasEstimationProxyErrors,
@@ -24,6 +26,7 @@ pub(crate) const PROXY_IMPLEMENTATION_ADDRESS_MARKER: &str =
     "A13dB4eCfbce0586E57D1AeE224FbE64706E8cd3";
 
 /// Estimates the gas limit for a user operation
+#[cfg_attr(feature = "test-utils", automock(type UO = rundler_types::v0_6::UserOperation;))]
 #[async_trait]
 pub trait CallGasEstimator: Send + Sync {
     /// The user operation type estimated by this estimator
@@ -20,22 +20,25 @@ use rundler_types::{GasEstimate, ValidationRevert};
 use crate::precheck::MIN_CALL_GAS_LIMIT;
 
 mod estimate_verification_gas;
+pub(crate) use estimate_verification_gas::GetOpWithLimitArgs;
 pub use estimate_verification_gas::{VerificationGasEstimator, VerificationGasEstimatorImpl};
 mod estimate_call_gas;
+#[cfg(feature = "test-utils")]
+pub use estimate_call_gas::MockCallGasEstimator;
 pub use estimate_call_gas::{
     CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization,
 };
 
 /// Gas estimation module for Entry Point v0.6
 mod v0_6;
-pub use v0_6::GasEstimator as GasEstimatorV0_6;
+pub use v0_6::{CallGasEstimatorSpecializationV06, GasEstimator as GasEstimatorV0_6};
 mod v0_7;
-pub use v0_7::GasEstimator as GasEstimatorV0_7;
+pub use v0_7::{CallGasEstimatorSpecializationV07, GasEstimator as GasEstimatorV0_7};
 
 /// Percentage by which to increase the verification gas limit after binary search
-const VERIFICATION_GAS_BUFFER_PERCENT: u32 = 10;
+pub(crate) const VERIFICATION_GAS_BUFFER_PERCENT: u32 = 10;
 /// Absolute value by which to increase the call gas limit after binary search
-const CALL_GAS_BUFFER_VALUE: u128 = 3000;
+pub(crate) const CALL_GAS_BUFFER_VALUE: u128 = 3000;
 
 /// Error type for gas estimation
 #[derive(Debug, thiserror::Error)]
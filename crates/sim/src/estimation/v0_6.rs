@@ -339,6 +339,13 @@ pub struct CallGasEstimatorSpecializationV06 {
     chain_spec: ChainSpec,
 }
 
+impl CallGasEstimatorSpecializationV06 {
+    /// Create a new specialization for the given chain
+    pub fn new(chain_spec: ChainSpec) -> Self {
+        Self { chain_spec }
+    }
+}
+
 impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV06 {
     type UO = UserOperation;
 
@@ -413,6 +413,13 @@ pub struct CallGasEstimatorSpecializationV07 {
     chain_spec: ChainSpec,
 }
 
+impl CallGasEstimatorSpecializationV07 {
+    /// Create a new specialization for the given chain
+    pub fn new(chain_spec: ChainSpec) -> Self {
+        Self { chain_spec }
+    }
+}
+
 impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV07 {
     type UO = UserOperation;
 
@@ -159,6 +159,12 @@ pub trait FeeEstimator: Send + Sync {
 
     /// Returns the required operation fees for the given bundle fees.
     fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees;
+
+    /// Returns suggested fees for a user operation.
+    ///
+    /// The priority fee is taken from the configured fee oracle and the max fee leaves
+    /// room for the latest block's base fee to double before the operation is priced out.
+    async fn suggested_op_fees(&self) -> anyhow::Result<GasFees>;
 }
 
 /// Gas fee estimator for a 4337 user operation.
@@ -198,6 +204,10 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimatorImpl<P, O> {
         Ok(self.provider.get_pending_base_fee().await?)
     }
 
+    async fn get_latest_base_fee(&self) -> anyhow::Result<u128> {
+        Ok(self.provider.get_latest_base_fee().await?)
+    }
+
     async fn get_priority_fee(&self) -> anyhow::Result<u128> {
         self.fee_oracle
             .estimate_priority_fee()
@@ -238,4 +248,50 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimator for FeeEstimatorImpl<P, O> {
     fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
         self.priority_fee_mode.required_fees(bundle_fees)
     }
+
+    async fn suggested_op_fees(&self) -> anyhow::Result<GasFees> {
+        let (base_fee, max_priority_fee_per_gas) =
+            try_join!(self.get_latest_base_fee(), self.get_priority_fee())?;
+
+        Ok(GasFees {
+            max_fee_per_gas: base_fee
+                .saturating_mul(2)
+                .saturating_add(max_priority_fee_per_gas),
+            max_priority_fee_per_gas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_provider::MockEvmProvider;
+
+    use super::*;
+    use crate::gas::ConstantOracle;
+
+    #[tokio::test]
+    async fn test_suggested_op_fees() {
+        let mut provider = MockEvmProvider::default();
+        provider
+            .expect_get_latest_base_fee()
+            .times(1)
+            .returning(|| Ok(1_000));
+
+        let estimator = FeeEstimatorImpl::new(
+            provider,
+            ConstantOracle::new(100),
+            PriorityFeeMode::BaseFeePercent(0),
+            0,
+            0,
+        );
+
+        let fees = estimator.suggested_op_fees().await.unwrap();
+        assert_eq!(
+            fees,
+            GasFees {
+                max_fee_per_gas: 2_100,
+                max_priority_fee_per_gas: 100,
+            }
+        );
+    }
 }
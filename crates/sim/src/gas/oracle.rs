@@ -63,6 +63,15 @@ where
             };
             Box::new(UsageBasedFeeOracle::new(provider, config))
         }
+        PriorityFeeOracleType::FeeHistory => {
+            let config = FeeHistoryOracleConfig {
+                blocks_history: chain_spec.fee_history_num_blocks,
+                percentile: chain_spec.fee_history_percentile,
+                minimum_fee: chain_spec.min_max_priority_fee_per_gas(),
+                maximum_fee: chain_spec.max_max_priority_fee_per_gas(),
+            };
+            Box::new(FeeHistoryOracle::new(provider, config))
+        }
     }
 }
 
@@ -32,13 +32,14 @@
 
 /// Gas estimation
 mod estimation;
-#[cfg(feature = "test-utils")]
-pub use estimation::MockGasEstimator;
 pub use estimation::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
+    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization,
+    CallGasEstimatorSpecializationV06, CallGasEstimatorSpecializationV07, GasEstimationError,
     GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7, Settings as EstimationSettings,
     VerificationGasEstimator, VerificationGasEstimatorImpl,
 };
+#[cfg(feature = "test-utils")]
+pub use estimation::{MockCallGasEstimator, MockGasEstimator};
 
 pub mod gas;
 #[cfg(feature = "test-utils")]
@@ -55,12 +56,16 @@ pub use precheck::{
 
 /// Simulation and violation checking
 pub mod simulation;
-#[cfg(feature = "test-utils")]
-pub use simulation::MockSimulator;
 pub use simulation::{
-    MempoolConfig, MempoolConfigs, Settings as SimulationSettings, SimulationError,
-    SimulationResult, Simulator,
+    can_add_to_bundle, detect_tracer_mode, validate_static, AdmissionPolicy, BundleOpResult,
+    BundleSimResult, Clock, GasEstimateOptions, MempoolConfig, MempoolConfigs,
+    NoopStorageSlotLabeler, PaymasterTimeRangeDecoder, Rejection, RejectionSink,
+    SenderIsPaymasterPolicy, Settings as SimulationSettings, SimulationError, SimulationOverrides,
+    SimulationResult, Simulator, StorageSlotLabeler, SystemClock, TokenPaymasterConfig, TracerMode,
+    TracingRejectionSink, ViolationPriority, CANONICAL_MEMPOOL_ID, DEPOSIT_TO_SELECTOR,
 };
+#[cfg(feature = "test-utils")]
+pub use simulation::{MockClock, MockSimulator};
 
 mod types;
 pub use types::{ExpectedStorage, ViolationError};
@@ -768,4 +768,43 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    #[tokio::test]
+    async fn test_pvg_accept_percent_only_for_da_chains() {
+        let settings = Settings {
+            base_fee_accept_percent: 80,
+            priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            pre_verification_gas_accept_percent: 50,
+            ..Default::default()
+        };
+
+        let mut async_data = get_test_async_data();
+        async_data.base_fee = 5_000;
+        async_data.min_pre_verification_gas = 1_000;
+
+        let op = UserOperation {
+            max_fee_per_gas: 5000,
+            max_priority_fee_per_gas: 0,
+            pre_verification_gas: 500,
+            call_gas_limit: MIN_CALL_GAS_LIMIT,
+            ..Default::default()
+        };
+
+        // without dynamic preVerificationGas the full minimum is enforced
+        let (cs, provider, entry_point, fee_estimator) = create_base_config();
+        let prechecker =
+            PrecheckerImpl::new(cs, Arc::new(provider), entry_point, fee_estimator, settings);
+        let res = prechecker.check_gas(&op, &async_data);
+        let mut expected = ArrayVec::<PrecheckViolation, 6>::new();
+        expected.push(PrecheckViolation::PreVerificationGasTooLow(500, 1_000));
+        assert_eq!(res, expected);
+
+        // with dynamic preVerificationGas the accept percent is applied
+        let (mut cs, provider, entry_point, fee_estimator) = create_base_config();
+        cs.da_pre_verification_gas = true;
+        let prechecker =
+            PrecheckerImpl::new(cs, Arc::new(provider), entry_point, fee_estimator, settings);
+        let res = prechecker.check_gas(&op, &async_data);
+        assert!(res.is_empty());
+    }
 }
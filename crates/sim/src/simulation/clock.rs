@@ -0,0 +1,74 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_types::Timestamp;
+
+/// Source of the current time used by the simulator for time range checks
+pub trait Clock: Debug + Send + Sync {
+    /// The current time
+    fn now(&self) -> Timestamp;
+}
+
+/// Clock backed by the system wall clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+pub use mock::MockClock;
+
+#[cfg(any(test, feature = "test-utils"))]
+mod mock {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use rundler_types::Timestamp;
+
+    use super::Clock;
+
+    /// Manually controlled clock for deterministic tests
+    #[derive(Debug, Default)]
+    pub struct MockClock(AtomicU64);
+
+    impl MockClock {
+        /// Create a new mock clock set to the given time
+        pub fn new(now: Timestamp) -> Self {
+            Self(AtomicU64::new(now.seconds_since_epoch()))
+        }
+
+        /// Set the current time
+        pub fn set(&self, now: Timestamp) {
+            self.0.store(now.seconds_since_epoch(), Ordering::SeqCst);
+        }
+
+        /// Move the current time forward
+        pub fn advance(&self, duration: Duration) {
+            self.0.fetch_add(duration.as_secs(), Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Timestamp {
+            self.0.load(Ordering::SeqCst).into()
+        }
+    }
+}
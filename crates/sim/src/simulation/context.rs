@@ -11,11 +11,15 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+};
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
 use anyhow::Context;
-use rundler_provider::BlockId;
+use rundler_provider::{AccountOverride, BlockId, StateOverride};
 use rundler_types::{
     pool::SimulationViolation, EntityInfos, EntityType, Opcode, StakeInfo, UserOperation,
     ValidationOutput,
@@ -45,6 +49,12 @@ pub(crate) struct TracerOutput {
     pub(crate) accessed_contracts: HashMap<Address, ContractInfo>,
     pub(crate) associated_slots_by_address: AssociatedSlotsByAddress,
     pub(crate) factory_called_create2_twice: bool,
+    /// Address of the contract deployed by the factory's CREATE2, if any
+    #[serde(default)]
+    pub(crate) factory_deployed_address: Option<Address>,
+    /// Runtime code of the contracts the factory deployed with CREATE, by address
+    #[serde(default)]
+    pub(crate) factory_created_contracts: HashMap<Address, Bytes>,
     pub(crate) expected_storage: ExpectedStorage,
 }
 
@@ -55,10 +65,23 @@ pub(crate) struct Phase {
     pub(crate) forbidden_precompiles_used: Vec<String>,
     pub(crate) storage_accesses: HashMap<Address, AccessInfo>,
     pub(crate) called_banned_entry_point_method: bool,
+    /// Selectors of the entry point methods that set `called_banned_entry_point_method`.
+    #[serde(default)]
+    pub(crate) banned_entry_point_methods: Vec<String>,
     pub(crate) called_non_entry_point_with_value: bool,
+    /// Targets of calls with value to contracts other than the entry point
+    #[serde(default)]
+    pub(crate) value_call_targets: Vec<Address>,
     pub(crate) ran_out_of_gas: bool,
     pub(crate) undeployed_contract_accesses: Vec<Address>,
+    /// Undeployed contracts that were called rather than only probed with an EXTCODE* opcode.
+    /// A subset of `undeployed_contract_accesses`.
+    #[serde(default)]
+    pub(crate) undeployed_contract_calls: Vec<Address>,
     pub(crate) ext_code_access_info: HashMap<Address, Opcode>,
+    /// Gas consumed during this phase
+    #[serde(default)]
+    pub(crate) gas_used: u64,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -77,10 +100,16 @@ pub(crate) struct AccessInfo {
     pub(crate) writes: HashMap<U256, u64>,
 }
 
+// Exclusive upper bound on `n` for an associated slot `keccak256(A || x) + n`
+const MAX_ASSOCIATED_SLOT_OFFSET: u64 = 128;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub(crate) struct AssociatedSlotsByAddress(pub(crate) HashMap<Address, BTreeSet<U256>>);
 
 impl AssociatedSlotsByAddress {
+    // Per ERC-7562 a slot is associated with an address `A` if it is `A` itself, or
+    // `keccak256(A || x) + n` for `n` in `0..128`. The offset covers the fields of a
+    // struct stored as a mapping value, as well as fixed size arrays.
     pub(crate) fn is_associated_slot(&self, address: Address, slot: U256) -> bool {
         if slot == U256::from_be_bytes(address.into_word().into()) {
             return true;
@@ -88,12 +117,10 @@ impl AssociatedSlotsByAddress {
         let Some(associated_slots) = self.0.get(&address) else {
             return false;
         };
-        let Some(&next_smallest_slot) =
-            associated_slots.range(..(slot + U256::from(1))).next_back()
-        else {
+        let Some(&next_smallest_slot) = associated_slots.range(..=slot).next_back() else {
             return false;
         };
-        (slot - next_smallest_slot) < U256::from(128)
+        (slot - next_smallest_slot) < U256::from(MAX_ASSOCIATED_SLOT_OFFSET)
     }
 
     pub(crate) fn addresses(&self) -> HashSet<Address> {
@@ -101,6 +128,145 @@ impl AssociatedSlotsByAddress {
     }
 }
 
+// Override the sender's balance so that it can pay its prefund during validation
+pub(crate) fn add_prefund_override(
+    state_override: &mut StateOverride,
+    sender: Address,
+    prefund_override: Option<U256>,
+) {
+    if let Some(balance) = prefund_override {
+        state_override
+            .entry(sender)
+            .or_insert_with(AccountOverride::default)
+            .balance = Some(balance);
+    }
+}
+
+/// Key of an operation's trace in a `TraceCache`.
+///
+/// The user operation hash leaves out the signature, while the trace verifies it, so ops that
+/// only differ in signature must not share a trace.
+pub(crate) fn trace_cache_key(op_hash: B256, signature: &[u8]) -> B256 {
+    keccak256([op_hash.as_slice(), signature].concat())
+}
+
+/// Cache of tracer outputs keyed by `trace_cache_key`, for the most recently traced block.
+///
+/// Outputs are only reused for the block they were traced at. The cache is cleared whenever an
+/// operation is traced at a different block, and traces at a block number are never cached.
+///
+/// The cache is only written once a trace has completed successfully, so a trace that fails or
+/// is dropped part way through never leaves a partial output behind.
+#[derive(Debug)]
+pub(crate) struct TraceCache<T> {
+    inner: Mutex<TraceCacheInner<T>>,
+}
+
+#[derive(Debug)]
+struct TraceCacheInner<T> {
+    block_hash: B256,
+    outputs: HashMap<B256, T>,
+}
+
+impl<T: Clone> TraceCache<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Mutex::new(TraceCacheInner {
+                block_hash: B256::ZERO,
+                outputs: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Get the cached output for an operation traced at the given block, if any
+    pub(crate) fn get(&self, key: B256, block_id: BlockId) -> Option<T> {
+        let block_hash = Self::block_hash(block_id)?;
+        let inner = self.inner.lock().unwrap();
+        if inner.block_hash != block_hash {
+            return None;
+        }
+        inner.outputs.get(&key).cloned()
+    }
+
+    /// Get the cached output for an operation traced at the given block, or run `trace`
+    /// and cache its output.
+    ///
+    /// Cancellation safe: the output is only cached after `trace` resolves successfully, and
+    /// there is no await point after that, so dropping the returned future leaves the cache
+    /// unchanged.
+    pub(crate) async fn get_or_trace<F>(
+        &self,
+        key: B256,
+        block_id: BlockId,
+        trace: F,
+    ) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        if let Some(out) = self.get(key, block_id) {
+            return Ok(out);
+        }
+        let out = trace.await?;
+        self.insert(key, block_id, out.clone());
+        Ok(out)
+    }
+
+    /// Cache the output of an operation traced at the given block, evicting outputs
+    /// of any other block
+    pub(crate) fn insert(&self, key: B256, block_id: BlockId, output: T) {
+        let Some(block_hash) = Self::block_hash(block_id) else {
+            return;
+        };
+        let mut inner = self.inner.lock().unwrap();
+        if inner.block_hash != block_hash {
+            inner.block_hash = block_hash;
+            inner.outputs.clear();
+        }
+        inner.outputs.insert(key, output);
+    }
+
+    fn block_hash(block_id: BlockId) -> Option<B256> {
+        match block_id {
+            BlockId::Hash(hash) => Some(hash.block_hash),
+            BlockId::Number(_) => None,
+        }
+    }
+}
+
+/// Message of the error the tracers throw to abort a trace that exceeds its budget
+pub(crate) const BUDGET_EXCEEDED_ERROR: &str = "simulation budget exceeded";
+
+/// Limits on the work a single validation trace may do, passed to the tracers as their config.
+/// Unlimited if unset.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TraceBudget {
+    pub(crate) max_steps: Option<u64>,
+    pub(crate) max_storage_accesses: Option<u64>,
+}
+
+impl TraceBudget {
+    pub(crate) fn new(sim_settings: &Settings) -> Self {
+        Self {
+            max_steps: sim_settings.max_trace_steps,
+            max_storage_accesses: sim_settings.max_trace_storage_accesses,
+        }
+    }
+
+    /// Returns true if a trace with the given number of steps and storage accesses
+    /// exceeds this budget
+    pub(crate) fn is_exceeded(&self, steps: u64, storage_accesses: u64) -> bool {
+        self.max_steps.is_some_and(|max| steps > max)
+            || self
+                .max_storage_accesses
+                .is_some_and(|max| storage_accesses > max)
+    }
+
+    pub(crate) fn tracer_config(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("budget should serialize")
+    }
+}
+
 /// Trait for providing the validation context for a user operation.
 #[async_trait::async_trait]
 pub trait ValidationContextProvider: Send + Sync {
@@ -108,10 +274,14 @@ pub trait ValidationContextProvider: Send + Sync {
     type UO: UserOperation;
 
     /// Get the validation context for a user operation.
+    ///
+    /// If `prefund_override` is set, the sender's balance is overridden with it while
+    /// tracing.
     async fn get_context(
         &self,
         op: Self::UO,
         block_id: BlockId,
+        prefund_override: Option<U256>,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>>;
 
     /// Get the violations specific to the particular entry point this provider targets.
@@ -181,3 +351,151 @@ where
         .context("tracer combined should contain two parts")?;
     Ok((a.parse()?, b.parse()?))
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{bytes, keccak256, uint};
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    fn associated_slots(address: Address) -> (AssociatedSlotsByAddress, U256) {
+        let mut preimage = address.into_word().to_vec();
+        preimage.extend_from_slice(&[0; 32]);
+        let base = U256::from_be_bytes(keccak256(preimage).0);
+        (
+            AssociatedSlotsByAddress(HashMap::from([(address, BTreeSet::from([base]))])),
+            base,
+        )
+    }
+
+    #[test]
+    fn test_associated_slot_struct_offsets() {
+        let address = Address::random();
+        let (slots, base) = associated_slots(address);
+
+        assert!(slots.is_associated_slot(address, base));
+        assert!(slots.is_associated_slot(address, base + uint!(1_U256)));
+        assert!(slots.is_associated_slot(address, base + uint!(5_U256)));
+        assert!(slots.is_associated_slot(address, base + uint!(127_U256)));
+        assert!(!slots.is_associated_slot(address, base + uint!(128_U256)));
+        assert!(!slots.is_associated_slot(address, base - uint!(1_U256)));
+        assert!(!slots.is_associated_slot(Address::random(), base + uint!(1_U256)));
+    }
+
+    #[test]
+    fn test_associated_slot_address() {
+        let address = Address::random();
+        let slots = AssociatedSlotsByAddress(HashMap::new());
+
+        assert!(slots.is_associated_slot(address, U256::from_be_bytes(address.into_word().0)));
+        assert!(!slots.is_associated_slot(address, U256::ZERO));
+    }
+
+    #[test]
+    fn test_add_prefund_override() {
+        let sender = Address::random();
+        let mut state_override = StateOverride::default();
+
+        add_prefund_override(&mut state_override, sender, None);
+        assert!(state_override.is_empty());
+
+        state_override.insert(
+            sender,
+            AccountOverride {
+                code: Some(bytes!("608060")),
+                ..Default::default()
+            },
+        );
+        add_prefund_override(&mut state_override, sender, Some(U256::from(100)));
+        let account = state_override.get(&sender).unwrap();
+        assert_eq!(account.balance, Some(U256::from(100)));
+        assert_eq!(account.code, Some(bytes!("608060")));
+    }
+
+    #[test]
+    fn test_associated_slot_max_slot() {
+        let address = Address::random();
+        let slots = AssociatedSlotsByAddress(HashMap::from([(
+            address,
+            BTreeSet::from([U256::MAX - uint!(1_U256)]),
+        )]));
+
+        assert!(slots.is_associated_slot(address, U256::MAX));
+    }
+
+    #[test]
+    fn test_trace_cache() {
+        let cache = TraceCache::new();
+        let (op_hash, block_a, block_b) = (B256::random(), B256::random(), B256::random());
+
+        cache.insert(op_hash, block_a.into(), 1);
+        assert_eq!(cache.get(op_hash, block_a.into()), Some(1));
+        assert_eq!(cache.get(B256::random(), block_a.into()), None);
+        assert_eq!(cache.get(op_hash, block_b.into()), None);
+
+        // a new block evicts the previous block's outputs
+        cache.insert(B256::random(), block_b.into(), 2);
+        assert_eq!(cache.get(op_hash, block_a.into()), None);
+
+        // traces at a block number are not cached
+        cache.insert(op_hash, BlockId::number(1), 3);
+        assert_eq!(cache.get(op_hash, BlockId::number(1)), None);
+    }
+
+    #[tokio::test]
+    async fn test_trace_cache_get_or_trace() {
+        let cache = TraceCache::new();
+        let (op_hash, block) = (B256::random(), B256::random());
+
+        // dropping a trace part way through doesn't write to the cache
+        let (tx, rx) = tokio::sync::oneshot::channel::<u64>();
+        let pending = cache.get_or_trace(op_hash, block.into(), async move {
+            rx.await.map_err(anyhow::Error::from)
+        });
+        assert!(pending.now_or_never().is_none());
+        drop(tx);
+        assert_eq!(cache.get(op_hash, block.into()), None);
+
+        // failed traces aren't cached
+        assert!(cache
+            .get_or_trace(op_hash, block.into(), async {
+                anyhow::bail!("trace failed")
+            })
+            .await
+            .is_err());
+        assert_eq!(cache.get(op_hash, block.into()), None);
+
+        // successful traces are cached and reused
+        assert_eq!(
+            cache
+                .get_or_trace(op_hash, block.into(), async { Ok(1) })
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            cache
+                .get_or_trace(op_hash, block.into(), async { anyhow::bail!("not traced") })
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_trace_budget() {
+        let budget = TraceBudget {
+            max_steps: Some(100),
+            max_storage_accesses: None,
+        };
+        assert!(!budget.is_exceeded(100, 1_000));
+        assert!(budget.is_exceeded(101, 0));
+        assert_eq!(
+            budget.tracer_config(),
+            serde_json::json!({ "maxSteps": 100, "maxStorageAccesses": null })
+        );
+
+        assert!(!TraceBudget::default().is_exceeded(u64::MAX, u64::MAX));
+    }
+}
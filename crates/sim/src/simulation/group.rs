@@ -0,0 +1,89 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::Address;
+
+use super::SimulationResult;
+
+/// Simulated operations partitioned by aggregator, as they are passed to
+/// `handleAggregatedOps`.
+///
+/// Operations are referred to by their index in the simulation results the
+/// groups were built from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleGroups {
+    /// Operations without an aggregator, in input order
+    pub non_aggregated: Vec<usize>,
+    /// Aggregated operations grouped by aggregator address. Groups are in order of
+    /// the first appearance of their aggregator, operations within a group are in
+    /// input order.
+    pub aggregated: Vec<(Address, Vec<usize>)>,
+}
+
+/// Partition simulated operations by the aggregator they use
+pub fn group_for_bundle(sims: &[SimulationResult]) -> BundleGroups {
+    let mut groups = BundleGroups::default();
+    for (i, sim) in sims.iter().enumerate() {
+        let Some(aggregator) = sim.aggregator_address() else {
+            groups.non_aggregated.push(i);
+            continue;
+        };
+        match groups.aggregated.iter_mut().find(|(a, _)| *a == aggregator) {
+            Some((_, ops)) => ops.push(i),
+            None => groups.aggregated.push((aggregator, vec![i])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_provider::AggregatorSimOut;
+
+    use super::*;
+
+    fn sim(aggregator: Option<Address>) -> SimulationResult {
+        SimulationResult {
+            aggregators: aggregator
+                .map(|address| AggregatorSimOut {
+                    address,
+                    signature: Default::default(),
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_for_bundle() {
+        let agg_a = Address::random();
+        let agg_b = Address::random();
+        let sims = vec![
+            sim(Some(agg_a)),
+            sim(None),
+            sim(Some(agg_b)),
+            sim(Some(agg_a)),
+            sim(None),
+        ];
+
+        assert_eq!(
+            group_for_bundle(&sims),
+            BundleGroups {
+                non_aggregated: vec![1, 4],
+                aggregated: vec![(agg_a, vec![0, 3]), (agg_b, vec![2])],
+            }
+        );
+        assert_eq!(group_for_bundle(&[]), BundleGroups::default());
+    }
+}
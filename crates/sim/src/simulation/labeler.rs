@@ -0,0 +1,41 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use alloy_primitives::{Address, U256};
+
+/// Operator defined labels for storage slots
+///
+/// Used by the simulator to attach a human readable description, such as
+/// "ERC20.balanceOf(sender)", to storage access violations.
+pub trait StorageSlotLabeler: Debug + Send + Sync {
+    /// Returns the label of the slot at `slot` of the contract at `address`, if known.
+    fn label(&self, address: Address, slot: U256) -> Option<String>;
+}
+
+impl StorageSlotLabeler for fn(Address, U256) -> Option<String> {
+    fn label(&self, address: Address, slot: U256) -> Option<String> {
+        self(address, slot)
+    }
+}
+
+/// Labeler that does not label any storage slots
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopStorageSlotLabeler;
+
+impl StorageSlotLabeler for NoopStorageSlotLabeler {
+    fn label(&self, _address: Address, _slot: U256) -> Option<String> {
+        None
+    }
+}
@@ -13,13 +13,19 @@
 
 use std::{collections::HashMap, str::FromStr};
 
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{Address, Selector, B256, U256};
 use rundler_types::{Entity, EntityType, Opcode};
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
 
 use crate::simulation::SimulationViolation;
 
+/// ID of the canonical mempool.
+///
+/// A simulator constructed without any mempool configurations falls back to a single mempool
+/// with this ID, enforcing the standard ERC-7562 rules with an empty allowlist.
+pub const CANONICAL_MEMPOOL_ID: B256 = B256::ZERO;
+
 /// A mempool configuration.
 ///
 /// Typically read from a JSON file using the `Deserialize` trait.
@@ -30,6 +36,16 @@ pub struct MempoolConfig {
     pub(crate) entry_point: Address,
     /// Allowlist to match violations against.
     pub(crate) allowlist: Vec<AllowlistEntry>,
+    /// Minimum max priority fee per gas an operation must pay to be included in this mempool.
+    #[serde(default)]
+    pub(crate) min_priority_fee: u128,
+    /// Preference of this mempool when an operation matches several, higher is preferred.
+    #[serde(default)]
+    pub(crate) priority: u32,
+    /// Reject operations whose call phase reverts when executed after validation. Off by
+    /// default as some operators intentionally accept reverting calls.
+    #[serde(default)]
+    pub(crate) call_phase_revert_check: bool,
 }
 
 impl MempoolConfig {
@@ -37,6 +53,11 @@ impl MempoolConfig {
     pub fn entry_point(&self) -> Address {
         self.entry_point
     }
+
+    /// Return true if operations whose call phase reverts are rejected from this mempool
+    pub fn call_phase_revert_check(&self) -> bool {
+        self.call_phase_revert_check
+    }
 }
 
 /// A collection of mempool configurations keyed by their ID.
@@ -106,8 +127,23 @@ pub(crate) enum AllowRule {
     InvalidStorageAccess { contract: Address, slot: U256 },
     /// Allowlist a call with value
     CallWithValue,
+    /// Allowlist a call with value to a specific target contract
+    CallWithValueTo { target: Address },
     /// Allowlist a not staked violation
     NotStaked,
+    /// Allowlist a nonce gap, for mempools that queue future-nonce operations. Nonce gaps
+    /// are attributed to the sender, so only `any` and `account` entries match.
+    NonceTooHigh,
+    /// Allowlist a paymaster deposit below the required prefund, for mempools that
+    /// tolerate just-in-time paymaster funding
+    PaymasterDepositTooLow,
+    /// Allowlist use of the GAS opcode other than as a call's gas argument
+    GasOpcodeMisuse,
+    /// Allowlist a factory using more gas than the configured factory gas limit
+    FactoryGasLimitExceeded,
+    /// Allowlist calling a specific entry point method, e.g. `addStake`. Entry point storage
+    /// accessed by the method is always allowed.
+    EntryPointMethod { method: Selector },
 }
 
 /// An allowlist entry
@@ -163,20 +199,44 @@ impl AllowlistEntry {
                     false
                 }
             }
-            AllowRule::InvalidStorageAccess { contract, slot } => {
-                if let SimulationViolation::InvalidStorageAccess(violation_entity, violation_slot) =
-                    violation
-                {
+            AllowRule::InvalidStorageAccess { contract, slot } => match violation {
+                SimulationViolation::InvalidStorageAccess(violation_entity, violation_slot, _)
+                | SimulationViolation::InvalidEntityStorageAccess(
+                    violation_entity,
+                    _,
+                    violation_slot,
+                ) => {
                     self.entity.is_allowed(violation_entity)
                         && *contract == violation_slot.address
                         && *slot == violation_slot.slot
+                }
+                _ => false,
+            },
+            AllowRule::CallWithValue => {
+                if let SimulationViolation::CallHadValue(violation_entity, _) = violation {
+                    self.entity.is_allowed(violation_entity)
                 } else {
                     false
                 }
             }
-            AllowRule::CallWithValue => {
-                if let SimulationViolation::CallHadValue(violation_entity) = violation {
+            AllowRule::CallWithValueTo { target } => {
+                if let SimulationViolation::CallHadValue(violation_entity, violation_target) =
+                    violation
+                {
                     self.entity.is_allowed(violation_entity)
+                        && violation_target.as_ref() == Some(target)
+                } else {
+                    false
+                }
+            }
+            AllowRule::EntryPointMethod { method } => {
+                if let SimulationViolation::CalledBannedEntryPointMethod(
+                    violation_entity,
+                    violation_method,
+                ) = violation
+                {
+                    self.entity.is_allowed(violation_entity)
+                        && violation_method.as_ref() == Some(method)
                 } else {
                     false
                 }
@@ -188,10 +248,63 @@ impl AllowlistEntry {
                     false
                 }
             }
+            AllowRule::NonceTooHigh => {
+                matches!(violation, SimulationViolation::NonceTooHigh(..))
+                    && matches!(
+                        self.entity,
+                        AllowEntity::Any | AllowEntity::Type(EntityType::Account)
+                    )
+            }
+            AllowRule::GasOpcodeMisuse => {
+                if let SimulationViolation::GasOpcodeMisuse(violation_entity) = violation {
+                    self.entity.is_allowed(violation_entity)
+                } else {
+                    false
+                }
+            }
+            AllowRule::PaymasterDepositTooLow => {
+                if let SimulationViolation::PaymasterDepositTooLow(violation_entity, _, _) =
+                    violation
+                {
+                    self.entity.is_allowed(violation_entity)
+                } else {
+                    false
+                }
+            }
+            AllowRule::FactoryGasLimitExceeded => {
+                if let SimulationViolation::FactoryGasLimitExceeded(violation_entity, _, _) =
+                    violation
+                {
+                    self.entity.is_allowed(violation_entity)
+                } else {
+                    false
+                }
+            }
         }
     }
 }
 
+/// Check if a violation is governed by mempool rules, i.e. whether it is either allowed or
+/// rejected depending on the mempool. Violations that disqualify an operation from every
+/// mempool, such as an invalid signature, are not.
+pub(crate) fn is_mempool_violation(violation: &SimulationViolation) -> bool {
+    matches!(
+        violation,
+        SimulationViolation::UsedForbiddenOpcode(..)
+            | SimulationViolation::UsedForbiddenPrecompile(..)
+            | SimulationViolation::InvalidStorageAccess(..)
+            | SimulationViolation::InvalidEntityStorageAccess(..)
+            | SimulationViolation::CallHadValue(..)
+            | SimulationViolation::CalledBannedEntryPointMethod(..)
+            | SimulationViolation::NotStaked(..)
+            | SimulationViolation::NonceTooHigh(..)
+            | SimulationViolation::GasOpcodeMisuse(..)
+            | SimulationViolation::PaymasterDepositTooLow(..)
+            | SimulationViolation::FactoryGasLimitExceeded(..)
+            | SimulationViolation::MempoolPriorityFeeTooLow(..)
+    )
+}
+
 /// Return value for matching mempools
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum MempoolMatchResult {
@@ -199,16 +312,30 @@ pub(crate) enum MempoolMatchResult {
     Matches(Vec<B256>),
     /// No mempools matched, with the index of the first violation that didn't match
     NoMatch(usize),
+    /// Mempools matched on violations, but the operation's max priority fee is below
+    /// all of their minimums. Contains the lowest minimum of the matched mempools.
+    PriorityFeeTooLow(u128),
 }
 
-/// Match mempools based on a list of violations. Operations are matched to each of the
-/// mempools in which all of their violations are allowlisted. If zero violations,
-/// an operation will match all mempools.
+/// Match mempools based on a list of violations and the operation's max priority fee.
+/// Operations are matched to each of the mempools in which all of their violations are
+/// allowlisted and whose minimum priority fee they meet. If zero violations, an operation
+/// will match all mempools whose minimum priority fee it meets.
+///
+/// Matched mempools are ordered by descending priority, ties broken by ascending id, so the
+/// preferred mempool is first.
 pub(crate) fn match_mempools(
     mempools: &HashMap<B256, MempoolConfig>,
+    mempool_ids: Option<&[B256]>,
     violations: &[SimulationViolation],
+    max_priority_fee_per_gas: u128,
 ) -> MempoolMatchResult {
-    let mut candidate_pools: Vec<B256> = mempools.keys().cloned().collect();
+    // Only consider the requested mempools, if any, ignoring ids that are not configured
+    let mut candidate_pools: Vec<B256> = mempools
+        .keys()
+        .filter(|id| mempool_ids.map_or(true, |ids| ids.contains(id)))
+        .cloned()
+        .collect();
     for (i, violation) in violations.iter().enumerate() {
         candidate_pools.retain(|p| {
             mempools[p]
@@ -220,13 +347,32 @@ pub(crate) fn match_mempools(
             return MempoolMatchResult::NoMatch(i);
         }
     }
-    MempoolMatchResult::Matches(candidate_pools)
+
+    let min_priority_fee = candidate_pools
+        .iter()
+        .map(|p| mempools[p].min_priority_fee)
+        .min();
+    candidate_pools.retain(|p| mempools[p].min_priority_fee <= max_priority_fee_per_gas);
+    candidate_pools.sort_by(|a, b| {
+        mempools[b]
+            .priority
+            .cmp(&mempools[a].priority)
+            .then_with(|| a.cmp(b))
+    });
+    match min_priority_fee {
+        Some(min_priority_fee) if candidate_pools.is_empty() => {
+            MempoolMatchResult::PriorityFeeTooLow(min_priority_fee)
+        }
+        _ => MempoolMatchResult::Matches(candidate_pools),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use alloy_primitives::U256;
-    use rundler_types::{pool::NeedsStakeInformation, StorageSlot, ViolationOpCode};
+    use alloy_primitives::{fixed_bytes, U256};
+    use rundler_types::{
+        pool::NeedsStakeInformation, StorageSlot, StorageSlotLabel, ViolationOpCode,
+    };
 
     use super::*;
 
@@ -399,6 +545,7 @@ mod tests {
                 address: entity_addr,
             },
             slot,
+            StorageSlotLabel::default(),
         );
         assert!(entry.is_allowed(&violation));
 
@@ -408,6 +555,7 @@ mod tests {
                 address: Address::random(),
             },
             slot,
+            StorageSlotLabel::default(),
         );
         assert!(!entry.is_allowed(&violation));
 
@@ -420,6 +568,7 @@ mod tests {
                 address: slot_addr,
                 slot: U256::ZERO,
             },
+            StorageSlotLabel::default(),
         );
         assert!(!entry.is_allowed(&violation));
 
@@ -432,8 +581,22 @@ mod tests {
                 address: Address::random(),
                 slot: U256::from(1234567890),
             },
+            StorageSlotLabel::default(),
         );
         assert!(!entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::InvalidEntityStorageAccess(
+            Entity {
+                kind: EntityType::Account,
+                address: entity_addr,
+            },
+            Entity {
+                kind: EntityType::Paymaster,
+                address: slot_addr,
+            },
+            slot,
+        );
+        assert!(entry.is_allowed(&violation));
     }
 
     #[test]
@@ -442,16 +605,170 @@ mod tests {
         let entry =
             AllowlistEntry::new(AllowEntity::Address(entity_addr), AllowRule::CallWithValue);
 
-        let violation = SimulationViolation::CallHadValue(Entity {
-            kind: EntityType::Account,
-            address: entity_addr,
-        });
+        let violation = SimulationViolation::CallHadValue(
+            Entity {
+                kind: EntityType::Account,
+                address: entity_addr,
+            },
+            None,
+        );
         assert!(entry.is_allowed(&violation));
 
-        let violation = SimulationViolation::CallHadValue(Entity {
-            kind: EntityType::Account,
+        let violation = SimulationViolation::CallHadValue(
+            Entity {
+                kind: EntityType::Account,
+                address: Address::random(),
+            },
+            None,
+        );
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_call_with_value_to() {
+        let entity_addr = Address::random();
+        let target = Address::random();
+        let entry = AllowlistEntry::new(
+            AllowEntity::Address(entity_addr),
+            AllowRule::CallWithValueTo { target },
+        );
+
+        let violation = SimulationViolation::CallHadValue(
+            Entity {
+                kind: EntityType::Account,
+                address: entity_addr,
+            },
+            Some(target),
+        );
+        assert!(entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::CallHadValue(
+            Entity {
+                kind: EntityType::Account,
+                address: entity_addr,
+            },
+            Some(Address::random()),
+        );
+        assert!(!entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::CallHadValue(
+            Entity {
+                kind: EntityType::Account,
+                address: entity_addr,
+            },
+            None,
+        );
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_entry_point_method() {
+        let add_stake = fixed_bytes!("0396cb60");
+        let entry = AllowlistEntry::new(
+            AllowEntity::Type(EntityType::Paymaster),
+            AllowRule::EntryPointMethod { method: add_stake },
+        );
+        let entity = Entity {
+            kind: EntityType::Paymaster,
             address: Address::random(),
-        });
+        };
+
+        let violation = SimulationViolation::CalledBannedEntryPointMethod(entity, Some(add_stake));
+        assert!(entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::CalledBannedEntryPointMethod(
+            entity,
+            Some(fixed_bytes!("12345678")),
+        );
+        assert!(!entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::CalledBannedEntryPointMethod(entity, None);
+        assert!(!entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::CalledBannedEntryPointMethod(
+            Entity {
+                kind: EntityType::Account,
+                address: entity.address,
+            },
+            Some(add_stake),
+        );
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_nonce_too_high() {
+        let entry = AllowlistEntry::new(
+            AllowEntity::Type(EntityType::Account),
+            AllowRule::NonceTooHigh,
+        );
+
+        let violation = SimulationViolation::NonceTooHigh(U256::from(1), U256::from(2));
+        assert!(entry.is_allowed(&violation));
+
+        let entry = AllowlistEntry::new(
+            AllowEntity::Type(EntityType::Paymaster),
+            AllowRule::NonceTooHigh,
+        );
+        assert!(!entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::NonceAlreadyUsed(U256::from(2), U256::from(1));
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_gas_opcode_misuse() {
+        let entry = AllowlistEntry::new(
+            AllowEntity::Type(EntityType::Account),
+            AllowRule::GasOpcodeMisuse,
+        );
+
+        let violation = SimulationViolation::GasOpcodeMisuse(Entity::account(Address::random()));
+        assert!(entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::GasOpcodeMisuse(Entity::paymaster(Address::random()));
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_paymaster_deposit_too_low() {
+        let paymaster = Address::random();
+        let entry = AllowlistEntry::new(
+            AllowEntity::Address(paymaster),
+            AllowRule::PaymasterDepositTooLow,
+        );
+
+        let violation = SimulationViolation::PaymasterDepositTooLow(
+            Entity::paymaster(paymaster),
+            U256::from(2),
+            U256::from(1),
+        );
+        assert!(entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::PaymasterDepositTooLow(
+            Entity::paymaster(Address::random()),
+            U256::from(2),
+            U256::from(1),
+        );
+        assert!(!entry.is_allowed(&violation));
+    }
+
+    #[test]
+    fn test_allowlist_factory_gas_limit_exceeded() {
+        let factory = Address::random();
+        let entry = AllowlistEntry::new(
+            AllowEntity::Address(factory),
+            AllowRule::FactoryGasLimitExceeded,
+        );
+
+        let violation =
+            SimulationViolation::FactoryGasLimitExceeded(Entity::factory(factory), 200, 100);
+        assert!(entry.is_allowed(&violation));
+
+        let violation = SimulationViolation::FactoryGasLimitExceeded(
+            Entity::factory(Address::random()),
+            200,
+            100,
+        );
         assert!(!entry.is_allowed(&violation));
     }
 
@@ -485,6 +802,20 @@ mod tests {
         assert!(!entry.is_allowed(&violation));
     }
 
+    #[test]
+    fn test_is_mempool_violation() {
+        assert!(is_mempool_violation(&SimulationViolation::CallHadValue(
+            Entity::paymaster(Address::random()),
+            None,
+        )));
+        assert!(is_mempool_violation(
+            &SimulationViolation::MempoolPriorityFeeTooLow(0, 100)
+        ));
+        assert!(!is_mempool_violation(
+            &SimulationViolation::InvalidSignature
+        ));
+    }
+
     #[test]
     fn test_match_none() {
         let contract = Address::random();
@@ -494,6 +825,9 @@ mod tests {
                 B256::random(),
                 MempoolConfig {
                     entry_point: Address::random(),
+                    min_priority_fee: 0,
+                    priority: 0,
+                    call_phase_revert_check: false,
                     allowlist: vec![AllowlistEntry::new(
                         AllowEntity::Type(EntityType::Account),
                         AllowRule::ForbiddenOpcode {
@@ -513,7 +847,7 @@ mod tests {
             ViolationOpCode(Opcode::BLOCKHASH),
         );
         assert_eq!(
-            match_mempools(&mempools, &[violation]),
+            match_mempools(&mempools, None, &[violation], 0),
             MempoolMatchResult::NoMatch(0)
         );
     }
@@ -527,6 +861,9 @@ mod tests {
                 B256::random(),
                 MempoolConfig {
                     entry_point: Address::random(),
+                    min_priority_fee: 0,
+                    priority: 0,
+                    call_phase_revert_check: false,
                     allowlist: vec![AllowlistEntry::new(
                         AllowEntity::Type(EntityType::Account),
                         AllowRule::ForbiddenOpcode {
@@ -556,7 +893,7 @@ mod tests {
             ),
         ];
         assert_eq!(
-            match_mempools(&mempools, &violations),
+            match_mempools(&mempools, None, &violations, 0),
             MempoolMatchResult::NoMatch(1)
         );
     }
@@ -572,6 +909,9 @@ mod tests {
                 mempool1,
                 MempoolConfig {
                     entry_point: Address::random(),
+                    min_priority_fee: 0,
+                    priority: 0,
+                    call_phase_revert_check: false,
                     allowlist: vec![AllowlistEntry::new(
                         AllowEntity::Type(EntityType::Account),
                         AllowRule::ForbiddenOpcode {
@@ -591,7 +931,7 @@ mod tests {
             ViolationOpCode(Opcode::GAS),
         )];
         assert_eq!(
-            match_mempools(&mempools, &violations),
+            match_mempools(&mempools, None, &violations, 0),
             MempoolMatchResult::Matches(vec![mempool1])
         );
     }
@@ -608,6 +948,9 @@ mod tests {
                 mempool1,
                 MempoolConfig {
                     entry_point: Address::random(),
+                    min_priority_fee: 0,
+                    priority: 0,
+                    call_phase_revert_check: false,
                     allowlist: vec![
                         AllowlistEntry::new(
                             AllowEntity::Type(EntityType::Account),
@@ -630,6 +973,9 @@ mod tests {
                 mempool2,
                 MempoolConfig {
                     entry_point: Address::random(),
+                    min_priority_fee: 0,
+                    priority: 0,
+                    call_phase_revert_check: false,
                     allowlist: vec![
                         AllowlistEntry::new(
                             AllowEntity::Type(EntityType::Account),
@@ -675,7 +1021,7 @@ mod tests {
             ),
         ];
 
-        match match_mempools(&mempools, &violations) {
+        match match_mempools(&mempools, None, &violations, 0) {
             MempoolMatchResult::Matches(mempools) => {
                 assert_eq!(mempools.len(), 2);
                 assert!(mempools.contains(&mempool1));
@@ -684,4 +1030,102 @@ mod tests {
             _ => panic!("Expected matches"),
         }
     }
+
+    #[test]
+    fn test_match_priority_fee() {
+        let mempool0 = B256::random();
+        let mempool1 = B256::random();
+        let mempools = HashMap::from([
+            (
+                mempool0,
+                MempoolConfig {
+                    min_priority_fee: 10,
+                    ..Default::default()
+                },
+            ),
+            (
+                mempool1,
+                MempoolConfig {
+                    min_priority_fee: 20,
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            match_mempools(&mempools, None, &[], 15),
+            MempoolMatchResult::Matches(vec![mempool0])
+        );
+        assert_eq!(
+            match_mempools(&mempools, None, &[], 5),
+            MempoolMatchResult::PriorityFeeTooLow(10)
+        );
+        match match_mempools(&mempools, None, &[], 20) {
+            MempoolMatchResult::Matches(mempools) => assert_eq!(mempools.len(), 2),
+            _ => panic!("Expected matches"),
+        }
+    }
+
+    #[test]
+    fn test_match_ordered_by_priority() {
+        let low = B256::with_last_byte(1);
+        let high = B256::with_last_byte(2);
+        let tied = B256::with_last_byte(3);
+        let mempools = HashMap::from([
+            (
+                low,
+                MempoolConfig {
+                    priority: 1,
+                    call_phase_revert_check: false,
+                    ..Default::default()
+                },
+            ),
+            (
+                tied,
+                MempoolConfig {
+                    priority: 10,
+                    call_phase_revert_check: false,
+                    ..Default::default()
+                },
+            ),
+            (
+                high,
+                MempoolConfig {
+                    priority: 10,
+                    call_phase_revert_check: false,
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            match_mempools(&mempools, None, &[], 0),
+            MempoolMatchResult::Matches(vec![high, tied, low])
+        );
+    }
+
+    #[test]
+    fn test_match_restricted_mempools() {
+        let mempool0 = B256::random();
+        let mempool1 = B256::random();
+        let mempools = HashMap::from([
+            (mempool0, MempoolConfig::default()),
+            (mempool1, MempoolConfig::default()),
+        ]);
+
+        assert_eq!(
+            match_mempools(&mempools, Some(&[mempool1]), &[], 0),
+            MempoolMatchResult::Matches(vec![mempool1])
+        );
+        // violations are only checked against the requested mempools
+        let violation = SimulationViolation::UsedForbiddenOpcode(
+            Entity::account(Address::random()),
+            Address::random(),
+            ViolationOpCode(Opcode::GASPRICE),
+        );
+        assert_eq!(
+            match_mempools(&mempools, Some(&[mempool0]), &[violation], 0),
+            MempoolMatchResult::NoMatch(0)
+        );
+    }
 }
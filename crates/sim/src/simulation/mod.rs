@@ -11,28 +11,64 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::collections::HashSet;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 #[cfg(feature = "test-utils")]
 use alloy_primitives::uint;
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{fixed_bytes, Address, Bytes, Selector, B256, U256};
+use anyhow::bail;
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_provider::{AggregatorSimOut, ProviderError};
+use rundler_provider::{AggregatorSimOut, ProviderError, StateOverride};
 use rundler_types::{
+    chain::ChainSpec,
     pool::{MempoolError, SimulationViolation},
-    EntityInfos, UserOperation, ValidTimeRange,
+    Entity, EntityInfos, EntityType, UserOperation, ValidTimeRange,
 };
+use serde::{Deserialize, Serialize};
+
+mod clock;
+#[cfg(any(test, feature = "test-utils"))]
+pub use clock::MockClock;
+pub use clock::{Clock, SystemClock};
 
 mod context;
 pub use context::ValidationContextProvider;
 
+mod group;
+pub use group::{group_for_bundle, BundleGroups};
+
+mod labeler;
+pub use labeler::{NoopStorageSlotLabeler, StorageSlotLabeler};
+
 mod mempool;
-pub use mempool::{MempoolConfig, MempoolConfigs};
+pub use mempool::{MempoolConfig, MempoolConfigs, CANONICAL_MEMPOOL_ID};
+
+mod policy;
+pub use policy::AdmissionPolicy;
+
+mod rank;
+pub use rank::{simulate_and_rank, RankedOperation, RankedOperations};
+
+mod rejection;
+pub use rejection::{Rejection, RejectionSink, TracingRejectionSink};
 
 mod simulator;
 pub use simulator::{new_v0_6_simulator, new_v0_7_simulator, SimulatorImpl};
 
+mod sponsorship;
+pub use sponsorship::PaymasterTimeRangeDecoder;
+
+mod static_check;
+pub use static_check::validate_static;
+
+mod tracer_mode;
+pub use tracer_mode::{detect_tracer_mode, TracerMode};
+
 mod unsafe_sim;
 pub use unsafe_sim::UnsafeSimulator;
 
@@ -41,21 +77,27 @@ pub mod v0_6;
 /// Entry Point v0.7 Tracing
 pub mod v0_7;
 
-use crate::{ExpectedStorage, ViolationError};
+use crate::{ExpectedStorage, GasEstimationError, ViolationError};
 
 /// The result of a successful simulation
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct SimulationResult {
     /// The mempool IDs that support this operation
     pub mempools: Vec<B256>,
     /// Gas used in the pre-op phase of simulation measured
     /// by the entry point
     pub pre_op_gas: u128,
+    /// Gas consumed by validation as measured by the tracer, summed over the
+    /// factory, account and paymaster validation phases. Unlike `pre_op_gas` this
+    /// excludes pre-verification gas. Zero if tracing was skipped.
+    pub validation_gas_used: u128,
+    /// Number of the block the operation was simulated at. Zero if tracing was skipped.
+    pub block_number: u64,
     /// The time range for which this operation is valid
     pub valid_time_range: ValidTimeRange,
-    /// If using an aggregator, the result of the aggregation
-    /// simulation
-    pub aggregator: Option<AggregatorSimOut>,
+    /// The results of the aggregation simulation, one per aggregator used by the
+    /// operation. Empty if not using an aggregator.
+    pub aggregators: Vec<AggregatorSimOut>,
     /// Code hash of all accessed contracts
     pub code_hash: B256,
     /// Whether the sender account is staked
@@ -67,17 +109,132 @@ pub struct SimulationResult {
     pub associated_addresses: HashSet<Address>,
     /// Expected storage values for all accessed slots during validation
     pub expected_storage: ExpectedStorage,
+    /// True if `expected_storage` was truncated to `Settings::max_expected_storage_slots`
+    /// and does not contain every slot accessed during validation.
+    pub storage_truncated: bool,
     /// Whether the operation requires a post-op
     pub requires_post_op: bool,
+    /// The context returned by the paymaster's validation, passed to its post-op.
+    /// Empty if there is no paymaster or it returned no context.
+    pub paymaster_context: Bytes,
     /// All the entities used in this operation and their staking state
     pub entity_infos: EntityInfos,
+    /// Whether the factory, sender, and paymaster had code deployed at the
+    /// simulated block. A sender mapped to `false` will be deployed by the factory.
+    pub entity_deployment: HashMap<EntityType, bool>,
+    /// True if the operation was validated with a plain `simulateValidation` call
+    /// instead of a trace. Opcode and storage access rules were NOT enforced and the
+    /// operation should not be treated as fully validated.
+    pub tracing_skipped: bool,
+    /// True if the sender's balance was overridden via `SimulationOverrides::prefund`.
+    /// The operation validated as if funded and must not be bundled on this result.
+    pub prefund_overridden: bool,
+    /// True if the signature was replaced via `SimulationOverrides::signature` and signature
+    /// failures were ignored. The operation's real signature is unchecked and it must not
+    /// be bundled on this result.
+    pub signature_bypassed: bool,
+    /// Violations that were allowed by the matched mempools' allowlists, with the entity
+    /// each is attributed to. Violations that can't be attributed are assigned to the
+    /// sender. Empty unless `Settings::collect_allowlisted_violations` is set.
+    pub allowlisted_violations: Vec<(Entity, SimulationViolation)>,
+    /// Opaque metadata attached by the caller, such as the source of the operation, echoed
+    /// back unchanged. Never inspected during validation.
+    pub metadata: Option<Bytes>,
+    /// Addresses without deployed code whose code was probed, but that weren't called,
+    /// during validation. Validation may have a different outcome once code is deployed
+    /// to any of them. Empty unless such probes were allowed.
+    pub probed_undeployed_addresses: HashSet<Address>,
 }
 
 impl SimulationResult {
     /// Get the aggregator address if one was used
+    ///
+    /// Deprecated: only returns the first aggregator, use `aggregators` to handle
+    /// operations using multiple aggregators.
     pub fn aggregator_address(&self) -> Option<Address> {
-        self.aggregator.as_ref().map(|agg| agg.address)
+        self.aggregators.first().map(|agg| agg.address)
     }
+
+    /// The amount of bundle gas to reserve for this operation.
+    ///
+    /// This is the larger of the operation's execution gas limit and the simulated
+    /// `pre_op_gas` plus its call gas limit.
+    pub fn bundle_gas<UO: UserOperation>(&self, op: &UO, chain_spec: &ChainSpec) -> u128 {
+        op.execution_gas_limit(chain_spec, None)
+            .max(self.pre_op_gas.saturating_add(op.call_gas_limit()))
+    }
+}
+
+/// Options for `SimulatorImpl::estimate_user_operation_gas`
+#[derive(Clone, Debug)]
+pub struct GasEstimateOptions {
+    /// Chain spec used to compute the pre-verification gas
+    pub chain_spec: ChainSpec,
+    /// Gas price used to compute the data availability portion of the
+    /// pre-verification gas on chains that require it
+    pub gas_price: u128,
+    /// Upper bound for the verification gas limit search
+    pub max_verification_gas: u128,
+    /// State override applied while estimating the verification gas limit
+    pub state_override: StateOverride,
+    /// If set, replaces the operation's signature, so that operations can be estimated
+    /// before they are signed
+    pub signature_override: Option<Bytes>,
+}
+
+/// Per-call overrides for `SimulatorImpl::simulate_validation_with_overrides`, used by gas
+/// estimation and debugging. Results of simulations using an override must not be used to
+/// bundle the operation.
+#[derive(Clone, Debug, Default)]
+pub struct SimulationOverrides {
+    /// If set, the operation's signature is replaced with this value before simulating and
+    /// account and paymaster signature failures are ignored, e.g. for unsigned operations.
+    /// Results are flagged with `signature_bypassed`.
+    pub signature: Option<Bytes>,
+    /// If set, the sender's balance is overridden with this value while tracing so that
+    /// operations from unfunded senders validate as if funded. Results are flagged with
+    /// `prefund_overridden`.
+    pub prefund: Option<U256>,
+    /// If set, the operation's call gas limit is replaced with this value before
+    /// simulating, e.g. to vary the call gas limit independently during gas estimation.
+    pub call_gas_limit: Option<u128>,
+}
+
+/// The result of simulating a full `handleOps` bundle
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleSimResult {
+    /// The result of each operation, in the order the operations were given
+    pub results: Vec<BundleOpResult>,
+    /// The index of the first operation that failed, if any
+    pub first_failed_index: Option<usize>,
+}
+
+impl BundleSimResult {
+    /// True if every operation in the bundle succeeded
+    pub fn is_success(&self) -> bool {
+        self.first_failed_index.is_none()
+    }
+}
+
+/// The result of a single operation within a simulated bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BundleOpResult {
+    /// The operation executed without causing the bundle to revert
+    Success,
+    /// The operation caused the bundle to revert, with the entry point's reason
+    Failed(String),
+}
+
+/// Returns true if an operation fits in a bundle that has already used `current_gas`
+/// without exceeding `max_bundle_gas`.
+pub fn can_add_to_bundle<UO: UserOperation>(
+    current_gas: u128,
+    op: &UO,
+    sim: &SimulationResult,
+    chain_spec: &ChainSpec,
+    max_bundle_gas: u128,
+) -> bool {
+    current_gas.saturating_add(sim.bundle_gas(op, chain_spec)) <= max_bundle_gas
 }
 
 /// The result of a failed simulation. We return a list of the violations that ocurred during the failed simulation
@@ -99,6 +256,19 @@ impl From<anyhow::Error> for SimulationError {
     }
 }
 
+impl SimulationError {
+    /// Order the violations, if any, most important first. If no priority is given, the
+    /// default `SimulationViolation` ordering is used.
+    pub fn prioritize(&mut self, priority: Option<&ViolationPriority>) {
+        if let ViolationError::Violations(violations) = &mut self.violation_error {
+            match priority {
+                Some(priority) => violations.sort_by(|a, b| priority.compare(a, b)),
+                None => violations.sort(),
+            }
+        }
+    }
+}
+
 impl From<ViolationError<SimulationViolation>> for SimulationError {
     fn from(violation_error: ViolationError<SimulationViolation>) -> Self {
         SimulationError {
@@ -117,7 +287,8 @@ impl From<SimulationError> for MempoolError {
             return Self::Other((*violation_error).clone().into());
         };
 
-        let Some(violation) = violations.iter_mut().min() else {
+        // Simulators order violations by priority, surface the most important one
+        let Some(violation) = violations.first_mut() else {
             return Self::Other((*violation_error).clone().into());
         };
 
@@ -129,6 +300,22 @@ impl From<SimulationError> for MempoolError {
     }
 }
 
+impl From<GasEstimationError> for SimulationError {
+    fn from(error: GasEstimationError) -> Self {
+        let violation_error = match error {
+            GasEstimationError::RevertInValidation(revert) => {
+                ViolationError::Violations(vec![SimulationViolation::ValidationRevert(revert)])
+            }
+            GasEstimationError::Other(error) => ViolationError::Other(error),
+            error => ViolationError::Other(error.into()),
+        };
+        SimulationError {
+            violation_error,
+            entity_infos: None,
+        }
+    }
+}
+
 impl From<ProviderError> for SimulationError {
     fn from(error: ProviderError) -> Self {
         SimulationError {
@@ -139,6 +326,12 @@ impl From<ProviderError> for SimulationError {
 }
 
 /// Simulator trait for running user operation simulations
+///
+/// Simulations are cancellation safe: dropping a simulation future before it completes,
+/// for example when a new block arrives, leaves any shared state such as the trace cache
+/// as it was. Outputs are only cached once a trace has completed successfully.
+///
+/// Violations in a returned error are ordered most important first, see `ViolationPriority`.
 #[cfg_attr(feature = "test-utils", automock(type UO = rundler_types::v0_6::UserOperation;))]
 #[async_trait::async_trait]
 pub trait Simulator: Send + Sync {
@@ -153,6 +346,72 @@ pub trait Simulator: Send + Sync {
         block_hash: B256,
         expected_code_hash: Option<B256>,
     ) -> Result<SimulationResult, SimulationError>;
+
+    /// Simulate a user operation, only matching it against the given subset of
+    /// the configured mempools.
+    ///
+    /// Errors if any of the given mempool ids are not configured.
+    async fn simulate_validation_in_mempools(
+        &self,
+        op: Self::UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        mempool_ids: Vec<B256>,
+    ) -> Result<SimulationResult, SimulationError>;
+
+    /// Simulate a user operation, attaching opaque metadata to the result.
+    ///
+    /// The metadata is passed through to `SimulationResult::metadata` untouched and plays no
+    /// part in validation, so the result is otherwise identical to `simulate_validation`.
+    async fn simulate_validation_with_metadata(
+        &self,
+        op: Self::UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        metadata: Option<Bytes>,
+    ) -> Result<SimulationResult, SimulationError> {
+        let mut result = self
+            .simulate_validation(op, block_hash, expected_code_hash)
+            .await?;
+        result.metadata = metadata;
+        Ok(result)
+    }
+
+    /// Explain why a user operation is not eligible for a specific mempool.
+    ///
+    /// Returns the mempool rule violation that disqualified the operation from the mempool,
+    /// or `None` if the operation is eligible. Failures that are not governed by mempool
+    /// rules, such as an invalid signature or an unknown mempool id, are returned as errors.
+    ///
+    /// Implementations that record rejections should override this so that explaining a
+    /// rejection does not record one.
+    async fn explain_mempool_rejection(
+        &self,
+        op: Self::UO,
+        block_hash: B256,
+        mempool_id: B256,
+    ) -> Result<Option<SimulationViolation>, SimulationError> {
+        mempool_rejection(
+            self.simulate_validation_in_mempools(op, block_hash, None, vec![mempool_id])
+                .await,
+        )
+    }
+}
+
+// Pick the mempool rule violation, if any, out of a simulation restricted to a single mempool
+fn mempool_rejection(
+    result: Result<SimulationResult, SimulationError>,
+) -> Result<Option<SimulationViolation>, SimulationError> {
+    let error = match result {
+        Ok(_) => return Ok(None),
+        Err(error) => error,
+    };
+    if let ViolationError::Violations(violations) = &error.violation_error {
+        if let Some(violation) = violations.iter().find(|v| mempool::is_mempool_violation(v)) {
+            return Ok(Some(violation.clone()));
+        }
+    }
+    Err(error)
 }
 
 /// Simulation Settings
@@ -167,8 +426,165 @@ pub struct Settings {
     /// The max duration of the custom javascript tracer. Must be in a format parseable by the
     /// ParseDuration function on an ethereum node. See Docs: https://pkg.go.dev/time#ParseDuration
     pub tracer_timeout: String,
+    /// Optional override of the order in which violations are surfaced when an operation is
+    /// rejected. If `None`, the default `SimulationViolation` ordering is used.
+    pub violation_priority: Option<ViolationPriority>,
+    /// If true, fall back to a plain `simulateValidation` call when the node does not
+    /// support `debug_traceCall`. Results are flagged with `tracing_skipped`.
+    pub trace_fallback_enabled: bool,
+    /// Admit to the pool, and bundle, operations whose simulation fell back to a plain
+    /// `simulateValidation` call. Otherwise results flagged with `tracing_skipped` are only
+    /// informational.
+    pub untraced_ops_allowed: bool,
+    /// Maximum duration of each stage of simulation (tracing, then code hash and
+    /// aggregator checks) before the operation is rejected as timed out.
+    pub simulation_timeout: Duration,
+    /// Precompile addresses allowed during validation in addition to the standard set.
+    /// Empty on standard Ethereum, set from the chain spec.
+    pub extra_allowed_precompiles: HashSet<Address>,
+    /// If true, check the operation nonce against the sender's next nonce before simulating.
+    /// Used nonces are rejected with `NonceAlreadyUsed`, nonce gaps with `NonceTooHigh`
+    /// unless allowlisted by a mempool.
+    pub nonce_check_enabled: bool,
+    /// ERC-20 token paymasters by paymaster address. Operations using one of these
+    /// paymasters have the sender's token balance and allowance checked before simulation.
+    pub token_paymasters: HashMap<Address, TokenPaymasterConfig>,
+    /// Contract code hashes, as returned by EXTCODEHASH, that are trusted when the code
+    /// accessed by an operation changes between simulations. If any contract accessed during
+    /// validation has trusted code, the change is accepted instead of rejecting the operation
+    /// with `CodeHashChanged`.
+    pub trusted_code_hashes: HashSet<B256>,
+    /// If true, reject operations whose validity time range is empty (`valid_after` and
+    /// `valid_until` both 0) with `SuspiciousTimeRange`. Otherwise such a range is treated
+    /// as valid for all time.
+    pub reject_empty_time_range: bool,
+    /// If true, operations using a paymaster are rejected with `PaymasterDepositTooLow`
+    /// when the paymaster's entry point deposit does not cover the required prefund,
+    /// unless allowlisted by a mempool.
+    pub paymaster_deposit_check_enabled: bool,
+    /// Maximum gas used to simulate handling operations. Operations whose combined
+    /// verification, call and pre-verification gas exceeds this are rejected with
+    /// `TotalGasLimitExceeded` before tracing.
+    pub max_simulate_handle_ops_gas: u64,
+    /// If set, bounds the number of simulation stages making provider calls (tracing, then
+    /// the contract checks) in flight at once across concurrent simulations by the same
+    /// simulator. Unbounded otherwise.
+    pub max_concurrent_provider_calls: Option<usize>,
+    /// If true, entities may probe undeployed addresses with EXTCODESIZE, EXTCODEHASH or
+    /// EXTCODECOPY, e.g. to check whether a counterfactual address is deployed. Only calls
+    /// into undeployed contracts are rejected with `AccessedUndeployedContract`.
+    pub allow_undeployed_contract_probes: bool,
+    /// Addresses of validator modules installed on modular accounts (ERC-6900/7579).
+    /// Storage of these contracts is treated like the sender's own storage when accessed
+    /// during account validation.
+    pub validator_modules: HashSet<Address>,
+    /// If set, the expected storage retained in a simulation result is truncated to this
+    /// many slots and the result is flagged with `storage_truncated`. Bounds the memory
+    /// held per operation. Unbounded otherwise.
+    pub max_expected_storage_slots: Option<usize>,
+    /// If true, validation trace outputs are cached by user operation hash and signature for
+    /// the most recently traced block, so re-validating an operation at the same block does
+    /// not trace it again.
+    pub trace_cache_enabled: bool,
+    /// Addresses that operations may not use as their sender, factory or paymaster, e.g.
+    /// sanctioned addresses. Such operations are rejected with `AddressDenied` before tracing.
+    pub denylist: HashSet<Address>,
+    /// If true, an entry point v0.6 trace that ends with fewer than three phases without
+    /// reverting with `FailedOp` is retried once with the tracer's gas limit multiplied by
+    /// `phase_retry_gas_factor`, to tell a gas-starved trace apart from a broken entry point.
+    /// Operations whose retried trace still has the wrong number of phases are rejected
+    /// with `WrongNumberOfPhases`.
+    pub phase_retry_enabled: bool,
+    /// Factor by which the tracer's gas limit is multiplied when retrying a trace with too
+    /// few phases.
+    pub phase_retry_gas_factor: u64,
+    /// If set, operations whose factory uses more than this much gas during deployment
+    /// are rejected with `FactoryGasLimitExceeded`, unless allowlisted by a mempool.
+    pub max_factory_gas: Option<u128>,
+    /// If set, simulations taking longer than this are logged at warn level, along with
+    /// the size of their trace.
+    pub slow_simulation_threshold: Option<Duration>,
+    /// Code hashes of helper contracts that factories may deploy with CREATE. If set,
+    /// factory CREATEs are allowed but deploying any other code is rejected with
+    /// `FactoryDeployedUnapprovedContract`. Otherwise CREATE is forbidden as usual.
+    pub factory_create_code_hashes: HashSet<B256>,
+    /// If set, violations that a mempool allowlisted are reported in
+    /// `SimulationResult::allowlisted_violations`, attributed to their entity.
+    pub collect_allowlisted_violations: bool,
+    /// Selectors of the entry point methods that entities may call during validation.
+    /// Calls to any other method are rejected with `CalledBannedEntryPointMethod`.
+    /// Defaults to only `depositTo`.
+    pub allowed_entry_point_methods: HashSet<Selector>,
+    /// How validation is traced. `Auto` must be resolved with [`detect_tracer_mode`]
+    /// before constructing a simulator, and is otherwise treated as `Custom`.
+    pub tracer_mode: TracerMode,
+    /// Maximum number of opcode steps a validation trace may execute before it is aborted
+    /// and the operation rejected with `SimulationBudgetExceeded`. Unlimited if unset.
+    pub max_trace_steps: Option<u64>,
+    /// Maximum number of storage accesses a validation trace may make before it is aborted
+    /// and the operation rejected with `SimulationBudgetExceeded`. Unlimited if unset.
+    pub max_trace_storage_accesses: Option<u64>,
+    /// Maximum number of distinct contracts validation may access. Operations accessing
+    /// more are rejected with `TooManyContractsAccessed` before their code hashes are
+    /// fetched. Unlimited if unset.
+    pub max_accessed_contracts: Option<usize>,
+    /// Gas added to the validation trace call on top of the verification gas, covering the
+    /// entry point's own overhead so that validation using all of its gas doesn't run the
+    /// trace out of gas at the entry point level.
+    pub tracer_gas_overhead: u64,
+    /// How operations whose sender is also their paymaster are handled
+    pub sender_is_paymaster_policy: SenderIsPaymasterPolicy,
+    /// If true, staked entities may use the block environment opcodes `TIMESTAMP`, `NUMBER`
+    /// and `COINBASE`, while unstaked entities using them are rejected with
+    /// `UsedBannedEnvironmentOpcode`. Otherwise they are forbidden for all entities.
+    pub staked_environment_opcodes_allowed: bool,
+}
+
+/// How operations whose sender is also their paymaster are handled.
+///
+/// Such an operation attributes the paymaster's stake and storage accesses to the sender,
+/// which can confuse reputation tracking and be used for griefing.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, parse_display::Display, parse_display::FromStr,
+)]
+#[display(style = "snake_case")]
+pub enum SenderIsPaymasterPolicy {
+    /// Reject the operation with `SenderIsPaymaster`
+    Reject,
+    /// Require the sender to be staked, rejecting the operation with `NotStaked` otherwise
+    #[default]
+    RequireStake,
+}
+
+/// Default gas added to the validation trace call for the entry point's own overhead
+pub const DEFAULT_TRACER_GAS_OVERHEAD: u64 = 100_000;
+
+/// Selector of the entry point's `depositTo(address)` method, the only entry point method
+/// the spec allows calling during validation
+pub const DEPOSIT_TO_SELECTOR: Selector = fixed_bytes!("b760faf9");
+
+const DEFAULT_SIMULATION_TIMEOUT: Duration = Duration::from_secs(20);
+const DEFAULT_PHASE_RETRY_GAS_FACTOR: u64 = 2;
+
+/// Configuration of an ERC-20 token paymaster
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPaymasterConfig {
+    /// The token the paymaster charges the sender in
+    pub token: Address,
+    /// Amount of the token, in its smallest unit, charged per 1 ETH of gas cost
+    pub tokens_per_eth: U256,
+}
+
+impl TokenPaymasterConfig {
+    /// The amount of tokens needed to cover a gas cost in wei
+    pub fn required_tokens(&self, gas_cost: U256) -> U256 {
+        gas_cost.saturating_mul(self.tokens_per_eth) / U256::from(WEI_PER_ETH)
+    }
 }
 
+const WEI_PER_ETH: u128 = 1_000_000_000_000_000_000;
+
 impl Settings {
     /// Create new settings
     pub fn new(min_unstake_delay: u32, min_stake_value: U256, tracer_timeout: String) -> Self {
@@ -176,7 +592,72 @@ impl Settings {
             min_unstake_delay,
             min_stake_value,
             tracer_timeout,
+            violation_priority: None,
+            trace_fallback_enabled: false,
+            untraced_ops_allowed: false,
+            simulation_timeout: DEFAULT_SIMULATION_TIMEOUT,
+            extra_allowed_precompiles: HashSet::new(),
+            nonce_check_enabled: false,
+            token_paymasters: HashMap::new(),
+            trusted_code_hashes: HashSet::new(),
+            reject_empty_time_range: false,
+            paymaster_deposit_check_enabled: false,
+            max_simulate_handle_ops_gas: u64::MAX,
+            max_concurrent_provider_calls: None,
+            allow_undeployed_contract_probes: false,
+            validator_modules: HashSet::new(),
+            max_expected_storage_slots: None,
+            trace_cache_enabled: false,
+            denylist: HashSet::new(),
+            phase_retry_enabled: false,
+            phase_retry_gas_factor: DEFAULT_PHASE_RETRY_GAS_FACTOR,
+            max_factory_gas: None,
+            slow_simulation_threshold: None,
+            factory_create_code_hashes: HashSet::new(),
+            collect_allowlisted_violations: false,
+            allowed_entry_point_methods: HashSet::from([DEPOSIT_TO_SELECTOR]),
+            tracer_mode: TracerMode::Custom,
+            max_trace_steps: None,
+            max_trace_storage_accesses: None,
+            max_accessed_contracts: None,
+            tracer_gas_overhead: DEFAULT_TRACER_GAS_OVERHEAD,
+            sender_is_paymaster_policy: SenderIsPaymasterPolicy::default(),
+            staked_environment_opcodes_allowed: false,
+        }
+    }
+}
+
+/// Operator defined priority of simulation violations.
+///
+/// Violations are referred to by their variant name, i.e. `NotStaked`. Listed violations
+/// are ordered ahead of unlisted ones in the order given. Ties, including between two
+/// unlisted violations, fall back to the default `SimulationViolation` ordering.
+#[derive(Debug, Clone, Default)]
+pub struct ViolationPriority(HashMap<String, usize>);
+
+impl ViolationPriority {
+    /// Create a new priority from a list of violation names, highest priority first.
+    ///
+    /// Errors if a name does not match a `SimulationViolation` variant.
+    pub fn new(names: Vec<String>) -> anyhow::Result<Self> {
+        let mut priority = HashMap::new();
+        for (i, name) in names.into_iter().enumerate() {
+            if !<SimulationViolation as strum::VariantNames>::VARIANTS.contains(&name.as_str()) {
+                bail!("unknown simulation violation in priority list: {name}");
+            }
+            priority.entry(name).or_insert(i);
         }
+        Ok(Self(priority))
+    }
+
+    /// Compare two violations, most important first.
+    pub fn compare(&self, a: &SimulationViolation, b: &SimulationViolation) -> Ordering {
+        self.rank(a).cmp(&self.rank(b)).then_with(|| a.cmp(b))
+    }
+
+    fn rank(&self, violation: &SimulationViolation) -> usize {
+        let name: &'static str = violation.into();
+        self.0.get(name).copied().unwrap_or(usize::MAX)
     }
 }
 
@@ -189,6 +670,243 @@ impl Default for Settings {
             // 10^18 wei = 1 eth
             min_stake_value: uint!(1_000_000_000_000_000_000_U256),
             tracer_timeout: "10s".to_string(),
+            violation_priority: None,
+            trace_fallback_enabled: false,
+            untraced_ops_allowed: false,
+            simulation_timeout: DEFAULT_SIMULATION_TIMEOUT,
+            extra_allowed_precompiles: HashSet::new(),
+            nonce_check_enabled: false,
+            token_paymasters: HashMap::new(),
+            trusted_code_hashes: HashSet::new(),
+            reject_empty_time_range: false,
+            paymaster_deposit_check_enabled: false,
+            max_simulate_handle_ops_gas: u64::MAX,
+            max_concurrent_provider_calls: None,
+            allow_undeployed_contract_probes: false,
+            validator_modules: HashSet::new(),
+            max_expected_storage_slots: None,
+            trace_cache_enabled: false,
+            denylist: HashSet::new(),
+            phase_retry_enabled: false,
+            phase_retry_gas_factor: DEFAULT_PHASE_RETRY_GAS_FACTOR,
+            max_factory_gas: None,
+            slow_simulation_threshold: None,
+            factory_create_code_hashes: HashSet::new(),
+            collect_allowlisted_violations: false,
+            allowed_entry_point_methods: HashSet::from([DEPOSIT_TO_SELECTOR]),
+            tracer_mode: TracerMode::Custom,
+            max_trace_steps: None,
+            max_trace_storage_accesses: None,
+            max_accessed_contracts: None,
+            tracer_gas_overhead: DEFAULT_TRACER_GAS_OVERHEAD,
+            sender_is_paymaster_policy: SenderIsPaymasterPolicy::default(),
+            staked_environment_opcodes_allowed: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::{
+        pool::NeedsStakeInformation, v0_6::UserOperation as UserOperationV0_6, Entity, EntityInfo,
+        StorageSlot, StorageSlotLabel,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_can_add_to_bundle() {
+        let chain_spec = ChainSpec::default();
+        let op = UserOperationV0_6 {
+            call_gas_limit: 100_000,
+            verification_gas_limit: 100_000,
+            pre_verification_gas: 50_000,
+            ..Default::default()
+        };
+        let limit_gas = op.execution_gas_limit(&chain_spec, None);
+
+        // simulated gas below the limits, limits dominate
+        let sim = SimulationResult {
+            pre_op_gas: 10_000,
+            ..Default::default()
+        };
+        assert_eq!(sim.bundle_gas(&op, &chain_spec), limit_gas);
+        assert!(can_add_to_bundle(0, &op, &sim, &chain_spec, limit_gas));
+        assert!(can_add_to_bundle(
+            1_000,
+            &op,
+            &sim,
+            &chain_spec,
+            limit_gas + 1_000
+        ));
+        assert!(!can_add_to_bundle(
+            1_001,
+            &op,
+            &sim,
+            &chain_spec,
+            limit_gas + 1_000
+        ));
+
+        // simulated gas above the limits dominates
+        let sim = SimulationResult {
+            pre_op_gas: limit_gas,
+            ..Default::default()
+        };
+        assert_eq!(sim.bundle_gas(&op, &chain_spec), limit_gas + 100_000);
+        assert!(!can_add_to_bundle(0, &op, &sim, &chain_spec, limit_gas));
+    }
+
+    #[test]
+    fn test_simulation_result_serde_round_trip() {
+        let sender = Address::random();
+        let factory = Address::random();
+        let mut expected_storage = ExpectedStorage::default();
+        expected_storage.insert(sender, U256::from(1), U256::from(2));
+
+        let result = SimulationResult {
+            mempools: vec![B256::random()],
+            pre_op_gas: 100_000,
+            validation_gas_used: 50_000,
+            block_number: 1_234,
+            valid_time_range: ValidTimeRange::new(10.into(), 20.into()),
+            aggregators: vec![AggregatorSimOut {
+                address: Address::random(),
+                signature: vec![1, 2, 3].into(),
+            }],
+            code_hash: B256::random(),
+            account_is_staked: true,
+            accessed_addresses: HashSet::from([sender, factory]),
+            associated_addresses: HashSet::from([sender]),
+            expected_storage,
+            storage_truncated: true,
+            requires_post_op: true,
+            paymaster_context: Bytes::from_static(&[1, 2, 3]),
+            entity_infos: EntityInfos {
+                factory: Some(EntityInfo::new(Entity::factory(factory), false)),
+                sender: EntityInfo::new(Entity::account(sender), true),
+                ..Default::default()
+            },
+            entity_deployment: HashMap::from([
+                (EntityType::Account, false),
+                (EntityType::Factory, true),
+            ]),
+            tracing_skipped: true,
+            prefund_overridden: true,
+            signature_bypassed: true,
+            allowlisted_violations: vec![
+                (
+                    Entity::account(sender),
+                    SimulationViolation::NonceTooHigh(U256::from(1), U256::from(2)),
+                ),
+                (
+                    Entity::factory(factory),
+                    SimulationViolation::InvalidStorageAccess(
+                        Entity::factory(factory),
+                        StorageSlot {
+                            address: sender,
+                            slot: U256::from(3),
+                        },
+                        StorageSlotLabel(Some("label".to_string())),
+                    ),
+                ),
+            ],
+            metadata: Some(Bytes::from_static(&[4, 5, 6])),
+            probed_undeployed_addresses: HashSet::from([factory]),
+        };
+
+        let json = serde_json::to_string(&result).unwrap();
+        let decoded: SimulationResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, result);
+
+        let encoded = bincode::serialize(&result).unwrap();
+        let decoded: SimulationResult = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded, result);
+
+        // an empty paymaster context is kept as empty bytes
+        let json = serde_json::to_value(SimulationResult::default()).unwrap();
+        assert_eq!(json["paymaster_context"], "0x");
+    }
+
+    #[test]
+    fn test_storage_slot_serde_round_trip() {
+        let slot = StorageSlot {
+            address: Address::random(),
+            slot: U256::from(42),
+        };
+        let json = serde_json::to_string(&slot).unwrap();
+        let decoded: StorageSlot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, slot);
+    }
+
+    #[test]
+    fn test_violation_priority() {
+        let storage = SimulationViolation::InvalidStorageAccess(
+            Entity::paymaster(Address::ZERO),
+            StorageSlot {
+                address: Address::ZERO,
+                slot: U256::ZERO,
+            },
+            StorageSlotLabel::default(),
+        );
+        let not_staked = SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+            needs_stake: Entity::paymaster(Address::ZERO),
+            accessing_entity: EntityType::Paymaster,
+            accessed_entity: None,
+            accessed_address: Address::ZERO,
+            slot: U256::ZERO,
+            min_stake: U256::ZERO,
+            min_unstake_delay: 0,
+        }));
+
+        let mut violations = vec![
+            not_staked.clone(),
+            storage.clone(),
+            SimulationViolation::InvalidSignature,
+        ];
+
+        violations.sort();
+        assert_eq!(
+            violations,
+            vec![
+                SimulationViolation::InvalidSignature,
+                storage.clone(),
+                not_staked.clone()
+            ]
+        );
+
+        let priority = ViolationPriority::new(vec!["NotStaked".to_string()]).unwrap();
+        violations.sort_by(|a, b| priority.compare(a, b));
+        assert_eq!(
+            violations,
+            vec![not_staked, SimulationViolation::InvalidSignature, storage]
+        );
+    }
+
+    #[test]
+    fn test_mempool_error_from_prioritized() {
+        let violations = vec![
+            SimulationViolation::InvalidSignature,
+            SimulationViolation::UnstakedAggregator,
+        ];
+        let priority = ViolationPriority::new(vec!["UnstakedAggregator".to_string()]).unwrap();
+
+        let mut error = SimulationError::from(ViolationError::Violations(violations.clone()));
+        error.prioritize(None);
+        assert!(matches!(
+            MempoolError::from(error),
+            MempoolError::SimulationViolation(SimulationViolation::InvalidSignature)
+        ));
+
+        let mut error = SimulationError::from(ViolationError::Violations(violations));
+        error.prioritize(Some(&priority));
+        assert!(matches!(
+            MempoolError::from(error),
+            MempoolError::SimulationViolation(SimulationViolation::UnstakedAggregator)
+        ));
+    }
+
+    #[test]
+    fn test_violation_priority_unknown() {
+        assert!(ViolationPriority::new(vec!["NotAViolation".to_string()]).is_err());
+    }
+}
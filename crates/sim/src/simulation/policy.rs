@@ -0,0 +1,31 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_types::pool::SimulationViolation;
+
+use crate::SimulationResult;
+
+/// Operator defined admission policy
+///
+/// Policies are evaluated by the simulator after an operation has passed all of the
+/// spec validation rules and its mempools have been matched. They allow operators to
+/// apply bespoke rules (blocklists, required paymasters, etc.) without modifying the
+/// core validation checks.
+pub trait AdmissionPolicy<UO>: Debug + Send + Sync {
+    /// Evaluate an operation and its successful simulation result.
+    ///
+    /// Returning a violation rejects the operation.
+    fn evaluate(&self, op: &UO, result: &SimulationResult) -> Result<(), SimulationViolation>;
+}
@@ -0,0 +1,162 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::B256;
+use futures_util::future;
+use rundler_types::{chain::ChainSpec, UserOperation};
+
+use super::{SimulationError, SimulationResult, Simulator};
+
+/// A successfully simulated operation and its expected profit to the bundler
+#[derive(Debug)]
+pub struct RankedOperation<UO> {
+    /// The operation
+    pub op: UO,
+    /// The result of simulating the operation
+    pub result: SimulationResult,
+    /// Expected profit, in wei, of bundling the operation: the fees it pays minus the
+    /// base fee cost of the bundle gas reserved for it. Negative if the operation's max
+    /// fee is below the base fee.
+    pub expected_profit: i128,
+}
+
+/// Operations ranked by expected profit
+#[derive(Debug)]
+pub struct RankedOperations<UO> {
+    /// Successfully simulated operations, most profitable first
+    pub ranked: Vec<RankedOperation<UO>>,
+    /// Operations that failed simulation and their errors, in input order
+    pub failed: Vec<(UO, SimulationError)>,
+}
+
+/// Simulate a batch of operations and rank the successful ones by expected bundler profit
+/// at `base_fee`.
+///
+/// The gas of an operation is the bundle gas reserved for it, see
+/// [`SimulationResult::bundle_gas`]. Operations with equal profit keep their input order.
+pub async fn simulate_and_rank<S: Simulator>(
+    simulator: &S,
+    chain_spec: &ChainSpec,
+    ops: Vec<S::UO>,
+    block_hash: B256,
+    base_fee: u128,
+) -> RankedOperations<S::UO> {
+    let results = future::join_all(
+        ops.iter()
+            .map(|op| simulator.simulate_validation(op.clone(), block_hash, None)),
+    )
+    .await;
+
+    let mut ranked = vec![];
+    let mut failed = vec![];
+    for (op, result) in ops.into_iter().zip(results) {
+        match result {
+            Ok(result) => {
+                let expected_profit = expected_profit(&op, &result, chain_spec, base_fee);
+                ranked.push(RankedOperation {
+                    op,
+                    result,
+                    expected_profit,
+                });
+            }
+            Err(error) => failed.push((op, error)),
+        }
+    }
+    ranked.sort_by(|a, b| b.expected_profit.cmp(&a.expected_profit));
+
+    RankedOperations { ranked, failed }
+}
+
+fn expected_profit<UO: UserOperation>(
+    op: &UO,
+    result: &SimulationResult,
+    chain_spec: &ChainSpec,
+    base_fee: u128,
+) -> i128 {
+    let gas = result.bundle_gas(op, chain_spec);
+    let fees = gas.saturating_mul(op.gas_price(base_fee));
+    let cost = gas.saturating_mul(base_fee);
+    if fees >= cost {
+        i128::try_from(fees - cost).unwrap_or(i128::MAX)
+    } else {
+        i128::try_from(cost - fees).map_or(i128::MIN, |loss| -loss)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::v0_6::UserOperation;
+
+    use super::*;
+    use crate::{simulation::MockSimulator, ViolationError};
+
+    fn op(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> UserOperation {
+        UserOperation {
+            call_gas_limit: 100_000,
+            verification_gas_limit: 100_000,
+            pre_verification_gas: 50_000,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_and_rank() {
+        let chain_spec = ChainSpec::default();
+        let base_fee = 100;
+
+        let mut simulator = MockSimulator::default();
+        simulator
+            .expect_simulate_validation()
+            .returning(|op, _, _| {
+                if op.max_fee_per_gas == 0 {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Other(anyhow::anyhow!("failed")),
+                        entity_infos: None,
+                    })
+                } else {
+                    Ok(SimulationResult::default())
+                }
+            });
+
+        let ops = vec![
+            // fee capped below base fee, a loss
+            op(50, 10),
+            // pays 5 wei per gas over the base fee
+            op(200, 5),
+            // fails simulation
+            op(0, 0),
+            // pays 20 wei per gas over the base fee
+            op(200, 20),
+        ];
+        let ranked =
+            simulate_and_rank(&simulator, &chain_spec, ops.clone(), B256::ZERO, base_fee).await;
+
+        let gas = SimulationResult::default().bundle_gas(&ops[0], &chain_spec) as i128;
+        assert_eq!(
+            ranked
+                .ranked
+                .iter()
+                .map(|r| (r.op.clone(), r.expected_profit))
+                .collect::<Vec<_>>(),
+            vec![
+                (ops[3].clone(), gas * 20),
+                (ops[1].clone(), gas * 5),
+                (ops[0].clone(), -gas * 50),
+            ]
+        );
+        assert_eq!(ranked.failed.len(), 1);
+        assert_eq!(ranked.failed[0].0, ops[2]);
+    }
+}
@@ -0,0 +1,61 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use alloy_primitives::{Address, B256};
+use async_trait::async_trait;
+use rundler_types::pool::SimulationViolation;
+
+use crate::ViolationError;
+
+/// A record of an operation rejected by simulation
+#[derive(Debug)]
+pub struct Rejection {
+    /// Entry point the operation was simulated against
+    pub entry_point: Address,
+    /// Hash of the rejected operation
+    pub op_hash: B256,
+    /// Sender of the rejected operation
+    pub sender: Address,
+    /// The violations, or other error, that caused the rejection
+    pub error: ViolationError<SimulationViolation>,
+}
+
+/// Sink for operations rejected by simulation
+///
+/// Called by the simulator for every rejected operation, e.g. to persist an audit
+/// log. Records are delivered from a spawned task and never block simulation.
+/// The default implementation discards all records.
+#[async_trait]
+pub trait RejectionSink: Debug + Send + Sync {
+    /// Record a rejected operation
+    async fn record(&self, _rejection: Rejection) {}
+}
+
+/// Sink that logs every rejected operation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingRejectionSink;
+
+#[async_trait]
+impl RejectionSink for TracingRejectionSink {
+    async fn record(&self, rejection: Rejection) {
+        tracing::info!(
+            "Simulation rejected op {:?} of sender {:?} on entry point {:?}: {:?}",
+            rejection.op_hash,
+            rejection.sender,
+            rejection.entry_point,
+            rejection.error
+        );
+    }
+}
@@ -12,46 +12,86 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     marker::PhantomData,
+    slice,
+    sync::Arc,
+    time::Instant,
 };
 
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{aliases::U192, keccak256, Address, Bytes, Selector, B256, U256};
+use alloy_sol_types::{Revert, SolError};
 use anyhow::Context;
 use async_trait::async_trait;
-use futures_util::TryFutureExt;
+use futures_util::{future, TryFutureExt};
+use metrics::{Counter, Histogram};
+use metrics_derive::Metrics;
 use rundler_provider::{
-    AggregatorOut, AggregatorSimOut, EntryPoint, EvmProvider, SignatureAggregator,
-    SimulationProvider,
+    AggregatorOut, AggregatorSimOut, BlockId, BundleHandler, DAGasProvider, EntryPoint,
+    EvmProvider, HandleOpsOut, ProviderError, SignatureAggregator, SimulationProvider,
+    StateOverride,
 };
 use rundler_types::{
     pool::{NeedsStakeInformation, SimulationViolation},
     v0_6::UserOperation as UserOperationV0_6,
     v0_7::UserOperation as UserOperationV0_7,
-    Entity, EntityInfo, EntityInfos, EntityType, Opcode, StorageSlot, UserOperation,
-    ValidTimeRange, ValidationOutput, ValidationReturnInfo, ViolationOpCode,
+    Entity, EntityInfo, EntityInfos, EntityType, GasEstimate, Opcode, StorageSlot,
+    StorageSlotLabel, UserOperation, UserOpsPerAggregator, ValidTimeRange, ValidationOutput,
+    ValidationReturnInfo, ViolationOpCode,
 };
+use rundler_utils::math;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
 use super::context::{
-    self, AccessInfo, AssociatedSlotsByAddress, ValidationContext, ValidationContextProvider,
+    self, AccessInfo, AssociatedSlotsByAddress, TracerOutput, ValidationContext,
+    ValidationContextProvider, BUDGET_EXCEEDED_ERROR,
 };
 use crate::{
+    estimation::{GetOpWithLimitArgs, CALL_GAS_BUFFER_VALUE, VERIFICATION_GAS_BUFFER_PERCENT},
+    gas,
     simulation::{
         mempool::{self, AllowEntity, AllowRule, MempoolConfig, MempoolMatchResult},
+        mempool_rejection, unsafe_sim,
         v0_6::ValidationContextProvider as ValidationContextProviderV0_6,
         v0_7::ValidationContextProvider as ValidationContextProviderV0_7,
-        Settings, Simulator,
+        AdmissionPolicy, BundleOpResult, BundleSimResult, Clock, GasEstimateOptions,
+        NoopStorageSlotLabeler, PaymasterTimeRangeDecoder, Rejection, RejectionSink,
+        SenderIsPaymasterPolicy, Settings, SimulationOverrides, Simulator, StorageSlotLabeler,
+        SystemClock, CANONICAL_MEMPOOL_ID,
     },
     types::ViolationError,
-    SimulationError, SimulationResult,
+    validate_static, CallGasEstimator, GasEstimationError, SimulationError, SimulationResult,
+    VerificationGasEstimator,
 };
 
+/// The call gas binary search stops once the range between the highest failing and lowest
+/// passing limit is at most this wide, so estimates exceed the true minimum by at most this
+/// much. The estimate itself is not rounded.
+const CALL_GAS_SEARCH_TOLERANCE: u128 = 4096;
+
 /// Create a new simulator for v0.6 entry point contracts
+///
+/// Validation traces are sent to `trace_provider` if set, so that they can be routed to a
+/// node with debug tracing support. Otherwise, and for all other calls, `provider` is used.
+///
+/// `admission_policies` are evaluated, in order, against every operation that passes validation.
+/// `rejection_sink`, if set, is sent a record of every rejected operation.
+/// `storage_slot_labeler` describes storage slots in storage access violations.
+/// `paymaster_time_range_decoders` reject operations sponsored by their paymaster whose
+/// sponsorship has expired, before tracing.
+#[allow(clippy::too_many_arguments)]
 pub fn new_v0_6_simulator<P, E>(
     provider: P,
+    trace_provider: Option<P>,
     entry_point: E,
+    chain_id: u64,
     sim_settings: Settings,
     mempool_configs: HashMap<B256, MempoolConfig>,
+    admission_policies: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_6>>>,
+    rejection_sink: Option<Arc<dyn RejectionSink>>,
+    storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    paymaster_time_range_decoders: HashMap<Address, Arc<dyn PaymasterTimeRangeDecoder>>,
 ) -> impl Simulator<UO = UserOperationV0_6>
 where
     P: EvmProvider + Clone,
@@ -60,21 +100,50 @@ where
         + SimulationProvider<UO = UserOperationV0_6>
         + Clone,
 {
-    SimulatorImpl::new(
+    let mut simulator = SimulatorImpl::new(
         provider.clone(),
         entry_point.clone(),
-        ValidationContextProviderV0_6::new(provider, entry_point, sim_settings.clone()),
+        ValidationContextProviderV0_6::new(
+            trace_provider.unwrap_or(provider),
+            entry_point,
+            chain_id,
+            sim_settings.clone(),
+        ),
         sim_settings,
         mempool_configs,
+        admission_policies,
+        chain_id,
+        rejection_sink,
     )
+    .with_storage_slot_labeler(storage_slot_labeler);
+    simulator
+        .paymaster_time_range_decoders
+        .extend(paymaster_time_range_decoders);
+    simulator
 }
 
-/// Create a new simulator for v0.6 entry point contracts
+/// Create a new simulator for v0.7 entry point contracts
+///
+/// Validation traces are sent to `trace_provider` if set, so that they can be routed to a
+/// node with debug tracing support. Otherwise, and for all other calls, `provider` is used.
+///
+/// `admission_policies` are evaluated, in order, against every operation that passes validation.
+/// `rejection_sink`, if set, is sent a record of every rejected operation.
+/// `storage_slot_labeler` describes storage slots in storage access violations.
+/// `paymaster_time_range_decoders` reject operations sponsored by their paymaster whose
+/// sponsorship has expired, before tracing.
+#[allow(clippy::too_many_arguments)]
 pub fn new_v0_7_simulator<P, E>(
     provider: P,
+    trace_provider: Option<P>,
     entry_point: E,
+    chain_id: u64,
     sim_settings: Settings,
     mempool_configs: HashMap<B256, MempoolConfig>,
+    admission_policies: Vec<Arc<dyn AdmissionPolicy<UserOperationV0_7>>>,
+    rejection_sink: Option<Arc<dyn RejectionSink>>,
+    storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    paymaster_time_range_decoders: HashMap<Address, Arc<dyn PaymasterTimeRangeDecoder>>,
 ) -> impl Simulator<UO = UserOperationV0_7>
 where
     P: EvmProvider + Clone,
@@ -83,13 +152,26 @@ where
         + SimulationProvider<UO = UserOperationV0_7>
         + Clone,
 {
-    SimulatorImpl::new(
+    let mut simulator = SimulatorImpl::new(
         provider.clone(),
         entry_point.clone(),
-        ValidationContextProviderV0_7::new(provider, entry_point, sim_settings.clone()),
+        ValidationContextProviderV0_7::new(
+            trace_provider.unwrap_or(provider),
+            entry_point,
+            chain_id,
+            sim_settings.clone(),
+        ),
         sim_settings,
         mempool_configs,
+        admission_policies,
+        chain_id,
+        rejection_sink,
     )
+    .with_storage_slot_labeler(storage_slot_labeler);
+    simulator
+        .paymaster_time_range_decoders
+        .extend(paymaster_time_range_decoders);
+    simulator
 }
 
 /// Simulator implementation.
@@ -111,6 +193,13 @@ pub struct SimulatorImpl<UO, P, E, V> {
     sim_settings: Settings,
     mempool_configs: HashMap<B256, MempoolConfig>,
     allow_unstaked_addresses: HashSet<Address>,
+    admission_policies: Vec<Arc<dyn AdmissionPolicy<UO>>>,
+    chain_id: u64,
+    rejection_sink: Option<Arc<dyn RejectionSink>>,
+    clock: Arc<dyn Clock>,
+    storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    paymaster_time_range_decoders: HashMap<Address, Arc<dyn PaymasterTimeRangeDecoder>>,
+    provider_call_limiter: Semaphore,
     _uo_type: PhantomData<UO>,
 }
 
@@ -125,14 +214,31 @@ where
     ///
     /// `mempool_configs` is a map of mempool IDs to mempool configurations.
     /// It is used during simulation to determine which mempools support
-    /// the violations found during simulation.
+    /// the violations found during simulation. If empty, a single canonical mempool
+    /// with ID `CANONICAL_MEMPOOL_ID` and no allowlist is used, so that operations
+    /// following the standard ERC-7562 rules are accepted.
+    ///
+    /// `admission_policies` are operator defined checks that are evaluated, in order,
+    /// against every operation that passes validation.
+    ///
+    /// `rejection_sink`, if set, is sent a record of every rejected operation.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         provider: P,
         entry_point: E,
         validation_context_provider: V,
         sim_settings: Settings,
         mempool_configs: HashMap<B256, MempoolConfig>,
+        admission_policies: Vec<Arc<dyn AdmissionPolicy<UO>>>,
+        chain_id: u64,
+        rejection_sink: Option<Arc<dyn RejectionSink>>,
     ) -> Self {
+        let mempool_configs = if mempool_configs.is_empty() {
+            HashMap::from([(CANONICAL_MEMPOOL_ID, MempoolConfig::default())])
+        } else {
+            mempool_configs
+        };
+
         // Get a list of entities that are allowed to act as staked entities despite being unstaked
         let mut allow_unstaked_addresses = HashSet::new();
         for config in mempool_configs.values() {
@@ -145,6 +251,13 @@ where
             }
         }
 
+        let provider_call_limiter = Semaphore::new(
+            sim_settings
+                .max_concurrent_provider_calls
+                .unwrap_or(Semaphore::MAX_PERMITS)
+                .clamp(1, Semaphore::MAX_PERMITS),
+        );
+
         Self {
             provider,
             entry_point,
@@ -152,24 +265,214 @@ where
             sim_settings,
             mempool_configs,
             allow_unstaked_addresses,
+            admission_policies,
+            chain_id,
+            rejection_sink,
+            clock: Arc::new(SystemClock),
+            storage_slot_labeler: Arc::new(NoopStorageSlotLabeler),
+            paymaster_time_range_decoders: HashMap::new(),
+            provider_call_limiter,
             _uo_type: PhantomData,
         }
     }
 
-    async fn validate_aggregator_signature(
+    /// Use the given clock, instead of the system clock, when checking time ranges
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Use the given labeler to describe storage slots in storage access violations
+    pub fn with_storage_slot_labeler(
+        self,
+        storage_slot_labeler: Arc<dyn StorageSlotLabeler>,
+    ) -> Self {
+        Self {
+            storage_slot_labeler,
+            ..self
+        }
+    }
+
+    /// Use the given decoder to reject operations sponsored by `paymaster` whose
+    /// sponsorship has expired, before tracing
+    pub fn with_paymaster_time_range_decoder(
+        mut self,
+        paymaster: Address,
+        decoder: Arc<dyn PaymasterTimeRangeDecoder>,
+    ) -> Self {
+        self.paymaster_time_range_decoders
+            .insert(paymaster, decoder);
+        self
+    }
+
+    /// The mempool configs this simulator matches operations against, keyed by mempool id
+    pub fn mempool_configs(&self) -> &HashMap<B256, MempoolConfig> {
+        &self.mempool_configs
+    }
+
+    /// The ids of the mempools this simulator matches operations against
+    pub fn mempool_ids(&self) -> impl Iterator<Item = &B256> {
+        self.mempool_configs.keys()
+    }
+
+    async fn validate_aggregator_signatures(
         &self,
         op: UO,
-        aggregator_address: Option<Address>,
-    ) -> Result<AggregatorOut, SimulationError> {
-        let Some(aggregator_address) = aggregator_address else {
-            return Ok(AggregatorOut::NotNeeded);
-        };
+        aggregator_addresses: Vec<Address>,
+        block_id: BlockId,
+    ) -> Result<Vec<AggregatorOut>, SimulationError> {
+        future::try_join_all(aggregator_addresses.into_iter().map(|aggregator_address| {
+            self.entry_point
+                .validate_user_op_signature(aggregator_address, op.clone(), Some(block_id))
+                .map_err(|e| {
+                    SimulationError::from(anyhow::anyhow!(
+                        "should call validate user op signature {e:?}"
+                    ))
+                })
+        }))
+        .await
+    }
 
-        Ok(self
+    // Compare the op nonce against the sender's next nonce from the entry point's nonce manager.
+    // Used nonces are always rejected, nonce gaps are rejected unless a mempool allows them.
+    // An allowed nonce gap is returned, to be matched against mempools with the other
+    // violations.
+    async fn check_nonce(
+        &self,
+        op: &UO,
+        block_id: BlockId,
+        mempool_ids: Option<&[B256]>,
+    ) -> Result<Option<SimulationViolation>, SimulationError> {
+        let nonce = op.nonce();
+        let expected = self
             .entry_point
-            .validate_user_op_signature(aggregator_address, op)
+            .get_nonce(op.sender(), U192::from(nonce >> 64), Some(block_id))
             .await
-            .context("should call validate user op signature")?)
+            .context("should call get_nonce")?;
+
+        let violation = match nonce.cmp(&expected) {
+            Ordering::Equal => return Ok(None),
+            Ordering::Less => SimulationViolation::NonceAlreadyUsed(expected, nonce),
+            Ordering::Greater => {
+                let violation = SimulationViolation::NonceTooHigh(expected, nonce);
+                if !matches!(
+                    mempool::match_mempools(
+                        &self.mempool_configs,
+                        mempool_ids,
+                        slice::from_ref(&violation),
+                        op.max_priority_fee_per_gas(),
+                    ),
+                    MempoolMatchResult::NoMatch(_)
+                ) {
+                    return Ok(Some(violation));
+                }
+                violation
+            }
+        };
+
+        Err(SimulationError {
+            violation_error: ViolationError::Violations(vec![violation]),
+            entity_infos: None,
+        })
+    }
+
+    // Check that the sender holds and has approved enough of a token paymaster's token to
+    // cover the operation's maximum gas cost.
+    async fn check_token_paymaster(
+        &self,
+        op: &UO,
+        block_id: BlockId,
+    ) -> Result<(), SimulationError> {
+        let Some(paymaster) = op.paymaster() else {
+            return Ok(());
+        };
+        let Some(config) = self.sim_settings.token_paymasters.get(&paymaster) else {
+            return Ok(());
+        };
+
+        let required = config.required_tokens(op.max_gas_cost());
+        let (balance, allowance) = future::try_join(
+            self.provider
+                .get_token_balance(config.token, op.sender(), Some(block_id)),
+            self.provider
+                .get_token_allowance(config.token, op.sender(), paymaster, Some(block_id)),
+        )
+        .await
+        .context("should get sender token balance and allowance")?;
+
+        let mut violations = vec![];
+        if balance < required {
+            violations.push(SimulationViolation::InsufficientTokenBalance(
+                config.token,
+                required,
+                required - balance,
+            ));
+        }
+        if allowance < required {
+            violations.push(SimulationViolation::InsufficientTokenAllowance(
+                config.token,
+                required,
+                required - allowance,
+            ));
+        }
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        Err(SimulationError {
+            violation_error: ViolationError::Violations(violations),
+            entity_infos: None,
+        })
+    }
+
+    // Check which of the factory, sender and paymaster currently have code deployed
+    async fn get_entity_deployment(
+        &self,
+        entity_infos: &EntityInfos,
+        block_id: BlockId,
+    ) -> Result<HashMap<EntityType, bool>, SimulationError> {
+        let entities = entity_infos
+            .entities()
+            .filter(|(kind, _)| *kind != EntityType::Aggregator)
+            .collect::<Vec<_>>();
+
+        let codes = future::try_join_all(
+            entities
+                .iter()
+                .map(|(_, info)| self.provider.get_code(info.address(), Some(block_id))),
+        )
+        .await
+        .context("should call get_code for entities")?;
+
+        Ok(entities
+            .into_iter()
+            .zip(codes)
+            .map(|((kind, _), code)| (kind, !code.is_empty()))
+            .collect())
+    }
+
+    // Whether any of the contracts has code trusted by the operator, comparing each
+    // contract's code hash (as returned by EXTCODEHASH) against `trusted_code_hashes`
+    async fn has_trusted_code(
+        &self,
+        addresses: impl IntoIterator<Item = Address>,
+        block_id: BlockId,
+    ) -> Result<bool, SimulationError> {
+        if self.sim_settings.trusted_code_hashes.is_empty() {
+            return Ok(false);
+        }
+        let codes = future::try_join_all(
+            addresses
+                .into_iter()
+                .map(|address| self.provider.get_code(address, Some(block_id))),
+        )
+        .await
+        .context("should call get_code for accessed contracts")?;
+
+        Ok(codes.iter().filter(|code| !code.is_empty()).any(|code| {
+            self.sim_settings
+                .trusted_code_hashes
+                .contains(&keccak256(code))
+        }))
     }
 
     // Parse the output from tracing and return a list of violations.
@@ -190,20 +493,65 @@ where
 
         let mut violations = vec![];
 
+        // Without a factory the sender must already be deployed (the entry point reverts
+        // otherwise), so there is no deployment to validate. Skip the factory phase and
+        // the factory CREATE2 checks entirely.
+        let skip_factory_checks = !has_factory;
+
         let sender_address = entity_infos.sender_address();
         for (index, phase) in tracer_out.phases.iter().enumerate().take(3) {
             let kind = context::entity_type_from_simulation_phase(index).unwrap();
+            if kind == EntityType::Factory && skip_factory_checks {
+                continue;
+            }
             let Some(ei) = entity_infos.get(kind) else {
                 continue;
             };
             for opcode in &phase.forbidden_opcodes_used {
                 let (contract, opcode) = context::parse_combined_context_str(opcode)?;
 
+                // [OP-012] - tracers only report GAS when it isn't followed by a call
+                if opcode == Opcode::GAS {
+                    let violation = SimulationViolation::GasOpcodeMisuse(ei.entity);
+                    if !violations.contains(&violation) {
+                        violations.push(violation);
+                    }
+                    continue;
+                }
+
+                // Factories may deploy allowlisted helper contracts with CREATE, the deployed
+                // code is checked below
+                if opcode == Opcode::CREATE
+                    && ei.entity.kind == EntityType::Factory
+                    && !self.sim_settings.factory_create_code_hashes.is_empty()
+                {
+                    continue;
+                }
+
                 // [OP-080] - staked entities are allowed to use BALANCE and SELFBALANCE
                 if ei.is_staked && (opcode == Opcode::BALANCE || opcode == Opcode::SELFBALANCE) {
                     continue;
                 }
 
+                // staked entities may be allowed to read the block environment
+                if self.sim_settings.staked_environment_opcodes_allowed
+                    && matches!(
+                        opcode,
+                        Opcode::TIMESTAMP | Opcode::NUMBER | Opcode::COINBASE
+                    )
+                {
+                    if !ei.is_staked {
+                        let violation = SimulationViolation::UsedBannedEnvironmentOpcode(
+                            ei.entity,
+                            ViolationOpCode(opcode),
+                        );
+                        if !violations.contains(&violation) {
+                            violations.push(violation);
+                        }
+                    }
+                    continue;
+                }
+
                 // [OP-011]
                 violations.push(SimulationViolation::UsedForbiddenOpcode(
                     ei.entity,
@@ -226,6 +574,13 @@ where
 
             for precompile in &phase.forbidden_precompiles_used {
                 let (contract, precompile) = context::parse_combined_context_str(precompile)?;
+                if self
+                    .sim_settings
+                    .extra_allowed_precompiles
+                    .contains(&precompile)
+                {
+                    continue;
+                }
                 // [OP-062]
                 violations.push(SimulationViolation::UsedForbiddenPrecompile(
                     ei.entity, contract, precompile,
@@ -244,6 +599,7 @@ where
                     entrypoint: *self.entry_point.address(),
                     has_factory,
                     entity: &ei.entity,
+                    validator_modules: &self.sim_settings.validator_modules,
                 });
 
                 for restriction in restrictions {
@@ -304,11 +660,27 @@ where
                             ))
                         }
                         StorageRestriction::Banned(slot) => {
+                            let slot = StorageSlot { address, slot };
+                            let owner = entity_infos
+                                .type_from_address(address)
+                                .and_then(|t| entity_infos.get(t));
                             // [STO-*]
-                            violations.push(SimulationViolation::InvalidStorageAccess(
-                                ei.entity,
-                                StorageSlot { address, slot },
-                            ));
+                            match owner {
+                                Some(owner) => violations.push(
+                                    SimulationViolation::InvalidEntityStorageAccess(
+                                        ei.entity,
+                                        owner.entity,
+                                        slot,
+                                    ),
+                                ),
+                                None => violations.push(SimulationViolation::InvalidStorageAccess(
+                                    ei.entity,
+                                    slot,
+                                    StorageSlotLabel(
+                                        self.storage_slot_labeler.label(slot.address, slot.slot),
+                                    ),
+                                )),
+                            }
                         }
                     }
                 }
@@ -316,22 +688,64 @@ where
 
             if phase.called_non_entry_point_with_value {
                 // [OP-061]
-                violations.push(SimulationViolation::CallHadValue(ei.entity));
+                if phase.value_call_targets.is_empty() {
+                    violations.push(SimulationViolation::CallHadValue(ei.entity, None));
+                } else {
+                    for target in &phase.value_call_targets {
+                        violations
+                            .push(SimulationViolation::CallHadValue(ei.entity, Some(*target)));
+                    }
+                }
             }
             if phase.called_banned_entry_point_method {
                 // [OP-054]
-                violations.push(SimulationViolation::CalledBannedEntryPointMethod(ei.entity));
+                let methods = phase
+                    .banned_entry_point_methods
+                    .iter()
+                    .filter_map(|method| method.parse::<Selector>().ok())
+                    .collect::<Vec<_>>();
+                if methods.is_empty() {
+                    violations.push(SimulationViolation::CalledBannedEntryPointMethod(
+                        ei.entity, None,
+                    ));
+                } else {
+                    for method in methods {
+                        violations.push(SimulationViolation::CalledBannedEntryPointMethod(
+                            ei.entity,
+                            Some(method),
+                        ));
+                    }
+                }
             }
 
             if phase.ran_out_of_gas {
                 // [OP-020]
-                violations.push(SimulationViolation::OutOfGas(ei.entity));
+                violations.push(SimulationViolation::OutOfGas(
+                    ei.entity,
+                    phase.gas_used as u128,
+                ));
+            }
+            if let Some(max_factory_gas) = self.sim_settings.max_factory_gas {
+                let gas_used = phase.gas_used as u128;
+                if ei.entity.kind == EntityType::Factory && gas_used > max_factory_gas {
+                    violations.push(SimulationViolation::FactoryGasLimitExceeded(
+                        ei.entity,
+                        gas_used,
+                        max_factory_gas,
+                    ));
+                }
             }
             for &address in &phase.undeployed_contract_accesses {
                 // OP-042 - Factory can access undeployed sender
                 if ei.entity.kind == EntityType::Factory && address == sender_address {
                     continue;
                 }
+                // Probing an undeployed address, without calling it, may be allowed
+                if self.sim_settings.allow_undeployed_contract_probes
+                    && !phase.undeployed_contract_calls.contains(&address)
+                {
+                    continue;
+                }
                 // OP-041 - Access to an address without deployed code is forbidden
                 violations.push(SimulationViolation::AccessedUndeployedContract(
                     ei.entity, address,
@@ -339,13 +753,53 @@ where
             }
         }
 
-        if !entry_point_out.return_info.is_valid_time_range() {
+        if !entry_point_out
+            .return_info
+            .is_valid_time_range_at(self.clock.now())
+        {
             violations.push(SimulationViolation::InvalidTimeRange(
                 entry_point_out.return_info.valid_until,
                 entry_point_out.return_info.valid_after,
             ));
         }
 
+        // A paymaster returning a context is called again in postOp, which requires
+        // stake regardless of its storage accesses
+        if !entry_point_out.return_info.paymaster_context.is_empty() {
+            if let Some(paymaster) = entity_infos.get(EntityType::Paymaster) {
+                if !paymaster.is_staked {
+                    // [EREP-050]
+                    violations.push(SimulationViolation::UnstakedPaymasterContext);
+                }
+            }
+        }
+
+        // A sender acting as its own paymaster takes on the paymaster's stake and storage
+        // attribution, so it is either rejected or must be staked
+        if let Some(paymaster) = entity_infos.get(EntityType::Paymaster) {
+            if paymaster.entity.address == sender_address {
+                match self.sim_settings.sender_is_paymaster_policy {
+                    SenderIsPaymasterPolicy::Reject => {
+                        violations.push(SimulationViolation::SenderIsPaymaster(sender_address));
+                    }
+                    SenderIsPaymasterPolicy::RequireStake if !paymaster.is_staked => {
+                        violations.push(SimulationViolation::NotStaked(Box::new(
+                            NeedsStakeInformation {
+                                needs_stake: paymaster.entity,
+                                accessing_entity: EntityType::Paymaster,
+                                accessed_entity: Some(EntityType::Account),
+                                accessed_address: sender_address,
+                                slot: U256::ZERO,
+                                min_stake: self.sim_settings.min_stake_value,
+                                min_unstake_delay: self.sim_settings.min_unstake_delay,
+                            },
+                        )));
+                    }
+                    SenderIsPaymasterPolicy::RequireStake => {}
+                }
+            }
+        }
+
         if let Some(aggregator_info) = entry_point_out.aggregator_info {
             if !context::is_staked(aggregator_info.stake_info, &self.sim_settings) {
                 // [EREP-040]
@@ -363,7 +817,7 @@ where
             }
         }
 
-        if tracer_out.factory_called_create2_twice {
+        if tracer_out.factory_called_create2_twice && !skip_factory_checks {
             let factory = entity_infos.get(EntityType::Factory);
             match factory {
                 Some(factory) => {
@@ -383,6 +837,34 @@ where
             }
         }
 
+        if !self.sim_settings.factory_create_code_hashes.is_empty() && !skip_factory_checks {
+            if let Some(factory) = entity_infos.get(EntityType::Factory) {
+                for (address, code) in &tracer_out.factory_created_contracts {
+                    let code_hash = keccak256(code);
+                    if !self
+                        .sim_settings
+                        .factory_create_code_hashes
+                        .contains(&code_hash)
+                    {
+                        violations.push(SimulationViolation::FactoryDeployedUnapprovedContract(
+                            factory.entity,
+                            *address,
+                            code_hash,
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(deployed) = tracer_out.factory_deployed_address {
+            if deployed != sender_address && !skip_factory_checks {
+                violations.push(SimulationViolation::SenderAddressMismatch(
+                    sender_address,
+                    deployed,
+                ));
+            }
+        }
+
         // Get violations specific to the implemented entry point from the context provider
         violations.extend(
             self.validation_context_provider
@@ -392,17 +874,57 @@ where
         Ok(violations)
     }
 
+    // Get the paymaster's deposit at the entry point, if the deposit check is enabled
+    async fn get_paymaster_deposit(
+        &self,
+        paymaster: Option<Address>,
+        block_id: BlockId,
+    ) -> Result<Option<U256>, SimulationError> {
+        match paymaster {
+            Some(paymaster) if self.sim_settings.paymaster_deposit_check_enabled => Ok(Some(
+                self.entry_point
+                    .balance_of(paymaster, Some(block_id))
+                    .await
+                    .context("should call balance_of")?,
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    async fn get_block_number(&self, block_id: BlockId) -> Result<u64, SimulationError> {
+        let block = self
+            .provider
+            .get_block(block_id)
+            .await
+            .context("should call get_block")?
+            .context("simulated block should exist")?;
+        Ok(block.header.number)
+    }
+
+    // Wait for a slot to make provider calls, bounding the requests in flight across
+    // concurrent simulations. The slot is released when the permit is dropped.
+    async fn acquire_provider_calls(&self) -> SemaphorePermit<'_> {
+        self.provider_call_limiter
+            .acquire()
+            .await
+            .expect("semaphore should not be closed")
+    }
+
     // Check the code hash of the entities associated with the user operation
     // if needed, validate that the signature is valid for the aggregator.
-    // Violations during this stage are always errors.
+    // Violations during this stage are errors, except for a low paymaster deposit
+    // which narrows `mempools` to those that allowlist it.
     async fn check_contracts(
         &self,
         op: UO,
         context: &mut ValidationContext<UO>,
         expected_code_hash: Option<B256>,
-    ) -> Result<(B256, Option<AggregatorSimOut>), SimulationError> {
+        mempools: &mut Vec<B256>,
+    ) -> Result<(B256, Vec<AggregatorSimOut>, HashMap<EntityType, bool>, u64), SimulationError>
+    {
         let &mut ValidationContext {
             block_id,
+            ref entity_infos,
             ref mut tracer_out,
             ref entry_point_out,
             ..
@@ -411,7 +933,32 @@ where
         // collect a vector of violations to ensure a deterministic error message
         let mut violations = vec![];
 
-        let aggregator_address = entry_point_out.aggregator_info.map(|info| info.address);
+        // The entry point reports at most one aggregator today
+        let aggregator_addresses = entry_point_out
+            .aggregator_info
+            .iter()
+            .map(|info| info.address)
+            .collect();
+        // Bound the cost of the code hash computation below
+        let num_contracts = tracer_out.accessed_contracts.len();
+        SimulationMetrics::default()
+            .accessed_contracts
+            .record(num_contracts as f64);
+        if self
+            .sim_settings
+            .max_accessed_contracts
+            .is_some_and(|max| num_contracts > max)
+        {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![
+                    SimulationViolation::TooManyContractsAccessed(num_contracts),
+                ]),
+                entity_infos: Some(*entity_infos),
+            });
+        }
+
+        let paymaster = op.paymaster();
+        let max_priority_fee_per_gas = op.max_priority_fee_per_gas();
         let code_hash_future = self
             .provider
             .get_code_hash(
@@ -421,25 +968,81 @@ where
             .map_err(|e| SimulationError::from(anyhow::anyhow!("should call get_code_hash {e:?}")));
 
         let aggregator_signature_future =
-            self.validate_aggregator_signature(op, aggregator_address);
+            self.validate_aggregator_signatures(op, aggregator_addresses, block_id);
+
+        let entity_deployment_future = self.get_entity_deployment(entity_infos, block_id);
+
+        let paymaster_deposit_future = self.get_paymaster_deposit(paymaster, block_id);
+
+        let block_number_future = self.get_block_number(block_id);
+
+        let _permit = self.acquire_provider_calls().await;
+        let (code_hash, aggregator_out, entity_deployment, paymaster_deposit, block_number) =
+            tokio::time::timeout(self.sim_settings.simulation_timeout, async {
+                tokio::try_join!(
+                    code_hash_future,
+                    aggregator_signature_future,
+                    entity_deployment_future,
+                    paymaster_deposit_future,
+                    block_number_future
+                )
+            })
+            .await
+            .map_err(|_| timed_out_error(Some(*entity_infos)))??;
 
-        let (code_hash, aggregator_out) =
-            tokio::try_join!(code_hash_future, aggregator_signature_future)?;
+        if let (Some(paymaster), Some(available)) = (paymaster, paymaster_deposit) {
+            let required = entry_point_out.return_info.prefund;
+            if available < required {
+                let violation = SimulationViolation::PaymasterDepositTooLow(
+                    Entity::paymaster(paymaster),
+                    required,
+                    available,
+                );
+                match mempool::match_mempools(
+                    &self.mempool_configs,
+                    Some(mempools.as_slice()),
+                    slice::from_ref(&violation),
+                    max_priority_fee_per_gas,
+                ) {
+                    MempoolMatchResult::Matches(pools) => *mempools = pools,
+                    _ => violations.push(violation),
+                }
+            }
+        }
 
         if let Some(expected_code_hash) = expected_code_hash {
             // [COD-010]
             if expected_code_hash != code_hash {
-                violations.push(SimulationViolation::CodeHashChanged)
+                // This simulation already ran against the new code, so a change to operator
+                // trusted code (e.g. a known account upgrade) is accepted.
+                let trusted = tokio::time::timeout(
+                    self.sim_settings.simulation_timeout,
+                    self.has_trusted_code(tracer_out.accessed_contracts.keys().copied(), block_id),
+                )
+                .await
+                .map_err(|_| timed_out_error(Some(*entity_infos)))??;
+                if trusted {
+                    tracing::info!("code hash changed from {expected_code_hash} to {code_hash}, accessed contracts include trusted code");
+                } else {
+                    violations.push(SimulationViolation::CodeHashChanged(
+                        expected_code_hash,
+                        code_hash,
+                    ))
+                }
             }
         }
-        let aggregator = match aggregator_out {
-            AggregatorOut::NotNeeded => None,
-            AggregatorOut::SuccessWithInfo(info) => Some(info),
-            AggregatorOut::ValidationReverted => {
-                violations.push(SimulationViolation::AggregatorValidationFailed);
-                None
+        let mut aggregators = vec![];
+        for out in aggregator_out {
+            match out {
+                AggregatorOut::NotNeeded => {}
+                AggregatorOut::SuccessWithInfo(info) => aggregators.push(info),
+                AggregatorOut::ValidationReverted(revert_data) => {
+                    violations.push(SimulationViolation::AggregatorValidationFailed(
+                        aggregator_revert_reason(&revert_data),
+                    ));
+                }
             }
-        };
+        }
 
         if !violations.is_empty() {
             return Err(SimulationError {
@@ -448,461 +1051,3817 @@ where
             });
         }
 
-        Ok((code_hash, aggregator))
+        Ok((code_hash, aggregators, entity_deployment, block_number))
     }
-}
-
-#[async_trait]
-impl<UO, P, E, V> Simulator for SimulatorImpl<UO, P, E, V>
-where
-    UO: UserOperation,
-    P: EvmProvider,
-    E: EntryPoint + SignatureAggregator<UO = UO>,
-    V: ValidationContextProvider<UO = UO>,
-{
-    type UO = UO;
 
-    async fn simulate_validation(
+    // Runs the checks that don't need a trace on the result of a simulation that fell back
+    // to a plain `simulateValidation` call: mempool matching of `violations` found before
+    // simulation, the code hash and paymaster deposit checks. Without a trace the accessed
+    // contracts are unknown, so the code hash only covers the operation's entities.
+    async fn check_untraced_result(
         &self,
-        op: UO,
+        op: &UO,
+        result: &mut SimulationResult,
         block_hash: B256,
         expected_code_hash: Option<B256>,
-    ) -> Result<SimulationResult, SimulationError> {
-        let block_id = block_hash.into();
-        let mut context = match self
-            .validation_context_provider
-            .get_context(op.clone(), block_id)
-            .await
-        {
-            Ok(context) => context,
-            error @ Err(_) => error?,
-        };
-
-        // Gather all violations from the tracer
-        let mut overridable_violations = self.gather_context_violations(&mut context)?;
-        // Sort violations so that the final error message is deterministic
-        overridable_violations.sort();
-        // Check violations against mempool rules, find supporting mempools, error if none found
-        let mempools = match mempool::match_mempools(&self.mempool_configs, &overridable_violations)
-        {
+        mempool_ids: Option<&[B256]>,
+        violations: &[SimulationViolation],
+    ) -> Result<(), SimulationError> {
+        let max_priority_fee_per_gas = op.max_priority_fee_per_gas();
+        let mut mempools = match mempool::match_mempools(
+            &self.mempool_configs,
+            mempool_ids,
+            violations,
+            max_priority_fee_per_gas,
+        ) {
             MempoolMatchResult::Matches(pools) => pools,
             MempoolMatchResult::NoMatch(i) => {
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![violations[i].clone()]),
+                    entity_infos: Some(result.entity_infos),
+                })
+            }
+            MempoolMatchResult::PriorityFeeTooLow(min_priority_fee) => {
                 return Err(SimulationError {
                     violation_error: ViolationError::Violations(vec![
-                        overridable_violations[i].clone()
+                        SimulationViolation::MempoolPriorityFeeTooLow(
+                            max_priority_fee_per_gas,
+                            min_priority_fee,
+                        ),
                     ]),
-                    entity_infos: Some(context.entity_infos),
+                    entity_infos: Some(result.entity_infos),
                 })
             }
         };
 
-        // Check code hash and aggregator signature, these can't fail
-        let (code_hash, aggregator) = self
-            .check_contracts(op, &mut context, expected_code_hash)
-            .await?;
+        let block_id = block_hash.into();
+        let paymaster = op.paymaster();
+        let code_hash_future = self
+            .provider
+            .get_code_hash(
+                result
+                    .entity_infos
+                    .entities()
+                    .map(|(_, info)| info.address())
+                    .collect(),
+                Some(block_id),
+            )
+            .map_err(|e| SimulationError::from(anyhow::anyhow!("should call get_code_hash {e:?}")));
+        let entity_deployment_future = self.get_entity_deployment(&result.entity_infos, block_id);
+        let paymaster_deposit_future = self.get_paymaster_deposit(paymaster, block_id);
+        let block_number_future = self.get_block_number(block_id);
 
-        // Transform outputs into success struct
-        let ValidationContext {
-            tracer_out,
-            entry_point_out,
-            accessed_addresses,
-            associated_addresses,
-            ..
-        } = context;
-        let ValidationOutput {
-            return_info,
-            sender_info,
-            ..
-        } = entry_point_out;
-        let account_is_staked = context::is_staked(sender_info, &self.sim_settings);
-        let ValidationReturnInfo {
-            pre_op_gas,
-            valid_after,
-            valid_until,
-            paymaster_context,
-            ..
-        } = return_info;
+        let _permit = self.acquire_provider_calls().await;
+        let (code_hash, entity_deployment, paymaster_deposit, block_number) =
+            tokio::time::timeout(self.sim_settings.simulation_timeout, async {
+                tokio::try_join!(
+                    code_hash_future,
+                    entity_deployment_future,
+                    paymaster_deposit_future,
+                    block_number_future
+                )
+            })
+            .await
+            .map_err(|_| timed_out_error(Some(result.entity_infos)))??;
 
-        // Conduct any stake overrides before assigning entity_infos
-        override_infos_staked(&mut context.entity_infos, &self.allow_unstaked_addresses);
+        let mut violations = vec![];
+        if let (Some(paymaster), Some(available)) = (paymaster, paymaster_deposit) {
+            // the entry point's prefund isn't known without a trace, it is the op's max cost
+            let required = op.max_gas_cost();
+            if available < required {
+                let violation = SimulationViolation::PaymasterDepositTooLow(
+                    Entity::paymaster(paymaster),
+                    required,
+                    available,
+                );
+                match mempool::match_mempools(
+                    &self.mempool_configs,
+                    Some(mempools.as_slice()),
+                    slice::from_ref(&violation),
+                    max_priority_fee_per_gas,
+                ) {
+                    MempoolMatchResult::Matches(pools) => mempools = pools,
+                    _ => violations.push(violation),
+                }
+            }
+        }
 
-        Ok(SimulationResult {
-            mempools,
-            pre_op_gas,
-            valid_time_range: ValidTimeRange::new(valid_after, valid_until),
-            aggregator,
-            code_hash,
-            account_is_staked,
-            accessed_addresses,
-            associated_addresses,
-            expected_storage: tracer_out.expected_storage,
-            requires_post_op: !paymaster_context.is_empty(),
-            entity_infos: context.entity_infos,
-        })
+        if let Some(expected_code_hash) = expected_code_hash {
+            // [COD-010]
+            if expected_code_hash != code_hash
+                && !tokio::time::timeout(
+                    self.sim_settings.simulation_timeout,
+                    self.has_trusted_code(
+                        result
+                            .entity_infos
+                            .entities()
+                            .map(|(_, info)| info.address()),
+                        block_id,
+                    ),
+                )
+                .await
+                .map_err(|_| timed_out_error(Some(result.entity_infos)))??
+            {
+                violations.push(SimulationViolation::CodeHashChanged(
+                    expected_code_hash,
+                    code_hash,
+                ))
+            }
+        }
+
+        if !violations.is_empty() {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(violations),
+                entity_infos: Some(result.entity_infos),
+            });
+        }
+
+        result.mempools = mempools;
+        result.code_hash = code_hash;
+        result.entity_deployment = entity_deployment;
+        result.block_number = block_number;
+        Ok(())
+    }
+
+    // Apply operator admission policies last, separate from the spec checks
+    fn check_admission_policies(
+        &self,
+        op: &UO,
+        result: &SimulationResult,
+    ) -> Result<(), SimulationError> {
+        for policy in &self.admission_policies {
+            if let Err(violation) = policy.evaluate(op, result) {
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![violation]),
+                    entity_infos: Some(result.entity_infos),
+                });
+            }
+        }
+        Ok(())
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-enum StorageRestriction {
-    /// (Entity needing stake, accessing entity type, accessed entity type, accessed address, accessed slot)
-    NeedsStake(EntityType, EntityType, Option<EntityType>, Address, U256),
-    AssociatedStorageDuringDeploy(Option<EntityType>, Address, U256),
-    Banned(U256),
-}
+impl<UO, P, E, V> SimulatorImpl<UO, P, E, V>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SignatureAggregator<UO = UO> + SimulationProvider<UO = UO>,
+    V: ValidationContextProvider<UO = UO>,
+{
+    /// Get the prefund required by the entry point for an operation.
+    ///
+    /// This is the maximum amount the operation can be charged, i.e. what the sender (or
+    /// paymaster) must have on deposit. The entry point's `simulateValidation` result is
+    /// used when it succeeds. Otherwise, e.g. when the payer is not yet funded, the value
+    /// is computed from the operation's gas limits and fees using the same formula.
+    pub async fn required_prefund(
+        &self,
+        op: UO,
+        block_hash: B256,
+    ) -> Result<U256, SimulationError> {
+        let required_prefund = op.max_gas_cost();
+        let output = self
+            .entry_point
+            .simulate_validation(op, Some(block_hash.into()))
+            .await
+            .context("should call simulate validation")?;
 
-#[derive(Clone, Debug)]
-struct ParseStorageAccess<'a> {
-    access_info: &'a AccessInfo,
-    slots_by_address: &'a AssociatedSlotsByAddress,
-    address: Address,
-    sender: Address,
-    entrypoint: Address,
-    has_factory: bool,
-    entity: &'a Entity,
-}
+        match output {
+            Ok(output) => {
+                let prefund = output.return_info.prefund;
+                if prefund != required_prefund {
+                    tracing::warn!("entry point required prefund {prefund} differs from computed prefund {required_prefund}");
+                }
+                Ok(prefund)
+            }
+            Err(revert) => {
+                tracing::debug!("simulate validation reverted while getting required prefund, using computed prefund: {revert:?}");
+                Ok(required_prefund)
+            }
+        }
+    }
 
-fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestriction> {
-    let ParseStorageAccess {
-        access_info,
-        address,
-        sender,
-        entrypoint,
-        entity,
-        slots_by_address,
-        has_factory,
-        ..
-    } = args;
+    /// Simulate a batch of user operations concurrently.
+    ///
+    /// Results are keyed by user operation hash so that callers can correlate them
+    /// independently of input order. Duplicate operations are simulated once and
+    /// collapse to a single entry.
+    pub async fn simulate_validation_batch(
+        &self,
+        ops: Vec<UO>,
+        block_hash: B256,
+    ) -> HashMap<B256, Result<SimulationResult, SimulationError>> {
+        let ops = ops
+            .into_iter()
+            .map(|op| (self.user_op_hash(&op), op))
+            .collect::<HashMap<_, _>>();
 
-    let mut restrictions = vec![];
+        future::join_all(ops.into_iter().map(|(hash, op)| async move {
+            (hash, self.simulate_validation(op, block_hash, None).await)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
 
-    // [STO-010] - always allowed to access storage on the account
-    // [OP-054] - block access to the entrypoint, except for depositTo and fallback
-    //   - this is handled at another level, so we don't need to check for it here
-    //   - at this level we can allow any entry point access through
-    if address.eq(&sender) || address.eq(&entrypoint) {
-        return restrictions;
+    /// Validate the aggregated signature of a set of operations that share an aggregator.
+    ///
+    /// Each operation's signature may pass `validateUserOpSignature` individually while
+    /// the combined signature fails, so bundles of aggregated operations must also be
+    /// checked as a whole using the aggregator's `validateSignatures`, at the same block the
+    /// operations were simulated at.
+    pub async fn validate_aggregated_signatures(
+        &self,
+        aggregator: Address,
+        ops: Vec<UO>,
+        block_hash: B256,
+    ) -> Result<(), SimulationError> {
+        let signature = self
+            .entry_point
+            .aggregate_signatures(aggregator, ops.clone())
+            .await
+            .context("should call aggregate signatures")?;
+        let valid = match signature {
+            Some(signature) => self
+                .entry_point
+                .validate_signatures(aggregator, ops, signature, Some(block_hash.into()))
+                .await
+                .context("should call validate signatures")?,
+            None => false,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![
+                    SimulationViolation::AggregatorValidationFailed(None),
+                ]),
+                entity_infos: None,
+            })
+        }
     }
 
-    let slots: Vec<&U256> = access_info
-        .reads
-        .keys()
-        .chain(access_info.writes.keys())
-        .collect();
+    /// Estimate the call gas limit of an operation.
+    ///
+    /// Binary searches for the smallest call gas limit with which the operation's execution
+    /// succeeds, simulating each candidate via `call_gas_estimator`. The operation's other
+    /// gas limits are used as is. Returns the minimal passing call gas limit plus a buffer.
+    pub async fn estimate_call_gas<C>(
+        &self,
+        call_gas_estimator: &C,
+        op: UO,
+        block_hash: B256,
+    ) -> Result<u128, GasEstimationError>
+    where
+        C: CallGasEstimator<UO = UO>,
+    {
+        let simulate = |call_gas_limit| {
+            let mut op = op.clone();
+            op.set_call_gas_limit(call_gas_limit);
+            call_gas_estimator.simulate_handle_op_with_result(
+                op,
+                block_hash,
+                StateOverride::default(),
+            )
+        };
 
-    for slot in slots {
-        let is_sender_associated = slots_by_address.is_associated_slot(sender, *slot);
-        // [STO-032]
-        let is_entity_associated = slots_by_address.is_associated_slot(entity.address, *slot);
-        // [STO-031]
-        let is_same_address = address.eq(&entity.address);
-        // [STO-033]
-        let is_read_permission = !access_info.writes.contains_key(slot);
+        let mut high = (self.sim_settings.max_simulate_handle_ops_gas as u128)
+            .saturating_sub(op.total_verification_gas_limit())
+            .saturating_sub(op.pre_verification_gas());
+        // If the call fails with all available gas it fails regardless of its limit
+        simulate(high).await?;
 
-        // [STO-021] - Associated storage on external contracts is allowed
-        if is_sender_associated && !is_same_address {
-            // [STO-022] - Factory must be staked to access associated storage in a deploy
-            if has_factory {
-                match entity.kind {
-                    EntityType::Paymaster | EntityType::Aggregator => {
-                        // If its a paymaster/aggregator, then the entity OR factory must be staked to access associated storage
-                        // during a deploy
-                        restrictions.push(StorageRestriction::AssociatedStorageDuringDeploy(
-                            Some(entity.kind),
-                            address,
-                            *slot,
-                        ));
-                    }
-                    // If its a factory/account, then the factory must be staked to access associated storage during a deploy
-                    EntityType::Account | EntityType::Factory => {
-                        restrictions.push(StorageRestriction::AssociatedStorageDuringDeploy(
-                            None, address, *slot,
-                        ));
-                    }
-                }
+        let mut low = 0;
+        while high - low > CALL_GAS_SEARCH_TOLERANCE {
+            let mid = low + (high - low) / 2;
+            match simulate(mid).await {
+                Ok(()) => high = mid,
+                Err(
+                    GasEstimationError::RevertInCallWithMessage(_)
+                    | GasEstimationError::RevertInCallWithBytes(_),
+                ) => low = mid,
+                Err(e) => return Err(e),
             }
-        } else if is_entity_associated || is_same_address {
-            restrictions.push(StorageRestriction::NeedsStake(
-                entity.kind,
-                entity.kind,
-                Some(entity.kind),
-                address,
-                *slot,
-            ));
-        } else if is_read_permission {
-            restrictions.push(StorageRestriction::NeedsStake(
-                entity.kind,
-                entity.kind,
-                None,
-                address,
-                *slot,
-            ));
-        } else {
-            restrictions.push(StorageRestriction::Banned(*slot));
         }
+
+        Ok(high + CALL_GAS_BUFFER_VALUE)
     }
 
-    restrictions
-}
+    /// Estimate the pre-verification gas, verification gas limit and call gas limit of an
+    /// operation.
+    ///
+    /// Composes the individual estimators, each using the results of the previous ones: the
+    /// pre-verification gas is calculated from the operation as given, the verification gas
+    /// limit is binary searched via `verification_gas_estimator`, and the call gas limit via
+    /// `call_gas_estimator` as in `estimate_call_gas`. The returned limits include the same
+    /// buffers as the gas estimators. The v0.7 paymaster verification gas limit is not
+    /// estimated, the operation's value is used as is.
+    ///
+    /// If `GasEstimateOptions::signature_override` is set it replaces the operation's
+    /// signature, so that operations can be estimated before they are signed.
+    ///
+    /// A successful estimate is NOT a validation success. No simulation rules are checked and
+    /// the signature may not have been verified, the operation must still pass
+    /// `simulate_validation` with its real signature.
+    pub async fn estimate_user_operation_gas<VE, C>(
+        &self,
+        verification_gas_estimator: &VE,
+        call_gas_estimator: &C,
+        mut op: UO,
+        block_hash: B256,
+        options: GasEstimateOptions,
+    ) -> Result<GasEstimate, SimulationError>
+    where
+        E: DAGasProvider<UO = UO>,
+        VE: VerificationGasEstimator<UO = UO>,
+        C: CallGasEstimator<UO = UO>,
+    {
+        if let Some(signature) = options.signature_override {
+            op.set_signature(signature);
+        }
 
-fn override_is_staked(ei: &mut EntityInfo, allow_unstaked_addresses: &HashSet<Address>) {
-    ei.is_staked = allow_unstaked_addresses.contains(&ei.entity.address) || ei.is_staked;
-}
+        let pre_verification_gas = gas::estimate_pre_verification_gas(
+            &options.chain_spec,
+            &self.entry_point,
+            &op,
+            &op,
+            block_hash.into(),
+            options.gas_price,
+        )
+        .await?;
+        op.set_pre_verification_gas(pre_verification_gas);
 
-fn override_infos_staked(eis: &mut EntityInfos, allow_unstaked_addresses: &HashSet<Address>) {
-    override_is_staked(&mut eis.sender, allow_unstaked_addresses);
+        let get_op_with_limit = |mut op: UO, args: GetOpWithLimitArgs| {
+            let GetOpWithLimitArgs { gas, fee } = args;
+            op.set_verification_gas_limit(gas);
+            op.set_max_fees(fee, fee);
+            op.set_call_gas_limit(0);
+            op
+        };
+        let verification_gas_limit = verification_gas_estimator
+            .estimate_verification_gas(
+                &op,
+                block_hash,
+                options.state_override,
+                options.max_verification_gas,
+                get_op_with_limit,
+            )
+            .await?;
+        let verification_gas_limit =
+            math::increase_by_percent(verification_gas_limit, VERIFICATION_GAS_BUFFER_PERCENT)
+                .min(options.max_verification_gas);
+        op.set_verification_gas_limit(verification_gas_limit);
 
-    if let Some(mut factory) = eis.factory {
-        override_is_staked(&mut factory, allow_unstaked_addresses);
+        let call_gas_limit = self
+            .estimate_call_gas(call_gas_estimator, op, block_hash)
+            .await?;
+
+        Ok(GasEstimate {
+            pre_verification_gas,
+            call_gas_limit,
+            verification_gas_limit,
+            paymaster_verification_gas_limit: None,
+        })
     }
-    if let Some(mut paymaster) = eis.paymaster {
-        override_is_staked(&mut paymaster, allow_unstaked_addresses);
+
+    /// Simulate a user operation with per-call overrides, e.g. for estimation and debugging.
+    ///
+    /// The operation is matched against all configured mempools. Results of simulations
+    /// using an override must not be used to admit or bundle the operation.
+    pub async fn simulate_validation_with_overrides(
+        &self,
+        op: UO,
+        block_hash: B256,
+        overrides: &SimulationOverrides,
+    ) -> Result<SimulationResult, SimulationError> {
+        self.simulate_and_record(op, block_hash, None, None, overrides)
+            .await
     }
-    if let Some(mut aggregator) = eis.aggregator {
-        override_is_staked(&mut aggregator, allow_unstaked_addresses);
+
+    // Decodes the paymaster's sponsorship time range with its registered decoder, if any
+    fn check_paymaster_sponsorship(&self, op: &UO) -> Option<SimulationViolation> {
+        let paymaster = op.paymaster()?;
+        let range = self
+            .paymaster_time_range_decoders
+            .get(&paymaster)?
+            .decode(op.paymaster_data())?;
+        (range.valid_until < self.clock.now()).then_some(
+            SimulationViolation::PaymasterSponsorshipExpired(
+                paymaster,
+                range.valid_until,
+                range.valid_after,
+            ),
+        )
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use alloy_primitives::{address, b256, bytes, uint, Bytes};
-    use context::ContractInfo;
-    use rundler_provider::{
-        AggregatorOut, BlockId, BlockNumberOrTag, MockEntryPointV0_6, MockEvmProvider,
-    };
-    use rundler_types::{
-        chain::ChainSpec,
-        v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
-        Opcode, StakeInfo,
-    };
+    fn user_op_hash(&self, op: &UO) -> B256 {
+        op.hash(*self.entry_point.address(), self.chain_id)
+    }
 
-    use self::context::{Phase, TracerOutput};
-    use super::*;
+    // Per ERC-4337 a `valid_until` of 0 means the operation does not expire. v0.7 validation
+    // data is normalized when parsed, but v0.6 return info is passed through verbatim, so
+    // normalize here. An all-zero range is ambiguous (always valid vs never valid): it is
+    // treated as valid for all time unless `reject_empty_time_range` is set.
+    fn normalize_time_range(
+        &self,
+        return_info: &mut ValidationReturnInfo,
+    ) -> Result<(), SimulationViolation> {
+        if return_info.valid_until != 0.into() {
+            return Ok(());
+        }
+        if return_info.valid_after == 0.into() && self.sim_settings.reject_empty_time_range {
+            return Err(SimulationViolation::SuspiciousTimeRange);
+        }
+        return_info.valid_until = u64::MAX.into();
+        Ok(())
+    }
 
-    mockall::mock! {
-        ValidationContextProviderV0_6 {}
+    // Run the simulation, recording a rejection with the sink on failure
+    async fn simulate_and_record(
+        &self,
+        op: UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        mempool_ids: Option<&[B256]>,
+        overrides: &SimulationOverrides,
+    ) -> Result<SimulationResult, SimulationError> {
+        let start = Instant::now();
+        let mut stats = TraceStats::default();
+        let res = self
+            .simulate_validation_inner(
+                op.clone(),
+                block_hash,
+                expected_code_hash,
+                mempool_ids,
+                overrides,
+                &mut stats,
+            )
+            .await
+            .map_err(|mut error| {
+                error.prioritize(self.sim_settings.violation_priority.as_ref());
+                error
+            });
 
-        #[async_trait::async_trait]
-        impl ValidationContextProvider for ValidationContextProviderV0_6 {
-            type UO = UserOperation;
-            async fn get_context(
-                &self,
-                op: UserOperationV0_6,
-                block_id: rundler_provider::BlockId,
-            ) -> Result<ValidationContext<UserOperationV0_6>, ViolationError<SimulationViolation>>;
-            fn get_specific_violations(
-                &self,
-                context: &ValidationContext<UserOperationV0_6>,
-            ) -> anyhow::Result<Vec<SimulationViolation>>;
+        if let Some(threshold) = self.sim_settings.slow_simulation_threshold {
+            let elapsed = start.elapsed();
+            if elapsed > threshold {
+                tracing::warn!(
+                    "Slow simulation of op {:?} from sender {:?} took {elapsed:?}: {} phases, {} storage accesses",
+                    self.user_op_hash(&op),
+                    op.sender(),
+                    stats.num_phases,
+                    stats.num_storage_accesses,
+                );
+            }
         }
-    }
 
-    fn create_base_config() -> (
-        MockEvmProvider,
-        MockEntryPointV0_6,
-        MockValidationContextProviderV0_6,
-    ) {
-        (
-            MockEvmProvider::new(),
-            MockEntryPointV0_6::new(),
-            MockValidationContextProviderV0_6::new(),
-        )
+        // Hand rejections off to the sink without blocking the simulation path
+        if let (Err(error), Some(sink)) = (&res, &self.rejection_sink) {
+            let sink = Arc::clone(sink);
+            let rejection = Rejection {
+                entry_point: *self.entry_point.address(),
+                op_hash: self.user_op_hash(&op),
+                sender: op.sender(),
+                error: error.violation_error.clone(),
+            };
+            tokio::spawn(async move { sink.record(rejection).await });
+        }
+
+        res
     }
 
-    fn get_test_context() -> ValidationContext<UserOperation> {
-        let tracer_out = TracerOutput {
-            accessed_contracts: HashMap::from([
-                (
-                    address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-                (
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-                (
-                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-            ]),
-            associated_slots_by_address: serde_json::from_str(r#"
-            {
-                "0x0000000000000000000000000000000000000000": [
-                    "0xd5c1ebdd81c5c7bebcd52bc11c8d37f7038b3c64f849c2ca58a022abeab1adae",
-                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
-                ],
-                "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4": [
-                    "0x3072884cc37d411af7360b34f105e1e860b1631783232a4f2d5c094d365cdaab",
-                    "0xf5357e1da3acf909ceaed3492183cbad85a3c9e1f0076495f66d3eed05219bd5",
-                    "0xf264fff4db20d04721712f34a6b5a8bca69a212345e40a92101082e79bdd1f0a"
-                ]
-            }
-            "#).unwrap(),
-            factory_called_create2_twice: false,
-            expected_storage: serde_json::from_str(r#"
-            {
-                "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789": {
-                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5": "0x0000000000000000000000000000000000000000000000000000000000000000",
+    async fn simulate_validation_inner(
+        &self,
+        mut op: UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        mempool_ids: Option<&[B256]>,
+        overrides: &SimulationOverrides,
+        stats: &mut TraceStats,
+    ) -> Result<SimulationResult, SimulationError> {
+        if let Err(violations) = validate_static(&op) {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(violations),
+                entity_infos: None,
+            });
+        }
+
+        if let Some(entity) = op
+            .entities()
+            .into_iter()
+            .find(|entity| self.sim_settings.denylist.contains(&entity.address))
+        {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![
+                    SimulationViolation::AddressDenied(entity.address),
+                ]),
+                entity_infos: None,
+            });
+        }
+
+        if let Some(unknown) = mempool_ids
+            .into_iter()
+            .flatten()
+            .find(|id| !self.mempool_configs.contains_key(*id))
+        {
+            Err(anyhow::anyhow!("unknown mempool id {unknown:?}"))?;
+        }
+
+        // An expired paymaster sponsorship can't become valid again, reject it before
+        // spending a trace on it
+        if let Some(violation) = self.check_paymaster_sponsorship(&op) {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![violation]),
+                entity_infos: None,
+            });
+        }
+
+        if let Some(call_gas_limit) = overrides.call_gas_limit {
+            op.set_call_gas_limit(call_gas_limit);
+        }
+
+        // An operation that can't fit in a simulated handleOps call can never be bundled,
+        // reject it before spending a trace on it
+        let total_gas = op
+            .total_verification_gas_limit()
+            .saturating_add(op.call_gas_limit())
+            .saturating_add(op.pre_verification_gas());
+        let max_gas = self.sim_settings.max_simulate_handle_ops_gas as u128;
+        if total_gas > max_gas {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![
+                    SimulationViolation::TotalGasLimitExceeded(total_gas, max_gas),
+                ]),
+                entity_infos: None,
+            });
+        }
+
+        let signature_bypassed = overrides.signature.is_some();
+        if let Some(signature) = &overrides.signature {
+            op.set_signature(signature.clone());
+        }
+
+        let block_id = block_hash.into();
+        let nonce_violation = if self.sim_settings.nonce_check_enabled {
+            self.check_nonce(&op, block_id, mempool_ids).await?
+        } else {
+            None
+        };
+        self.check_token_paymaster(&op, block_id).await?;
+
+        let permit = self.acquire_provider_calls().await;
+        let context = tokio::time::timeout(
+            self.sim_settings.simulation_timeout,
+            self.validation_context_provider
+                .get_context(op.clone(), block_id, overrides.prefund),
+        )
+        .await
+        .map_err(|_| timed_out_error(None))?;
+        drop(permit);
+        let mut context = match context {
+            Ok(context) => context,
+            Err(ViolationError::Other(error))
+                if self.sim_settings.trace_fallback_enabled && is_tracing_unsupported(&error) =>
+            {
+                tracing::warn!("debug_traceCall unsupported by node, falling back to simulateValidation without tracing: {error:?}");
+                let mut result = unsafe_sim::simulate_validation_unsafe(
+                    &self.entry_point,
+                    op.clone(),
+                    block_hash,
+                    signature_bypassed,
+                )
+                .await?;
+                self.check_untraced_result(
+                    &op,
+                    &mut result,
+                    block_hash,
+                    expected_code_hash,
+                    mempool_ids,
+                    nonce_violation.as_slice(),
+                )
+                .await?;
+                self.check_admission_policies(&op, &result)?;
+                return Ok(result);
+            }
+            Err(ViolationError::Other(error)) if is_budget_exceeded(&error) => {
+                SimulationMetrics::default()
+                    .budget_exceeded_count
+                    .increment(1);
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        SimulationViolation::SimulationBudgetExceeded,
+                    ]),
+                    entity_infos: None,
+                });
+            }
+            error @ Err(_) => error?,
+        };
+        *stats = TraceStats::new(&context.tracer_out);
+
+        if signature_bypassed {
+            let return_info = &mut context.entry_point_out.return_info;
+            return_info.account_sig_failed = false;
+            return_info.paymaster_sig_failed = false;
+        }
+
+        if let Err(violation) = self.normalize_time_range(&mut context.entry_point_out.return_info)
+        {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![violation]),
+                entity_infos: Some(context.entity_infos),
+            });
+        }
+
+        if let Some(violation) =
+            check_verification_gas_limit(&op, context.entry_point_out.return_info.pre_op_gas)
+        {
+            return Err(SimulationError {
+                violation_error: ViolationError::Violations(vec![violation]),
+                entity_infos: Some(context.entity_infos),
+            });
+        }
+
+        // Gather all violations from the tracer
+        let mut overridable_violations = self.gather_context_violations(&mut context)?;
+        overridable_violations.extend(nonce_violation);
+        // Sort violations so that the final error message is deterministic
+        match &self.sim_settings.violation_priority {
+            Some(priority) => overridable_violations.sort_by(|a, b| priority.compare(a, b)),
+            None => overridable_violations.sort(),
+        }
+        // Check violations and priority fee against mempool rules, find supporting mempools,
+        // error if none found
+        let max_priority_fee_per_gas = op.max_priority_fee_per_gas();
+        let mut mempools = match mempool::match_mempools(
+            &self.mempool_configs,
+            mempool_ids,
+            &overridable_violations,
+            max_priority_fee_per_gas,
+        ) {
+            MempoolMatchResult::Matches(pools) => pools,
+            MempoolMatchResult::NoMatch(i) => {
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        overridable_violations[i].clone()
+                    ]),
+                    entity_infos: Some(context.entity_infos),
+                })
+            }
+            MempoolMatchResult::PriorityFeeTooLow(min_priority_fee) => {
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        SimulationViolation::MempoolPriorityFeeTooLow(
+                            max_priority_fee_per_gas,
+                            min_priority_fee,
+                        ),
+                    ]),
+                    entity_infos: Some(context.entity_infos),
+                })
+            }
+        };
+
+        let allowlisted_violations = if self.sim_settings.collect_allowlisted_violations {
+            overridable_violations
+                .into_iter()
+                .map(|violation| {
+                    let entity = violation
+                        .entity()
+                        .unwrap_or_else(|| Entity::account(op.sender()));
+                    (entity, violation)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // Check code hash and aggregator signature, these can't fail
+        let (code_hash, aggregators, entity_deployment, block_number) = self
+            .check_contracts(op.clone(), &mut context, expected_code_hash, &mut mempools)
+            .await?;
+
+        // Transform outputs into success struct
+        let ValidationContext {
+            tracer_out,
+            entry_point_out,
+            accessed_addresses,
+            associated_addresses,
+            ..
+        } = context;
+        let ValidationOutput {
+            return_info,
+            sender_info,
+            ..
+        } = entry_point_out;
+        let account_is_staked = context::is_staked(sender_info, &self.sim_settings);
+        let ValidationReturnInfo {
+            pre_op_gas,
+            valid_after,
+            valid_until,
+            paymaster_context,
+            ..
+        } = return_info;
+
+        // Conduct any stake overrides before assigning entity_infos
+        override_infos_staked(&mut context.entity_infos, &self.allow_unstaked_addresses);
+
+        // Code probes of undeployed addresses are only allowed by settings or mempool
+        // allowlists. Their outcome changes once code is deployed, so they are reported.
+        let sender = context.entity_infos.sender_address();
+        let probed_undeployed_addresses = tracer_out
+            .phases
+            .iter()
+            .take(3)
+            .flat_map(|phase| {
+                phase
+                    .undeployed_contract_accesses
+                    .iter()
+                    .filter(|address| !phase.undeployed_contract_calls.contains(address))
+            })
+            .filter(|address| **address != sender)
+            .copied()
+            .collect();
+
+        let mut expected_storage = tracer_out.expected_storage;
+        let storage_truncated = self
+            .sim_settings
+            .max_expected_storage_slots
+            .is_some_and(|max_slots| expected_storage.truncate(max_slots));
+
+        let result = SimulationResult {
+            mempools,
+            pre_op_gas,
+            validation_gas_used: tracer_out
+                .phases
+                .iter()
+                .map(|phase| phase.gas_used as u128)
+                .sum(),
+            block_number,
+            valid_time_range: ValidTimeRange::new(valid_after, valid_until),
+            aggregators,
+            code_hash,
+            account_is_staked,
+            accessed_addresses,
+            associated_addresses,
+            expected_storage,
+            storage_truncated,
+            requires_post_op: !paymaster_context.is_empty(),
+            paymaster_context,
+            entity_infos: context.entity_infos,
+            entity_deployment,
+            tracing_skipped: false,
+            prefund_overridden: overrides.prefund.is_some(),
+            signature_bypassed,
+            allowlisted_violations,
+            metadata: None,
+            probed_undeployed_addresses,
+        };
+
+        self.check_admission_policies(&op, &result)?;
+
+        Ok(result)
+    }
+}
+
+impl<UO, P, E, V> SimulatorImpl<UO, P, E, V>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint
+        + SignatureAggregator<UO = UO>
+        + SimulationProvider<UO = UO>
+        + BundleHandler<UO = UO>,
+    V: ValidationContextProvider<UO = UO>,
+{
+    /// Simulate a full `handleOps` bundle at `block_hash` and report which operations revert.
+    ///
+    /// The bundle is called with `eth_call`. Whenever the entry point reports a failed
+    /// operation, that operation is marked as failed and the remaining operations are
+    /// simulated again, until the bundle succeeds or no operations remain. Operations are
+    /// sent without an aggregator, so operations requiring signature aggregation will fail.
+    ///
+    /// Returns an error if the bundle reverts in a way that can't be attributed to a single
+    /// operation.
+    pub async fn simulate_bundle(
+        &self,
+        ops: Vec<UO>,
+        beneficiary: Address,
+        block_hash: B256,
+    ) -> anyhow::Result<BundleSimResult> {
+        let mut results = vec![BundleOpResult::Success; ops.len()];
+        let mut remaining = (0..ops.len()).collect::<Vec<_>>();
+
+        while !remaining.is_empty() {
+            let user_ops = remaining.iter().map(|&i| ops[i].clone()).collect();
+            let out = self
+                .entry_point
+                .call_handle_ops(
+                    vec![UserOpsPerAggregator {
+                        user_ops,
+                        aggregator: Address::ZERO,
+                        signature: Bytes::new(),
+                    }],
+                    beneficiary,
+                    None,
+                    Some(block_hash.into()),
+                )
+                .await
+                .context("should call handle ops")?;
+
+            match out {
+                HandleOpsOut::Success => break,
+                HandleOpsOut::FailedOp(index, reason) => {
+                    anyhow::ensure!(
+                        index < remaining.len(),
+                        "entry point reported failed op index {index} for a bundle of {} ops",
+                        remaining.len()
+                    );
+                    let op_index = remaining.remove(index);
+                    results[op_index] = BundleOpResult::Failed(reason);
+                }
+                HandleOpsOut::SignatureValidationFailed(aggregator) => {
+                    anyhow::bail!(
+                        "bundle signature validation failed for aggregator {aggregator:?}"
+                    )
+                }
+                HandleOpsOut::PostOpRevert => {
+                    anyhow::bail!("bundle reverted in postOp, the failing operation is unknown")
+                }
+            }
+        }
+
+        let first_failed_index = results
+            .iter()
+            .position(|r| matches!(r, BundleOpResult::Failed(_)));
+        Ok(BundleSimResult {
+            results,
+            first_failed_index,
+        })
+    }
+}
+
+#[async_trait]
+impl<UO, P, E, V> Simulator for SimulatorImpl<UO, P, E, V>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SignatureAggregator<UO = UO> + SimulationProvider<UO = UO>,
+    V: ValidationContextProvider<UO = UO>,
+{
+    type UO = UO;
+
+    async fn simulate_validation(
+        &self,
+        op: UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        self.simulate_and_record(
+            op,
+            block_hash,
+            expected_code_hash,
+            None,
+            &SimulationOverrides::default(),
+        )
+        .await
+    }
+
+    async fn simulate_validation_in_mempools(
+        &self,
+        op: UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        mempool_ids: Vec<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        self.simulate_and_record(
+            op,
+            block_hash,
+            expected_code_hash,
+            Some(&mempool_ids),
+            &SimulationOverrides::default(),
+        )
+        .await
+    }
+
+    async fn explain_mempool_rejection(
+        &self,
+        op: UO,
+        block_hash: B256,
+        mempool_id: B256,
+    ) -> Result<Option<SimulationViolation>, SimulationError> {
+        // Explaining a rejection is a query, skip the rejection sink
+        let res = self
+            .simulate_validation_inner(
+                op,
+                block_hash,
+                None,
+                Some(&[mempool_id]),
+                &SimulationOverrides::default(),
+                &mut TraceStats::default(),
+            )
+            .await
+            .map_err(|mut error| {
+                error.prioritize(self.sim_settings.violation_priority.as_ref());
+                error
+            });
+        mempool_rejection(res)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum StorageRestriction {
+    /// (Entity needing stake, accessing entity type, accessed entity type, accessed address, accessed slot)
+    NeedsStake(EntityType, EntityType, Option<EntityType>, Address, U256),
+    AssociatedStorageDuringDeploy(Option<EntityType>, Address, U256),
+    Banned(U256),
+}
+
+#[derive(Clone, Debug)]
+struct ParseStorageAccess<'a> {
+    access_info: &'a AccessInfo,
+    slots_by_address: &'a AssociatedSlotsByAddress,
+    address: Address,
+    sender: Address,
+    entrypoint: Address,
+    has_factory: bool,
+    entity: &'a Entity,
+    validator_modules: &'a HashSet<Address>,
+}
+
+fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestriction> {
+    let ParseStorageAccess {
+        access_info,
+        address,
+        sender,
+        entrypoint,
+        entity,
+        slots_by_address,
+        has_factory,
+        validator_modules,
+        ..
+    } = args;
+
+    let mut restrictions = vec![];
+
+    // A self-deploying account is its own factory. Its storage is the account's own during
+    // account validation, but the factory storage rules still apply while deploying.
+    let is_self_deploying_factory = entity.kind == EntityType::Factory && entity.address == sender;
+
+    // [STO-010] - always allowed to access storage on the account
+    // [OP-054] - block access to the entrypoint, except for depositTo and fallback
+    //   - this is handled at another level, so we don't need to check for it here
+    //   - at this level we can allow any entry point access through. Entry point storage
+    //     can only be touched by calling the entry point, so whether the access is allowed
+    //     follows whether the method was allowed by the settings or a mempool's allowlist
+    if (address.eq(&sender) && !is_self_deploying_factory) || address.eq(&entrypoint) {
+        return restrictions;
+    }
+
+    // Validator modules installed on modular accounts (ERC-6900/7579) validate on behalf of
+    // the account, so their storage is treated like the account's own during account validation
+    if entity.kind == EntityType::Account && validator_modules.contains(&address) {
+        return restrictions;
+    }
+
+    let slots: Vec<&U256> = access_info
+        .reads
+        .keys()
+        .chain(access_info.writes.keys())
+        .collect();
+
+    for slot in slots {
+        let is_sender_associated = slots_by_address.is_associated_slot(sender, *slot);
+        // [STO-032]
+        let is_entity_associated = slots_by_address.is_associated_slot(entity.address, *slot);
+        // [STO-031]
+        let is_same_address = address.eq(&entity.address);
+        // [STO-033]
+        let is_read_permission = !access_info.writes.contains_key(slot);
+
+        // [STO-021] - Associated storage on external contracts is allowed
+        if is_sender_associated && !is_same_address {
+            // [STO-022] - Factory must be staked to access associated storage in a deploy
+            if has_factory {
+                match entity.kind {
+                    EntityType::Paymaster | EntityType::Aggregator => {
+                        // If its a paymaster/aggregator, then the entity OR factory must be staked to access associated storage
+                        // during a deploy
+                        restrictions.push(StorageRestriction::AssociatedStorageDuringDeploy(
+                            Some(entity.kind),
+                            address,
+                            *slot,
+                        ));
+                    }
+                    // If its a factory/account, then the factory must be staked to access associated storage during a deploy
+                    EntityType::Account | EntityType::Factory => {
+                        restrictions.push(StorageRestriction::AssociatedStorageDuringDeploy(
+                            None, address, *slot,
+                        ));
+                    }
+                }
+            }
+        } else if is_entity_associated || is_same_address {
+            restrictions.push(StorageRestriction::NeedsStake(
+                entity.kind,
+                entity.kind,
+                Some(entity.kind),
+                address,
+                *slot,
+            ));
+        } else if is_read_permission {
+            restrictions.push(StorageRestriction::NeedsStake(
+                entity.kind,
+                entity.kind,
+                None,
+                address,
+                *slot,
+            ));
+        } else {
+            restrictions.push(StorageRestriction::Banned(*slot));
+        }
+    }
+
+    restrictions
+}
+
+// Tracing runs with a generous gas limit, so validation can succeed in simulation while
+// using more gas than the operation's own verification gas limit allows. Such an operation
+// reverts when bundled.
+fn check_verification_gas_limit<UO: UserOperation>(
+    op: &UO,
+    pre_op_gas: u128,
+) -> Option<SimulationViolation> {
+    let needed = pre_op_gas.saturating_sub(op.pre_verification_gas());
+    let provided = op.total_verification_gas_limit();
+    (needed > provided).then_some(SimulationViolation::VerificationGasLimitTooLowForOp(
+        needed, provided,
+    ))
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "simulation")]
+struct SimulationMetrics {
+    #[metric(describe = "the count of simulations aborted for exceeding the trace budget.")]
+    budget_exceeded_count: Counter,
+    #[metric(describe = "the number of distinct contracts accessed during validation.")]
+    accessed_contracts: Histogram,
+}
+
+// Size of a validation trace, logged for slow simulations
+#[derive(Debug, Default, PartialEq)]
+struct TraceStats {
+    num_phases: usize,
+    num_storage_accesses: usize,
+}
+
+impl TraceStats {
+    fn new(tracer_out: &TracerOutput) -> Self {
+        Self {
+            num_phases: tracer_out.phases.len(),
+            num_storage_accesses: tracer_out
+                .phases
+                .iter()
+                .flat_map(|phase| phase.storage_accesses.values())
+                .map(|access_info| access_info.reads.len() + access_info.writes.len())
+                .sum(),
+        }
+    }
+}
+
+fn timed_out_error(entity_infos: Option<EntityInfos>) -> SimulationError {
+    SimulationError {
+        violation_error: ViolationError::Violations(vec![SimulationViolation::SimulationTimedOut]),
+        entity_infos,
+    }
+}
+
+/// Decodes an aggregator's `validateUserOpSignature` revert data into a reason, falling back
+/// to the raw revert data. `None` if the aggregator reverted without data.
+pub(crate) fn aggregator_revert_reason(revert_data: &Bytes) -> Option<String> {
+    if revert_data.is_empty() {
+        return None;
+    }
+    Some(
+        Revert::abi_decode(revert_data, false)
+            .map(|revert| revert.reason)
+            .unwrap_or_else(|_| revert_data.to_string()),
+    )
+}
+
+fn is_budget_exceeded(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|e| e.to_string().contains(BUDGET_EXCEEDED_ERROR))
+}
+
+fn is_tracing_unsupported(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        e.downcast_ref::<ProviderError>()
+            .is_some_and(ProviderError::is_method_not_supported)
+    })
+}
+
+fn override_is_staked(ei: &mut EntityInfo, allow_unstaked_addresses: &HashSet<Address>) {
+    ei.is_staked = allow_unstaked_addresses.contains(&ei.entity.address) || ei.is_staked;
+}
+
+fn override_infos_staked(eis: &mut EntityInfos, allow_unstaked_addresses: &HashSet<Address>) {
+    override_is_staked(&mut eis.sender, allow_unstaked_addresses);
+
+    if let Some(mut factory) = eis.factory {
+        override_is_staked(&mut factory, allow_unstaked_addresses);
+    }
+    if let Some(mut paymaster) = eis.paymaster {
+        override_is_staked(&mut paymaster, allow_unstaked_addresses);
+    }
+    if let Some(mut aggregator) = eis.aggregator {
+        override_is_staked(&mut aggregator, allow_unstaked_addresses);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, b256, bytes, uint, Bytes};
+    use context::ContractInfo;
+    use rundler_provider::{
+        AggregatorOut, Block, BlockHeader, BlockId, BlockNumberOrTag, MockEntryPointV0_6,
+        MockEvmProvider,
+    };
+    use rundler_types::{
+        chain::ChainSpec,
+        v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
+        AggregatorInfo, Opcode, StakeInfo, UserOperation as _, ValidationRevert,
+    };
+    use tokio::sync::mpsc;
+
+    use self::context::{Phase, TracerOutput};
+    use super::*;
+    use crate::simulation::{mempool::AllowlistEntry, MockClock, TokenPaymasterConfig};
+
+    mockall::mock! {
+        ValidationContextProviderV0_6 {}
+
+        #[async_trait::async_trait]
+        impl ValidationContextProvider for ValidationContextProviderV0_6 {
+            type UO = UserOperation;
+            async fn get_context(
+                &self,
+                op: UserOperationV0_6,
+                block_id: rundler_provider::BlockId,
+                prefund_override: Option<U256>,
+            ) -> Result<ValidationContext<UserOperationV0_6>, ViolationError<SimulationViolation>>;
+            fn get_specific_violations(
+                &self,
+                context: &ValidationContext<UserOperationV0_6>,
+            ) -> anyhow::Result<Vec<SimulationViolation>>;
+        }
+    }
+
+    const TEST_BLOCK_NUMBER: u64 = 1_000;
+
+    // An operation that passes the static field checks
+    fn test_op() -> UserOperation {
+        UserOperation {
+            sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+            verification_gas_limit: 10_000,
+            max_fee_per_gas: 1,
+            ..Default::default()
+        }
+    }
+
+    fn create_base_config() -> (
+        MockEvmProvider,
+        MockEntryPointV0_6,
+        MockValidationContextProviderV0_6,
+    ) {
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_block().returning(|_| {
+            Ok(Some(Block {
+                header: BlockHeader {
+                    number: TEST_BLOCK_NUMBER,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }))
+        });
+        (
+            provider,
+            MockEntryPointV0_6::new(),
+            MockValidationContextProviderV0_6::new(),
+        )
+    }
+
+    fn get_test_context() -> ValidationContext<UserOperation> {
+        let tracer_out = TracerOutput {
+            accessed_contracts: HashMap::from([
+                (
+                    address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"),
+                    ContractInfo {
+                        header: "0x608060".to_string(),
+                        opcode: Opcode::CALL,
+                        length: 32,
+                    }
+                ),
+                (
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    ContractInfo {
+                        header: "0x608060".to_string(),
+                        opcode: Opcode::CALL,
+                        length: 32,
+                    }
+                ),
+                (
+                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    ContractInfo {
+                        header: "0x608060".to_string(),
+                        opcode: Opcode::CALL,
+                        length: 32,
+                    }
+                ),
+            ]),
+            associated_slots_by_address: serde_json::from_str(r#"
+            {
+                "0x0000000000000000000000000000000000000000": [
+                    "0xd5c1ebdd81c5c7bebcd52bc11c8d37f7038b3c64f849c2ca58a022abeab1adae",
+                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+                ],
+                "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4": [
+                    "0x3072884cc37d411af7360b34f105e1e860b1631783232a4f2d5c094d365cdaab",
+                    "0xf5357e1da3acf909ceaed3492183cbad85a3c9e1f0076495f66d3eed05219bd5",
+                    "0xf264fff4db20d04721712f34a6b5a8bca69a212345e40a92101082e79bdd1f0a"
+                ]
+            }
+            "#).unwrap(),
+            factory_called_create2_twice: false,
+            factory_deployed_address: None,
+            factory_created_contracts: HashMap::new(),
+            expected_storage: serde_json::from_str(r#"
+            {
+                "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789": {
+                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5": "0x0000000000000000000000000000000000000000000000000000000000000000",
                     "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb6": "0x0000000000000000000000000000000000000000000000000000000000000000"
                 }
             }
-            "#).unwrap(),
-            phases: vec![
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: false,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses: HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                },
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: false,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses:  HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                },
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: false,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses: HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                }
-            ],
-            revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
+            "#).unwrap(),
+            phases: vec![
+                Phase {
+                    called_banned_entry_point_method: false,
+                    banned_entry_point_methods: vec![],
+                    called_non_entry_point_with_value: false,
+                    value_call_targets: vec![],
+                    forbidden_opcodes_used: vec![],
+                    forbidden_precompiles_used: vec![],
+                    ran_out_of_gas: false,
+                    storage_accesses: HashMap::new(),
+                    undeployed_contract_accesses: vec![],
+                    undeployed_contract_calls: vec![],
+                    ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                },
+                Phase {
+                    called_banned_entry_point_method: false,
+                    banned_entry_point_methods: vec![],
+                    called_non_entry_point_with_value: false,
+                    value_call_targets: vec![],
+                    forbidden_opcodes_used: vec![],
+                    forbidden_precompiles_used: vec![],
+                    ran_out_of_gas: false,
+                    storage_accesses:  HashMap::new(),
+                    undeployed_contract_accesses: vec![],
+                    undeployed_contract_calls: vec![],
+                    ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                },
+                Phase {
+                    called_banned_entry_point_method: false,
+                    banned_entry_point_methods: vec![],
+                    called_non_entry_point_with_value: false,
+                    value_call_targets: vec![],
+                    forbidden_opcodes_used: vec![],
+                    forbidden_precompiles_used: vec![],
+                    ran_out_of_gas: false,
+                    storage_accesses: HashMap::new(),
+                    undeployed_contract_accesses: vec![],
+                    undeployed_contract_calls: vec![],
+                    ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                }
+            ],
+            revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
+        };
+
+        ValidationContext {
+            op: UserOperation {
+                verification_gas_limit: 2000,
+                pre_verification_gas: 1000,
+                ..Default::default()
+            },
+            has_factory: true,
+            associated_addresses: HashSet::new(),
+            block_id: BlockId::Number(BlockNumberOrTag::Latest),
+            entity_infos: context::infos_from_validation_output(
+                Some(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789")),
+                address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                Some(address!("8abb13360b87be5eeb1b98647a016add927a136c")),
+                &ValidationOutput {
+                    return_info: ValidationReturnInfo::default(),
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                },
+                &Settings::default(),
+            ),
+            tracer_out,
+            entry_point_out: ValidationOutput {
+                return_info: ValidationReturnInfo {
+                    pre_op_gas: 3000,
+                    account_sig_failed: true,
+                    paymaster_sig_failed: true,
+                    ..Default::default()
+                },
+                sender_info: StakeInfo::default(),
+                factory_info: StakeInfo::default(),
+                paymaster_info: StakeInfo::default(),
+                aggregator_info: None,
+            },
+            accessed_addresses: HashSet::new(),
+        }
+    }
+
+    fn create_simulator(
+        provider: MockEvmProvider,
+        entry_point: MockEntryPointV0_6,
+        context: MockValidationContextProviderV0_6,
+    ) -> SimulatorImpl<
+        UserOperation,
+        MockEvmProvider,
+        MockEntryPointV0_6,
+        MockValidationContextProviderV0_6,
+    > {
+        let settings = Settings::default();
+
+        let mut mempool_configs = HashMap::new();
+        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+
+        SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            mempool_configs,
+            vec![],
+            0,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_mempool_configs() {
+        let (provider, entry_point, context) = create_base_config();
+        let simulator = create_simulator(provider, entry_point, context);
+
+        assert_eq!(simulator.mempool_configs().len(), 1);
+        assert!(simulator.mempool_configs().contains_key(&B256::ZERO));
+        assert_eq!(
+            simulator.mempool_ids().collect::<Vec<_>>(),
+            vec![&B256::ZERO]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| {
+                Ok((
+                    b256!("38138f1cb4653ab6ab1c89ae3a6acc8705b54bd16a997d880c4421014ed66c3d"),
+                    0,
+                ))
+            });
+
+        provider.expect_get_code_hash().returning(|_, _| {
+            Ok(b256!(
+                "091cd005abf68e7b82c951a8619f065986132f67a0945153533cfcdd93b6895f"
+            ))
+        });
+
+        // the sender is not yet deployed, the factory and paymaster are
+        provider.expect_get_code().returning(|address, _| {
+            if address == address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4") {
+                Ok(Bytes::new())
+            } else {
+                Ok(bytes!("608060"))
+            }
+        });
+
+        context
+            .expect_get_context()
+            .returning(move |_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let user_operation = UserOperationBuilder::new(&ChainSpec::default(),UserOperationRequiredFields {
+            sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+            nonce: U256::from(264),
+            init_code: Bytes::default(),
+            call_data: bytes!("b61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000"),
+            call_gas_limit: 9100,
+            verification_gas_limit: 64805,
+            pre_verification_gas: 46128,
+            max_fee_per_gas: 105000100,
+            max_priority_fee_per_gas: 105000000,
+            paymaster_and_data: Bytes::default(),
+            signature: bytes!("98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c"),
+        }).build();
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(user_operation, B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            res.entity_deployment,
+            HashMap::from([
+                (EntityType::Account, false),
+                (EntityType::Factory, true),
+                (EntityType::Paymaster, true),
+            ])
+        );
+        assert_eq!(res.block_number, TEST_BLOCK_NUMBER);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_at_historical_block() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        let block_hash = b256!("38138f1cb4653ab6ab1c89ae3a6acc8705b54bd16a997d880c4421014ed66c3d");
+        let block_id: BlockId = block_hash.into();
+        let aggregator = address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6");
+
+        // every state read must target the requested block, never latest
+        provider.expect_get_code_hash().returning(move |_, block| {
+            assert_eq!(block, Some(block_id));
+            Ok(B256::ZERO)
+        });
+        provider.expect_get_code().returning(move |_, block| {
+            assert_eq!(block, Some(block_id));
+            Ok(bytes!("608060"))
+        });
+        context.expect_get_context().returning(move |_, block, _| {
+            assert_eq!(block, block_id);
+            let mut context = get_test_context();
+            context.block_id = block;
+            context.entry_point_out.aggregator_info = Some(AggregatorInfo {
+                address: aggregator,
+                stake_info: StakeInfo {
+                    stake: U256::MAX,
+                    unstake_delay_sec: u32::MAX,
+                },
+            });
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(move |address, _, block| {
+                assert_eq!(block, Some(block_id));
+                Ok(AggregatorOut::SuccessWithInfo(AggregatorSimOut {
+                    address,
+                    signature: Bytes::new(),
+                }))
+            });
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(test_op(), block_hash, None)
+            .await
+            .unwrap();
+        assert_eq!(res.aggregator_address(), Some(aggregator));
+        assert_eq!(res.aggregators.len(), 1);
+    }
+
+    #[derive(Debug)]
+    struct SenderBlocklist(HashSet<Address>);
+
+    impl AdmissionPolicy<UserOperation> for SenderBlocklist {
+        fn evaluate(
+            &self,
+            op: &UserOperation,
+            _result: &SimulationResult,
+        ) -> Result<(), SimulationViolation> {
+            if self.0.contains(&op.sender) {
+                return Err(SimulationViolation::AdmissionPolicyRejected(
+                    "sender is blocked".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admission_policy() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let blocked = Address::random();
+        let mut mempool_configs = HashMap::new();
+        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            Settings::default(),
+            mempool_configs,
+            vec![Arc::new(SenderBlocklist(HashSet::from([blocked])))],
+            0,
+            None,
+        );
+
+        simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+
+        let op = UserOperation {
+            sender: blocked,
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op, B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::AdmissionPolicyRejected("sender is blocked".to_string())]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_explain_mempool_rejection() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let open_mempool = B256::random();
+        let priced_mempool = B256::random();
+        let mempool_configs = HashMap::from([
+            (open_mempool, MempoolConfig::default()),
+            (
+                priced_mempool,
+                MempoolConfig {
+                    min_priority_fee: 100,
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            Settings::default(),
+            mempool_configs,
+            vec![],
+            0,
+            Some(Arc::new(ChannelRejectionSink(tx))),
+        );
+
+        let explanation = simulator
+            .explain_mempool_rejection(test_op(), B256::ZERO, open_mempool)
+            .await
+            .unwrap();
+        assert_eq!(explanation, None);
+
+        let explanation = simulator
+            .explain_mempool_rejection(test_op(), B256::ZERO, priced_mempool)
+            .await
+            .unwrap();
+        assert_eq!(
+            explanation,
+            Some(SimulationViolation::MempoolPriorityFeeTooLow(0, 100))
+        );
+
+        simulator
+            .explain_mempool_rejection(test_op(), B256::ZERO, B256::random())
+            .await
+            .unwrap_err();
+
+        // explaining a rejection doesn't record one
+        drop(simulator);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_validate_aggregated_signatures() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let valid_aggregator = Address::random();
+        let failing_aggregator = Address::random();
+        let reverting_aggregator = Address::random();
+        let block_hash = B256::random();
+        entry_point
+            .expect_aggregate_signatures()
+            .returning(move |aggregator, _| {
+                if aggregator == reverting_aggregator {
+                    Ok(None)
+                } else {
+                    Ok(Some(bytes!("1234")))
+                }
+            });
+        entry_point
+            .expect_validate_signatures()
+            .returning(move |aggregator, ops, _, block_id| {
+                assert_eq!(ops.len(), 2);
+                assert_eq!(block_id, Some(block_hash.into()));
+                Ok(aggregator == valid_aggregator)
+            });
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let ops = vec![test_op(), test_op()];
+
+        simulator
+            .validate_aggregated_signatures(valid_aggregator, ops.clone(), block_hash)
+            .await
+            .unwrap();
+        for aggregator in [failing_aggregator, reverting_aggregator] {
+            let res = simulator
+                .validate_aggregated_signatures(aggregator, ops.clone(), block_hash)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                res.violation_error,
+                ViolationError::Violations(v)
+                    if v == vec![SimulationViolation::AggregatorValidationFailed(None)]
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validation_gas_used() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context.expect_get_context().returning(|_, _, _| {
+            let mut context = get_test_context();
+            context.tracer_out.phases[0].gas_used = 50_000;
+            context.tracer_out.phases[1].gas_used = 30_000;
+            context.tracer_out.phases[2].gas_used = 20_000;
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let result = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(result.validation_gas_used, 100_000);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_with_metadata() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let metadata = bytes!("c0ffee");
+        let tagged = simulator
+            .simulate_validation_with_metadata(test_op(), B256::ZERO, None, Some(metadata.clone()))
+            .await
+            .unwrap();
+        assert_eq!(tagged.metadata, Some(metadata));
+
+        // metadata is pass-through only and doesn't change the rest of the result
+        let untagged = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(untagged.metadata, None);
+        assert_eq!(
+            SimulationResult {
+                metadata: None,
+                ..tagged
+            },
+            untagged
+        );
+    }
+
+    #[test]
+    fn test_aggregator_revert_reason() {
+        let revert_data = Revert {
+            reason: "invalid signature".to_string(),
+        }
+        .abi_encode();
+        assert_eq!(
+            aggregator_revert_reason(&revert_data.into()),
+            Some("invalid signature".to_string())
+        );
+        assert_eq!(
+            aggregator_revert_reason(&bytes!("deadbeef")),
+            Some("0xdeadbeef".to_string())
+        );
+        assert_eq!(aggregator_revert_reason(&Bytes::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_simulation_budget_exceeded() {
+        let (provider, entry_point, mut context) = create_base_config();
+        context.expect_get_context().returning(|_, _, _| {
+            Err(ViolationError::Other(
+                anyhow::anyhow!("execution reverted: {BUDGET_EXCEEDED_ERROR}")
+                    .context("should trace simulate validation"),
+            ))
+        });
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::SimulationBudgetExceeded]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let ops = (0..3u64)
+            .map(|i| UserOperation {
+                nonce: U256::from(i),
+                ..test_op()
+            })
+            .collect::<Vec<_>>();
+
+        let mut seq = mockall::Sequence::new();
+        entry_point
+            .expect_call_handle_ops()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|ops, _, _, block_id| {
+                ops[0].user_ops.len() == 3 && *block_id == Some(B256::ZERO.into())
+            })
+            .returning(|_, _, _, _| Ok(HandleOpsOut::FailedOp(1, "AA23 reverted".to_string())));
+        entry_point
+            .expect_call_handle_ops()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|ops, _, _, _| {
+                ops[0]
+                    .user_ops
+                    .iter()
+                    .map(|op| op.nonce)
+                    .collect::<Vec<_>>()
+                    == vec![U256::from(0), U256::from(2)]
+            })
+            .returning(|_, _, _, _| Ok(HandleOpsOut::Success));
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_bundle(ops, Address::repeat_byte(1), B256::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            BundleSimResult {
+                results: vec![
+                    BundleOpResult::Success,
+                    BundleOpResult::Failed("AA23 reverted".to_string()),
+                    BundleOpResult::Success,
+                ],
+                first_failed_index: Some(1),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle_post_op_revert() {
+        let (provider, mut entry_point, context) = create_base_config();
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _| Ok(HandleOpsOut::PostOpRevert));
+
+        let simulator = create_simulator(provider, entry_point, context);
+        assert!(simulator
+            .simulate_bundle(vec![test_op()], Address::repeat_byte(1), B256::ZERO)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_too_many_contracts_accessed() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider.expect_get_code_hash().never();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let settings = Settings {
+            max_accessed_contracts: Some(2),
+            ..Default::default()
+        };
+        let mut mempool_configs = HashMap::new();
+        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            mempool_configs,
+            vec![],
+            0,
+            None,
+        );
+
+        // the test context accesses 3 contracts
+        let res = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await;
+        assert!(matches!(
+            res.unwrap_err().violation_error,
+            ViolationError::Violations(v) if v == vec![SimulationViolation::TooManyContractsAccessed(3)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_time_range() {
+        for reject in [false, true] {
+            let (mut provider, mut entry_point, mut context) = create_base_config();
+            provider
+                .expect_get_code_hash()
+                .returning(|_, _| Ok(B256::ZERO));
+            provider
+                .expect_get_code()
+                .returning(|_, _| Ok(bytes!("608060")));
+            // the test context has an all-zero time range
+            context
+                .expect_get_context()
+                .returning(|_, _, _| Ok(get_test_context()));
+            context
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            entry_point
+                .expect_validate_user_op_signature()
+                .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+            let settings = Settings {
+                reject_empty_time_range: reject,
+                ..Default::default()
+            };
+            let mut mempool_configs = HashMap::new();
+            mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context,
+                settings,
+                mempool_configs,
+                vec![],
+                0,
+                None,
+            );
+
+            let res = simulator
+                .simulate_validation(test_op(), B256::ZERO, None)
+                .await;
+            if reject {
+                assert!(matches!(
+                    res.unwrap_err().violation_error,
+                    ViolationError::Violations(v) if v == vec![SimulationViolation::SuspiciousTimeRange]
+                ));
+            } else {
+                assert_eq!(res.unwrap().valid_time_range, ValidTimeRange::all_time());
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_time_range() {
+        let (provider, entry_point, context) = create_base_config();
+        let simulator = create_simulator(provider, entry_point, context);
+
+        // a zero valid_until means no expiry
+        let mut return_info = ValidationReturnInfo {
+            valid_after: 10.into(),
+            ..Default::default()
+        };
+        simulator.normalize_time_range(&mut return_info).unwrap();
+        assert_eq!(return_info.valid_after, 10.into());
+        assert_eq!(return_info.valid_until, u64::MAX.into());
+
+        // non-zero ranges are left untouched
+        let mut return_info = ValidationReturnInfo {
+            valid_after: 10.into(),
+            valid_until: 20.into(),
+            ..Default::default()
+        };
+        simulator.normalize_time_range(&mut return_info).unwrap();
+        assert_eq!(return_info.valid_until, 20.into());
+    }
+
+    #[tokio::test]
+    async fn test_code_hash_changed() {
+        let expected_code_hash = B256::random();
+        let new_code_hash = B256::random();
+
+        // trust is per contract, the aggregate code hash isn't trusted
+        for (trusted_code_hash, accepted) in [
+            (None, false),
+            (Some(new_code_hash), false),
+            (Some(keccak256(bytes!("608060"))), true),
+        ] {
+            let (mut provider, mut entry_point, mut context) = create_base_config();
+            provider
+                .expect_get_code_hash()
+                .returning(move |_, _| Ok(new_code_hash));
+            provider
+                .expect_get_code()
+                .returning(|_, _| Ok(bytes!("608060")));
+            context
+                .expect_get_context()
+                .returning(|_, _, _| Ok(get_test_context()));
+            context
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            entry_point
+                .expect_validate_user_op_signature()
+                .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+            let settings = Settings {
+                trusted_code_hashes: trusted_code_hash.into_iter().collect(),
+                ..Default::default()
+            };
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context,
+                settings,
+                HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+                vec![],
+                0,
+                None,
+            );
+
+            let res = simulator
+                .simulate_validation(test_op(), B256::ZERO, Some(expected_code_hash))
+                .await;
+            if accepted {
+                assert_eq!(res.unwrap().code_hash, new_code_hash);
+            } else {
+                assert!(matches!(
+                    res.unwrap_err().violation_error,
+                    ViolationError::Violations(v)
+                        if v == vec![SimulationViolation::CodeHashChanged(expected_code_hash, new_code_hash)]
+                ));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_paymaster() {
+        let (mut provider, entry_point, context) = create_base_config();
+        let paymaster = Address::random();
+        let token = Address::random();
+        provider
+            .expect_get_token_balance()
+            .returning(|_, _, _| Ok(U256::from(40_000)));
+        provider
+            .expect_get_token_allowance()
+            .returning(move |_, _, spender, _| {
+                assert_eq!(spender, paymaster);
+                Ok(U256::from(100_000))
+            });
+
+        let settings = Settings {
+            token_paymasters: HashMap::from([(
+                paymaster,
+                TokenPaymasterConfig {
+                    token,
+                    tokens_per_eth: uint!(2_000_000_000_000_000_000_U256),
+                },
+            )]),
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::new(),
+            vec![],
+            0,
+            None,
+        );
+
+        // max gas cost of 50_000 wei requires 100_000 tokens
+        let op = UserOperation {
+            call_gas_limit: 10_000,
+            verification_gas_limit: 10_000,
+            pre_verification_gas: 10_000,
+            max_fee_per_gas: 1,
+            paymaster_and_data: paymaster.to_vec().into(),
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op, B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::InsufficientTokenBalance(
+                    token,
+                    U256::from(100_000),
+                    U256::from(60_000),
+                )]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_paymaster_deposit_check() {
+        let paymaster = Address::random();
+        let permissive_mempool = B256::random();
+
+        for allowlisted in [false, true] {
+            let (mut provider, mut entry_point, mut context) = create_base_config();
+            provider
+                .expect_get_code_hash()
+                .returning(|_, _| Ok(B256::ZERO));
+            provider
+                .expect_get_code()
+                .returning(|_, _| Ok(bytes!("608060")));
+            context.expect_get_context().returning(|_, _, _| {
+                let mut context = get_test_context();
+                context.entry_point_out.return_info.prefund = U256::from(1_000);
+                Ok(context)
+            });
+            context
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            entry_point
+                .expect_validate_user_op_signature()
+                .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+            entry_point
+                .expect_balance_of()
+                .returning(move |address, _| {
+                    assert_eq!(address, paymaster);
+                    Ok(U256::from(400))
+                });
+
+            let settings = Settings {
+                paymaster_deposit_check_enabled: true,
+                ..Default::default()
+            };
+            let mut mempool_configs = HashMap::from([(B256::ZERO, MempoolConfig::default())]);
+            if allowlisted {
+                mempool_configs.insert(
+                    permissive_mempool,
+                    MempoolConfig {
+                        allowlist: vec![AllowlistEntry {
+                            entity: AllowEntity::Type(EntityType::Paymaster),
+                            rule: AllowRule::PaymasterDepositTooLow,
+                        }],
+                        ..Default::default()
+                    },
+                );
+            }
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context,
+                settings,
+                mempool_configs,
+                vec![],
+                0,
+                None,
+            );
+
+            let op = UserOperation {
+                paymaster_and_data: paymaster.to_vec().into(),
+                ..test_op()
+            };
+            let res = simulator.simulate_validation(op, B256::ZERO, None).await;
+            if allowlisted {
+                assert_eq!(res.unwrap().mempools, vec![permissive_mempool]);
+            } else {
+                assert!(matches!(
+                    res.unwrap_err().violation_error,
+                    ViolationError::Violations(v)
+                        if v == vec![SimulationViolation::PaymasterDepositTooLow(
+                            Entity::paymaster(paymaster),
+                            U256::from(1_000),
+                            U256::from(400),
+                        )]
+                ));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_provider_calls() {
+        let (provider, entry_point, mut context) = create_base_config();
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Err(ViolationError::Other(anyhow::anyhow!("trace failed"))));
+
+        let settings = Settings {
+            max_concurrent_provider_calls: Some(1),
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+            vec![],
+            0,
+            None,
+        );
+
+        // tracing waits while another simulation holds the only slot
+        let permit = simulator.acquire_provider_calls().await;
+        let res = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            simulator.simulate_validation(test_op(), B256::ZERO, None),
+        )
+        .await;
+        assert!(res.is_err());
+
+        drop(permit);
+        let res = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await;
+        assert!(res.is_err());
+        assert_eq!(simulator.provider_call_limiter.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_signature_override() {
+        let dummy_signature = bytes!("deadbeef");
+
+        for bypassed in [false, true] {
+            let (mut provider, mut entry_point, mut context) = create_base_config();
+            provider
+                .expect_get_code_hash()
+                .returning(|_, _| Ok(B256::ZERO));
+            provider
+                .expect_get_code()
+                .returning(|_, _| Ok(bytes!("608060")));
+            let expected_signature = if bypassed {
+                dummy_signature.clone()
+            } else {
+                Bytes::new()
+            };
+            context.expect_get_context().returning(move |op, _, _| {
+                assert_eq!(op.signature, expected_signature);
+                Ok(get_test_context())
+            });
+            // the test context reports failed signatures
+            context
+                .expect_get_specific_violations()
+                .returning(|context| {
+                    if context.entry_point_out.return_info.account_sig_failed {
+                        Ok(vec![SimulationViolation::InvalidSignature])
+                    } else {
+                        Ok(vec![])
+                    }
+                });
+            entry_point
+                .expect_validate_user_op_signature()
+                .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+            let simulator = create_simulator(provider, entry_point, context);
+
+            let overrides = SimulationOverrides {
+                signature: bypassed.then(|| dummy_signature.clone()),
+                ..Default::default()
+            };
+            let res = simulator
+                .simulate_validation_with_overrides(test_op(), B256::ZERO, &overrides)
+                .await;
+            if bypassed {
+                assert!(res.unwrap().signature_bypassed);
+            } else {
+                assert!(matches!(
+                    res.unwrap_err().violation_error,
+                    ViolationError::Violations(v)
+                        if v == vec![SimulationViolation::InvalidSignature]
+                ));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefund_override() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .withf(|_, _, prefund_override| *prefund_override == Some(U256::from(1_000)))
+            .times(1)
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_context()
+            .withf(|_, _, prefund_override| prefund_override.is_none())
+            .times(1)
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let simulator = create_simulator(provider, entry_point, context);
+
+        let overrides = SimulationOverrides {
+            prefund: Some(U256::from(1_000)),
+            ..Default::default()
+        };
+        let res = simulator
+            .simulate_validation_with_overrides(test_op(), B256::ZERO, &overrides)
+            .await
+            .unwrap();
+        assert!(res.prefund_overridden);
+
+        // the override only applies to the call it was given to
+        let res = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert!(!res.prefund_overridden);
+    }
+
+    #[tokio::test]
+    async fn test_call_gas_limit_override() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context.expect_get_context().returning(|op, _, _| {
+            assert_eq!(op.call_gas_limit, 50_000);
+            Ok(get_test_context())
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let simulator = create_simulator(provider, entry_point, context);
+
+        let op = test_op();
+        let overrides = SimulationOverrides {
+            call_gas_limit: Some(50_000),
+            ..Default::default()
+        };
+        simulator
+            .simulate_validation_with_overrides(op.clone(), B256::ZERO, &overrides)
+            .await
+            .unwrap();
+        assert_eq!(op.call_gas_limit, 0);
+    }
+
+    #[tokio::test]
+    async fn test_denylist() {
+        let paymaster = address!("8abb13360b87be5eeb1b98647a016add927a136c");
+        let (provider, entry_point, mut context) = create_base_config();
+        context.expect_get_context().never();
+
+        let settings = Settings {
+            denylist: HashSet::from([paymaster]),
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+            vec![],
+            0,
+            None,
+        );
+
+        let op = UserOperation {
+            paymaster_and_data: paymaster.to_vec().into(),
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op, B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::AddressDenied(paymaster)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_static_violations() {
+        let (provider, entry_point, mut context) = create_base_config();
+        context.expect_get_context().never();
+        let simulator = create_simulator(provider, entry_point, context);
+
+        let op = UserOperation {
+            max_fee_per_gas: 0,
+            init_code: bytes!("5ff137d4"),
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op, B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![
+                    SimulationViolation::ZeroMaxFeePerGas,
+                    SimulationViolation::MalformedInitCode,
+                ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_canonical_mempool() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            Settings::default(),
+            HashMap::new(),
+            vec![],
+            0,
+            None,
+        );
+
+        let res = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(res.mempools, vec![CANONICAL_MEMPOOL_ID]);
+    }
+
+    #[tokio::test]
+    async fn test_collect_allowlisted_violations() {
+        let paymaster = Address::random();
+        let violation = SimulationViolation::GasOpcodeMisuse(Entity::paymaster(paymaster));
+
+        for collect in [false, true] {
+            let (mut provider, mut entry_point, mut context) = create_base_config();
+            provider
+                .expect_get_code_hash()
+                .returning(|_, _| Ok(B256::ZERO));
+            provider
+                .expect_get_code()
+                .returning(|_, _| Ok(bytes!("608060")));
+            context
+                .expect_get_context()
+                .returning(|_, _, _| Ok(get_test_context()));
+            let specific_violation = violation.clone();
+            context
+                .expect_get_specific_violations()
+                .returning(move |_| Ok(vec![specific_violation.clone()]));
+            entry_point
+                .expect_validate_user_op_signature()
+                .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+            let settings = Settings {
+                collect_allowlisted_violations: collect,
+                ..Default::default()
+            };
+            let mempool_configs = HashMap::from([(
+                B256::ZERO,
+                MempoolConfig {
+                    allowlist: vec![AllowlistEntry {
+                        entity: AllowEntity::Type(EntityType::Paymaster),
+                        rule: AllowRule::GasOpcodeMisuse,
+                    }],
+                    ..Default::default()
+                },
+            )]);
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context,
+                settings,
+                mempool_configs,
+                vec![],
+                0,
+                None,
+            );
+
+            let res = simulator
+                .simulate_validation(test_op(), B256::ZERO, None)
+                .await
+                .unwrap();
+            if collect {
+                assert_eq!(
+                    res.allowlisted_violations,
+                    vec![(Entity::paymaster(paymaster), violation.clone())]
+                );
+            } else {
+                assert!(res.allowlisted_violations.is_empty());
+            }
+        }
+    }
+
+    struct ThresholdCallGasEstimator(u128);
+
+    #[async_trait]
+    impl CallGasEstimator for ThresholdCallGasEstimator {
+        type UO = UserOperation;
+
+        async fn estimate_call_gas(
+            &self,
+            _op: UserOperation,
+            _block_hash: B256,
+            _state_override: StateOverride,
+        ) -> Result<u128, GasEstimationError> {
+            Ok(self.0)
+        }
+
+        async fn simulate_handle_op_with_result(
+            &self,
+            op: UserOperation,
+            _block_hash: B256,
+            _state_override: StateOverride,
+        ) -> Result<(), GasEstimationError> {
+            if op.call_gas_limit >= self.0 {
+                Ok(())
+            } else {
+                Err(GasEstimationError::RevertInCallWithBytes(Bytes::new()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas() {
+        let (provider, entry_point, context) = create_base_config();
+        let simulator = create_simulator(provider, entry_point, context);
+
+        let estimate = simulator
+            .estimate_call_gas(&ThresholdCallGasEstimator(100_000), test_op(), B256::ZERO)
+            .await
+            .unwrap();
+        let call_gas = estimate - CALL_GAS_BUFFER_VALUE;
+        assert!(call_gas >= 100_000);
+        assert!(call_gas <= 100_000 + CALL_GAS_SEARCH_TOLERANCE);
+
+        let res = simulator
+            .estimate_call_gas(&ThresholdCallGasEstimator(u128::MAX), test_op(), B256::ZERO)
+            .await;
+        assert!(matches!(
+            res,
+            Err(GasEstimationError::RevertInCallWithBytes(_))
+        ));
+    }
+
+    // Fails validation unless the op is signed
+    struct SignedVerificationGasEstimator(u128);
+
+    #[async_trait]
+    impl VerificationGasEstimator for SignedVerificationGasEstimator {
+        type UO = UserOperation;
+
+        async fn estimate_verification_gas<
+            F: Send + Sync + Fn(UserOperation, GetOpWithLimitArgs) -> UserOperation,
+        >(
+            &self,
+            op: &UserOperation,
+            _block_hash: B256,
+            _state_override: StateOverride,
+            max_guess: u128,
+            get_op_with_limit: F,
+        ) -> Result<u128, GasEstimationError> {
+            let op = get_op_with_limit(
+                op.clone(),
+                GetOpWithLimitArgs {
+                    gas: max_guess,
+                    fee: 0,
+                },
+            );
+            assert_eq!(op.verification_gas_limit, max_guess);
+            assert_eq!(op.call_gas_limit, 0);
+            if op.signature.is_empty() {
+                return Err(GasEstimationError::RevertInValidation(
+                    ValidationRevert::EntryPoint("AA24 signature error".to_string()),
+                ));
+            }
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_operation_gas() {
+        let dummy_signature = bytes!("deadbeef");
+        let options = GasEstimateOptions {
+            chain_spec: ChainSpec::default(),
+            gas_price: 0,
+            max_verification_gas: 1_000_000,
+            state_override: StateOverride::default(),
+            signature_override: None,
+        };
+
+        for bypassed in [false, true] {
+            let (provider, entry_point, context) = create_base_config();
+            let simulator = create_simulator(provider, entry_point, context);
+            let options = GasEstimateOptions {
+                signature_override: bypassed.then(|| dummy_signature.clone()),
+                ..options.clone()
+            };
+
+            let res = simulator
+                .estimate_user_operation_gas(
+                    &SignedVerificationGasEstimator(50_000),
+                    &ThresholdCallGasEstimator(100_000),
+                    test_op(),
+                    B256::ZERO,
+                    options.clone(),
+                )
+                .await;
+            if !bypassed {
+                // unsigned ops can't be estimated without a dummy signature
+                assert!(matches!(
+                    res.unwrap_err().violation_error,
+                    ViolationError::Violations(v)
+                        if matches!(v[..], [SimulationViolation::ValidationRevert(_)])
+                ));
+                continue;
+            }
+
+            let estimate = res.unwrap();
+            let signed_op = UserOperation {
+                signature: dummy_signature.clone(),
+                ..test_op()
+            };
+            assert_eq!(
+                estimate.pre_verification_gas,
+                signed_op.required_pre_verification_gas(&options.chain_spec, 1, 0)
+            );
+            assert_eq!(estimate.verification_gas_limit, 55_000);
+            let call_gas = estimate.call_gas_limit - CALL_GAS_BUFFER_VALUE;
+            assert!(call_gas >= 100_000);
+            assert!(call_gas <= 100_000 + CALL_GAS_SEARCH_TOLERANCE);
+            assert_eq!(estimate.paymaster_verification_gas_limit, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_gas_limit_exceeded() {
+        let (provider, entry_point, context) = create_base_config();
+        let settings = Settings {
+            max_simulate_handle_ops_gas: 30_000,
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+            vec![],
+            0,
+            None,
+        );
+
+        // one gas over the limit, rejected without tracing
+        let op = UserOperation {
+            call_gas_limit: 10_000,
+            verification_gas_limit: 10_000,
+            pre_verification_gas: 10_001,
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op, B256::ZERO, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            res.violation_error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::TotalGasLimitExceeded(30_001, 30_000)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_required_prefund() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let op = UserOperation {
+            call_gas_limit: 10_000,
+            verification_gas_limit: 20_000,
+            pre_verification_gas: 30_000,
+            max_fee_per_gas: 10,
+            ..test_op()
+        };
+        let computed = U256::from(600_000);
+        assert_eq!(op.max_gas_cost(), computed);
+
+        let mut seq = mockall::Sequence::new();
+        entry_point
+            .expect_simulate_validation()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo {
+                        prefund: U256::from(600_000),
+                        ..Default::default()
+                    },
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                }))
+            });
+        // unfunded sender
+        entry_point
+            .expect_simulate_validation()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| {
+                Ok(Err(ValidationRevert::EntryPoint(
+                    "AA21 didn't pay prefund".to_string(),
+                )))
+            });
+
+        let simulator = create_simulator(provider, entry_point, context);
+        for _ in 0..2 {
+            let prefund = simulator
+                .required_prefund(op.clone(), B256::ZERO)
+                .await
+                .unwrap();
+            assert_eq!(prefund, computed);
+        }
+    }
+
+    #[derive(Debug)]
+    struct ChannelRejectionSink(mpsc::UnboundedSender<Rejection>);
+
+    #[async_trait]
+    impl RejectionSink for ChannelRejectionSink {
+        async fn record(&self, rejection: Rejection) {
+            self.0.send(rejection).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejection_sink() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let entry_point_address = Address::random();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_get_nonce()
+            .returning(|_, _, _| Ok(U256::from(5)));
+
+        let settings = Settings {
+            nonce_check_enabled: true,
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::new(),
+            vec![],
+            1,
+            Some(Arc::new(ChannelRejectionSink(tx))),
+        );
+
+        let op = UserOperation {
+            sender: Address::random(),
+            nonce: U256::from(3),
+            ..test_op()
+        };
+        simulator
+            .simulate_validation(op.clone(), B256::ZERO, None)
+            .await
+            .unwrap_err();
+
+        let rejection = rx.recv().await.unwrap();
+        assert_eq!(rejection.entry_point, entry_point_address);
+        assert_eq!(rejection.op_hash, op.hash(entry_point_address, 1));
+        assert_eq!(rejection.sender, op.sender);
+        assert!(matches!(
+            rejection.error,
+            ViolationError::Violations(v)
+                if v == vec![SimulationViolation::NonceAlreadyUsed(U256::from(5), U256::from(3))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_batch() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let entry_point_address = Address::random();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        // duplicate operations are only simulated once
+        entry_point
+            .expect_get_nonce()
+            .times(2)
+            .returning(|_, _, _| Ok(U256::from(5)));
+
+        let settings = Settings {
+            nonce_check_enabled: true,
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::new(),
+            vec![],
+            1,
+            None,
+        );
+
+        let op_a = UserOperation {
+            sender: Address::random(),
+            nonce: U256::from(3),
+            ..test_op()
+        };
+        let op_b = UserOperation {
+            sender: Address::random(),
+            nonce: U256::from(4),
+            ..test_op()
+        };
+
+        let results = simulator
+            .simulate_validation_batch(vec![op_a.clone(), op_b.clone(), op_a.clone()], B256::ZERO)
+            .await;
+
+        assert_eq!(results.len(), 2);
+        for (op, nonce) in [(op_a, 3), (op_b, 4)] {
+            let result = results.get(&op.hash(entry_point_address, 1)).unwrap();
+            assert!(matches!(
+                result,
+                Err(SimulationError {
+                    violation_error: ViolationError::Violations(v),
+                    ..
+                }) if *v == vec![SimulationViolation::NonceAlreadyUsed(U256::from(5), U256::from(nonce))]
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_unknown_mempool() {
+        let (provider, entry_point, context) = create_base_config();
+        let mut mempool_configs = HashMap::new();
+        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            Settings::default(),
+            mempool_configs,
+            vec![],
+            0,
+            None,
+        );
+
+        let unknown = B256::random();
+        let res = simulator
+            .simulate_validation_in_mempools(test_op(), B256::ZERO, None, vec![B256::ZERO, unknown])
+            .await;
+        assert!(matches!(
+            res,
+            Err(SimulationError {
+                violation_error: ViolationError::Other(e),
+                ..
+            }) if e.to_string().contains(&format!("{unknown:?}"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_check() {
+        let (provider, mut entry_point, context) = create_base_config();
+        entry_point
+            .expect_get_nonce()
+            .returning(|_, _, _| Ok(U256::from(5)));
+
+        let settings = Settings {
+            nonce_check_enabled: true,
+            ..Default::default()
+        };
+        let mut mempool_configs = HashMap::new();
+        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            mempool_configs,
+            vec![],
+            0,
+            None,
+        );
+        let sender = Address::random();
+
+        for (nonce, expected) in [
+            (
+                3,
+                SimulationViolation::NonceAlreadyUsed(U256::from(5), U256::from(3)),
+            ),
+            (
+                7,
+                SimulationViolation::NonceTooHigh(U256::from(5), U256::from(7)),
+            ),
+        ] {
+            let op = UserOperation {
+                sender,
+                nonce: U256::from(nonce),
+                ..test_op()
+            };
+            let res = simulator
+                .simulate_validation(op, B256::ZERO, None)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                res.violation_error,
+                ViolationError::Violations(v) if v == vec![expected]
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nonce_gap_mempools() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context
+            .expect_get_context()
+            .returning(|_, _, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+        entry_point
+            .expect_get_nonce()
+            .returning(|_, _, _| Ok(U256::from(5)));
+
+        let strict_mempool = B256::random();
+        let gap_mempool = B256::random();
+        let mempool_configs = HashMap::from([
+            (strict_mempool, MempoolConfig::default()),
+            (
+                gap_mempool,
+                MempoolConfig {
+                    allowlist: vec![AllowlistEntry {
+                        entity: AllowEntity::Type(EntityType::Account),
+                        rule: AllowRule::NonceTooHigh,
+                    }],
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let settings = Settings {
+            nonce_check_enabled: true,
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            mempool_configs,
+            vec![],
+            0,
+            None,
+        );
+
+        let op_with_nonce = |nonce: u64| UserOperation {
+            nonce: U256::from(nonce),
+            ..test_op()
+        };
+        let res = simulator
+            .simulate_validation(op_with_nonce(5), B256::ZERO, None)
+            .await
+            .unwrap();
+        let mut mempools = res.mempools;
+        mempools.sort();
+        let mut expected = vec![strict_mempool, gap_mempool];
+        expected.sort();
+        assert_eq!(mempools, expected);
+
+        // an op with a nonce gap is only eligible for the mempool that allows gaps
+        let res = simulator
+            .simulate_validation(op_with_nonce(7), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(res.mempools, vec![gap_mempool]);
+    }
+
+    #[tokio::test]
+    async fn test_gather_context_violations() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+
+        // add forbidden opcodes and precompiles
+        context.tracer_out.phases[1].forbidden_opcodes_used = vec![
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
+        ];
+        context.tracer_out.phases[1].forbidden_precompiles_used = vec![String::from(
+            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
+        )];
+
+        // add a storage access for a random unrelated address
+        let mut writes: HashMap<U256, u64> = HashMap::new();
+
+        writes.insert(
+            uint!(0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256),
+            1,
+        );
+
+        context.tracer_out.phases[1].storage_accesses.insert(
+            address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+            AccessInfo {
+                reads: HashMap::new(),
+                writes,
+            },
+        );
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    ViolationOpCode(Opcode::GASPRICE),
+                ),
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    ViolationOpCode(Opcode::COINBASE),
+                ),
+                SimulationViolation::UsedForbiddenPrecompile(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    address!("0000000000000000000000000000000000000019"),
+                ),
+                SimulationViolation::InvalidStorageAccess(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    StorageSlot {
+                        address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+                        slot: uint!(
+                            0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256
+                        )
+                    },
+                    StorageSlotLabel::default(),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deployed_sender_skips_factory_checks() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // already deployed sender, no init code
+        let mut context = get_test_context();
+        context.has_factory = false;
+        context.entity_infos.factory = None;
+        context.tracer_out.factory_called_create2_twice = true;
+        context.tracer_out.phases[0].forbidden_opcodes_used = vec![String::from(
+            "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789:GASPRICE",
+        )];
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+
+        // the same trace with a factory is checked
+        let mut context = get_test_context();
+        context.tracer_out.factory_called_create2_twice = true;
+        let violations = simulator.gather_context_violations(&mut context).unwrap();
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::FactoryCalledCreate2Twice(address!(
+                "5ff137d4b0fdcd49dca30c7cf57e578a026d2789"
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_unstaked_paymaster_context() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator(provider, entry_point, context_provider);
+
+        // postOp paymaster that doesn't access any storage
+        let paymaster = address!("8abb13360b87be5eeb1b98647a016add927a136c");
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].storage_accesses = HashMap::new();
+        context.entry_point_out.return_info.paymaster_context = bytes!("1234");
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::UnstakedPaymasterContext]
+        );
+
+        // a staked paymaster may return a context
+        let mut context = get_test_context();
+        context.entry_point_out.return_info.paymaster_context = bytes!("1234");
+        context.entity_infos.set_paymaster(paymaster, true);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_sender_is_paymaster() {
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let simulator_with_policy = |policy| {
+            let (provider, mut entry_point, mut context_provider) = create_base_config();
+            entry_point
+                .expect_address()
+                .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+            context_provider
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            let settings = Settings {
+                sender_is_paymaster_policy: policy,
+                ..Default::default()
+            };
+            SimulatorImpl::new(
+                provider,
+                entry_point,
+                context_provider,
+                settings,
+                HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+                vec![],
+                0,
+                None,
+            )
+        };
+        let self_paymaster_context = |is_staked| {
+            let mut context = get_test_context();
+            context.tracer_out.phases[2].storage_accesses = HashMap::new();
+            context.entity_infos.set_paymaster(sender, is_staked);
+            context
+        };
+
+        // by default an unstaked sender can't be its own paymaster
+        let simulator = simulator_with_policy(SenderIsPaymasterPolicy::default());
+        assert_eq!(
+            simulator
+                .gather_context_violations(&mut self_paymaster_context(false))
+                .unwrap(),
+            vec![SimulationViolation::NotStaked(Box::new(
+                NeedsStakeInformation {
+                    needs_stake: Entity::paymaster(sender),
+                    accessing_entity: EntityType::Paymaster,
+                    accessed_entity: Some(EntityType::Account),
+                    accessed_address: sender,
+                    slot: U256::ZERO,
+                    min_stake: uint!(1000000000000000000_U256),
+                    min_unstake_delay: 84600,
+                }
+            ))]
+        );
+        assert_eq!(
+            simulator
+                .gather_context_violations(&mut self_paymaster_context(true))
+                .unwrap(),
+            vec![]
+        );
+
+        // rejected regardless of stake
+        let simulator = simulator_with_policy(SenderIsPaymasterPolicy::Reject);
+        assert_eq!(
+            simulator
+                .gather_context_violations(&mut self_paymaster_context(true))
+                .unwrap(),
+            vec![SimulationViolation::SenderIsPaymaster(sender)]
+        );
+
+        // a distinct paymaster isn't affected
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].storage_accesses = HashMap::new();
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_check_verification_gas_limit() {
+        let op = UserOperation {
+            pre_verification_gas: 1_000,
+            ..test_op()
+        };
+
+        assert_eq!(check_verification_gas_limit(&op, 11_000), None);
+        assert_eq!(
+            check_verification_gas_limit(&op, 11_001),
+            Some(SimulationViolation::VerificationGasLimitTooLowForOp(
+                10_001, 10_000
+            ))
+        );
+    }
+
+    #[test]
+    fn test_trace_stats() {
+        let mut context = get_test_context();
+        context.tracer_out.phases[1].storage_accesses.insert(
+            Address::random(),
+            AccessInfo {
+                reads: HashMap::from([(U256::from(1), U256::ZERO)]),
+                writes: HashMap::from([(U256::from(2), 1), (U256::from(3), 2)]),
+            },
+        );
+
+        assert_eq!(
+            TraceStats::new(&context.tracer_out),
+            TraceStats {
+                num_phases: 3,
+                num_storage_accesses: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_out_of_gas_gas_used() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator(provider, entry_point, context_provider);
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].storage_accesses = HashMap::new();
+        context.tracer_out.phases[1].ran_out_of_gas = true;
+        context.tracer_out.phases[1].gas_used = 40_000;
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::OutOfGas(
+                Entity::account(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")),
+                40_000
+            )]
+        );
+    }
+
+    #[test]
+    fn test_factory_gas_limit_exceeded() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let settings = Settings {
+            max_factory_gas: Some(50_000),
+            ..Default::default()
         };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context_provider,
+            settings,
+            HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+            vec![],
+            0,
+            None,
+        );
 
-        ValidationContext {
-            op: UserOperation {
-                verification_gas_limit: 2000,
-                pre_verification_gas: 1000,
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].storage_accesses = HashMap::new();
+        context.tracer_out.phases[0].gas_used = 50_000;
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+
+        context.tracer_out.phases[0].gas_used = 60_000;
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::FactoryGasLimitExceeded(
+                Entity::factory(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789")),
+                60_000,
+                50_000
+            )]
+        );
+    }
+
+    #[test]
+    fn test_factory_create_code_hashes() {
+        let factory = address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789");
+        let approved = address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6");
+        let unapproved = Address::random();
+        let approved_code = bytes!("6080604052");
+        let unapproved_code = bytes!("6080604053");
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[0].forbidden_opcodes_used = vec![format!("{factory}:CREATE")];
+        context.tracer_out.factory_created_contracts = HashMap::from([
+            (approved, approved_code.clone()),
+            (unapproved, unapproved_code.clone()),
+        ]);
+
+        for allowlisted in [false, true] {
+            let (provider, mut entry_point, mut context_provider) = create_base_config();
+            entry_point.expect_address().return_const(factory);
+            context_provider
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            let settings = Settings {
+                factory_create_code_hashes: if allowlisted {
+                    HashSet::from([keccak256(&approved_code)])
+                } else {
+                    HashSet::new()
+                },
                 ..Default::default()
+            };
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context_provider,
+                settings,
+                HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+                vec![],
+                0,
+                None,
+            );
+
+            let expected = if allowlisted {
+                SimulationViolation::FactoryDeployedUnapprovedContract(
+                    Entity::factory(factory),
+                    unapproved,
+                    keccak256(&unapproved_code),
+                )
+            } else {
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity::factory(factory),
+                    factory,
+                    ViolationOpCode(Opcode::CREATE),
+                )
+            };
+            assert_eq!(
+                simulator.gather_context_violations(&mut context).unwrap(),
+                vec![expected]
+            );
+        }
+    }
+
+    #[test]
+    fn test_self_deploying_account_storage() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator(provider, entry_point, context_provider);
+
+        // the factory is the sender
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let mut context = get_test_context();
+        context.entity_infos.set_factory(sender, false);
+        let access_info = AccessInfo {
+            reads: HashMap::new(),
+            writes: HashMap::from([(U256::from(1), 1)]),
+        };
+
+        // the account phase may always access its own storage
+        context.tracer_out.phases[1]
+            .storage_accesses
+            .insert(sender, access_info.clone());
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+
+        // the factory phase accessing the shared address needs a staked factory
+        context.tracer_out.phases[0]
+            .storage_accesses
+            .insert(sender, access_info);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::NotStaked(Box::new(
+                NeedsStakeInformation {
+                    needs_stake: Entity::factory(sender),
+                    accessing_entity: EntityType::Factory,
+                    accessed_entity: Some(EntityType::Factory),
+                    accessed_address: sender,
+                    slot: U256::from(1),
+                    min_stake: uint!(1000000000000000000_U256),
+                    min_unstake_delay: 84600,
+                }
+            ))]
+        );
+
+        context.entity_infos.set_factory(sender, true);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_sender_address_mismatch() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator(provider, entry_point, context_provider);
+
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let mut context = get_test_context();
+        context.tracer_out.factory_deployed_address = Some(sender);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+
+        let deployed = Address::random();
+        let mut context = get_test_context();
+        context.tracer_out.factory_deployed_address = Some(deployed);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::SenderAddressMismatch(sender, deployed)]
+        );
+    }
+
+    #[test]
+    fn test_gas_opcode_misuse() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // GAS misused in two contracts called by the account
+        let mut context = get_test_context();
+        context.tracer_out.phases[1].forbidden_opcodes_used = vec![
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GAS"),
+            String::from("0x1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6:GAS"),
+        ];
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::GasOpcodeMisuse(Entity::account(
+                address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+            ))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_storage_slot_labeler() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let token = address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6");
+        let mut context = get_test_context();
+        context.tracer_out.phases[1].storage_accesses.insert(
+            token,
+            AccessInfo {
+                reads: HashMap::new(),
+                writes: HashMap::from([(U256::from(7), 1)]),
             },
-            has_factory: true,
-            associated_addresses: HashSet::new(),
-            block_id: BlockId::Number(BlockNumberOrTag::Latest),
-            entity_infos: context::infos_from_validation_output(
-                Some(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789")),
-                address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                Some(address!("8abb13360b87be5eeb1b98647a016add927a136c")),
-                &ValidationOutput {
-                    return_info: ValidationReturnInfo::default(),
-                    sender_info: StakeInfo::default(),
-                    factory_info: StakeInfo::default(),
-                    paymaster_info: StakeInfo::default(),
-                    aggregator_info: None,
+        );
+
+        fn label(address: Address, slot: U256) -> Option<String> {
+            (address == address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6")
+                && slot == U256::from(7))
+            .then(|| "ERC20.totalSupply".to_string())
+        }
+        let simulator = create_simulator(provider, entry_point, context_provider)
+            .with_storage_slot_labeler(Arc::new(label as fn(Address, U256) -> Option<String>));
+
+        let violations = simulator.gather_context_violations(&mut context).unwrap();
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::InvalidStorageAccess(
+                Entity::account(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")),
+                StorageSlot {
+                    address: token,
+                    slot: U256::from(7),
                 },
-                &Settings::default(),
-            ),
-            tracer_out,
-            entry_point_out: ValidationOutput {
-                return_info: ValidationReturnInfo {
-                    pre_op_gas: 3000,
-                    account_sig_failed: true,
-                    paymaster_sig_failed: true,
-                    ..Default::default()
+                StorageSlotLabel(Some("ERC20.totalSupply".to_string())),
+            )]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            format!("account accessed forbidden storage at address {token:?} (ERC20.totalSupply) during validation")
+        );
+    }
+
+    #[test]
+    fn test_validator_module_storage() {
+        let module = address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6");
+
+        for installed in [false, true] {
+            let (provider, mut entry_point, mut context_provider) = create_base_config();
+            entry_point
+                .expect_address()
+                .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+            context_provider
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            let settings = Settings {
+                validator_modules: if installed {
+                    HashSet::from([module])
+                } else {
+                    HashSet::new()
                 },
-                sender_info: StakeInfo::default(),
-                factory_info: StakeInfo::default(),
-                paymaster_info: StakeInfo::default(),
-                aggregator_info: None,
-            },
-            accessed_addresses: HashSet::new(),
+                ..Default::default()
+            };
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context_provider,
+                settings,
+                HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+                vec![],
+                0,
+                None,
+            );
+
+            // the account writes to a non-associated slot of the module during validation
+            let mut context = get_test_context();
+            context.tracer_out.phases[1].storage_accesses.insert(
+                module,
+                AccessInfo {
+                    reads: HashMap::new(),
+                    writes: HashMap::from([(U256::from(1), 1)]),
+                },
+            );
+
+            let violations = simulator.gather_context_violations(&mut context).unwrap();
+            if installed {
+                assert_eq!(violations, vec![]);
+            } else {
+                assert_eq!(
+                    violations,
+                    vec![SimulationViolation::InvalidStorageAccess(
+                        Entity::account(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")),
+                        StorageSlot {
+                            address: module,
+                            slot: U256::from(1),
+                        },
+                        StorageSlotLabel::default(),
+                    )]
+                );
+            }
         }
     }
 
-    fn create_simulator(
-        provider: MockEvmProvider,
-        entry_point: MockEntryPointV0_6,
-        context: MockValidationContextProviderV0_6,
-    ) -> SimulatorImpl<
-        UserOperation,
-        MockEvmProvider,
-        MockEntryPointV0_6,
-        MockValidationContextProviderV0_6,
-    > {
-        let settings = Settings::default();
+    #[test]
+    fn test_undeployed_contract_probes() {
+        let undeployed = Address::random();
+        let account = Entity::account(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"));
 
-        let mut mempool_configs = HashMap::new();
-        mempool_configs.insert(B256::ZERO, MempoolConfig::default());
+        for allow_probes in [false, true] {
+            let (provider, mut entry_point, mut context_provider) = create_base_config();
+            entry_point
+                .expect_address()
+                .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+            context_provider
+                .expect_get_specific_violations()
+                .returning(|_| Ok(vec![]));
+            let settings = Settings {
+                allow_undeployed_contract_probes: allow_probes,
+                ..Default::default()
+            };
+            let simulator = SimulatorImpl::new(
+                provider,
+                entry_point,
+                context_provider,
+                settings,
+                HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+                vec![],
+                0,
+                None,
+            );
+
+            // the account only checks the code size of the undeployed address
+            let mut context = get_test_context();
+            context.tracer_out.phases[1].undeployed_contract_accesses = vec![undeployed];
+            let violations = simulator.gather_context_violations(&mut context).unwrap();
+            if allow_probes {
+                assert_eq!(violations, vec![]);
+            } else {
+                assert_eq!(
+                    violations,
+                    vec![SimulationViolation::AccessedUndeployedContract(
+                        account, undeployed
+                    )]
+                );
+            }
 
-        SimulatorImpl::new(provider, entry_point, context, settings, mempool_configs)
+            // the account calls into the undeployed address
+            context.tracer_out.phases[1].undeployed_contract_calls = vec![undeployed];
+            assert_eq!(
+                simulator.gather_context_violations(&mut context).unwrap(),
+                vec![SimulationViolation::AccessedUndeployedContract(
+                    account, undeployed
+                )]
+            );
+        }
     }
 
     #[tokio::test]
-    async fn test_simulate_validation() {
-        let (mut provider, mut entry_point, mut context) = create_base_config();
+    async fn test_probed_undeployed_addresses() {
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let probed = Address::random();
 
+        let (mut provider, mut entry_point, mut context) = create_base_config();
         provider
-            .expect_get_latest_block_hash_and_number()
-            .returning(|| {
-                Ok((
-                    b256!("38138f1cb4653ab6ab1c89ae3a6acc8705b54bd16a997d880c4421014ed66c3d"),
-                    0,
-                ))
-            });
-
-        provider.expect_get_code_hash().returning(|_, _| {
-            Ok(b256!(
-                "091cd005abf68e7b82c951a8619f065986132f67a0945153533cfcdd93b6895f"
-            ))
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("608060")));
+        context.expect_get_context().returning(move |_, _, _| {
+            let mut context = get_test_context();
+            // the factory probing the sender it deploys isn't reported
+            context.tracer_out.phases[0].undeployed_contract_accesses = vec![sender];
+            context.tracer_out.phases[1].undeployed_contract_accesses = vec![probed];
+            Ok(context)
         });
-
-        context
-            .expect_get_context()
-            .returning(move |_, _| Ok(get_test_context()));
         context
             .expect_get_specific_violations()
             .returning(|_| Ok(vec![]));
-
         entry_point
             .expect_validate_user_op_signature()
-            .returning(|_, _| Ok(AggregatorOut::NotNeeded));
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
 
-        let user_operation = UserOperationBuilder::new(&ChainSpec::default(),UserOperationRequiredFields {
-            sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-            nonce: U256::from(264),
-            init_code: Bytes::default(),
-            call_data: bytes!("b61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000"),
-            call_gas_limit: 9100,
-            verification_gas_limit: 64805,
-            pre_verification_gas: 46128,
-            max_fee_per_gas: 105000100,
-            max_priority_fee_per_gas: 105000000,
-            paymaster_and_data: Bytes::default(),
-            signature: bytes!("98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c"),
-        }).build();
+        let settings = Settings {
+            allow_undeployed_contract_probes: true,
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            entry_point,
+            context,
+            settings,
+            HashMap::from([(B256::ZERO, MempoolConfig::default())]),
+            vec![],
+            0,
+            None,
+        );
+        let result = simulator
+            .simulate_validation(test_op(), B256::ZERO, None)
+            .await
+            .unwrap();
+        assert_eq!(result.probed_undeployed_addresses, HashSet::from([probed]));
+    }
 
-        let simulator = create_simulator(provider, entry_point, context);
+    #[test]
+    fn test_time_range_with_mock_clock() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let clock = Arc::new(MockClock::new(1000.into()));
+        let simulator =
+            create_simulator(provider, entry_point, context_provider).with_clock(clock.clone());
+
+        // not yet valid, and expires within the buffer
+        let mut context = get_test_context();
+        context.entry_point_out.return_info.valid_after = 2000.into();
+        context.entry_point_out.return_info.valid_until = 1030.into();
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![SimulationViolation::InvalidTimeRange(
+                1030.into(),
+                2000.into()
+            )]
+        );
+
+        // valid once valid_after is reached
+        clock.advance(std::time::Duration::from_secs(1000));
+        let mut context = get_test_context();
+        context.entry_point_out.return_info.valid_after = 2000.into();
+        context.entry_point_out.return_info.valid_until = 1030.into();
+        assert_eq!(
+            simulator.gather_context_violations(&mut context).unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paymaster_sponsorship_expired() {
+        let (provider, entry_point, mut context_provider) = create_base_config();
+        context_provider.expect_get_context().never();
+        let paymaster = address!("8abb13360b87be5eeb1b98647a016add927a136c");
+
+        // valid until is the first 8 bytes of the paymaster data
+        fn decode(data: &[u8]) -> Option<ValidTimeRange> {
+            let valid_until = u64::from_be_bytes(data.get(..8)?.try_into().ok()?);
+            Some(ValidTimeRange {
+                valid_after: 0.into(),
+                valid_until: valid_until.into(),
+            })
+        }
+        let simulator = create_simulator(provider, entry_point, context_provider)
+            .with_clock(Arc::new(MockClock::new(1000.into())))
+            .with_paymaster_time_range_decoder(
+                paymaster,
+                Arc::new(decode as fn(&[u8]) -> Option<ValidTimeRange>),
+            );
+        let op_with = |paymaster: Address, valid_until: u64| UserOperation {
+            paymaster_and_data: [paymaster.as_slice(), &valid_until.to_be_bytes()]
+                .concat()
+                .into(),
+            ..test_op()
+        };
+        let expired =
+            SimulationViolation::PaymasterSponsorshipExpired(paymaster, 999.into(), 0.into());
+
+        assert_eq!(
+            simulator.check_paymaster_sponsorship(&op_with(paymaster, 999)),
+            Some(expired.clone())
+        );
+        assert_eq!(
+            simulator.check_paymaster_sponsorship(&op_with(paymaster, 1000)),
+            None
+        );
+        // paymasters without a decoder aren't checked
+        assert_eq!(
+            simulator.check_paymaster_sponsorship(&op_with(Address::random(), 999)),
+            None
+        );
+
+        // rejected without tracing
         let res = simulator
-            .simulate_validation(user_operation, B256::ZERO, None)
+            .simulate_validation(op_with(paymaster, 999), B256::ZERO, None)
             .await;
-        assert!(res.is_ok());
+        assert!(matches!(
+            res,
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(violations),
+                ..
+            }) if violations == vec![expired]
+        ));
     }
 
     #[tokio::test]
-    async fn test_gather_context_violations() {
+    async fn test_invalid_entity_storage_access() {
         let (provider, mut entry_point, mut context_provider) = create_base_config();
         entry_point
             .expect_address()
@@ -913,25 +4872,11 @@ mod tests {
 
         let mut context = get_test_context();
 
-        // add forbidden opcodes and precompiles
-        context.tracer_out.phases[1].forbidden_opcodes_used = vec![
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
-        ];
-        context.tracer_out.phases[1].forbidden_precompiles_used = vec![String::from(
-            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
-        )];
-
-        // add a storage access for a random unrelated address
+        // account writes to a non-associated slot on the paymaster
         let mut writes: HashMap<U256, u64> = HashMap::new();
-
-        writes.insert(
-            uint!(0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256),
-            1,
-        );
-
+        writes.insert(uint!(1_U256), 1);
         context.tracer_out.phases[1].storage_accesses.insert(
-            address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+            address!("8abb13360b87be5eeb1b98647a016add927a136c"),
             AccessInfo {
                 reads: HashMap::new(),
                 writes,
@@ -943,45 +4888,54 @@ mod tests {
 
         assert_eq!(
             res.unwrap(),
-            vec![
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ViolationOpCode(Opcode::GASPRICE),
-                ),
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ViolationOpCode(Opcode::COINBASE),
-                ),
-                SimulationViolation::UsedForbiddenPrecompile(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    address!("0000000000000000000000000000000000000019"),
-                ),
-                SimulationViolation::InvalidStorageAccess(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    StorageSlot {
-                        address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
-                        slot: uint!(
-                            0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256
-                        )
-                    }
-                ),
-            ]
+            vec![SimulationViolation::InvalidEntityStorageAccess(
+                Entity {
+                    kind: EntityType::Account,
+                    address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                },
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c")
+                },
+                StorageSlot {
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    slot: uint!(1_U256),
+                },
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extra_allowed_precompile() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[1].forbidden_precompiles_used = vec![String::from(
+            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
+        )];
+
+        let settings = Settings {
+            extra_allowed_precompiles: HashSet::from([address!(
+                "0000000000000000000000000000000000000019"
+            )]),
+            ..Default::default()
+        };
+        let simulator = SimulatorImpl::new(
+            provider,
+            ep,
+            context_provider,
+            settings,
+            HashMap::new(),
+            vec![],
+            0,
+            None,
         );
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(res.unwrap(), vec![]);
     }
 
     #[tokio::test]
@@ -1031,6 +4985,58 @@ mod tests {
         assert!(res.unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_banned_environment_opcode() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:TIMESTAMP"),
+            String::from("0x1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6:TIMESTAMP"),
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:NUMBER"),
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:COINBASE"),
+        ];
+
+        let mut simulator = create_simulator(provider, ep, context_provider);
+
+        // forbidden for all entities unless enabled
+        context.entity_infos.paymaster.as_mut().unwrap().is_staked = true;
+        let res = simulator.gather_context_violations(&mut context);
+        assert_eq!(res.unwrap().len(), 4);
+
+        simulator.sim_settings.staked_environment_opcodes_allowed = true;
+        context.entity_infos.paymaster.as_mut().unwrap().is_staked = false;
+        let res = simulator.gather_context_violations(&mut context);
+
+        // unstaked causes errors, one per opcode
+        let paymaster = Entity::paymaster(address!("8abb13360b87be5eeb1b98647a016add927a136c"));
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                SimulationViolation::UsedBannedEnvironmentOpcode(
+                    paymaster,
+                    ViolationOpCode(Opcode::TIMESTAMP)
+                ),
+                SimulationViolation::UsedBannedEnvironmentOpcode(
+                    paymaster,
+                    ViolationOpCode(Opcode::NUMBER)
+                ),
+                SimulationViolation::UsedBannedEnvironmentOpcode(
+                    paymaster,
+                    ViolationOpCode(Opcode::COINBASE)
+                ),
+            ]
+        );
+
+        // staked causes no errors
+        context.entity_infos.paymaster.as_mut().unwrap().is_staked = true;
+        let res = simulator.gather_context_violations(&mut context);
+        assert!(res.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_factory_staking() {
         let (provider, mut ep, mut context_provider) = create_base_config();
@@ -0,0 +1,35 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_types::ValidTimeRange;
+
+/// Operator defined decoder of the sponsorship time window a paymaster encodes in its
+/// paymaster data
+///
+/// Used by the simulator to reject operations with an expired sponsorship before tracing.
+/// Encodings vary by paymaster, so decoders are registered per paymaster address.
+pub trait PaymasterTimeRangeDecoder: Debug + Send + Sync {
+    /// Returns the time range encoded in `paymaster_data`, if it can be decoded.
+    ///
+    /// `paymaster_data` excludes the paymaster address and, for v0.7, its gas limits.
+    /// As in the entry point, a zero `validUntil` should be decoded as `Timestamp::MAX`.
+    fn decode(&self, paymaster_data: &[u8]) -> Option<ValidTimeRange>;
+}
+
+impl PaymasterTimeRangeDecoder for fn(&[u8]) -> Option<ValidTimeRange> {
+    fn decode(&self, paymaster_data: &[u8]) -> Option<ValidTimeRange> {
+        self(paymaster_data)
+    }
+}
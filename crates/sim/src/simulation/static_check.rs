@@ -0,0 +1,108 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use rundler_types::{pool::SimulationViolation, UserOperation};
+
+/// Validate the static fields of a user operation, i.e. the checks that don't need any
+/// chain state.
+///
+/// Run before simulating so that garbage operations are rejected without spending a
+/// trace call. Returns all violations found.
+pub fn validate_static<UO: UserOperation>(op: &UO) -> Result<(), Vec<SimulationViolation>> {
+    let mut violations = vec![];
+
+    if op.sender().is_zero() {
+        violations.push(SimulationViolation::ZeroSender);
+    }
+    if op.verification_gas_limit() == 0 {
+        violations.push(SimulationViolation::ZeroGasLimit(
+            "verificationGasLimit".to_string(),
+        ));
+    }
+    // An operation without call data doesn't need any call gas
+    if op.call_gas_limit() == 0 && !op.call_data().is_empty() {
+        violations.push(SimulationViolation::ZeroGasLimit(
+            "callGasLimit".to_string(),
+        ));
+    }
+    // A zero priority fee is valid on some networks, only the max fee is checked
+    if op.max_fee_per_gas() == 0 {
+        violations.push(SimulationViolation::ZeroMaxFeePerGas);
+    }
+    if op.has_malformed_init_code() {
+        violations.push(SimulationViolation::MalformedInitCode);
+    }
+    if op.has_malformed_paymaster_and_data() {
+        violations.push(SimulationViolation::MalformedPaymasterAndData);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, bytes, Address};
+    use rundler_types::v0_6;
+
+    use super::*;
+
+    fn valid_op() -> v0_6::UserOperation {
+        v0_6::UserOperation {
+            sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+            verification_gas_limit: 10_000,
+            max_fee_per_gas: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_static() {
+        assert_eq!(validate_static(&valid_op()), Ok(()));
+
+        let op = v0_6::UserOperation {
+            init_code: address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789")
+                .to_vec()
+                .into(),
+            paymaster_and_data: address!("8abb13360b87be5eeb1b98647a016add927a136c")
+                .to_vec()
+                .into(),
+            ..valid_op()
+        };
+        assert_eq!(validate_static(&op), Ok(()));
+
+        let op = v0_6::UserOperation {
+            sender: Address::ZERO,
+            call_data: bytes!("b61d27f6"),
+            verification_gas_limit: 0,
+            max_fee_per_gas: 0,
+            init_code: bytes!("5ff137d4"),
+            paymaster_and_data: bytes!("8abb1336"),
+            ..valid_op()
+        };
+        assert_eq!(
+            validate_static(&op),
+            Err(vec![
+                SimulationViolation::ZeroSender,
+                SimulationViolation::ZeroGasLimit("verificationGasLimit".to_string()),
+                SimulationViolation::ZeroGasLimit("callGasLimit".to_string()),
+                SimulationViolation::ZeroMaxFeePerGas,
+                SimulationViolation::MalformedInitCode,
+                SimulationViolation::MalformedPaymasterAndData,
+            ])
+        );
+    }
+}
@@ -0,0 +1,118 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use rundler_provider::{
+    EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions, GethDebugTracingOptions,
+    TransactionRequest,
+};
+use serde::{Deserialize, Serialize};
+
+// Minimal JS tracer used to probe for custom tracer support
+const PROBE_TRACER: &str = "{result: function() { return {}; }, fault: function() {}}";
+
+/// How validation is traced
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Deserialize,
+    Serialize,
+    parse_display::Display,
+    parse_display::FromStr,
+)]
+#[display(style = "snake_case")]
+pub enum TracerMode {
+    /// The bundler's custom JS tracer
+    #[default]
+    Custom,
+    /// The provider's default struct log tracer, for providers without custom tracer support.
+    ///
+    /// Only supported for entry point v0.6.
+    StructLogs,
+    /// Detect the mode from the provider's capabilities at startup, see [`detect_tracer_mode`]
+    Auto,
+}
+
+/// Detects the tracer mode supported by the provider.
+///
+/// Returns [`TracerMode::Custom`] if the provider runs custom JS tracers, else
+/// [`TracerMode::StructLogs`] if it runs the default struct log tracer. Falls back to
+/// [`TracerMode::Custom`] if neither probe succeeds, so that errors surface when tracing.
+pub async fn detect_tracer_mode<P: EvmProvider>(provider: &P) -> TracerMode {
+    let probe = |tracer| {
+        provider.debug_trace_call(
+            TransactionRequest::default(),
+            None,
+            GethDebugTracingCallOptions {
+                tracing_options: GethDebugTracingOptions {
+                    tracer,
+                    ..Default::default()
+                },
+                state_overrides: None,
+                block_overrides: None,
+            },
+        )
+    };
+
+    match probe(Some(GethDebugTracerType::JsTracer(
+        PROBE_TRACER.to_string(),
+    )))
+    .await
+    {
+        Ok(_) => TracerMode::Custom,
+        Err(error) => {
+            tracing::debug!("provider does not support custom tracers: {error:?}");
+            if probe(None).await.is_ok() {
+                TracerMode::StructLogs
+            } else {
+                TracerMode::Custom
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_provider::{DefaultFrame, GethTrace, MockEvmProvider, ProviderError};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_detect_tracer_mode() {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .returning(|_, _, _| Ok(GethTrace::JS(serde_json::json!({}))));
+        assert_eq!(detect_tracer_mode(&provider).await, TracerMode::Custom);
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .returning(|_, _, options| match options.tracing_options.tracer {
+                Some(_) => Err(ProviderError::Other(anyhow::anyhow!(
+                    "tracer not supported"
+                ))),
+                None => Ok(GethTrace::Default(DefaultFrame::default())),
+            });
+        assert_eq!(detect_tracer_mode(&provider).await, TracerMode::StructLogs);
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .returning(|_, _, _| Err(ProviderError::Other(anyhow::anyhow!("not found"))));
+        assert_eq!(detect_tracer_mode(&provider).await, TracerMode::Custom);
+    }
+}
@@ -17,7 +17,11 @@ use alloy_primitives::B256;
 use rundler_provider::{AggregatorOut, EntryPoint, SignatureAggregator, SimulationProvider};
 use rundler_types::{pool::SimulationViolation, EntityInfos, UserOperation, ValidTimeRange};
 
-use crate::{SimulationError, SimulationResult, Simulator, ViolationError};
+use super::simulator;
+use crate::{
+    validate_static, SimulationError, SimulationResult, Simulator, ViolationError,
+    ViolationPriority,
+};
 
 /// An unsafe simulator that can be used in place of a regular simulator
 /// to extract the information needed from simulation while avoiding the use
@@ -27,14 +31,16 @@ use crate::{SimulationError, SimulationResult, Simulator, ViolationError};
 /// performed.
 pub struct UnsafeSimulator<UO, E> {
     entry_point: E,
+    violation_priority: Option<ViolationPriority>,
     _uo_type: PhantomData<UO>,
 }
 
 impl<UO, E> UnsafeSimulator<UO, E> {
-    /// Creates a new unsafe simulator
-    pub fn new(entry_point: E) -> Self {
+    /// Creates a new unsafe simulator, ordering violations by `violation_priority` if set
+    pub fn new(entry_point: E, violation_priority: Option<ViolationPriority>) -> Self {
         Self {
             entry_point,
+            violation_priority,
             _uo_type: PhantomData,
         }
     }
@@ -48,97 +54,138 @@ where
 {
     type UO = UO;
 
-    // Run an unsafe simulation
-    //
-    // The only validation checks that are performed are signature checks
     async fn simulate_validation(
         &self,
         op: UO,
         block_hash: B256,
         _expected_code_hash: Option<B256>,
     ) -> Result<SimulationResult, SimulationError> {
-        tracing::info!("Performing unsafe simulation");
-
-        // simulate the validation
-        let validation_result = self
-            .entry_point
-            .simulate_validation(op.clone(), Some(block_hash.into()))
-            .await?;
-
-        let validation_result = match validation_result {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(SimulationError {
-                    violation_error: vec![SimulationViolation::ValidationRevert(err)].into(),
-                    entity_infos: None,
-                });
-            }
-        };
-
-        let valid_until = if validation_result.return_info.valid_until == 0.into() {
-            u64::MAX.into()
+        let res = if let Err(violations) = validate_static(&op) {
+            Err(SimulationError {
+                violation_error: violations.into(),
+                entity_infos: None,
+            })
         } else {
-            validation_result.return_info.valid_until
+            tracing::info!("Performing unsafe simulation");
+            simulate_validation_unsafe(&self.entry_point, op, block_hash, false).await
         };
+        res.map_err(|mut error| {
+            error.prioritize(self.violation_priority.as_ref());
+            error
+        })
+    }
 
-        let pre_op_gas = validation_result.return_info.pre_op_gas;
-        let valid_time_range =
-            ValidTimeRange::new(validation_result.return_info.valid_after, valid_until);
-        let requires_post_op = !validation_result.return_info.paymaster_context.is_empty();
-
-        let mut entity_infos = EntityInfos::default();
-        entity_infos.set_sender(op.sender(), false);
-        if let Some(f) = op.factory() {
-            entity_infos.set_factory(f, false);
-        }
-        if let Some(p) = op.paymaster() {
-            entity_infos.set_paymaster(p, false);
+    async fn simulate_validation_in_mempools(
+        &self,
+        op: UO,
+        block_hash: B256,
+        expected_code_hash: Option<B256>,
+        mempool_ids: Vec<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        // Unsafe simulation only supports the default mempool
+        if let Some(unknown) = mempool_ids.iter().find(|id| **id != B256::ZERO) {
+            Err(anyhow::anyhow!("unknown mempool id {unknown:?}"))?;
         }
-        if let Some(a) = validation_result.aggregator_info {
-            entity_infos.set_aggregator(a.address, false);
+        self.simulate_validation(op, block_hash, expected_code_hash)
+            .await
+    }
+}
+
+/// Run an unsafe simulation using a plain `simulateValidation` call.
+///
+/// The only validation checks that are performed are signature checks, and
+/// account and paymaster signature failures are ignored if `signature_bypassed`
+pub(crate) async fn simulate_validation_unsafe<UO, E>(
+    entry_point: &E,
+    op: UO,
+    block_hash: B256,
+    signature_bypassed: bool,
+) -> Result<SimulationResult, SimulationError>
+where
+    UO: UserOperation,
+    E: EntryPoint + SimulationProvider<UO = UO> + SignatureAggregator<UO = UO>,
+{
+    // simulate the validation
+    let validation_result = entry_point
+        .simulate_validation(op.clone(), Some(block_hash.into()))
+        .await?;
+
+    let validation_result = match validation_result {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(SimulationError {
+                violation_error: vec![SimulationViolation::ValidationRevert(err)].into(),
+                entity_infos: None,
+            });
         }
+    };
+
+    let valid_until = if validation_result.return_info.valid_until == 0.into() {
+        u64::MAX.into()
+    } else {
+        validation_result.return_info.valid_until
+    };
+
+    let pre_op_gas = validation_result.return_info.pre_op_gas;
+    let valid_time_range =
+        ValidTimeRange::new(validation_result.return_info.valid_after, valid_until);
+    let requires_post_op = !validation_result.return_info.paymaster_context.is_empty();
+
+    let mut entity_infos = EntityInfos::default();
+    entity_infos.set_sender(op.sender(), false);
+    if let Some(f) = op.factory() {
+        entity_infos.set_factory(f, false);
+    }
+    if let Some(p) = op.paymaster() {
+        entity_infos.set_paymaster(p, false);
+    }
+    if let Some(a) = validation_result.aggregator_info {
+        entity_infos.set_aggregator(a.address, false);
+    }
 
-        let mut violations = vec![];
-
-        let aggregator = if let Some(aggregator_info) = validation_result.aggregator_info {
-            let agg_out = self
-                .entry_point
-                .validate_user_op_signature(aggregator_info.address, op)
-                .await?;
-
-            match agg_out {
-                AggregatorOut::NotNeeded => None,
-                AggregatorOut::SuccessWithInfo(info) => Some(info),
-                AggregatorOut::ValidationReverted => {
-                    violations.push(SimulationViolation::AggregatorValidationFailed);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+    let mut violations = vec![];
 
-        if validation_result.return_info.account_sig_failed
-            || validation_result.return_info.paymaster_sig_failed
-        {
-            violations.push(SimulationViolation::InvalidSignature);
-        }
+    let mut aggregators = vec![];
+    if let Some(aggregator_info) = validation_result.aggregator_info {
+        let agg_out = entry_point
+            .validate_user_op_signature(aggregator_info.address, op, Some(block_hash.into()))
+            .await?;
 
-        if !violations.is_empty() {
-            Err(SimulationError {
-                violation_error: ViolationError::Violations(violations),
-                entity_infos: Some(entity_infos),
-            })?
-        } else {
-            Ok(SimulationResult {
-                mempools: vec![B256::ZERO],
-                pre_op_gas,
-                valid_time_range,
-                requires_post_op,
-                entity_infos,
-                aggregator,
-                ..Default::default()
-            })
+        match agg_out {
+            AggregatorOut::NotNeeded => {}
+            AggregatorOut::SuccessWithInfo(info) => aggregators.push(info),
+            AggregatorOut::ValidationReverted(revert_data) => {
+                violations.push(SimulationViolation::AggregatorValidationFailed(
+                    simulator::aggregator_revert_reason(&revert_data),
+                ));
+            }
         }
     }
+
+    if !signature_bypassed
+        && (validation_result.return_info.account_sig_failed
+            || validation_result.return_info.paymaster_sig_failed)
+    {
+        violations.push(SimulationViolation::InvalidSignature);
+    }
+
+    if !violations.is_empty() {
+        Err(SimulationError {
+            violation_error: ViolationError::Violations(violations),
+            entity_infos: Some(entity_infos),
+        })?
+    } else {
+        Ok(SimulationResult {
+            mempools: vec![B256::ZERO],
+            pre_op_gas,
+            valid_time_range,
+            requires_post_op,
+            paymaster_context: validation_result.return_info.paymaster_context,
+            entity_infos,
+            aggregators,
+            tracing_skipped: true,
+            signature_bypassed,
+            ..Default::default()
+        })
+    }
 }
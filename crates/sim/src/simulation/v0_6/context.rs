@@ -13,11 +13,11 @@
 
 use std::collections::HashSet;
 
-use alloy_primitives::hex;
+use alloy_primitives::{hex, Selector, U256};
 use alloy_sol_types::SolError;
 use anyhow::Context;
 use rundler_contracts::v0_6::IEntryPoint::FailedOp;
-use rundler_provider::{BlockId, EvmProvider, SimulationProvider};
+use rundler_provider::{BlockId, EntryPoint, EvmProvider, SimulationProvider};
 use rundler_types::{
     pool::SimulationViolation, v0_6::UserOperation, EntityType,
     UserOperation as UserOperationTrait, ValidationOutput,
@@ -29,7 +29,7 @@ use super::{
 };
 use crate::{
     simulation::context::{
-        self as sim_context, ValidationContext,
+        self as sim_context, TraceBudget, TracerOutput, ValidationContext,
         ValidationContextProvider as ValidationContextProviderTrait,
     },
     SimulationSettings, ViolationError,
@@ -52,13 +52,14 @@ where
         &self,
         op: Self::UO,
         block_id: BlockId,
+        prefund_override: Option<U256>,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>> {
         let factory_address = op.factory();
         let sender_address = op.sender;
         let paymaster_address = op.paymaster();
-        let tracer_out = self
+        let mut tracer_out = self
             .simulate_validation_tracer
-            .trace_simulate_validation(op.clone(), block_id)
+            .trace_simulate_validation(op.clone(), block_id, prefund_override)
             .await?;
         let num_phases = tracer_out.phases.len() as u32;
         // Check if there are too many phases here, then check too few at the
@@ -71,6 +72,33 @@ where
                 SimulationViolation::WrongNumberOfPhases(num_phases),
             ]))?
         }
+        // Too few phases without a phase failing can also mean that the trace ran
+        // out of gas before reaching the end of execution. Retry with more gas to
+        // tell this apart from a broken entry point.
+        if num_phases < 3
+            && self.sim_settings.phase_retry_enabled
+            && !reverted_with_failed_op(&tracer_out)
+        {
+            tracer_out = self
+                .simulate_validation_tracer
+                .trace_simulate_validation_with_gas_factor(
+                    op.clone(),
+                    block_id,
+                    prefund_override,
+                    self.sim_settings.phase_retry_gas_factor,
+                )
+                .await?;
+            let num_phases = tracer_out.phases.len() as u32;
+            if num_phases != 3 {
+                Err(ViolationError::Violations(vec![
+                    SimulationViolation::WrongNumberOfPhases(num_phases),
+                ]))?
+            }
+        }
+        allow_entry_point_methods(
+            &mut tracer_out,
+            &self.sim_settings.allowed_entry_point_methods,
+        );
         let Some(ref revert_data) = tracer_out.revert_data else {
             Err(ViolationError::Violations(vec![
                 SimulationViolation::DidNotRevert,
@@ -92,10 +120,11 @@ where
                     last_entity_type,
                     failed_op.reason,
                     entity_addr,
+                    revert_data_bytes.into(),
                 ),
             ]))?
         }
-        let Ok(entry_point_out) = ValidationOutput::decode_v0_6(revert_data_bytes) else {
+        let Ok(entry_point_out) = ValidationOutput::decode_v0_6(&revert_data_bytes) else {
             let entity_addr = match last_entity_type {
                 EntityType::Factory => factory_address,
                 EntityType::Paymaster => paymaster_address,
@@ -103,7 +132,11 @@ where
                 _ => None,
             };
             Err(ViolationError::Violations(vec![
-                SimulationViolation::UnintendedRevert(last_entity_type, entity_addr),
+                SimulationViolation::UnintendedRevert(
+                    last_entity_type,
+                    entity_addr,
+                    revert_data_bytes.into(),
+                ),
             ]))?
         };
         let entity_infos = sim_context::infos_from_validation_output(
@@ -140,14 +173,6 @@ where
             ..
         } = &context;
 
-        if context.op.paymaster().is_some()
-            && !entry_point_out.return_info.paymaster_context.is_empty()
-            && !context.entity_infos.paymaster.unwrap().is_staked
-        {
-            // [EREP-050] (only v0.6)
-            violations.push(SimulationViolation::UnstakedPaymasterContext);
-        }
-
         // v0.6 doesn't distinguish between the different types of signature failures
         // both of these will be set to true if the signature failed.
         if entry_point_out.return_info.account_sig_failed
@@ -175,18 +200,54 @@ where
     }
 }
 
+fn reverted_with_failed_op(tracer_out: &TracerOutput) -> bool {
+    tracer_out
+        .revert_data
+        .as_ref()
+        .and_then(|revert_data| hex::decode(revert_data).ok())
+        .is_some_and(|revert_data| FailedOp::abi_decode(&revert_data, false).is_ok())
+}
+
+// The tracer bans calls to every entry point method other than `depositTo`. Drop the methods
+// the operator allows, clearing the ban for phases that only called allowed methods.
+fn allow_entry_point_methods(tracer_out: &mut TracerOutput, allowed: &HashSet<Selector>) {
+    for phase in &mut tracer_out.phases {
+        if !phase.called_banned_entry_point_method || phase.banned_entry_point_methods.is_empty() {
+            continue;
+        }
+        phase.banned_entry_point_methods.retain(|method| {
+            !method
+                .parse::<Selector>()
+                .is_ok_and(|selector| allowed.contains(&selector))
+        });
+        if phase.banned_entry_point_methods.is_empty() {
+            phase.called_banned_entry_point_method = false;
+        }
+    }
+}
+
 impl<P, E> ValidationContextProvider<SimulateValidationTracerImpl<P, E>>
 where
     P: EvmProvider,
-    E: SimulationProvider<UO = UserOperation>,
+    E: EntryPoint + SimulationProvider<UO = UserOperation>,
 {
     /// Creates a new `ValidationContextProvider` for entry point v0.6 with the given provider and entry point.
-    pub(crate) fn new(provider: P, entry_point: E, sim_settings: SimulationSettings) -> Self {
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        chain_id: u64,
+        sim_settings: SimulationSettings,
+    ) -> Self {
         Self {
             simulate_validation_tracer: SimulateValidationTracerImpl::new(
                 provider,
                 entry_point,
                 sim_settings.tracer_timeout.clone(),
+                chain_id,
+                sim_settings.trace_cache_enabled,
+                sim_settings.tracer_mode,
+                TraceBudget::new(&sim_settings),
+                sim_settings.tracer_gas_overhead,
             ),
             sim_settings,
         }
@@ -195,135 +256,38 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
-    use alloy_primitives::{address, bytes, hex, Bytes, U256};
+    use alloy_primitives::{address, bytes, fixed_bytes, hex, Bytes, U256};
     use alloy_sol_types::SolError;
     use rundler_contracts::v0_6::IEntryPoint::FailedOp;
     use rundler_types::{
         chain::ChainSpec,
         v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
-        Opcode,
     };
-    use sim_context::ContractInfo;
 
     use super::*;
-    use crate::simulation::context::{Phase, TracerOutput};
+    use crate::simulation::v0_6::fixture::{MockSimulateValidationTracer, TracerFixture};
 
     fn get_test_tracer_output() -> TracerOutput {
-        TracerOutput {
-            accessed_contracts: HashMap::from([
-                (
-                    address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-                (
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-                (
-                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    ContractInfo {
-                        header: "0x608060".to_string(),
-                        opcode: Opcode::CALL,
-                        length: 32,
-                    }
-                ),
-            ]),
-            associated_slots_by_address: serde_json::from_str(r#"
-            {
-                "0x0000000000000000000000000000000000000000": [
-                    "0xd5c1ebdd81c5c7bebcd52bc11c8d37f7038b3c64f849c2ca58a022abeab1adae",
-                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
-                ],
-                "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4": [
-                    "0x3072884cc37d411af7360b34f105e1e860b1631783232a4f2d5c094d365cdaab",
-                    "0xf5357e1da3acf909ceaed3492183cbad85a3c9e1f0076495f66d3eed05219bd5",
-                    "0xf264fff4db20d04721712f34a6b5a8bca69a212345e40a92101082e79bdd1f0a"
-                ]
-            }
-            "#).unwrap(),
-            factory_called_create2_twice: false,
-            expected_storage: serde_json::from_str(r#"
-            {
-                "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789": {
-                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5": "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb6": "0x0000000000000000000000000000000000000000000000000000000000000000"
-                }
-            }
-            "#).unwrap(),
-            phases: vec![
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: false,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses: HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                },
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: true,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses:  HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                },
-                Phase {
-                    called_banned_entry_point_method: false,
-                    called_non_entry_point_with_value: false,
-                    forbidden_opcodes_used: vec![],
-                    forbidden_precompiles_used: vec![],
-                    ran_out_of_gas: false,
-                    storage_accesses: HashMap::new(),
-                    undeployed_contract_accesses: vec![],
-                    ext_code_access_info: HashMap::new(),
-                }
-            ],
-            revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
-        }
-    }
-
-    mockall::mock! {
-        Tracer {}
-
-        #[async_trait::async_trait]
-        impl SimulateValidationTracer for Tracer {
-            async fn trace_simulate_validation(
-                &self,
-                op: UserOperation,
-                block_id: BlockId,
-            ) -> anyhow::Result<TracerOutput>;
-        }
+        TracerFixture::new("value_call").load()
     }
 
     #[tokio::test]
     async fn test_create_context_two_phases_unintended_revert() {
-        let mut tracer = MockTracer::new();
-
-        tracer.expect_trace_simulate_validation().returning(|_, _| {
-            let mut tracer_output = get_test_tracer_output();
-            tracer_output.revert_data = Some(hex::encode(
-                FailedOp {
-                    opIndex: U256::from(100),
-                    reason: "AA23 reverted (or OOG)".to_string(),
-                }
-                .abi_encode(),
-            ));
-            Ok(tracer_output)
-        });
+        let mut tracer = MockSimulateValidationTracer::new();
+
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.revert_data = Some(hex::encode(
+                    FailedOp {
+                        opIndex: U256::from(100),
+                        reason: "AA23 reverted (or OOG)".to_string(),
+                    }
+                    .abi_encode(),
+                ));
+                Ok(tracer_output)
+            });
 
         let user_operation = UserOperationBuilder::new(&ChainSpec::default(),UserOperationRequiredFields {
             sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
@@ -345,7 +309,7 @@ mod tests {
         };
 
         let res = context
-            .get_context(user_operation.clone(), BlockId::Number(0.into()))
+            .get_context(user_operation.clone(), BlockId::Number(0.into()), None)
             .await;
 
         assert!(matches!(
@@ -355,9 +319,150 @@ mod tests {
                 Some(&SimulationViolation::UnintendedRevertWithMessage(
                     EntityType::Paymaster,
                     ref reason,
-                    _
+                    _,
+                    _,
                 )) if reason == "AA23 reverted (or OOG)"
             )
         ));
     }
+
+    fn get_test_user_operation() -> UserOperation {
+        UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                nonce: U256::from(264),
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: 9100,
+                verification_gas_limit: 64805,
+                pre_verification_gas: 46128,
+                max_fee_per_gas: 105000100,
+                max_priority_fee_per_gas: 105000000,
+                paymaster_and_data: Bytes::default(),
+                signature: Bytes::default(),
+            },
+        )
+        .build()
+    }
+
+    fn get_test_tracer_output_out_of_gas() -> TracerOutput {
+        let mut tracer_output = get_test_tracer_output();
+        tracer_output.phases.truncate(2);
+        tracer_output.revert_data = None;
+        tracer_output
+    }
+
+    #[tokio::test]
+    async fn test_create_context_from_fixture() {
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: TracerFixture::new("value_call").tracer(),
+            sim_settings: Default::default(),
+        };
+
+        let context = context
+            .get_context(get_test_user_operation(), BlockId::Number(0.into()), None)
+            .await
+            .unwrap();
+
+        assert_eq!(context.tracer_out.phases.len(), 3);
+        assert!(context.tracer_out.phases[1].called_non_entry_point_with_value);
+        assert_eq!(context.entry_point_out.return_info.pre_op_gas, 0x14eff);
+    }
+
+    #[tokio::test]
+    async fn test_create_context_allowed_entry_point_method() {
+        let selector = fixed_bytes!("205c2878");
+        let mut tracer = MockSimulateValidationTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(move |_, _, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.phases[1].called_banned_entry_point_method = true;
+                tracer_output.phases[1].banned_entry_point_methods = vec![selector.to_string()];
+                tracer_output.phases[2].called_banned_entry_point_method = true;
+                tracer_output.phases[2].banned_entry_point_methods = vec!["0x12345678".to_string()];
+                Ok(tracer_output)
+            });
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: SimulationSettings {
+                allowed_entry_point_methods: HashSet::from([
+                    crate::simulation::DEPOSIT_TO_SELECTOR,
+                    selector,
+                ]),
+                ..Default::default()
+            },
+        };
+
+        let context = context
+            .get_context(get_test_user_operation(), BlockId::Number(0.into()), None)
+            .await
+            .unwrap();
+
+        assert!(!context.tracer_out.phases[1].called_banned_entry_point_method);
+        assert!(context.tracer_out.phases[2].called_banned_entry_point_method);
+        assert_eq!(
+            context.tracer_out.phases[2].banned_entry_point_methods,
+            vec!["0x12345678".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_context_too_few_phases_retry() {
+        let mut tracer = MockSimulateValidationTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _, _| Ok(get_test_tracer_output_out_of_gas()));
+        tracer
+            .expect_trace_simulate_validation_with_gas_factor()
+            .withf(|_, _, _, gas_factor| *gas_factor == 2)
+            .times(1)
+            .returning(|_, _, _, _| Ok(get_test_tracer_output()));
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: SimulationSettings {
+                phase_retry_enabled: true,
+                ..Default::default()
+            },
+        };
+
+        let res = context
+            .get_context(get_test_user_operation(), BlockId::Number(0.into()), None)
+            .await;
+
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_create_context_too_few_phases_after_retry() {
+        let mut tracer = MockSimulateValidationTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _, _| Ok(get_test_tracer_output_out_of_gas()));
+        tracer
+            .expect_trace_simulate_validation_with_gas_factor()
+            .times(1)
+            .returning(|_, _, _, _| Ok(get_test_tracer_output_out_of_gas()));
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: SimulationSettings {
+                phase_retry_enabled: true,
+                ..Default::default()
+            },
+        };
+
+        let res = context
+            .get_context(get_test_user_operation(), BlockId::Number(0.into()), None)
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(ViolationError::Violations(violations))
+                if violations == vec![SimulationViolation::WrongNumberOfPhases(2)]
+        ));
+    }
 }
@@ -0,0 +1,208 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Recorded tracer outputs for golden tests.
+//!
+//! Fixtures are JSON files under `testdata/tracer/v0_6` in this crate, in the same format
+//! the validation tracer returns. Set `RUNDLER_RECORD_TRACER_FIXTURES` when running
+//! tests against a live node to have [`TracerFixture::record_trace`] (re)write them.
+
+use std::{fs, path::PathBuf};
+
+use alloy_primitives::U256;
+use anyhow::Context;
+use rundler_provider::BlockId;
+use rundler_types::v0_6::UserOperation;
+
+use super::tracer::SimulateValidationTracer;
+use crate::simulation::context::TracerOutput;
+
+const FIXTURE_DIR: &str = "testdata/tracer/v0_6";
+const RECORD_ENV_VAR: &str = "RUNDLER_RECORD_TRACER_FIXTURES";
+
+mockall::mock! {
+    pub(super) SimulateValidationTracer {}
+
+    #[async_trait::async_trait]
+    impl SimulateValidationTracer for SimulateValidationTracer {
+        async fn trace_simulate_validation(
+            &self,
+            op: UserOperation,
+            block_id: BlockId,
+            prefund_override: Option<U256>,
+        ) -> anyhow::Result<TracerOutput>;
+
+        async fn trace_simulate_validation_with_gas_factor(
+            &self,
+            op: UserOperation,
+            block_id: BlockId,
+            prefund_override: Option<U256>,
+            gas_factor: u64,
+        ) -> anyhow::Result<TracerOutput>;
+    }
+}
+
+/// A named tracer output recorded to a JSON file.
+#[derive(Debug)]
+pub(super) struct TracerFixture {
+    path: PathBuf,
+}
+
+impl TracerFixture {
+    pub(super) fn new(name: &str) -> Self {
+        Self {
+            path: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join(FIXTURE_DIR)
+                .join(format!("{name}.json")),
+        }
+    }
+
+    /// Loads the recorded tracer output, panicking if the fixture is missing or malformed.
+    pub(super) fn load(&self) -> TracerOutput {
+        let json = fs::read_to_string(&self.path)
+            .unwrap_or_else(|e| panic!("should read fixture {}: {e}", self.path.display()));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("should parse fixture {}: {e}", self.path.display()))
+    }
+
+    /// Returns a mock tracer whose `trace_simulate_validation` always returns this fixture.
+    pub(super) fn tracer(&self) -> MockSimulateValidationTracer {
+        let out = self.load();
+        let mut tracer = MockSimulateValidationTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(move |_, _, _| Ok(out.clone()));
+        tracer
+    }
+
+    /// Writes `out` to the fixture file, replacing any previous recording.
+    pub(super) fn record(&self, out: &TracerOutput) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).context("should create fixture directory")?;
+        }
+        let json = serde_json::to_string_pretty(out)?;
+        fs::write(&self.path, json + "\n")
+            .with_context(|| format!("should write fixture {}", self.path.display()))
+    }
+
+    /// Traces `op` with a real tracer, recording the output to the fixture file if
+    /// `RUNDLER_RECORD_TRACER_FIXTURES` is set.
+    pub(super) async fn record_trace(
+        &self,
+        tracer: &impl SimulateValidationTracer,
+        op: UserOperation,
+        block_id: BlockId,
+    ) -> anyhow::Result<TracerOutput> {
+        let out = tracer.trace_simulate_validation(op, block_id, None).await?;
+        if std::env::var_os(RECORD_ENV_VAR).is_some() {
+            self.record(&out)?;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rundler_provider::{AlloyEntryPointV0_6, AlloyEvmProvider};
+    use rundler_types::chain::ChainSpec;
+
+    use super::*;
+    use crate::simulation::{
+        context::TraceBudget, v0_6::tracer::SimulateValidationTracerImpl, TracerMode,
+    };
+
+    #[test]
+    fn test_record_round_trip() {
+        let out = TracerFixture::new("value_call").load();
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = TracerFixture {
+            path: dir.path().join("round_trip.json"),
+        };
+
+        fixture.record(&out).unwrap();
+        let loaded = fixture.load();
+
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&out).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_trace_returns_output() {
+        let fixture = TracerFixture::new("value_call");
+        let out = fixture
+            .record_trace(
+                &fixture.tracer(),
+                UserOperation::default(),
+                BlockId::Number(0.into()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(out.phases.len(), 3);
+    }
+
+    // Run locally against a node with the v0.6 entry point deployed with
+    // `NODE_HTTP=<url> cargo test -p rundler-sim -- --ignored`, and additionally set
+    // `RUNDLER_RECORD_TRACER_FIXTURES` to write the `default_op` fixture.
+    #[tokio::test]
+    #[ignore]
+    async fn test_record_trace_live() {
+        let node_http = std::env::var("NODE_HTTP").expect("NODE_HTTP should be set");
+        let chain_spec = ChainSpec::default();
+        let provider = Arc::new(
+            rundler_provider::new_alloy_provider(
+                &node_http,
+                rundler_provider::DEFAULT_PROVIDER_SERVICE_NAME,
+            )
+            .unwrap(),
+        );
+        let (da_gas_oracle, _) =
+            rundler_provider::new_alloy_da_gas_oracle(&chain_spec, provider.clone());
+        let entry_point = AlloyEntryPointV0_6::new(
+            chain_spec.clone(),
+            10_000_000,
+            20_000_000,
+            20_000_000,
+            provider.clone(),
+            da_gas_oracle,
+        );
+        let tracer = SimulateValidationTracerImpl::new(
+            AlloyEvmProvider::new(provider),
+            entry_point,
+            "10s".to_string(),
+            chain_spec.id,
+            false,
+            TracerMode::Custom,
+            TraceBudget::default(),
+            0,
+        );
+
+        let fixture = TracerFixture::new("default_op");
+        let out = fixture
+            .record_trace(&tracer, UserOperation::default(), BlockId::latest())
+            .await
+            .unwrap();
+
+        assert!(!out.phases.is_empty());
+        if std::env::var_os(RECORD_ENV_VAR).is_some() {
+            assert_eq!(
+                serde_json::to_value(fixture.load()).unwrap(),
+                serde_json::to_value(&out).unwrap()
+            );
+        }
+    }
+}
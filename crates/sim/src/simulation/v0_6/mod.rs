@@ -14,6 +14,9 @@
 mod context;
 pub(crate) use context::ValidationContextProvider;
 
+#[cfg(test)]
+mod fixture;
+mod struct_logs;
 mod tracer;
 
 /// Required buffer for verification gas limit when targeting the 0.6 entrypoint contract
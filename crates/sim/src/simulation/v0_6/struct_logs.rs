@@ -0,0 +1,491 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Reconstruction of the validation tracer's output from default struct logs.
+//!
+//! Used for providers that support `debug_traceCall` but not custom JS tracers. Mirrors
+//! the checks of `validationTracerV0_6.ts` as closely as the opcode stream allows, with
+//! these differences:
+//! - Contract code isn't available, so accessed contracts have an empty header and
+//!   a contract is considered undeployed if a call to it doesn't enter a frame, or
+//!   `EXTCODESIZE` returns zero.
+//! - Contracts deployed by the factory with `CREATE` are recorded with empty code, so they
+//!   never match an allowlisted code hash.
+
+use std::collections::{BTreeMap, HashMap};
+
+use alloy_primitives::{address, fixed_bytes, hex, Address, Bytes, Selector, B256, U256};
+use rundler_provider::{DefaultFrame, StructLog};
+use rundler_types::Opcode;
+
+use crate::{
+    simulation::context::{
+        AccessInfo, AssociatedSlotsByAddress, ContractInfo, Phase, TracerOutput,
+    },
+    ExpectedStorage,
+};
+
+const DEPOSIT_TO_SELECTOR: Selector = fixed_bytes!("b760faf9");
+const SSTORE_REQUIRED_GAS: u64 = 2300;
+// Max precompile address 0x10000
+const MAX_PRECOMPILE_ADDRESS: Address = address!("0000000000000000000000000000000000010000");
+const FORBIDDEN_OPCODES: &[Opcode] = &[
+    Opcode::GASPRICE,
+    Opcode::GASLIMIT,
+    Opcode::DIFFICULTY,
+    Opcode::TIMESTAMP,
+    Opcode::BASEFEE,
+    Opcode::BLOCKHASH,
+    Opcode::BLOBBASEFEE,
+    Opcode::BLOBHASH,
+    Opcode::NUMBER,
+    Opcode::SELFBALANCE,
+    Opcode::BALANCE,
+    Opcode::ORIGIN,
+    Opcode::CREATE,
+    Opcode::COINBASE,
+    Opcode::SELFDESTRUCT,
+];
+const ALLOWED_PRECOMPILES: &[Address] = &[
+    address!("0000000000000000000000000000000000000001"), // ecRecover
+    address!("0000000000000000000000000000000000000002"), // SHA2-256
+    address!("0000000000000000000000000000000000000003"), // RIPEMD-160
+    address!("0000000000000000000000000000000000000004"), // identity
+    address!("0000000000000000000000000000000000000005"), // modexp
+    address!("0000000000000000000000000000000000000006"), // ecAdd
+    address!("0000000000000000000000000000000000000007"), // ecMul
+    address!("0000000000000000000000000000000000000008"), // ecPairing
+    address!("0000000000000000000000000000000000000009"), // black2f
+    address!("0000000000000000000000000000000000000100"), // RIP-7212
+];
+
+/// Reconstructs the validation tracer's output from the struct logs of tracing
+/// `simulateValidation` with memory enabled.
+///
+/// `entry_point` is the traced entry point, i.e. the address executing at depth 1, and
+/// `factory` is the operation's factory, used to attribute `CREATE`s during deployment.
+pub(super) fn tracer_output_from_struct_logs(
+    frame: &DefaultFrame,
+    entry_point: Address,
+    factory: Option<Address>,
+) -> TracerOutput {
+    let logs = &frame.struct_logs;
+    let addresses = frame_addresses(logs, entry_point);
+
+    let mut out = TracerOutput {
+        phases: vec![],
+        revert_data: None,
+        accessed_contracts: HashMap::new(),
+        associated_slots_by_address: AssociatedSlotsByAddress(HashMap::new()),
+        factory_called_create2_twice: false,
+        factory_deployed_address: None,
+        factory_created_contracts: HashMap::new(),
+        expected_storage: ExpectedStorage::default(),
+    };
+    let mut phase = Phase::default();
+    let mut factory_create2_count = 0;
+    // first value seen per slot, `None` if the slot was written before it was read
+    let mut first_values: HashMap<Address, BTreeMap<U256, Option<U256>>> = HashMap::new();
+    let mut phase_start_gas = None;
+    let mut last_entry_point_gas = 0;
+
+    for (i, log) in logs.iter().enumerate() {
+        let op = &*log.op;
+        let address = addresses[i];
+        let next = logs.get(i + 1);
+        let entered_frame = next.is_some_and(|next| next.depth > log.depth);
+
+        if log.gas < log.gas_cost || (op == "SSTORE" && log.gas < SSTORE_REQUIRED_GAS) {
+            phase.ran_out_of_gas = true;
+        }
+
+        if log.depth == 1 {
+            phase_start_gas.get_or_insert(log.gas);
+            last_entry_point_gas = log.gas;
+            if op == "NUMBER" {
+                phase.gas_used = phase_start_gas.map_or(0, |start| start - last_entry_point_gas);
+                out.phases.push(std::mem::take(&mut phase));
+                phase_start_gas = Some(last_entry_point_gas);
+            } else if op == "REVERT" {
+                if let (Some(offset), Some(len)) = (peek_usize(log, 0), peek_usize(log, 1)) {
+                    out.revert_data = Some(hex::encode_prefixed(memory_slice(log, offset, len)));
+                }
+            }
+        } else {
+            // The entry point is allowed to freely call GAS, but otherwise a call opcode
+            // must come next
+            if i > 0 && logs[i - 1].op == "GAS" && !is_call(op) {
+                push_unique(&mut phase.forbidden_opcodes_used, format!("{address}:GAS"));
+            }
+            if op
+                .parse::<Opcode>()
+                .is_ok_and(|opcode| FORBIDDEN_OPCODES.contains(&opcode))
+            {
+                push_unique(&mut phase.forbidden_opcodes_used, format!("{address}:{op}"));
+            }
+        }
+
+        // The opcode following an EXTCODE* consumes its result, EXTCODESIZE ISZERO
+        // is the common pattern of checking whether an address is a contract
+        if i >= 2 {
+            let second_last = &logs[i - 2];
+            if is_ext_code(&second_last.op)
+                && !(second_last.op == "EXTCODESIZE" && logs[i - 1].op == "ISZERO")
+            {
+                if let (Some(target), Ok(opcode)) = (
+                    peek_address(second_last, 0),
+                    second_last.op.parse::<Opcode>(),
+                ) {
+                    phase.ext_code_access_info.insert(target, opcode);
+                }
+            }
+        }
+
+        match op {
+            "CREATE2" => {
+                if out.phases.is_empty() {
+                    factory_create2_count += 1;
+                    if entered_frame && out.factory_deployed_address.is_none() {
+                        out.factory_deployed_address = Some(addresses[i + 1]);
+                    }
+                } else {
+                    push_unique(
+                        &mut phase.forbidden_opcodes_used,
+                        format!("{address}:CREATE2"),
+                    );
+                }
+            }
+            "CREATE" => {
+                if out.phases.is_empty() && entered_frame && Some(address) == factory {
+                    out.factory_created_contracts
+                        .insert(addresses[i + 1], Bytes::new());
+                }
+            }
+            "KECCAK256" | "SHA3" => {
+                // keccak(A || x) where A is a 20 byte address left padded to 32 bytes
+                if let (Some(offset), Some(len)) = (peek_usize(log, 0), peek_usize(log, 1)) {
+                    if len >= 32 {
+                        let word = memory_slice(log, offset, 32);
+                        if word[..12].iter().all(|b| *b == 0) {
+                            if let Some(slot) = next.and_then(|next| peek(next, 0)) {
+                                out.associated_slots_by_address
+                                    .0
+                                    .entry(Address::from_slice(&word[12..]))
+                                    .or_default()
+                                    .insert(slot);
+                            }
+                        }
+                    }
+                }
+            }
+            "SLOAD" | "SSTORE" | "TLOAD" | "TSTORE" => {
+                if let Some(slot) = peek(log, 0) {
+                    let access =
+                        phase
+                            .storage_accesses
+                            .entry(address)
+                            .or_insert_with(|| AccessInfo {
+                                reads: HashMap::new(),
+                                writes: HashMap::new(),
+                            });
+                    let value = match op {
+                        "SLOAD" => Some(storage_value(log, slot)),
+                        _ => None,
+                    };
+                    // the entry point can access whatever it wants
+                    if log.depth > 1 {
+                        match value {
+                            Some(value) => {
+                                if !access.reads.contains_key(&slot)
+                                    && !access.writes.contains_key(&slot)
+                                {
+                                    access.reads.insert(slot, value);
+                                }
+                            }
+                            None => *access.writes.entry(slot).or_default() += 1,
+                        }
+                    }
+                    first_values
+                        .entry(address)
+                        .or_default()
+                        .entry(slot)
+                        .or_insert(value);
+                }
+            }
+            _ => {}
+        }
+
+        if is_ext_code(op) || is_call(op) {
+            let target_index = if is_call(op) { 1 } else { 0 };
+            let Some(target) = peek_address(log, target_index) else {
+                continue;
+            };
+            if target < MAX_PRECOMPILE_ADDRESS {
+                if !ALLOWED_PRECOMPILES.contains(&target) {
+                    push_unique(
+                        &mut phase.forbidden_precompiles_used,
+                        format!("{address}:{target}"),
+                    );
+                }
+            } else {
+                let code_length = match op {
+                    "EXTCODESIZE" => next
+                        .and_then(|next| peek(next, 0))
+                        .map(|v| v.saturating_to::<u64>()),
+                    "EXTCODECOPY" | "EXTCODEHASH" => None,
+                    _ => Some(u64::from(entered_frame)),
+                };
+                if !out.accessed_contracts.contains_key(&target)
+                    || phase.undeployed_contract_accesses.contains(&target)
+                {
+                    if code_length == Some(0) {
+                        push_unique(&mut phase.undeployed_contract_accesses, target);
+                        if is_call(op) {
+                            push_unique(&mut phase.undeployed_contract_calls, target);
+                        }
+                    } else {
+                        phase.undeployed_contract_accesses.retain(|a| *a != target);
+                        phase.undeployed_contract_calls.retain(|a| *a != target);
+                    }
+                }
+                out.accessed_contracts.insert(
+                    target,
+                    ContractInfo {
+                        header: "0x".to_string(),
+                        opcode: op.parse().unwrap_or(Opcode::CALL),
+                        length: code_length.unwrap_or_default(),
+                    },
+                );
+            }
+        }
+
+        // calls made by the entry point itself aren't restricted
+        if is_call(op) && log.depth > 1 {
+            let Some(target) = peek_address(log, 1) else {
+                continue;
+            };
+            let (value, args_index) = match op {
+                "CALL" | "CALLCODE" => (peek(log, 2), 3),
+                _ => (None, 2),
+            };
+            if target == entry_point {
+                if let (Some(offset), Some(len)) =
+                    (peek_usize(log, args_index), peek_usize(log, args_index + 1))
+                {
+                    let selector = memory_slice(log, offset, len.min(4));
+                    if selector.len() == 4 && selector[..] != DEPOSIT_TO_SELECTOR[..] {
+                        phase.called_banned_entry_point_method = true;
+                        push_unique(
+                            &mut phase.banned_entry_point_methods,
+                            hex::encode_prefixed(&selector),
+                        );
+                    }
+                }
+            } else if value.is_some_and(|value| value > U256::ZERO) {
+                phase.called_non_entry_point_with_value = true;
+                push_unique(&mut phase.value_call_targets, target);
+            }
+        }
+    }
+
+    phase.gas_used = phase_start_gas.map_or(0, |start| start - last_entry_point_gas);
+    out.phases.push(phase);
+    out.factory_called_create2_twice = factory_create2_count > 1;
+    for (address, slots) in first_values {
+        for (slot, value) in slots {
+            if let Some(value) = value {
+                out.expected_storage.insert(address, slot, value);
+            }
+        }
+    }
+    out
+}
+
+// How the executing address of a frame is known
+#[derive(Clone, Copy)]
+enum FrameAddress {
+    Known(Address),
+    // a contract being created, known once the creating frame resumes
+    Created(Option<Address>),
+    // a DELEGATECALL or CALLCODE, executing at the address of the frame at this index
+    Parent(usize),
+}
+
+// Returns the address executing each log.
+fn frame_addresses(logs: &[StructLog], entry_point: Address) -> Vec<Address> {
+    let mut frames = vec![FrameAddress::Known(entry_point)];
+    let mut open = vec![0];
+    let mut frame_indexes = Vec::with_capacity(logs.len());
+
+    for (i, log) in logs.iter().enumerate() {
+        while (open.len() as u64) > log.depth.max(1) {
+            let returned = open.pop().unwrap();
+            if let FrameAddress::Created(None) = frames[returned] {
+                // the creating frame resumes with the created address on its stack
+                frames[returned] = FrameAddress::Created(peek_address(log, 0));
+            }
+        }
+        if (open.len() as u64) < log.depth && i > 0 {
+            let prev = &logs[i - 1];
+            let parent = *open.last().unwrap();
+            frames.push(match &*prev.op {
+                "CALL" | "STATICCALL" => {
+                    FrameAddress::Known(peek_address(prev, 1).unwrap_or_default())
+                }
+                "DELEGATECALL" | "CALLCODE" => FrameAddress::Parent(parent),
+                _ => FrameAddress::Created(None),
+            });
+            open.push(frames.len() - 1);
+        }
+        frame_indexes.push(*open.last().unwrap());
+    }
+
+    frame_indexes
+        .into_iter()
+        .map(|mut index| loop {
+            match frames[index] {
+                FrameAddress::Known(address) => break address,
+                FrameAddress::Created(address) => break address.unwrap_or_default(),
+                FrameAddress::Parent(parent) => index = parent,
+            }
+        })
+        .collect()
+}
+
+fn is_call(op: &str) -> bool {
+    matches!(op, "CALL" | "CALLCODE" | "DELEGATECALL" | "STATICCALL")
+}
+
+fn is_ext_code(op: &str) -> bool {
+    matches!(op, "EXTCODESIZE" | "EXTCODECOPY" | "EXTCODEHASH")
+}
+
+fn push_unique<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+// Returns the `n`th item from the top of the stack
+fn peek(log: &StructLog, n: usize) -> Option<U256> {
+    let stack = log.stack.as_ref()?;
+    stack.len().checked_sub(n + 1).map(|i| stack[i])
+}
+
+fn peek_usize(log: &StructLog, n: usize) -> Option<usize> {
+    peek(log, n).and_then(|v| usize::try_from(v).ok())
+}
+
+fn peek_address(log: &StructLog, n: usize) -> Option<Address> {
+    peek(log, n).map(|v| Address::from_word(v.into()))
+}
+
+fn storage_value(log: &StructLog, slot: U256) -> U256 {
+    log.storage
+        .as_ref()
+        .and_then(|storage| storage.get(&B256::from(slot)))
+        .map_or(U256::ZERO, |value| U256::from_be_bytes(value.0))
+}
+
+// Reads `len` bytes of memory at `offset`, zero padded past the end of memory
+fn memory_slice(log: &StructLog, offset: usize, len: usize) -> Vec<u8> {
+    let words = log.memory.as_deref().unwrap_or_default();
+    let len = len.min(words.len() * 32);
+    (offset..offset.saturating_add(len))
+        .map(|pos| {
+            words
+                .get(pos / 32)
+                .map(|word| word.strip_prefix("0x").unwrap_or(word))
+                .and_then(|word| word.get((pos % 32) * 2..(pos % 32) * 2 + 2))
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::b256;
+
+    use super::*;
+
+    const ENTRY_POINT: Address = address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789");
+    const SENDER: Address = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+
+    fn log(depth: u64, gas: u64, op: &str, stack: Vec<U256>) -> StructLog {
+        StructLog {
+            depth,
+            gas,
+            gas_cost: 3,
+            op: op.to_string(),
+            stack: Some(stack),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tracer_output_from_struct_logs() {
+        let slot = U256::from(7);
+        let value = b256!("000000000000000000000000000000000000000000000000000000000000002a");
+        let mut sload = log(2, 8_000, "SLOAD", vec![slot]);
+        sload.storage = Some(BTreeMap::from([(B256::from(slot), value)]));
+        let mut revert = log(1, 1_000, "REVERT", vec![U256::from(4), U256::ZERO]);
+        revert.memory = Some(vec![
+            "deadbeef00000000000000000000000000000000000000000000000000000000".to_string(),
+        ]);
+
+        let frame = DefaultFrame {
+            struct_logs: vec![
+                log(1, 10_000, "NUMBER", vec![]),
+                log(
+                    1,
+                    9_000,
+                    "CALL",
+                    vec![
+                        U256::ZERO,
+                        U256::ZERO,
+                        U256::ZERO,
+                        U256::ZERO,
+                        U256::ZERO,
+                        U256::from_be_slice(SENDER.as_slice()),
+                        U256::from(5_000),
+                    ],
+                ),
+                log(2, 8_500, "TIMESTAMP", vec![]),
+                sload,
+                log(2, 7_000, "STOP", vec![]),
+                log(1, 6_000, "NUMBER", vec![U256::from(1)]),
+                revert,
+            ],
+            ..Default::default()
+        };
+
+        let out = tracer_output_from_struct_logs(&frame, ENTRY_POINT, None);
+
+        assert_eq!(out.phases.len(), 3);
+        assert!(out.phases[0].forbidden_opcodes_used.is_empty());
+        let phase = &out.phases[1];
+        assert_eq!(phase.gas_used, 4_000);
+        assert_eq!(
+            phase.forbidden_opcodes_used,
+            vec![format!("{SENDER}:TIMESTAMP")]
+        );
+        assert_eq!(
+            phase.storage_accesses[&SENDER].reads,
+            HashMap::from([(slot, U256::from(42))])
+        );
+        assert!(phase.undeployed_contract_accesses.is_empty());
+        assert_eq!(out.revert_data.as_deref(), Some("0xdeadbeef"));
+        assert_eq!(out.expected_storage.0[&SENDER][&B256::from(slot)], value);
+    }
+}
@@ -12,16 +12,22 @@
 
 use std::{convert::TryFrom, fmt::Debug};
 
+use alloy_primitives::U256;
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use rundler_provider::{
-    BlockId, EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethTrace, SimulationProvider,
+    BlockId, EntryPoint, EvmProvider, GethDebugTracerConfig, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace,
+    SimulationProvider,
 };
-use rundler_types::v0_6::UserOperation;
+use rundler_types::{v0_6::UserOperation, UserOperation as _};
 use serde::Deserialize;
 
-use crate::simulation::context::TracerOutput;
+use super::struct_logs;
+use crate::simulation::{
+    context::{self, TraceBudget, TraceCache, TracerOutput, BUDGET_EXCEEDED_ERROR},
+    TracerMode,
+};
 
 impl TryFrom<GethTrace> for TracerOutput {
     type Error = anyhow::Error;
@@ -38,11 +44,23 @@ impl TryFrom<GethTrace> for TracerOutput {
 /// Trait for tracing the simulation of a user operation.
 #[async_trait]
 pub(super) trait SimulateValidationTracer: Send + Sync {
-    /// Traces the simulation of a user operation.
+    /// Traces the simulation of a user operation, overriding the sender's balance with
+    /// `prefund_override` if set.
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        prefund_override: Option<U256>,
+    ) -> anyhow::Result<TracerOutput>;
+
+    /// Traces the simulation of a user operation with the gas limit of the simulation
+    /// call multiplied by `gas_factor`. Outputs are never cached.
+    async fn trace_simulate_validation_with_gas_factor(
+        &self,
+        op: UserOperation,
+        block_id: BlockId,
+        prefund_override: Option<U256>,
+        gas_factor: u64,
     ) -> anyhow::Result<TracerOutput>;
 }
 
@@ -52,6 +70,11 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
     provider: P,
     entry_point: E,
     tracer_timeout: String,
+    chain_id: u64,
+    trace_cache: Option<TraceCache<TracerOutput>>,
+    tracer_mode: TracerMode,
+    budget: TraceBudget,
+    gas_overhead: u64,
 }
 
 /// Runs the bundler's custom tracer on the entry point's `simulateValidation`
@@ -61,47 +84,161 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
 impl<P, E> SimulateValidationTracer for SimulateValidationTracerImpl<P, E>
 where
     P: EvmProvider,
-    E: SimulationProvider<UO = UserOperation>,
+    E: EntryPoint + SimulationProvider<UO = UserOperation>,
 {
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        prefund_override: Option<U256>,
+    ) -> anyhow::Result<TracerOutput> {
+        // traces with a prefund override are one-offs, they aren't cached
+        let Some(cache) = self
+            .trace_cache
+            .as_ref()
+            .filter(|_| prefund_override.is_none())
+        else {
+            return self.trace(op, block_id, prefund_override, None).await;
+        };
+        let key = context::trace_cache_key(
+            op.hash(*self.entry_point.address(), self.chain_id),
+            &op.signature,
+        );
+        cache
+            .get_or_trace(key, block_id, self.trace(op, block_id, None, None))
+            .await
+    }
+
+    async fn trace_simulate_validation_with_gas_factor(
+        &self,
+        op: UserOperation,
+        block_id: BlockId,
+        prefund_override: Option<U256>,
+        gas_factor: u64,
     ) -> anyhow::Result<TracerOutput> {
-        let (tx, state_override) = self
+        self.trace(op, block_id, prefund_override, Some(gas_factor))
+            .await
+    }
+}
+
+impl<P, E> SimulateValidationTracerImpl<P, E>
+where
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UserOperation>,
+{
+    async fn trace(
+        &self,
+        op: UserOperation,
+        block_id: BlockId,
+        prefund_override: Option<U256>,
+        gas_factor: Option<u64>,
+    ) -> anyhow::Result<TracerOutput> {
+        let sender = op.sender;
+        let factory = op.factory();
+        let (mut tx, mut state_override) = self
             .entry_point
             .get_tracer_simulate_validation_call(op)
             .context("should get simulate validation call")?;
+        context::add_prefund_override(&mut state_override, sender, prefund_override);
+        if let Some(gas_factor) = gas_factor {
+            tx.gas = tx.gas.map(|gas| gas.saturating_mul(gas_factor.into()));
+        }
+        tx.gas = tx
+            .gas
+            .map(|gas| gas.saturating_add(self.gas_overhead.into()));
 
-        TracerOutput::try_from(
-            self.provider
-                .debug_trace_call(
-                    tx,
-                    Some(block_id),
-                    GethDebugTracingCallOptions {
-                        tracing_options: GethDebugTracingOptions {
-                            tracer: Some(GethDebugTracerType::JsTracer(
-                                validation_tracer_js().to_string(),
-                            )),
-                            timeout: Some(self.tracer_timeout.clone()),
-                            ..Default::default()
-                        },
-                        state_overrides: Some(state_override),
-                        block_overrides: None,
-                    },
-                )
-                .await?,
-        )
+        let tracing_options = match self.tracer_mode {
+            TracerMode::StructLogs => GethDebugTracingOptions {
+                config: GethDefaultTracingOptions {
+                    enable_memory: Some(true),
+                    ..Default::default()
+                },
+                timeout: Some(self.tracer_timeout.clone()),
+                ..Default::default()
+            },
+            TracerMode::Custom | TracerMode::Auto => GethDebugTracingOptions {
+                tracer: Some(GethDebugTracerType::JsTracer(
+                    validation_tracer_js().to_string(),
+                )),
+                tracer_config: GethDebugTracerConfig(self.budget.tracer_config()),
+                timeout: Some(self.tracer_timeout.clone()),
+                ..Default::default()
+            },
+        };
+        let trace = self
+            .provider
+            .debug_trace_call(
+                tx,
+                Some(block_id),
+                GethDebugTracingCallOptions {
+                    tracing_options,
+                    state_overrides: Some(state_override),
+                    block_overrides: None,
+                },
+            )
+            .await?;
+
+        match (self.tracer_mode, trace) {
+            (TracerMode::StructLogs, GethTrace::Default(frame)) => {
+                // the default tracer can't be aborted early, so the budget is checked
+                // once the trace completes
+                let storage_accesses = frame
+                    .struct_logs
+                    .iter()
+                    .filter(|log| matches!(&*log.op, "SLOAD" | "SSTORE" | "TLOAD" | "TSTORE"))
+                    .count();
+                if self
+                    .budget
+                    .is_exceeded(frame.struct_logs.len() as u64, storage_accesses as u64)
+                {
+                    bail!(BUDGET_EXCEEDED_ERROR);
+                }
+                Ok(struct_logs::tracer_output_from_struct_logs(
+                    &frame,
+                    *self.entry_point.address(),
+                    factory,
+                ))
+            }
+            (TracerMode::StructLogs, _) => bail!("expected struct logs from default tracer"),
+            (_, trace) => TracerOutput::try_from(trace),
+        }
     }
 }
 
 impl<P, E> SimulateValidationTracerImpl<P, E> {
     /// Creates a new instance of the bundler's custom tracer.
-    pub(crate) fn new(provider: P, entry_point: E, tracer_timeout: String) -> Self {
+    ///
+    /// If `trace_cache_enabled` is set, outputs are cached by user operation hash and
+    /// signature for the most recently traced block, and tracing the same operation at the
+    /// same block again returns the cached output.
+    ///
+    /// With [`TracerMode::StructLogs`] the provider's default tracer is used, and the
+    /// output is reconstructed from its struct logs.
+    ///
+    /// Traces exceeding `budget` fail with an error containing `BUDGET_EXCEEDED_ERROR`.
+    ///
+    /// `gas_overhead` is added to the gas of the entry point's `simulateValidation` call,
+    /// so that the verification gas isn't shared with the entry point's own overhead.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        tracer_timeout: String,
+        chain_id: u64,
+        trace_cache_enabled: bool,
+        tracer_mode: TracerMode,
+        budget: TraceBudget,
+        gas_overhead: u64,
+    ) -> Self {
         Self {
             provider,
             entry_point,
             tracer_timeout,
+            chain_id,
+            trace_cache: trace_cache_enabled.then(TraceCache::new),
+            tracer_mode,
+            budget,
+            gas_overhead,
         }
     }
 }
@@ -109,3 +246,122 @@ impl<P, E> SimulateValidationTracerImpl<P, E> {
 fn validation_tracer_js() -> &'static str {
     include_str!("../../../tracer/dist/validationTracerV0_6.js").trim_end_matches(";export{};")
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{bytes, Address, B256};
+    use rundler_provider::{
+        MockEntryPointV0_6, MockEvmProvider, ProviderError, StateOverride, TransactionRequest,
+    };
+
+    use super::*;
+
+    const MAX_VERIFICATION_GAS: u64 = 1_000_000;
+
+    fn empty_trace() -> GethTrace {
+        GethTrace::JS(serde_json::json!({
+            "phases": [],
+            "revertData": null,
+            "accessedContracts": {},
+            "associatedSlotsByAddress": {},
+            "factoryCalledCreate2Twice": false,
+            "expectedStorage": {},
+        }))
+    }
+
+    // Validation uses all of the verification gas, so the trace only succeeds if the call
+    // has gas left for the entry point's own overhead
+    fn create_tracer(
+        gas_overhead: u64,
+    ) -> SimulateValidationTracerImpl<MockEvmProvider, MockEntryPointV0_6> {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point.expect_address().return_const(Address::ZERO);
+        entry_point
+            .expect_get_tracer_simulate_validation_call()
+            .returning(|_| {
+                Ok((
+                    TransactionRequest {
+                        gas: Some(MAX_VERIFICATION_GAS.into()),
+                        ..Default::default()
+                    },
+                    StateOverride::default(),
+                ))
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider.expect_debug_trace_call().returning(|tx, _, _| {
+            if tx.gas.is_some_and(|gas| gas > MAX_VERIFICATION_GAS.into()) {
+                Ok(empty_trace())
+            } else {
+                Err(ProviderError::Other(anyhow::anyhow!("out of gas")))
+            }
+        });
+
+        SimulateValidationTracerImpl::new(
+            provider,
+            entry_point,
+            "10s".to_string(),
+            0,
+            false,
+            TracerMode::Custom,
+            TraceBudget::default(),
+            gas_overhead,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_trace_gas_overhead() {
+        let op = UserOperation::default();
+
+        assert!(create_tracer(0)
+            .trace_simulate_validation(op.clone(), BlockId::latest(), None)
+            .await
+            .is_err());
+        assert!(create_tracer(50_000)
+            .trace_simulate_validation(op, BlockId::latest(), None)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_trace_cache_keyed_by_signature() {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point.expect_address().return_const(Address::ZERO);
+        entry_point
+            .expect_get_tracer_simulate_validation_call()
+            .returning(|_| Ok((TransactionRequest::default(), StateOverride::default())));
+        // one trace per signature, the repeated op is served from the cache
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_debug_trace_call()
+            .times(2)
+            .returning(|_, _, _| Ok(empty_trace()));
+
+        let tracer = SimulateValidationTracerImpl::new(
+            provider,
+            entry_point,
+            "10s".to_string(),
+            0,
+            true,
+            TracerMode::Custom,
+            TraceBudget::default(),
+            0,
+        );
+
+        let op = UserOperation {
+            signature: bytes!("01"),
+            ..Default::default()
+        };
+        let dummy_signature_op = UserOperation {
+            signature: bytes!("02"),
+            ..op.clone()
+        };
+        let block_id: BlockId = B256::ZERO.into();
+        for op in [op.clone(), dummy_signature_op, op] {
+            tracer
+                .trace_simulate_validation(op, block_id, None)
+                .await
+                .unwrap();
+        }
+    }
+}
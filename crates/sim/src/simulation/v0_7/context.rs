@@ -16,7 +16,7 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 use alloy_primitives::{
     address,
     hex::{self, FromHex},
-    keccak256, Address, Bytes, U256,
+    keccak256, Address, Bytes, Selector, U256,
 };
 use alloy_sol_types::SolType;
 use anyhow::{bail, Context};
@@ -33,11 +33,11 @@ use super::tracer::{
 };
 use crate::{
     simulation::context::{
-        self as sim_context, AccessInfo, AssociatedSlotsByAddress, Phase,
+        self as sim_context, AccessInfo, AssociatedSlotsByAddress, Phase, TraceBudget,
         TracerOutput as ContextTracerOutput, ValidationContext,
         ValidationContextProvider as ValidationContextProviderTrait,
     },
-    SimulationSettings, ViolationError,
+    SimulationSettings, TracerMode, ViolationError,
 };
 
 // Banned opcodes
@@ -67,7 +67,6 @@ const SIMULATE_VALIDATION_METHOD: &str = "0xee219423";
 const CREATE_SENDER_METHOD: &str = "0x570e1a36";
 const VALIDATE_USER_OP_METHOD: &str = "0x19822f7c";
 const VALIDATE_PAYMASTER_USER_OP_METHOD: &str = "0x52b7512c";
-const DEPOSIT_TO_METHOD: &str = "0xb760faf9";
 // Max precompile address 0x10000
 const MAX_PRECOMPILE_ADDRESS: Address = address!("0000000000000000000000000000000000010000");
 
@@ -89,10 +88,11 @@ where
         &self,
         op: Self::UO,
         block_id: BlockId,
+        prefund_override: Option<U256>,
     ) -> Result<ValidationContext<Self::UO>, ViolationError<SimulationViolation>> {
         let tracer_out = self
             .simulate_validation_tracer
-            .trace_simulate_validation(op.clone(), block_id)
+            .trace_simulate_validation(op.clone(), block_id, prefund_override)
             .await?;
 
         let call_stack = self.parse_call_stack(tracer_out.calls.clone())?;
@@ -131,6 +131,39 @@ where
 
         // Check the call stack for calls with value or to the entry point
         for (i, call) in call_stack.iter().enumerate() {
+            // The factory's successful CREATE2 deploys the sender
+            if call.call_type == Opcode::CREATE2
+                && Some(call.from) == op.factory()
+                && matches!(call.exit_type, ExitType::Return)
+                && tracer_out.factory_deployed_address.is_none()
+            {
+                tracer_out.factory_deployed_address = Some(call.to);
+            }
+
+            // Helper contracts the factory deploys with CREATE, checked against the allowlist
+            if call.call_type == Opcode::CREATE
+                && Some(call.from) == op.factory()
+                && matches!(call.exit_type, ExitType::Return)
+            {
+                let code = hex::decode(&call.exit_data).context("should decode created code")?;
+                tracer_out
+                    .factory_created_contracts
+                    .insert(call.to, code.into());
+            }
+
+            // Gas used by each validation phase is the gas used by its call from the entry point
+            if call.from == self.entry_point_address {
+                let phase = match call.method.as_str() {
+                    CREATE_SENDER_METHOD => Some(0),
+                    VALIDATE_USER_OP_METHOD => Some(1),
+                    VALIDATE_PAYMASTER_USER_OP_METHOD => Some(2),
+                    _ => None,
+                };
+                if let Some(phase) = phase {
+                    tracer_out.phases[phase].gas_used += call.gas_used;
+                }
+            }
+
             if call.to == self.entry_point_address
                 && (call.from != self.entry_point_address && call.from != Address::ZERO)
             {
@@ -138,22 +171,34 @@ where
                 if call.method == "0x" && call.from == op.sender() {
                     continue;
                 }
-                // [OP-052] - can only call depositTo() from sender or factory
-                if call.method == DEPOSIT_TO_METHOD
-                    && (call.from == op.sender() || Some(call.from) == op.factory())
+                // [OP-052] - can only call depositTo() from sender or factory. Operators
+                // may allow additional methods with `allowed_entry_point_methods`.
+                if call.method.parse::<Selector>().is_ok_and(|selector| {
+                    self.sim_settings
+                        .allowed_entry_point_methods
+                        .contains(&selector)
+                }) && (call.from == op.sender() || Some(call.from) == op.factory())
                 {
                     continue;
                 }
 
                 // [OP-054] all other calls to entry point are banned
                 let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
-                tracer_out.phases[phase].called_banned_entry_point_method = true;
+                let phase = &mut tracer_out.phases[phase];
+                phase.called_banned_entry_point_method = true;
+                if !phase.banned_entry_point_methods.contains(&call.method) {
+                    phase.banned_entry_point_methods.push(call.method.clone());
+                }
             }
 
             // [OP-061] calls with value are banned, except for the calls above
             if call.value.is_some_and(|v| v != U256::ZERO) {
                 let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
-                tracer_out.phases[phase].called_non_entry_point_with_value = true;
+                let phase = &mut tracer_out.phases[phase];
+                phase.called_non_entry_point_with_value = true;
+                if !phase.value_call_targets.contains(&call.to) {
+                    phase.value_call_targets.push(call.to);
+                }
             }
         }
 
@@ -288,12 +333,14 @@ impl<T> ValidationContextProvider<T> {
         let mut phases = vec![Phase::default(); 3];
         let mut factory_called_create2_twice = false;
 
-        // Check factory
-        if let Some(call_from_entry_point) = tracer_out
-            .calls_from_entry_point
-            .iter()
-            .find(|c| c.top_level_method_sig == CREATE_SENDER_METHOD)
-        {
+        // Check factory, skipped entirely for an already deployed sender
+        let factory_call = op.factory().and_then(|_| {
+            tracer_out
+                .calls_from_entry_point
+                .iter()
+                .find(|c| c.top_level_method_sig == CREATE_SENDER_METHOD)
+        });
+        if let Some(call_from_entry_point) = factory_call {
             phases[0] = Self::parse_call_to_phase(call_from_entry_point, EntityType::Factory);
             // [OP-031] - create call can only be called once
             if let Some(count) = call_from_entry_point.opcodes.get(&Opcode::CREATE2) {
@@ -357,6 +404,8 @@ impl<T> ValidationContextProvider<T> {
             accessed_contracts,
             associated_slots_by_address: AssociatedSlotsByAddress(associated_slots_by_address),
             factory_called_create2_twice,
+            factory_deployed_address: None,
+            factory_created_contracts: HashMap::new(),
             expected_storage: tracer_out.expected_storage,
         })
     }
@@ -393,6 +442,7 @@ impl<T> ValidationContextProvider<T> {
 
         let mut forbidden_precompiles_used = vec![];
         let mut undeployed_contract_accesses = vec![];
+        let mut undeployed_contract_calls = vec![];
         call.contract_info.iter().for_each(|(address, info)| {
             if info.length == 0 {
                 if *address < MAX_PRECOMPILE_ADDRESS {
@@ -403,6 +453,12 @@ impl<T> ValidationContextProvider<T> {
                 } else {
                     // [OP-041]
                     undeployed_contract_accesses.push(*address);
+                    if !matches!(
+                        info.opcode,
+                        Opcode::EXTCODESIZE | Opcode::EXTCODEHASH | Opcode::EXTCODECOPY
+                    ) {
+                        undeployed_contract_calls.push(*address);
+                    }
                 }
             }
         });
@@ -412,11 +468,15 @@ impl<T> ValidationContextProvider<T> {
             forbidden_precompiles_used,
             storage_accesses,
             called_banned_entry_point_method: false, // set during call stack parsing
+            banned_entry_point_methods: vec![],      // set during call stack parsing
             called_non_entry_point_with_value: false, // set during call stack parsing
+            value_call_targets: vec![],              // set during call stack parsing
             // [OP-020]
             ran_out_of_gas: call.oog.unwrap_or(false),
             undeployed_contract_accesses,
+            undeployed_contract_calls,
             ext_code_access_info: call.ext_code_access_info.clone(),
+            gas_used: 0, // set during call stack parsing
         }
     }
 
@@ -462,13 +522,27 @@ where
     E: EntryPoint + SimulationProvider<UO = UserOperation>,
 {
     /// Creates a new `ValidationContextProvider` for entry point v0.7 with the given provider and entry point.
-    pub(crate) fn new(provider: P, entry_point: E, sim_settings: SimulationSettings) -> Self {
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        chain_id: u64,
+        sim_settings: SimulationSettings,
+    ) -> Self {
+        if sim_settings.tracer_mode == TracerMode::StructLogs {
+            tracing::warn!(
+                "struct log tracing is not supported for entry point v0.7, using the custom tracer"
+            );
+        }
         Self {
             entry_point_address: *entry_point.address(),
             simulate_validation_tracer: SimulateValidationTracerImpl::new(
                 provider,
                 entry_point,
                 sim_settings.tracer_timeout.clone(),
+                chain_id,
+                sim_settings.trace_cache_enabled,
+                TraceBudget::new(&sim_settings),
+                sim_settings.tracer_gas_overhead,
             ),
             sim_settings,
         }
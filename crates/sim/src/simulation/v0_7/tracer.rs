@@ -16,13 +16,16 @@ use alloy_primitives::{Address, U256};
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use rundler_provider::{
-    BlockId, EvmProvider, GethDebugTracerType, GethDebugTracingCallOptions,
-    GethDebugTracingOptions, GethTrace, SimulationProvider,
+    BlockId, EntryPoint, EvmProvider, GethDebugTracerConfig, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, SimulationProvider,
 };
-use rundler_types::{v0_7::UserOperation, Opcode};
+use rundler_types::{v0_7::UserOperation, Opcode, UserOperation as _};
 use serde::Deserialize;
 
-use crate::{simulation::context::ContractInfo, ExpectedStorage};
+use crate::{
+    simulation::context::{self, ContractInfo, TraceBudget, TraceCache},
+    ExpectedStorage,
+};
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -112,11 +115,13 @@ impl TryFrom<GethTrace> for TracerOutput {
 /// Trait for tracing the simulation of a user operation.
 #[async_trait]
 pub(super) trait SimulateValidationTracer: Send + Sync {
-    /// Traces the simulation of a user operation.
+    /// Traces the simulation of a user operation, overriding the sender's balance with
+    /// `prefund_override` if set.
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        prefund_override: Option<U256>,
     ) -> anyhow::Result<TracerOutput>;
 }
 
@@ -126,6 +131,10 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
     provider: P,
     entry_point: E,
     tracer_timeout: String,
+    chain_id: u64,
+    trace_cache: Option<TraceCache<TracerOutput>>,
+    budget: TraceBudget,
+    gas_overhead: u64,
 }
 
 /// Runs the bundler's custom tracer on the entry point's `simulateValidation`
@@ -135,17 +144,52 @@ pub(crate) struct SimulateValidationTracerImpl<P, E> {
 impl<P, E> SimulateValidationTracer for SimulateValidationTracerImpl<P, E>
 where
     P: EvmProvider,
-    E: SimulationProvider<UO = UserOperation>,
+    E: EntryPoint + SimulationProvider<UO = UserOperation>,
 {
     async fn trace_simulate_validation(
         &self,
         op: UserOperation,
         block_id: BlockId,
+        prefund_override: Option<U256>,
+    ) -> anyhow::Result<TracerOutput> {
+        // traces with a prefund override are one-offs, they aren't cached
+        let Some(cache) = self
+            .trace_cache
+            .as_ref()
+            .filter(|_| prefund_override.is_none())
+        else {
+            return self.trace(op, block_id, prefund_override).await;
+        };
+        let key = context::trace_cache_key(
+            op.hash(*self.entry_point.address(), self.chain_id),
+            &op.signature,
+        );
+        cache
+            .get_or_trace(key, block_id, self.trace(op, block_id, None))
+            .await
+    }
+}
+
+impl<P, E> SimulateValidationTracerImpl<P, E>
+where
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UserOperation>,
+{
+    async fn trace(
+        &self,
+        op: UserOperation,
+        block_id: BlockId,
+        prefund_override: Option<U256>,
     ) -> anyhow::Result<TracerOutput> {
-        let (tx, state_override) = self
+        let sender = op.sender;
+        let (mut tx, mut state_override) = self
             .entry_point
             .get_tracer_simulate_validation_call(op)
             .context("should get tracer simulate validation call")?;
+        context::add_prefund_override(&mut state_override, sender, prefund_override);
+        tx.gas = tx
+            .gas
+            .map(|gas| gas.saturating_add(self.gas_overhead.into()));
 
         let out = self
             .provider
@@ -157,6 +201,7 @@ where
                         tracer: Some(GethDebugTracerType::JsTracer(
                             validation_tracer_js().to_string(),
                         )),
+                        tracer_config: GethDebugTracerConfig(self.budget.tracer_config()),
                         timeout: Some(self.tracer_timeout.clone()),
                         ..Default::default()
                     },
@@ -165,18 +210,39 @@ where
                 },
             )
             .await?;
-
         TracerOutput::try_from(out)
     }
 }
 
 impl<P, E> SimulateValidationTracerImpl<P, E> {
     /// Creates a new instance of the bundler's custom tracer.
-    pub(crate) fn new(provider: P, entry_point: E, tracer_timeout: String) -> Self {
+    ///
+    /// If `trace_cache_enabled` is set, outputs are cached by user operation hash and
+    /// signature for the most recently traced block, and tracing the same operation at the
+    /// same block again returns the cached output.
+    ///
+    /// Traces exceeding `budget` fail with an error containing `BUDGET_EXCEEDED_ERROR`.
+    ///
+    /// `gas_overhead` is added to the gas of the entry point's `simulateValidation` call,
+    /// so that the verification gas isn't shared with the entry point's own overhead.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        provider: P,
+        entry_point: E,
+        tracer_timeout: String,
+        chain_id: u64,
+        trace_cache_enabled: bool,
+        budget: TraceBudget,
+        gas_overhead: u64,
+    ) -> Self {
         Self {
             provider,
             entry_point,
             tracer_timeout,
+            chain_id,
+            trace_cache: trace_cache_enabled.then(TraceCache::new),
+            budget,
+            gas_overhead,
         }
     }
 }
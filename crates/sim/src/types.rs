@@ -19,7 +19,7 @@ use serde::{Deserialize, Serialize};
 
 /// The expected storage values for a user operation that must
 /// be checked to determine if this operation is valid.
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct ExpectedStorage(pub BTreeMap<Address, BTreeMap<B256, B256>>);
 
 impl ExpectedStorage {
@@ -54,6 +54,31 @@ impl ExpectedStorage {
             .or_default()
             .insert(B256::from(slot), B256::from(value));
     }
+
+    /// The number of storage slots across all addresses.
+    pub fn num_slots(&self) -> usize {
+        self.0.values().map(BTreeMap::len).sum()
+    }
+
+    /// Retain at most `max_slots` storage slots, dropping the rest in address and slot order.
+    ///
+    /// Returns true if any slots were dropped.
+    pub fn truncate(&mut self, max_slots: usize) -> bool {
+        let mut remaining = max_slots;
+        let mut truncated = false;
+        self.0.retain(|_, values_by_slot| {
+            if values_by_slot.len() > remaining {
+                truncated = true;
+                *values_by_slot = std::mem::take(values_by_slot)
+                    .into_iter()
+                    .take(remaining)
+                    .collect();
+            }
+            remaining -= values_by_slot.len();
+            !values_by_slot.is_empty()
+        });
+        truncated
+    }
 }
 
 use std::fmt::{Display, Formatter};
@@ -109,3 +134,36 @@ impl<T: Display> Display for ViolationError<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_storage_truncate() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut expected_storage = ExpectedStorage::default();
+        for slot in 0..3 {
+            expected_storage.insert(a, U256::from(slot), U256::ZERO);
+            expected_storage.insert(b, U256::from(slot), U256::ZERO);
+        }
+        assert_eq!(expected_storage.num_slots(), 6);
+
+        // under the cap, nothing is dropped
+        assert!(!expected_storage.truncate(6));
+        assert_eq!(expected_storage.num_slots(), 6);
+
+        // the first address is kept intact, the second is cut short
+        assert!(expected_storage.truncate(4));
+        assert_eq!(expected_storage.num_slots(), 4);
+        assert_eq!(expected_storage.0[&a].len(), 3);
+        assert_eq!(
+            expected_storage.0[&b].keys().collect::<Vec<_>>(),
+            vec![&B256::ZERO]
+        );
+
+        // addresses left without slots are removed
+        assert!(expected_storage.truncate(2));
+        assert!(!expected_storage.0.contains_key(&b));
+    }
+}
@@ -24,6 +24,8 @@ interface Output {
   accessedContracts: Record<string, ContractInfo>;
   associatedSlotsByAddress: Record<string, string[]>;
   factoryCalledCreate2Twice: boolean;
+  factoryDeployedAddress: string | null;
+  factoryCreatedContracts: Record<string, string>;
   expectedStorage: Record<string, Record<string, string>>;
 }
 
@@ -32,11 +34,15 @@ interface Phase {
   forbiddenPrecompilesUsed: string[];
   storageAccesses: Record<string, AccessInfo>;
   calledBannedEntryPointMethod: boolean;
+  bannedEntryPointMethods: string[];
   addressesCallingWithValue: string[];
   calledNonEntryPointWithValue: boolean;
+  valueCallTargets: string[];
   ranOutOfGas: boolean;
   undeployedContractAccesses: string[];
+  undeployedContractCalls: string[];
   extCodeAccessInfo: Record<string, string>;
+  gasUsed: number;
 }
 
 interface AccessInfo {
@@ -62,14 +68,21 @@ type InternalPhase = Omit<
   | "forbiddenOpcodesUsed"
   | "forbiddenPrecompilesUsed"
   | "storageAccesses"
+  | "bannedEntryPointMethods"
   | "addressesCallingWithValue"
+  | "valueCallTargets"
   | "undeployedContractAccesses"
+  | "undeployedContractCalls"
+  | "gasUsed"
 > & {
   forbiddenOpcodesUsed: StringSet;
   forbiddenPrecompilesUsed: StringSet;
   storageAccesses: Record<string, AccessInfo>;
+  bannedEntryPointMethods: StringSet;
   addressesCallingWithValue: StringSet;
+  valueCallTargets: StringSet;
   undeployedContractAccesses: StringSet;
+  undeployedContractCalls: StringSet;
 };
 
 type StringSet = Record<string, boolean | undefined>;
@@ -83,6 +96,8 @@ type StringSet = Record<string, boolean | undefined>;
 
   const DEPOSIT_TO_SELECTOR = "0xb760faf9";
   const SSTORE_REQUIRED_GAS = 2300;
+  // Thrown to abort the trace, matched by the bundler to reject the operation
+  const BUDGET_EXCEEDED_ERROR = "simulation budget exceeded";
   const FORBIDDEN_OPCODES = stringSet([
     "GASPRICE",
     "GASLIMIT",
@@ -133,11 +148,24 @@ type StringSet = Record<string, boolean | undefined>;
   const associatedSlotsByAddressMap: Record<string, StringSet> = {};
   const allStorageAccesses: Record<string, Record<string, string | null>> = {};
   let factoryCreate2Count = 0;
+  let factoryDeployedAddress: string | null = null;
+  // Runtime code of contracts the factory deployed with CREATE, by address
+  const factoryCreatedContracts: Record<string, string> = {};
+  // For each open frame, the address it deploys if it's a factory CREATE
+  const factoryCreateFrames: (string | null)[] = [];
   let currentPhase = newInternalPhase();
+  // Gas remaining in the entry point frame at the start of the current phase
+  let phaseStartGas: number | null = null;
+  let lastEntryPointGas = 0;
   let entryPointAddress = "";
   let pendingKeccakAddress = "";
   let last: RelevantStepData | null = null;
   let secondLast: RelevantStepData | null = null;
+  // Work budget from the tracer config, unlimited if null
+  let maxSteps: number | null = null;
+  let maxStorageAccesses: number | null = null;
+  let stepCount = 0;
+  let storageAccessCount = 0;
 
   function newInternalPhase(): InternalPhase {
     return {
@@ -145,10 +173,13 @@ type StringSet = Record<string, boolean | undefined>;
       forbiddenPrecompilesUsed: {},
       storageAccesses: {},
       calledBannedEntryPointMethod: false,
+      bannedEntryPointMethods: {},
       addressesCallingWithValue: {},
       calledNonEntryPointWithValue: false,
+      valueCallTargets: {},
       ranOutOfGas: false,
       undeployedContractAccesses: {},
+      undeployedContractCalls: {},
       extCodeAccessInfo: {},
     };
   }
@@ -164,26 +195,38 @@ type StringSet = Record<string, boolean | undefined>;
     const forbiddenPrecompilesUsed = Object.keys(
       currentPhase.forbiddenPrecompilesUsed
     );
+    const bannedEntryPointMethods = Object.keys(
+      currentPhase.bannedEntryPointMethods
+    );
     const addressesCallingWithValue = Object.keys(
       currentPhase.addressesCallingWithValue
     );
+    const valueCallTargets = Object.keys(currentPhase.valueCallTargets);
     const undeployedContractAccesses = Object.keys(
       currentPhase.undeployedContractAccesses
     );
+    const undeployedContractCalls = Object.keys(
+      currentPhase.undeployedContractCalls
+    );
 
     const phase: Phase = {
       forbiddenOpcodesUsed,
       forbiddenPrecompilesUsed,
       storageAccesses: currentPhase.storageAccesses,
       calledBannedEntryPointMethod,
+      bannedEntryPointMethods,
       addressesCallingWithValue,
       calledNonEntryPointWithValue,
+      valueCallTargets,
       ranOutOfGas,
       undeployedContractAccesses,
+      undeployedContractCalls,
       extCodeAccessInfo,
+      gasUsed: phaseStartGas === null ? 0 : phaseStartGas - lastEntryPointGas,
     };
     phases.push(phase);
     currentPhase = newInternalPhase();
+    phaseStartGas = lastEntryPointGas;
   }
 
   function bigIntToNumber(n: BigInt): number {
@@ -242,6 +285,8 @@ type StringSet = Record<string, boolean | undefined>;
         accessedContracts,
         associatedSlotsByAddress,
         factoryCalledCreate2Twice: factoryCreate2Count > 1,
+        factoryDeployedAddress,
+        factoryCreatedContracts,
         expectedStorage,
       };
     },
@@ -249,6 +294,12 @@ type StringSet = Record<string, boolean | undefined>;
 
     fault(_log, _db): void { },
 
+    setup(config): void {
+      const budget = JSON.parse(config);
+      maxSteps = budget.maxSteps ?? null;
+      maxStorageAccesses = budget.maxStorageAccesses ?? null;
+    },
+
     step(log, db): void {
       if (!entryPointAddress) {
         entryPointAddress = toHex(log.contract.getAddress());
@@ -256,6 +307,17 @@ type StringSet = Record<string, boolean | undefined>;
 
       const opcode = log.op.toString();
 
+      stepCount++;
+      if (READ_WRITE_OPCODES[opcode]) {
+        storageAccessCount++;
+      }
+      if (
+        (maxSteps !== null && stepCount > maxSteps) ||
+        (maxStorageAccesses !== null && storageAccessCount > maxStorageAccesses)
+      ) {
+        throw new Error(BUDGET_EXCEEDED_ERROR);
+      }
+
       if (log.getGas() < log.getCost() || (
         opcode === 'SSTORE' && log.getGas() < SSTORE_REQUIRED_GAS
       )) {
@@ -276,6 +338,10 @@ type StringSet = Record<string, boolean | undefined>;
 
       const entryPointIsExecuting = log.getDepth() === 1;
       if (entryPointIsExecuting) {
+        if (phaseStartGas === null) {
+          phaseStartGas = log.getGas();
+        }
+        lastEntryPointGas = log.getGas();
         if (opcode === "NUMBER") {
           concludePhase();
         } else if (opcode === "REVERT") {
@@ -388,8 +454,13 @@ type StringSet = Record<string, boolean | undefined>;
             // of the phase.
             if (db.getCode(address).length === 0) {
               currentPhase.undeployedContractAccesses[addressHex] = true;
+              // Distinguish calls from EXTCODE* probes of the address
+              if (CALL_OPCODES[opcode]) {
+                currentPhase.undeployedContractCalls[addressHex] = true;
+              }
             } else {
               delete currentPhase.undeployedContractAccesses[addressHex];
+              delete currentPhase.undeployedContractCalls[addressHex];
             }
           }
           accessedContracts[addressHex] = {
@@ -410,6 +481,19 @@ type StringSet = Record<string, boolean | undefined>;
     },
 
     enter(frame) {
+      factoryCreateFrames.push(
+        frame.getType() === "CREATE" && phases.length === 0
+          ? toHex(frame.getTo())
+          : null
+      );
+      if (
+        frame.getType() === "CREATE2" &&
+        phases.length === 0 &&
+        factoryDeployedAddress === null
+      ) {
+        // The first CREATE2 of the factory phase deploys the sender.
+        factoryDeployedAddress = toHex(frame.getTo());
+      }
       const from = toHex(frame.getFrom());
       if (from === entryPointAddress) {
         return;
@@ -420,12 +504,12 @@ type StringSet = Record<string, boolean | undefined>;
         // The spec says that calling entry point methods other than `depositTo`
         // is banned. We deviate and also allow calling the entrypoint with no
         // calldata, as this is equivalent to calling `depositTo` and without it
-        // many spec tests fail.
-        if (
-          input.length > 0 &&
-          toHex(input).substring(0, 10) !== DEPOSIT_TO_SELECTOR
-        ) {
+        // many spec tests fail. The selectors of banned calls are recorded so
+        // that operator allowed methods can be let through afterwards.
+        const selector = toHex(input).substring(0, 10);
+        if (input.length > 0 && selector !== DEPOSIT_TO_SELECTOR) {
           currentPhase.calledBannedEntryPointMethod = true;
+          currentPhase.bannedEntryPointMethods[selector] = true;
         }
       }
       const value = frame.getValue();
@@ -434,10 +518,17 @@ type StringSet = Record<string, boolean | undefined>;
           currentPhase.addressesCallingWithValue[from] = true;
         } else {
           currentPhase.calledNonEntryPointWithValue = true;
+          currentPhase.valueCallTargets[toHex(frame.getTo())] = true;
         }
       }
     },
 
-    exit(_frame) { },
+    exit(frame) {
+      const createdAddress = factoryCreateFrames.pop();
+      if (createdAddress && frame.getError() == null) {
+        // The output of a CREATE frame is the deployed runtime code
+        factoryCreatedContracts[createdAddress] = toHex(frame.getOutput());
+      }
+    },
   };
 })();
@@ -130,9 +130,15 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
   lastThreeOpcodes: RelevantStepData[]
   stopCollectingTopic: string
   stopCollecting: boolean
+  frameTypes: string[]
   currentLevel: TopLevelCallInfo
   topLevelCallCounter: number
   allStorageAccesses: Record<string, Record<string, string | null>>
+  // work budget from the tracer config, unlimited if null
+  maxSteps: number | null
+  maxStorageAccesses: number | null
+  stepCount: number
+  storageAccessCount: number
   countSlot: (list: { [key: string]: number | undefined }, key: any) => void
   computeIfAbsent<K extends keyof any, V>(
     map: Record<K, V>,
@@ -165,8 +171,19 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
     // event sent after all validations are done: keccak("BeforeExecution()")
     stopCollectingTopic: 'bb47ee3e183a558b1a2ff0874b079f3fc5478b7454eacf2bfc5af2ff5878f972',
     stopCollecting: false,
+    frameTypes: [],
     topLevelCallCounter: 0,
     allStorageAccesses: {},
+    maxSteps: null,
+    maxStorageAccesses: null,
+    stepCount: 0,
+    storageAccessCount: 0,
+
+    setup (config: string): void {
+      const budget = JSON.parse(config)
+      this.maxSteps = budget.maxSteps ?? null
+      this.maxStorageAccesses = budget.maxStorageAccesses ?? null
+    },
 
     fault (log: LogStep, _db: LogDb): void {
       var err = "";
@@ -209,6 +226,7 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
         return
       }
       // this.debug.push('enter gas=', frame.getGas(), ' type=', frame.getType(), ' to=', toHex(frame.getTo()), ' in=', toHex(frame.getInput()).slice(0, 500))
+      this.frameTypes.push(frame.getType())
       this.calls.push({
         type: frame.getType(),
         from: toHex(frame.getFrom()),
@@ -222,10 +240,13 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       if (this.stopCollecting) {
         return
       }
+      // the output of a CREATE frame is the deployed code, which is kept in full so
+      // that its hash can be checked
+      const data = toHex(frame.getOutput())
       this.calls.push({
         type: frame.getError() != null ? 'REVERT' : 'RETURN',
         gasUsed: frame.getGasUsed(),
-        data: toHex(frame.getOutput()).slice(0, 4000)
+        data: this.frameTypes.pop() === 'CREATE' ? data : data.slice(0, 4000)
       })
     },
 
@@ -255,6 +276,19 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       }
       const opcode = log.op.toString()
 
+      // abort the trace once validation exceeds its work budget, the bundler matches
+      // this message to reject the operation
+      this.stepCount++
+      if (opcode === 'SLOAD' || opcode === 'SSTORE' || opcode === 'TLOAD' || opcode === 'TSTORE') {
+        this.storageAccessCount++
+      }
+      if (
+        (this.maxSteps !== null && this.stepCount > this.maxSteps) ||
+        (this.maxStorageAccesses !== null && this.storageAccessCount > this.maxStorageAccesses)
+      ) {
+        throw new Error('simulation budget exceeded')
+      }
+
       const stackSize = log.stack.length()
       const stackTop3 = []
       for (let i = 0; i < 3 && i < stackSize; i++) {
@@ -352,12 +386,16 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
         const addr = toAddress(log.stack.peek(idx).toString(16))
         const addrHex = toHex(addr)
         // this.debug.push('op=' + opcode + ' last=' + this.lastOp + ' stacksize=' + log.stack.length() + ' addr=' + addrHex)
-        if (this.currentLevel.contractInfo[addrHex] == null && !isAllowedPrecompiled(addr)) {
+        const info = this.currentLevel.contractInfo[addrHex]
+        if (info == null && !isAllowedPrecompiled(addr)) {
           this.currentLevel.contractInfo[addrHex] = {
             length: db.getCode(addr).length,
             opcode,
             header: toHex(db.getCode(addr).subarray(0, 3))
           }
+        } else if (info != null && info.opcode.startsWith('EXT') && !opcode.startsWith('EXT')) {
+          // MODIFICATION: record a call following an EXTCODE* probe, keeping the code length at first access
+          info.opcode = opcode
         }
       }
 
@@ -13,7 +13,7 @@
 
 //! Protobuf utilities
 
-use alloy_primitives::{Address, Bytes, B256, U128, U256};
+use alloy_primitives::{Address, Bytes, Selector, B256, U128, U256};
 
 /// Error type for conversions from protobuf types to Ethers/local types.
 #[derive(Debug, thiserror::Error)]
@@ -99,6 +99,14 @@ impl FromFixedLengthProtoBytes for B256 {
     }
 }
 
+impl FromFixedLengthProtoBytes for Selector {
+    const LEN: usize = 4;
+
+    fn from_fixed_length_bytes(bytes: &[u8]) -> Self {
+        Self::from_slice(bytes)
+    }
+}
+
 impl FromFixedLengthProtoBytes for u128 {
     const LEN: usize = 16;
 
@@ -144,6 +152,12 @@ impl ToProtoBytes for Bytes {
     }
 }
 
+impl ToProtoBytes for Selector {
+    fn to_proto_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
 impl ToProtoBytes for u128 {
     fn to_proto_bytes(&self) -> Vec<u8> {
         self.to_le_bytes().into()
@@ -15,7 +15,7 @@
 
 use std::str::FromStr;
 
-use alloy_primitives::Address;
+use alloy_primitives::{uint, Address, U256};
 use serde::{Deserialize, Serialize};
 
 use crate::da::DAGasOracleType;
@@ -74,6 +74,9 @@ pub struct ChainSpec {
     /// true if Data Availability (DA) calldata gas should be included in the gas limit
     /// only applies when da_pre_verification_gas is true
     pub include_da_gas_in_gas_limit: bool,
+    /// Precompile addresses allowed during validation on this chain in addition
+    /// to the standard set, e.g. chain specific BLS or modexp variants
+    pub extra_allowed_precompiles: Vec<Address>,
 
     /*
      * Fee estimation
@@ -90,6 +93,20 @@ pub struct ChainSpec {
     /// Some chains have artificially high block gas limits but
     /// actually cap block gas usage at a lower value.
     pub congestion_trigger_usage_ratio_threshold: f64,
+    /// Number of recent blocks sampled by the fee history oracle
+    pub fee_history_num_blocks: u64,
+    /// Reward percentile sampled from each block by the fee history oracle
+    pub fee_history_percentile: f64,
+
+    /*
+     * Staking
+     */
+    /// The minimum amount of stake, in the chain's native token, that an entity must have on
+    /// the entry point to be considered staked
+    pub min_stake_value: U256,
+    /// The minimum unstake delay, in seconds, that an entity must have configured on the
+    /// entry point to be considered staked
+    pub min_unstake_delay: u32,
 
     /*
      * Bundle building
@@ -129,6 +146,8 @@ pub enum PriorityFeeOracleType {
     Provider,
     /// Use the usage based oracle
     UsageBased,
+    /// Use a percentile of the priority fees paid in recent blocks
+    FeeHistory,
 }
 
 impl Default for ChainSpec {
@@ -151,11 +170,17 @@ impl Default for ChainSpec {
             da_gas_oracle_type: DAGasOracleType::default(),
             da_gas_oracle_contract_address: Address::ZERO,
             include_da_gas_in_gas_limit: false,
+            extra_allowed_precompiles: vec![],
             priority_fee_oracle_type: PriorityFeeOracleType::default(),
             min_max_priority_fee_per_gas: 0,
             max_max_priority_fee_per_gas: u64::MAX,
             congestion_trigger_usage_ratio_threshold: 0.75,
+            fee_history_num_blocks: 15,
+            fee_history_percentile: 50.0,
             max_transaction_size_bytes: 131072, // 128 KiB
+            // 10^18 wei = 1 eth
+            min_stake_value: uint!(1_000_000_000_000_000_000_U256),
+            min_unstake_delay: 84600,
             bundle_max_send_interval_millis: u64::MAX,
             flashbots_enabled: false,
             flashbots_relay_url: None,
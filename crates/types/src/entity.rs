@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Display, hash::Hash, str::FromStr};
+use std::{collections::HashMap, fmt::Display, hash::Hash, str::FromStr};
 
 use alloy_primitives::Address;
 use anyhow::bail;
@@ -117,14 +117,35 @@ impl Display for Entity {
     }
 }
 
+// Human readable formats use `{"<kind>": "<address>"}`, binary formats a (kind, address) tuple
 impl Serialize for Entity {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return (self.kind, self.address).serialize(serializer);
+        }
         let mut e = serializer.serialize_struct("Entity", 1)?;
         e.serialize_field(self.kind.to_str(), &self.address.to_checksum(None))?;
         e.end()
     }
 }
 
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let (kind, address) = <(EntityType, Address)>::deserialize(deserializer)?;
+            return Ok(Self::new(kind, address));
+        }
+        let fields = HashMap::<EntityType, Address>::deserialize(deserializer)?;
+        let mut fields = fields.into_iter();
+        match (fields.next(), fields.next()) {
+            (Some((kind, address)), None) => Ok(Self::new(kind, address)),
+            _ => Err(serde::de::Error::custom(
+                "entity must have exactly one field",
+            )),
+        }
+    }
+}
+
 /// Updates that can be applied to an entity
 #[derive(Display, Debug, Clone, Ord, Copy, Eq, PartialEq, EnumIter, PartialOrd, Deserialize)]
 #[display(style = "camelCase")]
@@ -160,7 +181,7 @@ pub struct EntityUpdate {
 }
 
 /// additional context about an entity
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
 pub struct EntityInfo {
     /// The entity
     pub entity: Entity,
@@ -191,7 +212,7 @@ impl EntityInfo {
 }
 
 /// additional context for all the entities used in an op
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
 pub struct EntityInfos {
     /// The entity info for the factory
     pub factory: Option<EntityInfo>,
@@ -16,7 +16,7 @@ use serde::{Deserialize, Serialize};
 use strum::{AsRefStr, Display, EnumCount, EnumIter, EnumString, VariantNames};
 
 /// A wrapper around Opcode that implements extra traits
-#[derive(Debug, PartialEq, Clone, parse_display::Display, Eq)]
+#[derive(Debug, PartialEq, Clone, parse_display::Display, Eq, Deserialize, Serialize)]
 #[display("{0:?}")]
 pub struct ViolationOpCode(pub Opcode);
 
@@ -11,11 +11,12 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, Bytes, Selector, B256, U256};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    validation_results::ValidationRevert, Entity, EntityType, StorageSlot, Timestamp,
-    ViolationOpCode,
+    validation_results::ValidationRevert, Entity, EntityType, StorageSlot, StorageSlotLabel,
+    Timestamp, ViolationOpCode,
 };
 
 /// Pool server error type
@@ -100,6 +101,12 @@ pub enum MempoolError {
     /// Call gas limit efficiency too low
     #[error("Call gas limit efficiency too low. Required: {0}, Actual: {1}")]
     CallGasLimitEfficiencyTooLow(f32, f32),
+    /// The operation's call phase reverts when executed after validation
+    #[error("Operation call phase reverted: {reason}")]
+    CallPhaseReverted {
+        /// The decoded revert reason, or the hex encoded revert data if not decodable
+        reason: String,
+    },
 }
 
 /// Precheck violation enumeration
@@ -148,7 +155,19 @@ pub enum PrecheckViolation {
 }
 
 /// All possible simulation violations
-#[derive(Clone, Debug, parse_display::Display, Ord, Eq, PartialOrd, PartialEq)]
+#[derive(
+    Clone,
+    Debug,
+    parse_display::Display,
+    Ord,
+    Eq,
+    PartialOrd,
+    PartialEq,
+    strum::IntoStaticStr,
+    strum::VariantNames,
+    Deserialize,
+    Serialize,
+)]
 pub enum SimulationViolation {
     // Make sure to maintain the order here based on the importance
     // of the violation for converting to an JSON RPC error
@@ -182,22 +201,23 @@ pub enum SimulationViolation {
     #[display("factory may only call CREATE2 once during initialization")]
     FactoryCalledCreate2Twice(Address),
     /// The user operation accessed a storage slot that is not allowed
-    #[display("{0.kind} accessed forbidden storage at address {1:?} during validation")]
-    InvalidStorageAccess(Entity, StorageSlot),
+    #[display("{0.kind} accessed forbidden storage at address {1:?}{2} during validation")]
+    InvalidStorageAccess(Entity, StorageSlot, StorageSlotLabel),
     /// The user operation accessed a storage slot on the sender while being deployed
     /// and the accessing entity or the factory is not staked
     #[display("Sender storage at slot {1:?} accessed during deployment. Factory or accessing entity ({0:?}) must be staked")]
     AssociatedStorageDuringDeploy(Option<Entity>, StorageSlot),
-    /// The user operation called an entry point method that is not allowed
+    /// The user operation called an entry point method that is not allowed. Includes the
+    /// selector of the method, if known.
     #[display("{0.kind} called entry point method other than depositTo")]
-    CalledBannedEntryPointMethod(Entity),
+    CalledBannedEntryPointMethod(Entity, Option<Selector>),
     /// The user operation made a call that contained value to a contract other than the entrypoint
-    /// during validation
+    /// during validation. Includes the target of the call, if known.
     #[display("{0.kind} must not send ETH during validation (except from account to entry point)")]
-    CallHadValue(Entity),
-    /// The code hash of accessed contracts changed on the second simulation
-    #[display("code accessed by validation has changed since the last time validation was run")]
-    CodeHashChanged,
+    CallHadValue(Entity, Option<Address>),
+    /// The code hash of accessed contracts changed on the second simulation (expected, actual)
+    #[display("code accessed by validation has changed since the last time validation was run: expected code hash {0}, found {1}")]
+    CodeHashChanged(B256, B256),
     /// The user operation contained an entity that accessed storage without being staked
     #[display("{0.needs_stake} needs to be staked: {0.accessing_entity} accessed storage at {0.accessed_address} slot {0.slot} (associated with {0.accessed_entity:?})")]
     NotStaked(Box<NeedsStakeInformation>),
@@ -207,12 +227,12 @@ pub enum SimulationViolation {
     /// The user operation uses an aggregator entity and it is not staked
     #[display("An aggregator must be staked, regardless of storager usage")]
     UnstakedAggregator,
-    /// Simulation reverted with an unintended reason, containing a message
+    /// Simulation reverted with an unintended reason, containing a message and the raw revert data
     #[display("reverted while simulating {0} validation: {1}")]
-    UnintendedRevertWithMessage(EntityType, String, Option<Address>),
-    /// Simulation reverted with an unintended reason
+    UnintendedRevertWithMessage(EntityType, String, Option<Address>, Bytes),
+    /// Simulation reverted with an unintended reason, containing the raw revert data
     #[display("reverted while simulating {0} validation")]
-    UnintendedRevert(EntityType, Option<Address>),
+    UnintendedRevert(EntityType, Option<Address>, Bytes),
     /// Validation revert (only used for unsafe sim)
     #[display("validation revert: {0}")]
     ValidationRevert(ValidationRevert),
@@ -222,22 +242,301 @@ pub enum SimulationViolation {
     /// Simulation had the wrong number of phases
     #[display("simulateValidation should have 3 parts but had {0} instead. Make sure your EntryPoint is valid")]
     WrongNumberOfPhases(u32),
-    /// The user operation ran out of gas during validation
-    #[display("ran out of gas during {0.kind} validation")]
-    OutOfGas(Entity),
-    /// The user operation aggregator signature validation failed
-    #[display("aggregator signature validation failed")]
-    AggregatorValidationFailed,
+    /// The user operation ran out of gas during validation. Contains the gas the entity's
+    /// validation used before running out, or zero if it was not measured.
+    #[display("ran out of gas during {0.kind} validation after using {1} gas")]
+    OutOfGas(Entity, u128),
+    /// The user operation aggregator signature validation failed, with the aggregator's
+    /// revert reason if it reverted with one
+    #[display("aggregator signature validation failed, reason: {0:?}")]
+    AggregatorValidationFailed(Option<String>),
     /// Verification gas limit doesn't have the required buffer on the measured gas
     #[display("verification gas limit doesn't have the required buffer on the measured gas, limit: {0}, needed: {1}")]
     VerificationGasLimitBufferTooLow(u128, u128),
     /// Unsupported contract type
     #[display("accessed unsupported contract type: {0:?} at {1:?}. Address must be whitelisted")]
     AccessedUnsupportedContractType(String, Address),
+    /// The operation's max priority fee is below the minimum of every mempool whose
+    /// rules it otherwise satisfies
+    #[display(
+        "maxPriorityFeePerGas is {0} but mempools allowing this operation require at least {1}"
+    )]
+    MempoolPriorityFeeTooLow(u128, u128),
+    /// Simulation did not complete within the configured timeout
+    #[display("simulation timed out")]
+    SimulationTimedOut,
+    /// The operation nonce is ahead of the sender's next nonce, leaving a gap
+    #[display("nonce gap: sender's next nonce is {0} but operation nonce is {1}")]
+    NonceTooHigh(U256, U256),
+    /// The operation nonce has already been used by the sender
+    #[display("nonce already used: sender's next nonce is {0} but operation nonce is {1}")]
+    NonceAlreadyUsed(U256, U256),
+    /// The operation was rejected by an operator defined admission policy
+    #[display("operation rejected by admission policy: {0}")]
+    AdmissionPolicyRejected(String),
+    /// The sender's balance of a token paymaster's token is too low (token, required, shortfall)
+    #[display("sender token balance too low: requires {1} of token {0}, short by {2}")]
+    InsufficientTokenBalance(Address, U256, U256),
+    /// The sender's token allowance to a token paymaster is too low (token, required, shortfall)
+    #[display("sender token allowance too low: requires {1} of token {0}, short by {2}")]
+    InsufficientTokenAllowance(Address, U256, U256),
+    /// The validity time range is empty (`valid_after` and `valid_until` are both 0)
+    /// and the simulator is configured to reject such ranges
+    #[display("validity time range is empty: valid_after and valid_until are both 0")]
+    SuspiciousTimeRange,
+    /// The paymaster's entry point deposit is below the operation's required prefund
+    /// (paymaster, required, available)
+    #[display(
+        "paymaster {0.address} deposit is {2} but must be at least {1} to pay for this operation"
+    )]
+    PaymasterDepositTooLow(Entity, U256, U256),
+    /// The operation's combined verification, call and pre-verification gas exceeds the
+    /// maximum gas used to simulate handling operations (total, limit)
+    #[display("total gas limit is {0} but must be at most {1} to be simulated")]
+    TotalGasLimitExceeded(u128, u128),
+    /// One of the operation's entity addresses is on the operator's denylist
+    #[display("address {0:?} is denied")]
+    AddressDenied(Address),
+    /// The operation's sender is the zero address
+    #[display("sender must not be the zero address")]
+    ZeroSender,
+    /// A gas limit that the operation requires is zero. Contains the field name.
+    #[display("{0} must be non-zero")]
+    ZeroGasLimit(String),
+    /// The operation's max fee per gas is zero
+    #[display("maxFeePerGas must be non-zero")]
+    ZeroMaxFeePerGas,
+    /// The operation's init code is not empty but does not start with a factory address
+    #[display("initCode must be empty or start with a 20 byte factory address")]
+    MalformedInitCode,
+    /// The operation's paymaster and data is not empty but does not start with a
+    /// paymaster address
+    #[display("paymasterAndData must be empty or start with a 20 byte paymaster address")]
+    MalformedPaymasterAndData,
+    /// The factory used more gas during deployment than the operator configured limit
+    /// (factory, gas used, limit)
+    #[display("factory {0.address} used {1} gas during deployment but the limit is {2}")]
+    FactoryGasLimitExceeded(Entity, u128, u128),
+    /// The paymaster's sponsorship, as decoded from its paymaster data, has expired
+    /// (paymaster, valid until, valid after)
+    #[display("paymaster {0} sponsorship expired. validUntil: {1}, validAfter: {2}")]
+    PaymasterSponsorshipExpired(Address, Timestamp, Timestamp),
+    /// The verification gas used during simulation exceeds the operation's own
+    /// verification gas limit, so it would revert on chain (needed, provided)
+    #[display("verification gas limit too low for the operation, needed: {0}, provided: {1}")]
+    VerificationGasLimitTooLowForOp(u128, u128),
+    /// The factory deployed a contract with CREATE whose code hash is not allowlisted
+    /// (factory, deployed address, code hash)
+    #[display("factory {0.address} deployed contract {1} with unapproved code hash {2}")]
+    FactoryDeployedUnapprovedContract(Entity, Address, B256),
+    /// Validation exceeded the configured work budget of trace steps or storage accesses,
+    /// and the trace was aborted
+    #[display("simulation exceeded its work budget")]
+    SimulationBudgetExceeded,
+    /// Validation accessed more distinct contracts than the configured maximum
+    /// (number of contracts accessed)
+    #[display("validation accessed too many contracts: {0}")]
+    TooManyContractsAccessed(usize),
+    /// The operation's sender is also its paymaster, and the operator rejects such operations
+    /// (sender address)
+    #[display("sender {0} is also the paymaster")]
+    SenderIsPaymaster(Address),
+    /// The user operation accessed a non-associated storage slot owned by another entity
+    /// in the operation. This usually indicates a design bug rather than an unrelated access.
+    #[display(
+        "{0.kind} accessed forbidden storage of {1.kind} at address {2:?} during validation"
+    )]
+    InvalidEntityStorageAccess(Entity, Entity, StorageSlot),
+    /// The user operation used the result of the GAS opcode for something other than
+    /// the gas argument of an immediately following call
+    #[display("{0.kind} uses GAS opcode other than as the gas argument of a call")]
+    GasOpcodeMisuse(Entity),
+    /// The user operation used a block environment opcode that is only allowed for
+    /// staked entities
+    #[display("unstaked {0.kind} uses banned environment opcode: {1}")]
+    UsedBannedEnvironmentOpcode(Entity, ViolationOpCode),
+    /// The user operation factory deployed a contract at an address other than the declared sender.
+    /// Fields are the declared sender and the deployed address.
+    #[display("factory deployed the sender at {1:?}, but the operation declares sender {0:?}")]
+    SenderAddressMismatch(Address, Address),
+}
+
+impl SimulationViolation {
+    /// Returns the entity this violation is attributed to, if it is attributable
+    pub fn entity(&self) -> Option<Entity> {
+        match self {
+            Self::UsedForbiddenOpcode(entity, ..)
+            | Self::GasOpcodeMisuse(entity)
+            | Self::UsedBannedEnvironmentOpcode(entity, _)
+            | Self::UsedForbiddenPrecompile(entity, ..)
+            | Self::AccessedUndeployedContract(entity, _)
+            | Self::InvalidStorageAccess(entity, ..)
+            | Self::InvalidEntityStorageAccess(entity, ..)
+            | Self::CalledBannedEntryPointMethod(entity, _)
+            | Self::CallHadValue(entity, _)
+            | Self::OutOfGas(entity, _)
+            | Self::PaymasterDepositTooLow(entity, ..)
+            | Self::FactoryGasLimitExceeded(entity, ..)
+            | Self::FactoryDeployedUnapprovedContract(entity, ..) => Some(*entity),
+            Self::AssociatedStorageDuringDeploy(entity, _) => *entity,
+            Self::NotStaked(info) => Some(info.needs_stake),
+            Self::FactoryCalledCreate2Twice(address) => Some(Entity::factory(*address)),
+            Self::PaymasterSponsorshipExpired(address, ..) | Self::SenderIsPaymaster(address) => {
+                Some(Entity::paymaster(*address))
+            }
+            Self::UnintendedRevertWithMessage(kind, _, Some(address), _)
+            | Self::UnintendedRevert(kind, Some(address), _) => Some(Entity::new(*kind, *address)),
+            _ => None,
+        }
+    }
+
+    /// Returns the category of this violation, used to decide how to respond to it
+    pub fn category(&self) -> ViolationCategory {
+        match self {
+            Self::UsedForbiddenOpcode(..)
+            | Self::GasOpcodeMisuse(_)
+            | Self::UsedForbiddenPrecompile(..)
+            | Self::AccessedUndeployedContract(..)
+            | Self::FactoryCalledCreate2Twice(_)
+            | Self::SenderAddressMismatch(..)
+            | Self::InvalidStorageAccess(..)
+            | Self::InvalidEntityStorageAccess(..)
+            | Self::CalledBannedEntryPointMethod(_, _)
+            | Self::CallHadValue(..)
+            | Self::OutOfGas(..)
+            | Self::AccessedUnsupportedContractType(..)
+            | Self::AdmissionPolicyRejected(_)
+            | Self::AddressDenied(_)
+            | Self::FactoryGasLimitExceeded(..)
+            | Self::FactoryDeployedUnapprovedContract(..)
+            | Self::SimulationBudgetExceeded
+            | Self::TooManyContractsAccessed(_)
+            | Self::SenderIsPaymaster(_) => ViolationCategory::SpecBanned,
+            Self::MempoolPriorityFeeTooLow(..)
+            | Self::InsufficientTokenBalance(..)
+            | Self::InsufficientTokenAllowance(..)
+            | Self::PaymasterDepositTooLow(..)
+            | Self::PaymasterSponsorshipExpired(..) => ViolationCategory::Economic,
+            Self::UsedBannedEnvironmentOpcode(..)
+            | Self::AssociatedStorageDuringDeploy(..)
+            | Self::NotStaked(_)
+            | Self::UnstakedPaymasterContext
+            | Self::UnstakedAggregator => ViolationCategory::Staking,
+            Self::CodeHashChanged(..)
+            | Self::DidNotRevert
+            | Self::WrongNumberOfPhases(_)
+            | Self::SimulationTimedOut
+            | Self::NonceTooHigh(..) => ViolationCategory::Transient,
+            Self::InvalidSignature
+            | Self::InvalidAccountSignature
+            | Self::InvalidTimeRange(..)
+            | Self::InvalidPaymasterSignature
+            | Self::UnintendedRevertWithMessage(..)
+            | Self::UnintendedRevert(..)
+            | Self::ValidationRevert(_)
+            | Self::AggregatorValidationFailed(_)
+            | Self::VerificationGasLimitBufferTooLow(..)
+            | Self::NonceAlreadyUsed(..)
+            | Self::SuspiciousTimeRange
+            | Self::TotalGasLimitExceeded(..)
+            | Self::ZeroSender
+            | Self::ZeroGasLimit(_)
+            | Self::ZeroMaxFeePerGas
+            | Self::MalformedInitCode
+            | Self::MalformedPaymasterAndData
+            | Self::VerificationGasLimitTooLowForOp(..) => ViolationCategory::Malformed,
+        }
+    }
+
+    /// Returns a stable, machine-readable code for this violation.
+    ///
+    /// Unlike the `Display` text, codes never change once assigned, so clients can branch
+    /// on them and localize the message themselves.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidSignature => "SIM_INVALID_SIGNATURE",
+            Self::InvalidAccountSignature => "SIM_INVALID_ACCOUNT_SIGNATURE",
+            Self::InvalidTimeRange(..) => "SIM_INVALID_TIME_RANGE",
+            Self::InvalidPaymasterSignature => "SIM_INVALID_PAYMASTER_SIGNATURE",
+            Self::UsedForbiddenOpcode(..) => "SIM_BANNED_OPCODE",
+            Self::GasOpcodeMisuse(..) => "SIM_GAS_OPCODE_MISUSE",
+            Self::UsedBannedEnvironmentOpcode(..) => "SIM_BANNED_ENVIRONMENT_OPCODE",
+            Self::UsedForbiddenPrecompile(..) => "SIM_BANNED_PRECOMPILE",
+            Self::AccessedUndeployedContract(..) => "SIM_ACCESSED_UNDEPLOYED_CONTRACT",
+            Self::FactoryCalledCreate2Twice(..) => "SIM_FACTORY_CREATE2_TWICE",
+            Self::SenderAddressMismatch(..) => "SIM_SENDER_ADDRESS_MISMATCH",
+            Self::InvalidStorageAccess(..) => "SIM_INVALID_STORAGE_ACCESS",
+            Self::InvalidEntityStorageAccess(..) => "SIM_INVALID_ENTITY_STORAGE_ACCESS",
+            Self::AssociatedStorageDuringDeploy(..) => "SIM_ASSOCIATED_STORAGE_DURING_DEPLOY",
+            Self::CalledBannedEntryPointMethod(..) => "SIM_BANNED_ENTRY_POINT_METHOD",
+            Self::CallHadValue(..) => "SIM_CALL_WITH_VALUE",
+            Self::CodeHashChanged(..) => "SIM_CODE_HASH_CHANGED",
+            Self::NotStaked(..) => "SIM_NOT_STAKED",
+            Self::UnstakedPaymasterContext => "SIM_UNSTAKED_PAYMASTER_CONTEXT",
+            Self::UnstakedAggregator => "SIM_UNSTAKED_AGGREGATOR",
+            Self::UnintendedRevertWithMessage(..) => "SIM_UNINTENDED_REVERT_WITH_MESSAGE",
+            Self::UnintendedRevert(..) => "SIM_UNINTENDED_REVERT",
+            Self::ValidationRevert(..) => "SIM_VALIDATION_REVERT",
+            Self::DidNotRevert => "SIM_DID_NOT_REVERT",
+            Self::WrongNumberOfPhases(..) => "SIM_WRONG_NUMBER_OF_PHASES",
+            Self::OutOfGas(..) => "SIM_OUT_OF_GAS",
+            Self::AggregatorValidationFailed(..) => "SIM_AGGREGATOR_VALIDATION_FAILED",
+            Self::VerificationGasLimitBufferTooLow(..) => {
+                "SIM_VERIFICATION_GAS_LIMIT_BUFFER_TOO_LOW"
+            }
+            Self::AccessedUnsupportedContractType(..) => "SIM_ACCESSED_UNSUPPORTED_CONTRACT_TYPE",
+            Self::MempoolPriorityFeeTooLow(..) => "SIM_MEMPOOL_PRIORITY_FEE_TOO_LOW",
+            Self::SimulationTimedOut => "SIM_TIMED_OUT",
+            Self::NonceTooHigh(..) => "SIM_NONCE_TOO_HIGH",
+            Self::NonceAlreadyUsed(..) => "SIM_NONCE_ALREADY_USED",
+            Self::AdmissionPolicyRejected(..) => "SIM_ADMISSION_POLICY_REJECTED",
+            Self::InsufficientTokenBalance(..) => "SIM_INSUFFICIENT_TOKEN_BALANCE",
+            Self::InsufficientTokenAllowance(..) => "SIM_INSUFFICIENT_TOKEN_ALLOWANCE",
+            Self::SuspiciousTimeRange => "SIM_SUSPICIOUS_TIME_RANGE",
+            Self::PaymasterDepositTooLow(..) => "SIM_PAYMASTER_DEPOSIT_TOO_LOW",
+            Self::TotalGasLimitExceeded(..) => "SIM_TOTAL_GAS_LIMIT_EXCEEDED",
+            Self::AddressDenied(..) => "SIM_ADDRESS_DENIED",
+            Self::ZeroSender => "SIM_ZERO_SENDER",
+            Self::ZeroGasLimit(..) => "SIM_ZERO_GAS_LIMIT",
+            Self::ZeroMaxFeePerGas => "SIM_ZERO_MAX_FEE_PER_GAS",
+            Self::MalformedInitCode => "SIM_MALFORMED_INIT_CODE",
+            Self::MalformedPaymasterAndData => "SIM_MALFORMED_PAYMASTER_AND_DATA",
+            Self::FactoryGasLimitExceeded(..) => "SIM_FACTORY_GAS_LIMIT_EXCEEDED",
+            Self::PaymasterSponsorshipExpired(..) => "SIM_PAYMASTER_SPONSORSHIP_EXPIRED",
+            Self::VerificationGasLimitTooLowForOp(..) => {
+                "SIM_VERIFICATION_GAS_LIMIT_TOO_LOW_FOR_OP"
+            }
+            Self::FactoryDeployedUnapprovedContract(..) => {
+                "SIM_FACTORY_DEPLOYED_UNAPPROVED_CONTRACT"
+            }
+            Self::SimulationBudgetExceeded => "SIM_BUDGET_EXCEEDED",
+            Self::TooManyContractsAccessed(..) => "SIM_TOO_MANY_CONTRACTS_ACCESSED",
+            Self::SenderIsPaymaster(..) => "SIM_SENDER_IS_PAYMASTER",
+        }
+    }
+}
+
+/// Category of a simulation violation, for responding to kinds of violations differently
+/// without matching every variant
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, parse_display::Display)]
+#[display(style = "snake_case")]
+pub enum ViolationCategory {
+    /// An entity broke a validation rule of the spec or of the operator
+    SpecBanned,
+    /// The operation, or an entity paying for it, can't currently pay enough
+    Economic,
+    /// An entity must be staked to do what it did during validation
+    Staking,
+    /// Caused by the state of the chain or the bundler rather than the operation, so the
+    /// same operation may succeed if retried later
+    Transient,
+    /// The operation itself is invalid: malformed fields, limits, signatures or time
+    /// range, or its validation reverted
+    Malformed,
 }
 
 /// Information about a storage violation based on stake status
-#[derive(Debug, PartialEq, Clone, PartialOrd, Eq, Ord)]
+#[derive(Debug, PartialEq, Clone, PartialOrd, Eq, Ord, Deserialize, Serialize)]
 pub struct NeedsStakeInformation {
     /// Entity needing stake info
     pub needs_stake: Entity,
@@ -254,3 +553,286 @@ pub struct NeedsStakeInformation {
     /// Minumum delay after an unstake event
     pub min_unstake_delay: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use strum::VariantNames;
+
+    use super::*;
+    use crate::Opcode;
+
+    // One instance of every violation variant, with its expected category
+    fn violation_cases() -> Vec<(SimulationViolation, ViolationCategory)> {
+        let entity = Entity::account(Address::ZERO);
+        let slot = StorageSlot {
+            address: Address::ZERO,
+            slot: U256::ZERO,
+        };
+        let opcode = ViolationOpCode(Opcode::CREATE);
+        vec![
+            (
+                SimulationViolation::InvalidSignature,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::InvalidAccountSignature,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::InvalidTimeRange(Timestamp::default(), Timestamp::default()),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::InvalidPaymasterSignature,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::UsedForbiddenOpcode(entity, Address::ZERO, opcode.clone()),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::GasOpcodeMisuse(entity),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::UsedBannedEnvironmentOpcode(entity, opcode),
+                ViolationCategory::Staking,
+            ),
+            (
+                SimulationViolation::UsedForbiddenPrecompile(entity, Address::ZERO, Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::AccessedUndeployedContract(entity, Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::FactoryCalledCreate2Twice(Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::SenderAddressMismatch(Address::ZERO, Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::InvalidStorageAccess(entity, slot, StorageSlotLabel(None)),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::InvalidEntityStorageAccess(entity, entity, slot),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::AssociatedStorageDuringDeploy(Some(entity), slot),
+                ViolationCategory::Staking,
+            ),
+            (
+                SimulationViolation::CalledBannedEntryPointMethod(entity, None),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::CallHadValue(entity, None),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::CodeHashChanged(B256::ZERO, B256::ZERO),
+                ViolationCategory::Transient,
+            ),
+            (
+                SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+                    needs_stake: entity,
+                    accessing_entity: EntityType::Account,
+                    accessed_entity: None,
+                    accessed_address: Address::ZERO,
+                    slot: U256::ZERO,
+                    min_stake: U256::ZERO,
+                    min_unstake_delay: 0,
+                })),
+                ViolationCategory::Staking,
+            ),
+            (
+                SimulationViolation::UnstakedPaymasterContext,
+                ViolationCategory::Staking,
+            ),
+            (
+                SimulationViolation::UnstakedAggregator,
+                ViolationCategory::Staking,
+            ),
+            (
+                SimulationViolation::UnintendedRevertWithMessage(
+                    EntityType::Account,
+                    String::new(),
+                    None,
+                    Bytes::new(),
+                ),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::UnintendedRevert(EntityType::Account, None, Bytes::new()),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::ValidationRevert(ValidationRevert::EntryPoint(String::new())),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::DidNotRevert,
+                ViolationCategory::Transient,
+            ),
+            (
+                SimulationViolation::WrongNumberOfPhases(0),
+                ViolationCategory::Transient,
+            ),
+            (
+                SimulationViolation::OutOfGas(entity, 0),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::AggregatorValidationFailed(None),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::VerificationGasLimitBufferTooLow(0, 0),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::AccessedUnsupportedContractType(String::new(), Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::MempoolPriorityFeeTooLow(0, 0),
+                ViolationCategory::Economic,
+            ),
+            (
+                SimulationViolation::SimulationTimedOut,
+                ViolationCategory::Transient,
+            ),
+            (
+                SimulationViolation::NonceTooHigh(U256::ZERO, U256::ZERO),
+                ViolationCategory::Transient,
+            ),
+            (
+                SimulationViolation::NonceAlreadyUsed(U256::ZERO, U256::ZERO),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::AdmissionPolicyRejected(String::new()),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::InsufficientTokenBalance(
+                    Address::ZERO,
+                    U256::ZERO,
+                    U256::ZERO,
+                ),
+                ViolationCategory::Economic,
+            ),
+            (
+                SimulationViolation::InsufficientTokenAllowance(
+                    Address::ZERO,
+                    U256::ZERO,
+                    U256::ZERO,
+                ),
+                ViolationCategory::Economic,
+            ),
+            (
+                SimulationViolation::SuspiciousTimeRange,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::PaymasterDepositTooLow(entity, U256::ZERO, U256::ZERO),
+                ViolationCategory::Economic,
+            ),
+            (
+                SimulationViolation::TotalGasLimitExceeded(0, 0),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::AddressDenied(Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::ZeroSender,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::ZeroGasLimit(String::new()),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::ZeroMaxFeePerGas,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::MalformedInitCode,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::MalformedPaymasterAndData,
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::FactoryGasLimitExceeded(entity, 0, 0),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::PaymasterSponsorshipExpired(
+                    Address::ZERO,
+                    Timestamp::default(),
+                    Timestamp::default(),
+                ),
+                ViolationCategory::Economic,
+            ),
+            (
+                SimulationViolation::VerificationGasLimitTooLowForOp(0, 0),
+                ViolationCategory::Malformed,
+            ),
+            (
+                SimulationViolation::FactoryDeployedUnapprovedContract(
+                    entity,
+                    Address::ZERO,
+                    B256::ZERO,
+                ),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::SimulationBudgetExceeded,
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::TooManyContractsAccessed(0),
+                ViolationCategory::SpecBanned,
+            ),
+            (
+                SimulationViolation::SenderIsPaymaster(Address::ZERO),
+                ViolationCategory::SpecBanned,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_violation_categories() {
+        let cases = violation_cases();
+
+        // every variant must be listed in `violation_cases`
+        let names: HashSet<&'static str> = cases.iter().map(|(v, _)| v.into()).collect();
+        assert_eq!(
+            names,
+            SimulationViolation::VARIANTS.iter().copied().collect()
+        );
+
+        for (violation, category) in cases {
+            assert_eq!(violation.category(), category, "{violation:?}");
+        }
+    }
+
+    #[test]
+    fn test_violation_codes_unique() {
+        // `violation_cases` covers every variant, so distinct codes must match the variant count
+        let codes: HashSet<&'static str> =
+            violation_cases().iter().map(|(v, _)| v.code()).collect();
+        assert_eq!(codes.len(), SimulationViolation::VARIANTS.len());
+    }
+}
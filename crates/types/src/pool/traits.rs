@@ -20,7 +20,10 @@ use mockall::automock;
 
 use super::{
     error::PoolError,
-    types::{NewHead, PaymasterMetadata, PoolOperation, Reputation, ReputationStatus, StakeStatus},
+    types::{
+        NewHead, PaymasterMetadata, PoolOperation, PooledOp, Reputation, ReputationChange,
+        ReputationStatus, StakeStatus,
+    },
 };
 use crate::{EntityUpdate, UserOperationId, UserOperationVariant};
 
@@ -34,6 +37,10 @@ pub trait Pool: Send + Sync {
     /// Get the supported entry points of the pool
     async fn get_supported_entry_points(&self) -> PoolResult<Vec<Address>>;
 
+    /// Get the aggregators whose operations the pool accepts for an entry point.
+    /// Empty if aggregation isn't configured.
+    async fn get_supported_aggregators(&self, entry_point: Address) -> PoolResult<Vec<Address>>;
+
     /// Add an operation to the pool
     async fn add_op(&self, entry_point: Address, op: UserOperationVariant) -> PoolResult<B256>;
 
@@ -50,6 +57,13 @@ pub trait Pool: Send + Sync {
     /// Returns None if the operation is not found
     async fn get_op_by_hash(&self, hash: B256) -> PoolResult<Option<PoolOperation>>;
 
+    /// Get the operations of a sender currently in the pool, sorted by nonce
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PooledOp>>;
+
     /// Remove operations from the pool by hash
     async fn remove_ops(&self, entry_point: Address, ops: Vec<B256>) -> PoolResult<()>;
 
@@ -60,6 +74,10 @@ pub trait Pool: Send + Sync {
         id: UserOperationId,
     ) -> PoolResult<Option<B256>>;
 
+    /// Remove an operation from the pool by hash
+    /// Returns true if the operation was present and removed, false if it was not found
+    async fn remove_op_by_hash(&self, entry_point: Address, hash: B256) -> PoolResult<bool>;
+
     /// Update operations associated with entities from the pool
     async fn update_entities(
         &self,
@@ -73,6 +91,16 @@ pub trait Pool: Send + Sync {
     /// has processed all operations up to that head.
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>>;
 
+    /// Subscribe to reputation status changes of the entities tracked by the pool of an
+    /// entry point.
+    ///
+    /// Only changes made after subscribing are received. Changes are skipped if the
+    /// subscriber falls behind, use `get_reputation_status` to check the current status.
+    async fn subscribe_reputation_changes(
+        &self,
+        entry_point: Address,
+    ) -> PoolResult<Pin<Box<dyn Stream<Item = ReputationChange> + Send>>>;
+
     /// Get reputation status given entrypoint and address
     async fn get_reputation_status(
         &self,
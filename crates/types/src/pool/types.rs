@@ -28,6 +28,17 @@ pub struct NewHead {
     pub block_number: u64,
 }
 
+/// A change in the reputation status of an address tracked by the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReputationChange {
+    /// The address whose status changed
+    pub address: Address,
+    /// The status before the change
+    pub old_status: ReputationStatus,
+    /// The status after the change
+    pub new_status: ReputationStatus,
+}
+
 /// The reputation of an entity
 #[derive(Debug, Clone)]
 pub struct Reputation {
@@ -99,6 +110,20 @@ pub struct PaymasterMetadata {
     pub pending_balance: U256,
 }
 
+/// Summary of an operation in the pool, enough to display its status without
+/// fetching the full operation
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PooledOp {
+    /// The hash of the operation
+    pub hash: B256,
+    /// The nonce of the operation
+    pub nonce: U256,
+    /// The max fee per gas of the operation
+    pub max_fee_per_gas: u128,
+    /// The max priority fee per gas of the operation
+    pub max_priority_fee_per_gas: u128,
+}
+
 /// A user operation with additional metadata from validation.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PoolOperation {
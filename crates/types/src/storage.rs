@@ -13,13 +13,31 @@
 
 //! Types for interacting with EVM storage
 
+use std::fmt::{self, Display, Formatter};
+
 use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
 
 /// An EVM storage slot
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct StorageSlot {
     /// The address of the contract owning this slot
     pub address: Address,
     /// The storage slot
     pub slot: U256,
 }
+
+/// An optional human readable label for a storage slot, e.g. "ERC20.balanceOf(sender)"
+///
+/// Displays as the label in parentheses, preceded by a space, or as nothing if unlabeled.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct StorageSlotLabel(pub Option<String>);
+
+impl Display for StorageSlotLabel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(label) => write!(f, " ({label})"),
+            None => Ok(()),
+        }
+    }
+}
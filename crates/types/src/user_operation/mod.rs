@@ -81,11 +81,27 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     fn nonce(&self) -> U256;
 
     /// Get the user operation paymaster address, if any
+    ///
+    /// A zero paymaster address is treated as no paymaster, as it is by the entry point.
     fn paymaster(&self) -> Option<Address>;
 
     /// Get the user operation factory address, if any
+    ///
+    /// A zero factory address is treated as no factory.
     fn factory(&self) -> Option<Address>;
 
+    /// Returns true if the init code is malformed, i.e. it is not empty but does not
+    /// start with a factory address
+    fn has_malformed_init_code(&self) -> bool;
+
+    /// Returns true if the paymaster and data is malformed, i.e. it is not empty but does
+    /// not start with a paymaster address
+    fn has_malformed_paymaster_and_data(&self) -> bool;
+
+    /// Get the paymaster specific data, without the paymaster address or gas limits.
+    /// Empty if there is no paymaster.
+    fn paymaster_data(&self) -> &[u8];
+
     /// Get the user operation calldata
     fn call_data(&self) -> &Bytes;
 
@@ -104,10 +120,13 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Returns the maximum cost, in wei, of this user operation
     fn max_gas_cost(&self) -> U256;
 
-    /// Returns the gas price for this UO given the base fee
+    /// Returns the effective gas price this UO pays given the base fee
+    ///
+    /// Matches the entry point's pricing: `min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)`.
+    /// Use this rather than recomputing the formula so that ranking and profitability agree.
     fn gas_price(&self, base_fee: u128) -> u128 {
         self.max_fee_per_gas()
-            .min(base_fee + self.max_priority_fee_per_gas())
+            .min(base_fee.saturating_add(self.max_priority_fee_per_gas()))
     }
 
     /*
@@ -157,6 +176,27 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Used when a user op is using a signature aggregator prior to being submitted
     fn clear_signature(&mut self);
 
+    /// Replace the signature field of the user op
+    ///
+    /// Used to simulate with a dummy signature before the user op is signed
+    fn set_signature(&mut self, signature: Bytes);
+
+    /// Replace the call gas limit of the user op
+    ///
+    /// Used to simulate with varying call gas limits during gas estimation
+    fn set_call_gas_limit(&mut self, call_gas_limit: u128);
+
+    /// Replace the verification gas limit of the user op
+    ///
+    /// Used to simulate with varying verification gas limits during gas estimation
+    fn set_verification_gas_limit(&mut self, verification_gas_limit: u128);
+
+    /// Replace the pre-verification gas of the user op
+    fn set_pre_verification_gas(&mut self, pre_verification_gas: u128);
+
+    /// Replace the max fee and max priority fee per gas of the user op
+    fn set_max_fees(&mut self, max_fee_per_gas: u128, max_priority_fee_per_gas: u128);
+
     /// Abi encode size of the user operation
     fn abi_encoded_size(&self) -> usize;
 
@@ -379,6 +419,27 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn has_malformed_init_code(&self) -> bool {
+        match self {
+            UserOperationVariant::V0_6(op) => op.has_malformed_init_code(),
+            UserOperationVariant::V0_7(op) => op.has_malformed_init_code(),
+        }
+    }
+
+    fn has_malformed_paymaster_and_data(&self) -> bool {
+        match self {
+            UserOperationVariant::V0_6(op) => op.has_malformed_paymaster_and_data(),
+            UserOperationVariant::V0_7(op) => op.has_malformed_paymaster_and_data(),
+        }
+    }
+
+    fn paymaster_data(&self) -> &[u8] {
+        match self {
+            UserOperationVariant::V0_6(op) => op.paymaster_data(),
+            UserOperationVariant::V0_7(op) => op.paymaster_data(),
+        }
+    }
+
     fn call_data(&self) -> &Bytes {
         match self {
             UserOperationVariant::V0_6(op) => op.call_data(),
@@ -470,6 +531,45 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn set_signature(&mut self, signature: Bytes) {
+        match self {
+            UserOperationVariant::V0_6(op) => op.set_signature(signature),
+            UserOperationVariant::V0_7(op) => op.set_signature(signature),
+        }
+    }
+
+    fn set_call_gas_limit(&mut self, call_gas_limit: u128) {
+        match self {
+            UserOperationVariant::V0_6(op) => op.set_call_gas_limit(call_gas_limit),
+            UserOperationVariant::V0_7(op) => op.set_call_gas_limit(call_gas_limit),
+        }
+    }
+
+    fn set_verification_gas_limit(&mut self, verification_gas_limit: u128) {
+        match self {
+            UserOperationVariant::V0_6(op) => op.set_verification_gas_limit(verification_gas_limit),
+            UserOperationVariant::V0_7(op) => op.set_verification_gas_limit(verification_gas_limit),
+        }
+    }
+
+    fn set_pre_verification_gas(&mut self, pre_verification_gas: u128) {
+        match self {
+            UserOperationVariant::V0_6(op) => op.set_pre_verification_gas(pre_verification_gas),
+            UserOperationVariant::V0_7(op) => op.set_pre_verification_gas(pre_verification_gas),
+        }
+    }
+
+    fn set_max_fees(&mut self, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) {
+        match self {
+            UserOperationVariant::V0_6(op) => {
+                op.set_max_fees(max_fee_per_gas, max_priority_fee_per_gas)
+            }
+            UserOperationVariant::V0_7(op) => {
+                op.set_max_fees(max_fee_per_gas, max_priority_fee_per_gas)
+            }
+        }
+    }
+
     fn abi_encoded_size(&self) -> usize {
         match self {
             UserOperationVariant::V0_6(op) => op.abi_encoded_size(),
@@ -606,6 +706,23 @@ fn random_bytes(fill_size: usize) -> Bytes {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_gas_price() {
+        let op = v0_6::UserOperation {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 10,
+            ..Default::default()
+        };
+
+        // priority fee fits under the max fee
+        assert_eq!(op.gas_price(80), 90);
+        // exactly at the max fee
+        assert_eq!(op.gas_price(90), 100);
+        // capped by the max fee
+        assert_eq!(op.gas_price(91), 100);
+        assert_eq!(op.gas_price(u128::MAX), 100);
+    }
+
     #[test]
     fn test_byte_array_abi_len() {
         let b = Bytes::from(vec![0u8; 32]);
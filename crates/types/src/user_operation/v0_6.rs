@@ -188,6 +188,18 @@ impl UserOperationTrait for UserOperation {
         Self::get_address_from_field(&self.paymaster_and_data)
     }
 
+    fn has_malformed_init_code(&self) -> bool {
+        !self.init_code.is_empty() && self.init_code.len() < 20
+    }
+
+    fn has_malformed_paymaster_and_data(&self) -> bool {
+        !self.paymaster_and_data.is_empty() && self.paymaster_and_data.len() < 20
+    }
+
+    fn paymaster_data(&self) -> &[u8] {
+        self.paymaster_and_data.get(20..).unwrap_or_default()
+    }
+
     fn call_data(&self) -> &Bytes {
         &self.call_data
     }
@@ -265,6 +277,27 @@ impl UserOperationTrait for UserOperation {
         self.signature = Bytes::default();
     }
 
+    fn set_signature(&mut self, signature: Bytes) {
+        self.signature = signature;
+    }
+
+    fn set_call_gas_limit(&mut self, call_gas_limit: u128) {
+        self.call_gas_limit = call_gas_limit;
+    }
+
+    fn set_verification_gas_limit(&mut self, verification_gas_limit: u128) {
+        self.verification_gas_limit = verification_gas_limit;
+    }
+
+    fn set_pre_verification_gas(&mut self, pre_verification_gas: u128) {
+        self.pre_verification_gas = pre_verification_gas;
+    }
+
+    fn set_max_fees(&mut self, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) {
+        self.max_fee_per_gas = max_fee_per_gas;
+        self.max_priority_fee_per_gas = max_priority_fee_per_gas;
+    }
+
     fn abi_encoded_size(&self) -> usize {
         ABI_ENCODED_USER_OPERATION_FIXED_LEN
             + super::byte_array_abi_len(&self.init_code)
@@ -297,7 +330,7 @@ impl UserOperation {
         if data.len() < 20 {
             None
         } else {
-            Some(Address::from_slice(&data[..20]))
+            Some(Address::from_slice(&data[..20])).filter(|address| !address.is_zero())
         }
     }
 
@@ -784,6 +817,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_zero_address_paymaster() {
+        let paymaster_and_data: Bytes = "0x00000000000000000000000000000000000000000123"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            UserOperation::get_address_from_field(&paymaster_and_data),
+            None
+        );
+
+        let sender = Address::random();
+        let op = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                nonce: U256::ZERO,
+                init_code: Bytes::default(),
+                call_data: Bytes::default(),
+                call_gas_limit: 0,
+                verification_gas_limit: 0,
+                pre_verification_gas: 0,
+                max_fee_per_gas: 0,
+                max_priority_fee_per_gas: 0,
+                paymaster_and_data,
+                signature: Bytes::default(),
+            },
+        )
+        .build();
+        assert_eq!(op.paymaster(), None);
+        assert_eq!(op.entities(), vec![Entity::account(sender)]);
+    }
+
     #[test]
     fn test_abi_encoded_size() {
         let operation = UserOperationBuilder::new(
@@ -124,11 +124,26 @@ impl UserOperationTrait for UserOperation {
     }
 
     fn paymaster(&self) -> Option<Address> {
-        self.paymaster
+        self.paymaster.filter(|paymaster| !paymaster.is_zero())
     }
 
     fn factory(&self) -> Option<Address> {
-        self.factory
+        self.factory.filter(|factory| !factory.is_zero())
+    }
+
+    fn has_malformed_init_code(&self) -> bool {
+        self.factory.is_none() && !self.factory_data.is_empty()
+    }
+
+    fn has_malformed_paymaster_and_data(&self) -> bool {
+        self.paymaster.is_none()
+            && (!self.paymaster_data.is_empty()
+                || self.paymaster_verification_gas_limit != 0
+                || self.paymaster_post_op_gas_limit != 0)
+    }
+
+    fn paymaster_data(&self) -> &[u8] {
+        &self.paymaster_data
     }
 
     fn call_data(&self) -> &Bytes {
@@ -148,10 +163,10 @@ impl UserOperationTrait for UserOperation {
 
     fn entities(&self) -> Vec<Entity> {
         let mut ret = vec![Entity::account(self.sender)];
-        if let Some(factory) = self.factory {
+        if let Some(factory) = self.factory() {
             ret.push(Entity::factory(factory));
         }
-        if let Some(paymaster) = self.paymaster {
+        if let Some(paymaster) = self.paymaster() {
             ret.push(Entity::paymaster(paymaster));
         }
         ret
@@ -224,6 +239,37 @@ impl UserOperationTrait for UserOperation {
         self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
     }
 
+    fn set_signature(&mut self, signature: Bytes) {
+        self.signature = signature;
+        self.packed = pack_user_operation(self.clone());
+        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+    }
+
+    fn set_call_gas_limit(&mut self, call_gas_limit: u128) {
+        self.call_gas_limit = call_gas_limit;
+        self.packed = pack_user_operation(self.clone());
+        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+    }
+
+    fn set_verification_gas_limit(&mut self, verification_gas_limit: u128) {
+        self.verification_gas_limit = verification_gas_limit;
+        self.packed = pack_user_operation(self.clone());
+        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+    }
+
+    fn set_pre_verification_gas(&mut self, pre_verification_gas: u128) {
+        self.pre_verification_gas = pre_verification_gas;
+        self.packed = pack_user_operation(self.clone());
+        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+    }
+
+    fn set_max_fees(&mut self, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) {
+        self.max_fee_per_gas = max_fee_per_gas;
+        self.max_priority_fee_per_gas = max_priority_fee_per_gas;
+        self.packed = pack_user_operation(self.clone());
+        self.hash = hash_packed_user_operation(&self.packed, self.entry_point, self.chain_id);
+    }
+
     fn abi_encoded_size(&self) -> usize {
         ABI_ENCODED_USER_OPERATION_FIXED_LEN
             + super::byte_array_abi_len(&self.packed.initCode)
@@ -969,4 +1015,30 @@ mod tests {
         assert_eq!(uo.paymaster_verification_gas_limit, 10);
         assert_eq!(uo.paymaster_post_op_gas_limit, 20);
     }
+
+    #[test]
+    fn test_zero_address_paymaster() {
+        let sender = Address::random();
+        let cs = ChainSpec::default();
+
+        let uo = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 0,
+                verification_gas_limit: 0,
+                pre_verification_gas: 0,
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+                signature: Bytes::new(),
+            },
+        )
+        .paymaster(Address::ZERO, 10, 20, Bytes::new())
+        .build();
+
+        assert_eq!(uo.paymaster(), None);
+        assert_eq!(uo.entities(), vec![Entity::account(sender)]);
+    }
 }
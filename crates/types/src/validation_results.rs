@@ -31,6 +31,7 @@ use rundler_contracts::{
         ValidationResult as ValidationResultV0_7,
     },
 };
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{Timestamp, ValidTimeRange, TIME_RANGE_BUFFER};
 
@@ -39,7 +40,7 @@ use crate::{Timestamp, ValidTimeRange, TIME_RANGE_BUFFER};
 const SIG_VALIDATION_FAILED: Address = address!("0000000000000000000000000000000000000001");
 
 /// Error during validation simulation
-#[derive(Clone, Debug, thiserror::Error, Eq, PartialEq)]
+#[derive(Clone, Debug, thiserror::Error, Eq, PartialEq, Deserialize, Serialize)]
 pub enum ValidationRevert {
     /// The entry point reverted
     #[error("{0}")]
@@ -60,7 +61,27 @@ pub enum ValidationRevert {
     Unknown(Bytes),
     /// Validation reverted with a panic
     #[error("panic: {0}")]
-    Panic(Panic),
+    Panic(#[serde(with = "panic_code")] Panic),
+}
+
+// Panics are serialized as their code
+mod panic_code {
+    use super::*;
+
+    pub(super) fn serialize<S: Serializer>(
+        panic: &Panic,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        panic.code.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Panic, D::Error> {
+        Ok(Panic {
+            code: U256::deserialize(deserializer)?,
+        })
+    }
 }
 
 impl PartialOrd for ValidationRevert {
@@ -245,6 +266,8 @@ impl TryFrom<ValidationResultV0_7> for ValidationOutput {
 pub struct ValidationReturnInfo {
     /// The amount of gas used before the op was executed (pre verification gas and validation gas)
     pub pre_op_gas: u128,
+    /// The required prefund computed by the entry point
+    pub prefund: U256,
     /// Whether the account signature verification failed
     pub account_sig_failed: bool,
     /// Whether the paymaster signature verification failed
@@ -260,7 +283,11 @@ pub struct ValidationReturnInfo {
 impl ValidationReturnInfo {
     /// helper function to check if the returned time range is valid
     pub fn is_valid_time_range(&self) -> bool {
-        let now = Timestamp::now();
+        self.is_valid_time_range_at(Timestamp::now())
+    }
+
+    /// helper function to check if the returned time range is valid at the given time
+    pub fn is_valid_time_range_at(&self, now: Timestamp) -> bool {
         self.valid_after <= now || self.valid_until > now.add(TIME_RANGE_BUFFER)
     }
 }
@@ -272,7 +299,7 @@ impl TryFrom<ReturnInfoV0_6> for ValidationReturnInfo {
     fn try_from(value: ReturnInfoV0_6) -> Result<Self, Self::Error> {
         let ReturnInfoV0_6 {
             preOpGas,
-            prefund: _,
+            prefund,
             sigFailed,
             validAfter,
             validUntil,
@@ -282,6 +309,7 @@ impl TryFrom<ReturnInfoV0_6> for ValidationReturnInfo {
             pre_op_gas: preOpGas
                 .try_into()
                 .map_err(|_| "preOpGas is larger than u128")?,
+            prefund,
             // In v0.6 if one signature fails both do
             account_sig_failed: sigFailed,
             paymaster_sig_failed: sigFailed,
@@ -298,7 +326,7 @@ impl TryFrom<ReturnInfoV0_7> for ValidationReturnInfo {
     fn try_from(value: ReturnInfoV0_7) -> Result<Self, Self::Error> {
         let ReturnInfoV0_7 {
             preOpGas,
-            prefund: _,
+            prefund,
             accountValidationData,
             paymasterValidationData,
             paymasterContext,
@@ -314,6 +342,7 @@ impl TryFrom<ReturnInfoV0_7> for ValidationReturnInfo {
             pre_op_gas: preOpGas
                 .try_into()
                 .map_err(|_| "preOpGas is larger than u128")?,
+            prefund,
             account_sig_failed: !account.signature_valid(),
             paymaster_sig_failed: !paymaster.signature_valid(),
             valid_after: intersect_range.valid_after,